@@ -7,6 +7,8 @@ public enum ContextDBError: Error, LocalizedError, Sendable {
 	case notFound(String)
 	case database(String)
 	case panic(String)
+	case conflict(String)
+	case duplicate(String)
 	case unknown(status: Int32, message: String)
 	case missingOutput
 
@@ -18,6 +20,8 @@ public enum ContextDBError: Error, LocalizedError, Sendable {
 			.notFound(let message),
 			.database(let message),
 			.panic(let message),
+			.conflict(let message),
+			.duplicate(let message),
 			.unknown(_, let message):
 			return message
 		case .missingOutput:
@@ -34,7 +38,7 @@ public final class ContextDatabase {
 
 	public init(path: URL? = nil) throws {
 		let version = contextdb_abi_version()
-		guard version == 1 else {
+		guard version == 2 else {
 			throw ContextDBError.incompatibleABIVersion(version)
 		}
 
@@ -176,6 +180,10 @@ public final class ContextDatabase {
 			return .database(value)
 		case CONTEXTDB_STATUS_PANIC:
 			return .panic(value)
+		case CONTEXTDB_STATUS_CONFLICT:
+			return .conflict(value)
+		case CONTEXTDB_STATUS_DUPLICATE:
+			return .duplicate(value)
 		default:
 			return .unknown(status: status, message: value)
 		}
@@ -23,6 +23,8 @@ enum {
     CONTEXTDB_STATUS_NOT_FOUND = 2,
     CONTEXTDB_STATUS_DATABASE = 3,
     CONTEXTDB_STATUS_PANIC = 4,
+    CONTEXTDB_STATUS_CONFLICT = 5,
+    CONTEXTDB_STATUS_DUPLICATE = 6,
 };
 
 uint32_t contextdb_abi_version(void);
@@ -47,6 +49,11 @@ int32_t contextdb_get_json(const ContextDBHandle *handle,
                            const char *id,
                            char **out_json);
 int32_t contextdb_update_json(ContextDBHandle *handle, const char *json);
+// Returns CONTEXTDB_STATUS_CONFLICT when the stored version is no longer
+// expected_version.
+int32_t contextdb_update_if_version_json(ContextDBHandle *handle,
+                                         const char *json,
+                                         uint64_t expected_version);
 int32_t contextdb_delete_id(ContextDBHandle *handle, const char *id);
 int32_t contextdb_query_json(const ContextDBHandle *handle,
                              const char *json,
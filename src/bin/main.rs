@@ -1,8 +1,13 @@
 use clap::{Parser, Subcommand};
 use colored::*;
-use contextdb::{ContextDB, EmbeddingProfile, Entry, ExpressionFilter, Query, QueryOrder};
+use contextdb::{
+	ContextDB, EmbeddingProfile, Entry, EntryPatch, EvalCase, EvalConfig, EvalMetrics,
+	ExpressionFilter, JsonPatchOperation, MergeStrategy, Query, QueryOrder, RetentionPolicy,
+	RevisionDiff, TextChange, TrashPolicy, DEFAULT_COLLECTION,
+};
 use dialoguer::{theme::ColorfulTheme, Input};
 use indicatif::{ProgressBar, ProgressStyle};
+use std::io::Write;
 use std::path::PathBuf;
 use tabled::{settings::Style, Table, Tabled};
 
@@ -43,12 +48,32 @@ enum Commands {
 		/// Related entry UUIDs
 		#[arg(short, long, value_delimiter = ',')]
 		relation: Vec<uuid::Uuid>,
+
+		/// Collection to add the entry to
+		#[arg(long, default_value = DEFAULT_COLLECTION)]
+		collection: String,
+
+		/// RFC 3339 time after which the entry is hidden and can be swept
+		#[arg(long)]
+		expires_at: Option<chrono::DateTime<chrono::Utc>>,
 	},
 
-	/// Show database statistics
+	/// Show database and relation-graph statistics
 	Stats {
 		/// Path to the database file
 		path: PathBuf,
+
+		/// Number of most-connected entries to report
+		#[arg(short, long, default_value = "5")]
+		top: usize,
+
+		/// List entries that no tracked read has returned instead
+		#[arg(long)]
+		cold: bool,
+
+		/// Output format (table, json)
+		#[arg(short, long, default_value = "table")]
+		format: String,
 	},
 
 	/// Search entries by text
@@ -66,6 +91,10 @@ enum Commands {
 		/// Output format (table, json, plain)
 		#[arg(short, long, default_value = "table")]
 		format: String,
+
+		/// Restrict to these collections (comma-delimited); all collections if omitted
+		#[arg(long, value_delimiter = ',')]
+		collection: Vec<String>,
 	},
 
 	/// List all entries
@@ -84,6 +113,16 @@ enum Commands {
 		/// Output format (table, json, plain)
 		#[arg(short, long, default_value = "table")]
 		format: String,
+
+		/// Restrict to these collections (comma-delimited); all collections if omitted
+		#[arg(long, value_delimiter = ',')]
+		collection: Vec<String>,
+	},
+
+	/// List collections with their entry counts and embedding profiles
+	Collections {
+		/// Path to the database file
+		path: PathBuf,
 	},
 
 	/// Show details of a specific entry
@@ -92,7 +131,12 @@ enum Commands {
 		path: PathBuf,
 
 		/// Entry ID (UUID)
-		id: String,
+		#[arg(required_unless_present = "key")]
+		id: Option<String>,
+
+		/// Look the entry up by its external ID instead
+		#[arg(long, conflicts_with = "id")]
+		key: Option<String>,
 	},
 
 	/// Export database to JSON
@@ -103,6 +147,10 @@ enum Commands {
 		/// Output file (stdout if not specified)
 		#[arg(short, long)]
 		output: Option<PathBuf>,
+
+		/// Restrict to these collections (comma-delimited); all collections if omitted
+		#[arg(long, value_delimiter = ',')]
+		collection: Vec<String>,
 	},
 
 	/// Import entries from JSON
@@ -114,6 +162,18 @@ enum Commands {
 		input: PathBuf,
 	},
 
+	/// Partially update an entry from a JSON patch file
+	Patch {
+		/// Path to the database file
+		path: PathBuf,
+
+		/// Entry UUID or unique prefix
+		id: String,
+
+		/// JSON file with optional expression, meaning, and context merge patch
+		patch: PathBuf,
+	},
+
 	/// Delete an entry
 	Delete {
 		/// Path to the database file
@@ -127,6 +187,23 @@ enum Commands {
 		force: bool,
 	},
 
+	/// Erase an entry and its revision history, leaving only a tombstone
+	Purge {
+		/// Path to the database file
+		path: PathBuf,
+
+		/// Entry UUID, or unique prefix of a current entry
+		id: String,
+
+		/// Skip confirmation
+		#[arg(short, long)]
+		force: bool,
+
+		/// Vacuum afterwards so erased bytes leave the database file
+		#[arg(long)]
+		vacuum: bool,
+	},
+
 	/// Interactive REPL mode
 	Repl {
 		/// Path to the database file
@@ -141,6 +218,10 @@ enum Commands {
 		/// Number of recent entries
 		#[arg(short, long, default_value = "10")]
 		count: usize,
+
+		/// Restrict to these collections (comma-delimited); all collections if omitted
+		#[arg(long, value_delimiter = ',')]
+		collection: Vec<String>,
 	},
 
 	/// Check database and index integrity
@@ -178,6 +259,29 @@ enum Commands {
 		/// Embedding dimensions
 		#[arg(long, requires = "model")]
 		dimensions: Option<usize>,
+		/// Collection whose profile to show or configure
+		#[arg(long, default_value = DEFAULT_COLLECTION)]
+		collection: String,
+	},
+
+	/// Add a directed relation between two entries
+	Relate {
+		/// Path to the database file
+		path: PathBuf,
+		/// Source entry UUID or unique prefix
+		from: String,
+		/// Target entry UUID or unique prefix
+		to: String,
+	},
+
+	/// Remove a directed relation between two entries
+	Unrelate {
+		/// Path to the database file
+		path: PathBuf,
+		/// Source entry UUID or unique prefix
+		from: String,
+		/// Target entry UUID or unique prefix
+		to: String,
 	},
 
 	/// Print durable revision history for an entry
@@ -186,6 +290,119 @@ enum Commands {
 		path: PathBuf,
 		/// Entry UUID or unique prefix
 		id: String,
+
+		/// Show field-by-field changes between consecutive revisions
+		#[arg(long)]
+		diff: bool,
+	},
+
+	/// Print the change feed as JSON lines
+	Changes {
+		/// Path to the database file
+		path: PathBuf,
+
+		/// Print changes after this sequence number
+		#[arg(long, default_value = "0")]
+		since: u64,
+
+		/// Keep polling for new changes
+		#[arg(short, long)]
+		follow: bool,
+
+		/// Polling interval in milliseconds when following
+		#[arg(long, default_value = "1000")]
+		interval_ms: u64,
+	},
+
+	/// List, restore, or empty soft-deleted entries
+	Trash {
+		#[command(subcommand)]
+		command: TrashCommand,
+	},
+
+	/// Delete expired entries, recording delete revisions
+	Sweep {
+		/// Path to the database file
+		path: PathBuf,
+
+		/// Entries deleted per transaction
+		#[arg(long, default_value = "500")]
+		batch_size: usize,
+	},
+
+	/// Find entries with near-identical meanings and merge each group into its oldest entry
+	Dedup {
+		/// Path to the database file
+		path: PathBuf,
+
+		/// Minimum cosine similarity for two entries to count as duplicates
+		#[arg(short, long, default_value = "0.95")]
+		threshold: f32,
+
+		/// Report clusters without merging them
+		#[arg(long)]
+		dry_run: bool,
+	},
+
+	/// Score queries against relevance judgments with recall, precision, MRR, and nDCG
+	Eval {
+		/// Path to the database file
+		path: PathBuf,
+
+		/// JSON file of evaluation cases, each a query with relevance judgments
+		qrels: PathBuf,
+
+		/// Rank cutoff the metrics are computed at
+		#[arg(short, long, default_value = "10")]
+		k: usize,
+
+		/// JSON ranking config applied to every query
+		#[arg(long)]
+		config: Option<PathBuf>,
+
+		/// Second JSON ranking config to compare against the first
+		#[arg(long)]
+		compare: Option<PathBuf>,
+
+		/// Output format (table, json)
+		#[arg(short, long, default_value = "table")]
+		format: String,
+	},
+
+	/// Prune and compact revision history with a retention policy
+	Compact {
+		/// Path to the database file
+		path: PathBuf,
+
+		/// Keep this many of each entry's most recent revisions
+		#[arg(long)]
+		keep_last: Option<usize>,
+
+		/// Keep revisions recorded within this many days
+		#[arg(long)]
+		keep_days: Option<u32>,
+
+		/// Allow delete revisions to be pruned
+		#[arg(long)]
+		drop_deletes: bool,
+
+		/// Store retained revisions as deltas against the previous revision
+		#[arg(long)]
+		delta: bool,
+
+		/// Save the given options as the database's retention policy
+		#[arg(long)]
+		save: bool,
+	},
+
+	/// Restore an entry to an earlier revision, or undelete it
+	Revert {
+		/// Path to the database file
+		path: PathBuf,
+		/// Entry UUID, or unique prefix of a current entry
+		id: String,
+		/// Revision UUID or unique prefix; omit to undelete a deleted entry
+		revision: Option<String>,
 	},
 }
 
@@ -217,6 +434,48 @@ impl From<&Entry> for EntryRow {
 	}
 }
 
+#[derive(Subcommand)]
+enum TrashCommand {
+	/// List entries in the trash
+	List {
+		/// Path to the database file
+		path: PathBuf,
+	},
+
+	/// Move an entry out of the trash together with its relations
+	Restore {
+		/// Path to the database file
+		path: PathBuf,
+
+		/// Entry UUID, or unique prefix of a trashed entry
+		id: String,
+	},
+
+	/// Permanently remove trashed entries whose grace period has ended
+	Empty {
+		/// Path to the database file
+		path: PathBuf,
+
+		/// Remove every trashed entry regardless of the grace period
+		#[arg(long)]
+		all: bool,
+	},
+
+	/// Show or change whether deletes go to the trash
+	Policy {
+		/// Path to the database file
+		path: PathBuf,
+
+		/// Move deleted entries to the trash instead of removing them
+		#[arg(long)]
+		soft_delete: Option<bool>,
+
+		/// Days a trashed entry stays restorable
+		#[arg(long)]
+		grace_days: Option<u32>,
+	},
+}
+
 fn main() {
 	let cli = Cli::parse();
 
@@ -228,26 +487,59 @@ fn main() {
 			meaning,
 			context,
 			relation,
-		} => cmd_add(path, expression, meaning, context, relation),
-		Commands::Stats { path } => cmd_stats(path),
+			collection,
+			expires_at,
+		} => cmd_add(
+			path, expression, meaning, context, relation, collection, expires_at,
+		),
+		Commands::Stats {
+			path,
+			cold: true,
+			format,
+			..
+		} => cmd_cold(path, format),
+		Commands::Stats {
+			path,
+			top,
+			cold: false,
+			format,
+		} => cmd_stats(path, top, format),
 		Commands::Search {
 			path,
 			query,
 			limit,
 			format,
-		} => cmd_search(path, query, limit, format),
+			collection,
+		} => cmd_search(path, query, limit, format, collection),
 		Commands::List {
 			path,
 			limit,
 			offset,
 			format,
-		} => cmd_list(path, limit, offset, format),
-		Commands::Show { path, id } => cmd_show(path, id),
-		Commands::Export { path, output } => cmd_export(path, output),
+			collection,
+		} => cmd_list(path, limit, offset, format, collection),
+		Commands::Collections { path } => cmd_collections(path),
+		Commands::Show { path, id, key } => cmd_show(path, id, key),
+		Commands::Export {
+			path,
+			output,
+			collection,
+		} => cmd_export(path, output, collection),
 		Commands::Import { path, input } => cmd_import(path, input),
+		Commands::Patch { path, id, patch } => cmd_patch(path, id, patch),
 		Commands::Delete { path, id, force } => cmd_delete(path, id, force),
+		Commands::Purge {
+			path,
+			id,
+			force,
+			vacuum,
+		} => cmd_purge(path, id, force, vacuum),
 		Commands::Repl { path } => cmd_repl(path),
-		Commands::Recent { path, count } => cmd_recent(path, count),
+		Commands::Recent {
+			path,
+			count,
+			collection,
+		} => cmd_recent(path, count, collection),
 		Commands::Check { path } => cmd_check(path),
 		Commands::Backup { path, output } => cmd_backup(path, output),
 		Commands::Restore {
@@ -259,8 +551,41 @@ fn main() {
 			model,
 			version,
 			dimensions,
-		} => cmd_profile(path, model, version, dimensions),
-		Commands::Revisions { path, id } => cmd_revisions(path, id),
+			collection,
+		} => cmd_profile(path, model, version, dimensions, collection),
+		Commands::Relate { path, from, to } => cmd_relate(path, from, to, true),
+		Commands::Unrelate { path, from, to } => cmd_relate(path, from, to, false),
+		Commands::Revisions { path, id, diff } => cmd_revisions(path, id, diff),
+		Commands::Revert { path, id, revision } => cmd_revert(path, id, revision),
+		Commands::Changes {
+			path,
+			since,
+			follow,
+			interval_ms,
+		} => cmd_changes(path, since, follow, interval_ms),
+		Commands::Compact {
+			path,
+			keep_last,
+			keep_days,
+			drop_deletes,
+			delta,
+			save,
+		} => cmd_compact(path, keep_last, keep_days, drop_deletes, delta, save),
+		Commands::Sweep { path, batch_size } => cmd_sweep(path, batch_size),
+		Commands::Dedup {
+			path,
+			threshold,
+			dry_run,
+		} => cmd_dedup(path, threshold, dry_run),
+		Commands::Eval {
+			path,
+			qrels,
+			k,
+			config,
+			compare,
+			format,
+		} => cmd_eval(path, qrels, k, config, compare, format),
+		Commands::Trash { command } => cmd_trash(command),
 	};
 
 	if let Err(e) = result {
@@ -315,6 +640,7 @@ fn cmd_profile(
 	model: Option<String>,
 	version: Option<String>,
 	dimensions: Option<usize>,
+	collection: String,
 ) -> Result<(), Box<dyn std::error::Error>> {
 	let mut db = open_db(&path)?;
 	if let Some(model) = model {
@@ -323,24 +649,522 @@ fn cmd_profile(
 			version,
 			dimensions: dimensions.ok_or("--dimensions is required with --model")?,
 		};
-		db.set_embedding_profile(&profile)?;
+		db.set_collection_embedding_profile(&collection, &profile)?;
 		println!("{} Embedding profile configured", "✓".green().bold());
 		return Ok(());
 	}
-	match db.embedding_profile()? {
+	match db.collection_embedding_profile(&collection)? {
 		Some(profile) => println!("{}", serde_json::to_string_pretty(&profile)?),
 		None => println!("No embedding profile configured."),
 	}
 	Ok(())
 }
 
-fn cmd_revisions(path: PathBuf, id: String) -> Result<(), Box<dyn std::error::Error>> {
+fn cmd_changes(
+	path: PathBuf,
+	since: u64,
+	follow: bool,
+	interval_ms: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+	const PAGE_SIZE: usize = 1000;
+	let db = open_db(&path)?;
+	let mut seq = since;
+	loop {
+		let changes = db.changes_since(seq, PAGE_SIZE)?;
+		{
+			let mut stdout = std::io::stdout().lock();
+			for change in &changes {
+				writeln!(stdout, "{}", serde_json::to_string(change)?)?;
+				seq = change.seq;
+			}
+			stdout.flush()?;
+		}
+		if changes.len() == PAGE_SIZE {
+			continue;
+		}
+		if !follow {
+			return Ok(());
+		}
+		std::thread::sleep(std::time::Duration::from_millis(interval_ms));
+	}
+}
+
+fn cmd_revisions(path: PathBuf, id: String, diff: bool) -> Result<(), Box<dyn std::error::Error>> {
 	let db = open_db(&path)?;
 	let id = match uuid::Uuid::parse_str(&id) {
 		Ok(id) => id,
 		Err(_) => find_entry_by_partial_id(&db, &id)?.id,
 	};
-	println!("{}", serde_json::to_string_pretty(&db.revisions(id)?)?);
+	let revisions = db.revisions(id)?;
+	if !diff {
+		println!("{}", serde_json::to_string_pretty(&revisions)?);
+		return Ok(());
+	}
+
+	if let Some(first) = revisions.first() {
+		println!(
+			"{} {} {:?} {}",
+			"●".bright_blue(),
+			&first.revision_id.to_string()[..8],
+			first.operation,
+			first
+				.recorded_at
+				.format("%Y-%m-%d %H:%M:%S")
+				.to_string()
+				.dimmed()
+		);
+	}
+	for pair in revisions.windows(2) {
+		print_revision_diff(&RevisionDiff::between(&pair[0], &pair[1]));
+	}
+	Ok(())
+}
+
+fn print_revision_diff(diff: &RevisionDiff) {
+	println!(
+		"\n{} {} → {} {:?} {}",
+		"●".bright_blue(),
+		&diff.from_revision.to_string()[..8],
+		&diff.to_revision.to_string()[..8],
+		diff.operation,
+		diff.to_recorded_at
+			.format("%Y-%m-%d %H:%M:%S")
+			.to_string()
+			.dimmed()
+	);
+	if diff.is_empty() {
+		println!("  {}", "(no changes)".dimmed());
+		return;
+	}
+	if diff.expression_changed() {
+		let rendered: String = diff
+			.expression
+			.iter()
+			.map(|change| match change {
+				TextChange::Equal(text) => text.normal().to_string(),
+				TextChange::Insert(text) => text.green().underline().to_string(),
+				TextChange::Delete(text) => text.red().strikethrough().to_string(),
+			})
+			.collect();
+		println!("  {} {}", "Expression:".bold(), rendered);
+	}
+	if !diff.context.is_empty() {
+		println!("  {}", "Context:".bold());
+		for operation in &diff.context {
+			match operation {
+				JsonPatchOperation::Add { path, value } => {
+					println!(
+						"    {}",
+						format!("+ {} {}", display_path(path), value).green()
+					)
+				}
+				JsonPatchOperation::Remove { path } => {
+					println!("    {}", format!("- {}", display_path(path)).red())
+				}
+				JsonPatchOperation::Replace { path, value } => {
+					println!(
+						"    {}",
+						format!("~ {} {}", display_path(path), value).yellow()
+					)
+				}
+			}
+		}
+	}
+	for id in &diff.relations_added {
+		println!("  {} {}", "Relation:".bold(), format!("+ {id}").green());
+	}
+	for id in &diff.relations_removed {
+		println!("  {} {}", "Relation:".bold(), format!("- {id}").red());
+	}
+	match diff.meaning_drift {
+		Some(drift) if drift.abs() > f32::EPSILON => {
+			println!("  {} {:.4}", "Meaning drift:".bold(), drift)
+		}
+		Some(_) => {}
+		None => println!("  {} dimensions changed", "Meaning drift:".bold()),
+	}
+}
+
+fn display_path(path: &str) -> &str {
+	if path.is_empty() {
+		"/"
+	} else {
+		path
+	}
+}
+
+fn cmd_compact(
+	path: PathBuf,
+	keep_last: Option<usize>,
+	keep_days: Option<u32>,
+	drop_deletes: bool,
+	delta: bool,
+	save: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let mut db = open_db(&path)?;
+	let explicit = keep_last.is_some() || keep_days.is_some() || drop_deletes || delta;
+	let policy = if explicit {
+		RetentionPolicy {
+			keep_last,
+			keep_for_days: keep_days,
+			keep_deletes: !drop_deletes,
+			delta_encoding: delta,
+		}
+	} else if save {
+		return Err("--save requires at least one retention option".into());
+	} else {
+		db.retention_policy()?
+	};
+	if save {
+		db.set_retention_policy(&policy)?;
+	}
+	let report = db.compact_revisions_with(&policy)?;
+	println!(
+		"{} Compacted {} revisions: {} removed, {} stored as deltas",
+		"✓".green().bold(),
+		report.revisions_examined,
+		report.revisions_removed,
+		report.delta_revisions
+	);
+	println!(
+		"  Snapshot bytes: {} → {}",
+		report.bytes_before, report.bytes_after
+	);
+	Ok(())
+}
+
+fn cmd_trash(command: TrashCommand) -> Result<(), Box<dyn std::error::Error>> {
+	match command {
+		TrashCommand::List { path } => {
+			let db = open_db(&path)?;
+			let deleted = db.list_deleted()?;
+			if deleted.is_empty() {
+				println!("{}", "Trash is empty.".yellow());
+			}
+			for item in deleted {
+				println!(
+					"{}  {}  {}",
+					item.entry.id.to_string().dimmed(),
+					item.deleted_at.format("%Y-%m-%d %H:%M"),
+					item.entry.expression
+				);
+			}
+		}
+		TrashCommand::Restore { path, id } => {
+			let mut db = open_db(&path)?;
+			let id = match uuid::Uuid::parse_str(&id) {
+				Ok(id) => id,
+				Err(_) => {
+					let matches: Vec<uuid::Uuid> = db
+						.list_deleted()?
+						.into_iter()
+						.map(|item| item.entry.id)
+						.filter(|candidate| candidate.to_string().starts_with(&id))
+						.collect();
+					match matches.as_slice() {
+						[id] => *id,
+						[] => return Err(format!("No trashed entry found with ID: {id}").into()),
+						_ => return Err(format!("Multiple trashed entries match {id}").into()),
+					}
+				}
+			};
+			let entry = db.restore_deleted(id)?;
+			println!("{} Restored entry {}", "✓".green().bold(), entry.id);
+		}
+		TrashCommand::Empty { path, all } => {
+			let mut db = open_db(&path)?;
+			let removed = if all {
+				let ids: Vec<uuid::Uuid> = db
+					.list_deleted()?
+					.into_iter()
+					.map(|item| item.entry.id)
+					.collect();
+				db.remove_from_trash(&ids)?;
+				ids
+			} else {
+				db.empty_trash()?
+			};
+			println!(
+				"{} Removed {} entries from the trash",
+				"✓".green().bold(),
+				removed.len()
+			);
+		}
+		TrashCommand::Policy {
+			path,
+			soft_delete,
+			grace_days,
+		} => {
+			let mut db = open_db(&path)?;
+			let mut policy = db.trash_policy()?;
+			if soft_delete.is_some() || grace_days.is_some() {
+				policy = TrashPolicy {
+					soft_delete: soft_delete.unwrap_or(policy.soft_delete),
+					grace_period_days: grace_days.unwrap_or(policy.grace_period_days),
+				};
+				db.set_trash_policy(&policy)?;
+			}
+			println!(
+				"Soft delete: {}, grace period: {} days",
+				if policy.soft_delete { "on" } else { "off" },
+				policy.grace_period_days
+			);
+		}
+	}
+	Ok(())
+}
+
+fn cmd_sweep(path: PathBuf, batch_size: usize) -> Result<(), Box<dyn std::error::Error>> {
+	let mut db = open_db(&path)?;
+	let swept = db.sweep_expired_in_batches(batch_size)?;
+	println!(
+		"{} Swept {} expired entries",
+		"✓".green().bold(),
+		swept.len()
+	);
+	Ok(())
+}
+
+fn cmd_dedup(
+	path: PathBuf,
+	threshold: f32,
+	dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let mut db = open_db(&path)?;
+	let clusters = db.find_near_duplicates(threshold)?;
+	if clusters.is_empty() {
+		println!("{}", "No near-duplicate entries found".yellow());
+		return Ok(());
+	}
+
+	for (index, cluster) in clusters.iter().enumerate() {
+		println!(
+			"{} {} ({}, similarity ≥ {:.3})",
+			"Cluster".cyan().bold(),
+			index + 1,
+			cluster.collection,
+			cluster.min_similarity
+		);
+		for id in &cluster.ids {
+			let entry = db.get(*id)?;
+			println!(
+				"  {} {}",
+				&id.to_string()[..8],
+				truncate(&entry.expression, 60)
+			);
+		}
+		if !dry_run {
+			let survivor = db.merge(&cluster.ids, MergeStrategy::KeepOldest)?;
+			println!(
+				"  {} Merged into {}",
+				"✓".green().bold(),
+				&survivor.id.to_string()[..8]
+			);
+		}
+	}
+	if dry_run {
+		println!(
+			"{} near-duplicate clusters; run without --dry-run to merge them",
+			clusters.len()
+		);
+	} else {
+		println!(
+			"{} Merged {} near-duplicate clusters",
+			"✓".green().bold(),
+			clusters.len()
+		);
+	}
+	Ok(())
+}
+
+#[derive(Tabled)]
+struct EvalRow {
+	#[tabled(rename = "Case")]
+	case: String,
+	#[tabled(rename = "Recall")]
+	recall: String,
+	#[tabled(rename = "Precision")]
+	precision: String,
+	#[tabled(rename = "MRR")]
+	reciprocal_rank: String,
+	#[tabled(rename = "nDCG")]
+	ndcg: String,
+}
+
+impl EvalRow {
+	fn new(case: &str, metrics: &EvalMetrics) -> Self {
+		Self {
+			case: truncate(case, 40),
+			recall: format!("{:.3}", metrics.recall),
+			precision: format!("{:.3}", metrics.precision),
+			reciprocal_rank: format!("{:.3}", metrics.reciprocal_rank),
+			ndcg: format!("{:.3}", metrics.ndcg),
+		}
+	}
+}
+
+#[derive(Tabled)]
+struct EvalComparisonRow {
+	#[tabled(rename = "Metric")]
+	metric: &'static str,
+	#[tabled(rename = "Baseline")]
+	baseline: String,
+	#[tabled(rename = "Candidate")]
+	candidate: String,
+	#[tabled(rename = "Change")]
+	change: String,
+}
+
+fn read_eval_config(path: Option<PathBuf>) -> Result<EvalConfig, Box<dyn std::error::Error>> {
+	match path {
+		Some(path) => Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?),
+		None => Ok(EvalConfig::default()),
+	}
+}
+
+fn cmd_eval(
+	path: PathBuf,
+	qrels: PathBuf,
+	k: usize,
+	config: Option<PathBuf>,
+	compare: Option<PathBuf>,
+	format: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let db = open_db(&path)?;
+	let cases: Vec<EvalCase> = serde_json::from_str(&std::fs::read_to_string(&qrels)?)?;
+	let baseline = read_eval_config(config)?;
+
+	let Some(candidate) = compare else {
+		let report = db.evaluate(&cases, k, &baseline)?;
+		if format == "json" {
+			println!("{}", serde_json::to_string_pretty(&report)?);
+			return Ok(());
+		}
+		println!("{} {} cases at k = {}", "Evaluated".cyan(), cases.len(), k);
+		println!();
+		let mut rows: Vec<EvalRow> = report
+			.cases
+			.iter()
+			.map(|case| EvalRow::new(&case.name, &case.metrics))
+			.collect();
+		rows.push(EvalRow::new("Mean", &report.mean));
+		println!("{}", Table::new(rows).with(Style::rounded()));
+		return Ok(());
+	};
+
+	let candidate = read_eval_config(Some(candidate))?;
+	let comparison = db.compare_configs(&cases, k, &baseline, &candidate)?;
+	if format == "json" {
+		println!("{}", serde_json::to_string_pretty(&comparison)?);
+		return Ok(());
+	}
+	println!(
+		"{} {} cases at k = {}, baseline against candidate",
+		"Compared".cyan(),
+		cases.len(),
+		k
+	);
+	println!();
+	let (base, cand, delta) = (
+		&comparison.baseline.mean,
+		&comparison.candidate.mean,
+		&comparison.delta,
+	);
+	let rows = [
+		("Recall", base.recall, cand.recall, delta.recall),
+		("Precision", base.precision, cand.precision, delta.precision),
+		(
+			"MRR",
+			base.reciprocal_rank,
+			cand.reciprocal_rank,
+			delta.reciprocal_rank,
+		),
+		("nDCG", base.ndcg, cand.ndcg, delta.ndcg),
+	]
+	.map(|(metric, baseline, candidate, change)| EvalComparisonRow {
+		metric,
+		baseline: format!("{:.3}", baseline),
+		candidate: format!("{:.3}", candidate),
+		change: format!("{:+.3}", change),
+	});
+	println!("{}", Table::new(rows).with(Style::rounded()));
+	println!();
+	println!("{}", "nDCG by case".cyan().bold());
+	for (before, after) in comparison
+		.baseline
+		.cases
+		.iter()
+		.zip(&comparison.candidate.cases)
+	{
+		println!(
+			"  {} {:.3} → {:.3} ({:+.3})",
+			truncate(&before.name, 40),
+			before.metrics.ndcg,
+			after.metrics.ndcg,
+			after.metrics.ndcg - before.metrics.ndcg
+		);
+	}
+	Ok(())
+}
+
+fn cmd_revert(
+	path: PathBuf,
+	id: String,
+	revision: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let mut db = open_db(&path)?;
+	let id = match uuid::Uuid::parse_str(&id) {
+		Ok(id) => id,
+		Err(_) => find_entry_by_partial_id(&db, &id)?.id,
+	};
+	let entry = match revision {
+		Some(revision) => {
+			let matches: Vec<_> = db
+				.revisions(id)?
+				.into_iter()
+				.filter(|candidate| candidate.revision_id.to_string().starts_with(&revision))
+				.collect();
+			let revision_id = match matches.as_slice() {
+				[only] => only.revision_id,
+				[] => return Err(format!("No revision found matching '{}'", revision).into()),
+				_ => {
+					return Err(format!(
+						"{} revisions match '{}'; use a longer prefix",
+						matches.len(),
+						revision
+					)
+					.into())
+				}
+			};
+			db.revert(id, revision_id)?
+		}
+		None => db.undelete(id)?,
+	};
+	println!(
+		"{} Restored {} - {}",
+		"✓".green().bold(),
+		entry.id,
+		truncate(&entry.expression, 60)
+	);
+	Ok(())
+}
+
+fn cmd_relate(
+	path: PathBuf,
+	from: String,
+	to: String,
+	relate: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let mut db = open_db(&path)?;
+	let from = find_entry_by_partial_id(&db, &from)?.id;
+	let to = find_entry_by_partial_id(&db, &to)?.id;
+	if relate {
+		db.relate(from, to)?;
+		println!("{} Related {} -> {}", "✓".green().bold(), from, to);
+	} else {
+		db.unrelate(from, to)?;
+		println!("{} Unrelated {} -> {}", "✓".green().bold(), from, to);
+	}
 	Ok(())
 }
 
@@ -350,16 +1174,23 @@ fn cmd_add(
 	meaning: Vec<f32>,
 	context: Option<String>,
 	relations: Vec<uuid::Uuid>,
+	collection: String,
+	expires_at: Option<chrono::DateTime<chrono::Utc>>,
 ) -> Result<(), Box<dyn std::error::Error>> {
 	let mut db = open_db(&path)?;
 	let context = context
 		.map(|value| serde_json::from_str(&value))
 		.transpose()?
 		.unwrap_or(serde_json::Value::Null);
-	let mut entry = Entry::new(meaning, expression).with_context(context);
+	let mut entry = Entry::new(meaning, expression)
+		.with_context(context)
+		.in_collection(collection);
 	for relation in relations {
 		entry = entry.add_relation(relation);
 	}
+	if let Some(expires_at) = expires_at {
+		entry = entry.with_expiry(expires_at);
+	}
 	db.insert(&entry)?;
 	println!("{} Added entry {}", "✓".green().bold(), entry.id);
 	Ok(())
@@ -379,9 +1210,26 @@ fn cmd_init(path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
 	Ok(())
 }
 
-fn cmd_stats(path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+fn cmd_stats(path: PathBuf, top: usize, format: String) -> Result<(), Box<dyn std::error::Error>> {
 	let db = open_db(&path)?;
 	let count = db.count()?;
+	let graph = db.graph_stats(top)?;
+
+	if format == "json" {
+		let dimensions = db
+			.query(&Query::new().with_limit(1).without_access_tracking())?
+			.first()
+			.map(|result| result.entry.meaning.len());
+		let stats = serde_json::json!({
+			"path": path,
+			"backend": db.backend_name(),
+			"entries": count,
+			"vector_dimensions": dimensions,
+			"graph": graph,
+		});
+		println!("{}", serde_json::to_string_pretty(&stats)?);
+		return Ok(());
+	}
 
 	println!("{}", "Database Statistics".cyan().bold());
 	println!("{}", "─".repeat(40));
@@ -391,7 +1239,7 @@ fn cmd_stats(path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
 
 	if count > 0 {
 		// Get sample to show vector dimensions
-		let results = db.query(&Query::new().with_limit(1))?;
+		let results = db.query(&Query::new().with_limit(1).without_access_tracking())?;
 		if let Some(first) = results.first() {
 			println!(
 				"  {} {}",
@@ -401,22 +1249,110 @@ fn cmd_stats(path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
 		}
 	}
 
+	println!("{}", "─".repeat(40));
+	println!("{}", "Relation Graph".cyan().bold());
+	println!("  {} {}", "Relations:".bold(), graph.relation_count);
+	println!(
+		"  {} {} (largest {})",
+		"Components:".bold(),
+		graph.component_count,
+		graph.largest_component_size
+	);
+	println!(
+		"  {} {}",
+		"Isolated entries:".bold(),
+		graph.isolated_entries.len()
+	);
+	println!("  {} {}", "Sink entries:".bold(), graph.sink_entries.len());
+	if !graph.dangling_relations.is_empty() {
+		println!(
+			"  {} {}",
+			"Dangling relations:".bold(),
+			graph.dangling_relations.len().to_string().yellow()
+		);
+	}
+	if !graph.self_related_entries.is_empty() {
+		println!(
+			"  {} {}",
+			"Self-relations:".bold(),
+			graph.self_related_entries.len().to_string().yellow()
+		);
+	}
+	if graph.is_acyclic() {
+		println!("  {} none", "Cycles:".bold());
+	} else {
+		println!(
+			"  {} {}",
+			"Cycles:".bold(),
+			graph.cycles.len().to_string().yellow()
+		);
+	}
+	if !graph.most_connected.is_empty() {
+		println!("  {}", "Most connected:".bold());
+		for degree in &graph.most_connected {
+			println!(
+				"    {} in {} / out {}",
+				&degree.entry_id.to_string()[..8],
+				degree.in_degree,
+				degree.out_degree
+			);
+		}
+	}
+
 	println!("{}", "─".repeat(40));
 	Ok(())
 }
 
+fn cmd_cold(path: PathBuf, format: String) -> Result<(), Box<dyn std::error::Error>> {
+	let db = open_db(&path)?;
+	let tracking = db.access_policy()?.track;
+	let cold = db.never_accessed()?;
+
+	if format == "json" {
+		let report = serde_json::json!({
+			"tracking": tracking,
+			"never_accessed": cold,
+		});
+		println!("{}", serde_json::to_string_pretty(&report)?);
+		return Ok(());
+	}
+
+	if !tracking {
+		println!(
+			"{} access tracking is off, so reads are not being recorded",
+			"Note:".yellow()
+		);
+	}
+	if cold.is_empty() {
+		println!("{}", "Every entry has been accessed.".green());
+		return Ok(());
+	}
+	println!(
+		"{} {} never-accessed entries, oldest first",
+		"Showing".cyan(),
+		cold.len()
+	);
+	println!();
+	let rows: Vec<EntryRow> = cold.iter().map(EntryRow::from).collect();
+	let table = Table::new(rows).with(Style::rounded()).to_string();
+	println!("{}", table);
+	Ok(())
+}
+
 fn cmd_search(
 	path: PathBuf,
 	query: String,
 	limit: usize,
 	format: String,
+	collections: Vec<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
 	let db = open_db(&path)?;
 
 	let results = db.query(
 		&Query::new()
 			.with_expression(ExpressionFilter::Contains(query.clone()))
-			.with_limit(limit),
+			.with_limit(limit)
+			.in_collections(collections),
 	)?;
 
 	if results.is_empty() {
@@ -459,11 +1395,25 @@ fn cmd_list(
 	limit: usize,
 	offset: usize,
 	format: String,
+	collections: Vec<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
 	let db = open_db(&path)?;
-	let total = db.count()?;
+	let total = if collections.is_empty() {
+		db.count()?
+	} else {
+		db.collections()?
+			.iter()
+			.filter(|info| collections.contains(&info.name))
+			.map(|info| info.entries)
+			.sum()
+	};
 
-	let results = db.query(&Query::new().with_offset(offset).with_limit(limit))?;
+	let results = db.query(
+		&Query::new()
+			.with_offset(offset)
+			.with_limit(limit)
+			.in_collections(collections),
+	)?;
 
 	println!(
 		"{} {} of {} entries",
@@ -497,16 +1447,36 @@ fn cmd_list(
 	Ok(())
 }
 
-fn cmd_show(path: PathBuf, id: String) -> Result<(), Box<dyn std::error::Error>> {
+fn cmd_show(
+	path: PathBuf,
+	id: Option<String>,
+	key: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
 	let db = open_db(&path)?;
 
-	// Try to find entry by partial ID match
-	let entry = find_entry_by_partial_id(&db, &id)?;
+	let entry = match (id, key) {
+		(_, Some(key)) => db
+			.get_by_external_id(&key)?
+			.ok_or_else(|| format!("No entry found with external ID '{}'", key))?,
+		// Try to find entry by partial ID match
+		(Some(id), None) => find_entry_by_partial_id(&db, &id)?,
+		(None, None) => return Err("Pass an entry ID or --key".into()),
+	};
 
 	println!("{}", "Entry Details".cyan().bold());
 	println!("{}", "─".repeat(60));
 	println!("  {} {}", "ID:".bold(), entry.id);
 	println!("  {} {}", "Expression:".bold(), entry.expression);
+	println!("  {} {}", "Collection:".bold(), entry.collection);
+	if let Some(external_id) = &entry.external_id {
+		println!("  {} {}", "External ID:".bold(), external_id);
+	}
+	if let Some(importance) = entry.importance {
+		println!("  {} {:.2}", "Importance:".bold(), importance);
+	}
+	if let Some(expires_at) = entry.expires_at {
+		println!("  {} {}", "Expires:".bold(), expires_at.to_rfc3339());
+	}
 	println!("  {} {} dimensions", "Meaning:".bold(), entry.meaning.len());
 	println!("  {} {}", "Context:".bold(), entry.context);
 	println!("  {} {}", "Created:".bold(), entry.created_at);
@@ -539,7 +1509,36 @@ fn cmd_show(path: PathBuf, id: String) -> Result<(), Box<dyn std::error::Error>>
 	Ok(())
 }
 
-fn cmd_export(path: PathBuf, output: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+fn cmd_collections(path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+	let db = open_db(&path)?;
+	let collections = db.collections()?;
+	if collections.is_empty() {
+		println!("{}", "No collections found.".yellow());
+		return Ok(());
+	}
+	for info in collections {
+		let dimensions = info
+			.vector_dimension
+			.map_or_else(|| "-".to_string(), |dimension| format!("{dimension}d"));
+		let model = info
+			.embedding_profile
+			.map_or_else(|| "no profile".to_string(), |profile| profile.model);
+		println!(
+			"{}  {} entries  {}  {}",
+			info.name.bold(),
+			info.entries,
+			dimensions,
+			model
+		);
+	}
+	Ok(())
+}
+
+fn cmd_export(
+	path: PathBuf,
+	output: Option<PathBuf>,
+	collections: Vec<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
 	let db = open_db(&path)?;
 	let count = db.count()?;
 
@@ -550,7 +1549,11 @@ fn cmd_export(path: PathBuf, output: Option<PathBuf>) -> Result<(), Box<dyn std:
 			.progress_chars("#>-"),
 	);
 
-	let results = db.query(&Query::new())?;
+	let results = db.query(
+		&Query::new()
+			.in_collections(collections)
+			.without_access_tracking(),
+	)?;
 	let entries: Vec<&Entry> = results.iter().map(|r| &r.entry).collect();
 
 	pb.finish_with_message("done");
@@ -663,17 +1666,89 @@ fn cmd_delete(path: PathBuf, id: String, force: bool) -> Result<(), Box<dyn std:
 	}
 
 	db.delete(entry.id)?;
-	println!("{} Deleted entry {}", "✓".green().bold(), entry.id);
+	if db.trash_policy()?.soft_delete {
+		println!(
+			"{} Moved entry {} to the trash",
+			"✓".green().bold(),
+			entry.id
+		);
+	} else {
+		println!("{} Deleted entry {}", "✓".green().bold(), entry.id);
+	}
+
+	Ok(())
+}
+
+fn cmd_patch(path: PathBuf, id: String, patch: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+	let patch: EntryPatch = serde_json::from_str(&std::fs::read_to_string(&patch)?)?;
+	if patch.is_empty() {
+		return Err("Patch file sets no fields".into());
+	}
+	let mut db = open_db(&path)?;
+	let id = find_entry_by_partial_id(&db, &id)?.id;
+	let entry = db.patch(id, &patch)?;
+	println!("{} Patched entry {}", "✓".green().bold(), entry.id);
+	println!("  Expression: {}", entry.expression);
+	println!("  Context: {}", entry.context);
 
 	Ok(())
 }
 
-fn cmd_recent(path: PathBuf, count: usize) -> Result<(), Box<dyn std::error::Error>> {
+fn cmd_purge(
+	path: PathBuf,
+	id: String,
+	force: bool,
+	vacuum: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let mut db = open_db(&path)?;
+	let id = match uuid::Uuid::parse_str(&id) {
+		Ok(id) => id,
+		Err(_) => find_entry_by_partial_id(&db, &id)?.id,
+	};
+
+	if !force {
+		println!("{}", "Entry to purge:".yellow().bold());
+		println!("  ID: {}", id);
+		if let Ok(entry) = db.get(id) {
+			println!("  Expression: {}", entry.expression);
+		}
+		println!(
+			"  {}",
+			"The entry and its entire revision history will be erased.".red()
+		);
+		println!();
+
+		let confirm: String = Input::with_theme(&ColorfulTheme::default())
+			.with_prompt("Type 'purge' to confirm")
+			.interact_text()?;
+
+		if confirm != "purge" {
+			println!("{}", "Cancelled.".yellow());
+			return Ok(());
+		}
+	}
+
+	db.purge(id)?;
+	println!("{} Purged entry {}", "✓".green().bold(), id);
+	if vacuum {
+		db.vacuum()?;
+		println!("{} Vacuumed database", "✓".green().bold());
+	}
+
+	Ok(())
+}
+
+fn cmd_recent(
+	path: PathBuf,
+	count: usize,
+	collections: Vec<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
 	let db = open_db(&path)?;
 	let results = db.query(
 		&Query::new()
 			.with_order(QueryOrder::CreatedAtDesc)
-			.with_limit(count),
+			.with_limit(count)
+			.in_collections(collections),
 	)?;
 
 	if results.is_empty() {
@@ -827,7 +1902,7 @@ fn find_entry_by_partial_id(
 	}
 
 	// Try partial match
-	let results = db.query(&Query::new())?;
+	let results = db.query(&Query::new().without_access_tracking())?;
 	let matches: Vec<_> = results
 		.iter()
 		.filter(|r| r.entry.id.to_string().starts_with(partial_id))
@@ -835,7 +1910,7 @@ fn find_entry_by_partial_id(
 
 	match matches.len() {
 		0 => Err(format!("No entry found matching '{}'", partial_id).into()),
-		1 => Ok(matches[0].entry.clone()),
+		1 => Ok(db.get(matches[0].entry.id)?),
 		n => {
 			println!(
 				"{} {} entries match '{}':",
@@ -0,0 +1,280 @@
+use crate::storage::{StorageError, StorageResult};
+use crate::types::Entry;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use uuid::Uuid;
+
+/// Context key under which chunk entries record their document and position
+pub const CHUNK_CONTEXT_KEY: &str = "chunk";
+
+/// Unit a [`Chunker`] counts when sizing chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkUnit {
+	/// Whitespace-separated tokens
+	Tokens,
+	/// Sentences ending in `.`, `!`, or `?`
+	Sentences,
+	/// Paragraphs separated by blank lines
+	Paragraphs,
+}
+
+/// Splits long text into overlapping chunks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chunker {
+	/// Unit counted by `size` and `overlap`
+	pub unit: ChunkUnit,
+	/// Units per chunk
+	pub size: usize,
+	/// Units each chunk repeats from the end of the previous one
+	pub overlap: usize,
+}
+
+/// A document entry and its chunk entries, ready for `insert_batch`
+#[derive(Debug, Clone)]
+pub struct ChunkedDocument {
+	/// Parent entry holding the full text
+	pub document: Entry,
+	/// Chunk entries in document order
+	pub chunks: Vec<Entry>,
+}
+
+impl Chunker {
+	/// Chunk by `size` units without overlap
+	pub fn new(unit: ChunkUnit, size: usize) -> Self {
+		Self {
+			unit,
+			size,
+			overlap: 0,
+		}
+	}
+
+	/// Repeat `overlap` units from the end of each chunk at the start of the next
+	pub fn with_overlap(mut self, overlap: usize) -> Self {
+		self.overlap = overlap;
+		self
+	}
+
+	/// Split `text` into chunks
+	///
+	/// Each chunk is a slice of `text`, from the start of its first unit to
+	/// the end of its last, so spacing and line breaks inside it are kept.
+	/// Fails when `size` is zero or `overlap` is not smaller than `size`.
+	pub fn split(&self, text: &str) -> StorageResult<Vec<String>> {
+		Ok(self
+			.spans(text)?
+			.into_iter()
+			.map(|span| text[span].to_string())
+			.collect())
+	}
+
+	/// Byte ranges of the chunks [`split`](Self::split) returns
+	pub fn spans(&self, text: &str) -> StorageResult<Vec<Range<usize>>> {
+		if self.size == 0 || self.overlap >= self.size {
+			return Err(StorageError::InvalidArgument(
+				"Chunk size must be positive and larger than the overlap".to_string(),
+			));
+		}
+		let units = match self.unit {
+			ChunkUnit::Tokens => text.split_whitespace().collect(),
+			ChunkUnit::Sentences => split_sentences(text),
+			ChunkUnit::Paragraphs => split_paragraphs(text),
+		};
+		// Units are slices of `text`, so their addresses give their offsets.
+		let offset = |unit: &str| unit.as_ptr() as usize - text.as_ptr() as usize;
+		let step = self.size - self.overlap;
+		let mut spans = Vec::new();
+		let mut start = 0;
+		while start < units.len() {
+			let end = (start + self.size).min(units.len());
+			let last = units[end - 1];
+			spans.push(offset(units[start])..offset(last) + last.len());
+			if end == units.len() {
+				break;
+			}
+			start += step;
+		}
+		Ok(spans)
+	}
+
+	/// Chunk `document`'s expression into entries related to the document
+	///
+	/// `embed` supplies each chunk's meaning. Every chunk relates to the
+	/// document (part of) and to the chunk after it (next). Under
+	/// [`CHUNK_CONTEXT_KEY`] it records the document ID, the next chunk's ID,
+	/// its position, and its byte range in the document's expression, so the
+	/// kind of each relation does not depend on their order. Chunks share the
+	/// document's collection, tenant, and expiry.
+	pub fn chunk_document(
+		&self,
+		document: Entry,
+		mut embed: impl FnMut(&str) -> Vec<f32>,
+	) -> StorageResult<ChunkedDocument> {
+		let spans = self.spans(&document.expression)?;
+		let ids: Vec<Uuid> = spans.iter().map(|_| Uuid::new_v4()).collect();
+		let chunks = spans
+			.iter()
+			.enumerate()
+			.map(|(index, span)| {
+				let text = &document.expression[span.clone()];
+				let mut chunk = Entry::new(embed(text), text.to_string())
+					.in_collection(document.collection.clone())
+					.for_tenant(document.tenant.clone())
+					.with_context(serde_json::json!({
+						CHUNK_CONTEXT_KEY: {
+							"document": document.id,
+							"next": ids.get(index + 1),
+							"index": index,
+							"start": span.start,
+							"end": span.end,
+						}
+					}));
+				chunk.id = ids[index];
+				chunk.expires_at = document.expires_at;
+				chunk.relations.push(document.id);
+				if let Some(next) = ids.get(index + 1) {
+					chunk.relations.push(*next);
+				}
+				chunk
+			})
+			.collect();
+		Ok(ChunkedDocument { document, chunks })
+	}
+}
+
+impl ChunkedDocument {
+	/// The document followed by its chunks
+	pub fn entries(&self) -> Vec<Entry> {
+		std::iter::once(&self.document)
+			.chain(&self.chunks)
+			.cloned()
+			.collect()
+	}
+}
+
+/// Document an entry is a chunk of, read from its context
+pub fn chunk_document_id(entry: &Entry) -> Option<Uuid> {
+	chunk_context_id(entry, "document")
+}
+
+/// Chunk that follows an entry in its document, read from its context
+pub fn chunk_next_id(entry: &Entry) -> Option<Uuid> {
+	chunk_context_id(entry, "next")
+}
+
+fn chunk_context_id(entry: &Entry, field: &str) -> Option<Uuid> {
+	entry
+		.context
+		.get(CHUNK_CONTEXT_KEY)?
+		.get(field)?
+		.as_str()
+		.and_then(|id| Uuid::parse_str(id).ok())
+}
+
+fn split_sentences(text: &str) -> Vec<&str> {
+	let mut sentences = Vec::new();
+	let mut start = 0;
+	let mut characters = text.char_indices().peekable();
+	while let Some((index, character)) = characters.next() {
+		let ends_sentence = matches!(character, '.' | '!' | '?')
+			&& characters
+				.peek()
+				.map_or(true, |(_, next)| next.is_whitespace());
+		if ends_sentence {
+			let end = index + character.len_utf8();
+			sentences.push(text[start..end].trim());
+			start = end;
+		}
+	}
+	sentences.push(text[start..].trim());
+	sentences.retain(|sentence| !sentence.is_empty());
+	sentences
+}
+
+fn split_paragraphs(text: &str) -> Vec<&str> {
+	let mut paragraphs = Vec::new();
+	let mut start = 0;
+	let mut offset = 0;
+	for line in text.split_inclusive('\n') {
+		if line.trim().is_empty() {
+			paragraphs.push(text[start..offset].trim());
+			start = offset + line.len();
+		}
+		offset += line.len();
+	}
+	paragraphs.push(text[start..].trim());
+	paragraphs.retain(|paragraph| !paragraph.is_empty());
+	paragraphs
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_split_by_tokens_with_overlap() {
+		let chunker = Chunker::new(ChunkUnit::Tokens, 3).with_overlap(1);
+
+		let chunks = chunker.split("one two  three four\nfive six").unwrap();
+
+		assert_eq!(
+			chunks,
+			vec!["one two  three", "three four\nfive", "five six"]
+		);
+		assert_eq!(
+			chunker.spans("one two  three four\nfive six").unwrap()[1],
+			9..24
+		);
+		assert!(Chunker::new(ChunkUnit::Tokens, 2)
+			.with_overlap(2)
+			.split("text")
+			.is_err());
+		assert!(chunker.split("   ").unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_split_by_sentences_and_paragraphs() {
+		let sentences = Chunker::new(ChunkUnit::Sentences, 2)
+			.split("Version 1.5 shipped. It fixed login! Did it help? Yes")
+			.unwrap();
+		let paragraphs = Chunker::new(ChunkUnit::Paragraphs, 1)
+			.split("First line\nstill first\n\n  \nSecond\n")
+			.unwrap();
+
+		assert_eq!(
+			sentences,
+			vec!["Version 1.5 shipped. It fixed login!", "Did it help? Yes"]
+		);
+		assert_eq!(paragraphs, vec!["First line\nstill first", "Second"]);
+		let spaced = Chunker::new(ChunkUnit::Sentences, 2)
+			.split("One.\n  Two!  Three?")
+			.unwrap();
+		assert_eq!(spaced, vec!["One.\n  Two!", "Three?"]);
+	}
+
+	#[test]
+	fn test_chunk_document_wires_part_of_and_next_relations() {
+		let document = Entry::new(vec![0.5], "Alpha beta gamma delta".to_string())
+			.in_collection("docs")
+			.for_tenant("acme");
+		let chunked = Chunker::new(ChunkUnit::Tokens, 2)
+			.chunk_document(document.clone(), |text| vec![text.len() as f32])
+			.unwrap();
+
+		assert_eq!(chunked.chunks.len(), 2);
+		let (first, second) = (&chunked.chunks[0], &chunked.chunks[1]);
+		assert_eq!(first.expression, "Alpha beta");
+		assert_eq!(first.meaning, vec![10.0]);
+		assert_eq!(first.relations, vec![document.id, second.id]);
+		assert_eq!(second.relations, vec![document.id]);
+		assert_eq!(second.context["chunk"]["index"], 1);
+		assert_eq!(second.context["chunk"]["start"], 11);
+		assert_eq!(chunk_document_id(second), Some(document.id));
+		assert_eq!(chunk_next_id(first), Some(second.id));
+		assert_eq!(chunk_next_id(second), None);
+		assert_eq!(second.collection, "docs");
+		assert_eq!(second.tenant, "acme");
+		assert_eq!(chunked.entries()[0].id, document.id);
+		assert_eq!(chunk_document_id(&document), None);
+	}
+}
@@ -0,0 +1,280 @@
+use crate::types::{cosine_similarity, Entry};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// What an insert does when an entry with the same content already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateAction {
+	/// Keep the existing entry and drop the new one
+	#[default]
+	Skip,
+	/// Apply the new entry's context to the existing entry as an RFC 7396 merge patch
+	MergeContext,
+	/// Fail with `StorageError::Duplicate`, which carries the existing entry's ID
+	Reject,
+}
+
+/// Deduplication of inserts by a hash of normalized content
+///
+/// Two entries are duplicates when they share a tenant and collection and
+/// their expressions match after trimming, collapsing whitespace, and
+/// lowercasing. With `include_context`, their contexts must match too.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DedupPolicy {
+	/// Hash entry content and enforce its uniqueness
+	pub enabled: bool,
+	/// Include the context in the hash
+	pub include_context: bool,
+	/// What inserting a duplicate does
+	pub on_duplicate: DuplicateAction,
+}
+
+impl DedupPolicy {
+	/// Hex-encoded hash of `entry`'s content under this policy
+	pub fn content_hash(&self, entry: &Entry) -> String {
+		let mut hash = Fnv128::new();
+		hash.write(normalize_expression(&entry.expression).as_bytes());
+		if self.include_context {
+			// serde_json sorts object keys, so equal contexts serialize identically.
+			hash.write(&[0]);
+			hash.write(entry.context.to_string().as_bytes());
+		}
+		hash.finish_hex()
+	}
+}
+
+/// Trim, collapse whitespace runs to one space, and lowercase an expression
+pub fn normalize_expression(expression: &str) -> String {
+	expression
+		.split_whitespace()
+		.collect::<Vec<_>>()
+		.join(" ")
+		.to_lowercase()
+}
+
+/// Which entry survives a merge
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+	/// Keep the first ID passed to the merge
+	#[default]
+	KeepFirst,
+	/// Keep the entry created first
+	KeepOldest,
+	/// Keep the entry created last
+	KeepNewest,
+}
+
+impl MergeStrategy {
+	/// Index of the surviving entry in `entries`, which must not be empty
+	pub fn survivor(&self, entries: &[Entry]) -> usize {
+		let by_creation = |(_, entry): &(usize, &Entry)| (entry.created_at, entry.id);
+		let indexed = entries.iter().enumerate();
+		match self {
+			MergeStrategy::KeepFirst => 0,
+			MergeStrategy::KeepOldest => indexed.min_by_key(by_creation).map_or(0, |(i, _)| i),
+			MergeStrategy::KeepNewest => indexed.max_by_key(by_creation).map_or(0, |(i, _)| i),
+		}
+	}
+}
+
+/// Entries whose meanings are near duplicates of each other
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NearDuplicateCluster {
+	/// Collection holding the entries
+	pub collection: String,
+	/// Entries in the cluster, oldest first
+	pub ids: Vec<Uuid>,
+	/// Lowest similarity among the pairs that joined the cluster
+	pub min_similarity: f32,
+}
+
+/// Group entries whose meaning similarity reaches `threshold`
+///
+/// Clustering is single-linkage: an entry joins a cluster when it is similar
+/// enough to any member. Only entries of the same tenant and collection with
+/// vectors of the same length are compared. Clusters are ordered by their
+/// oldest entry.
+pub fn cluster_near_duplicates(entries: &[Entry], threshold: f32) -> Vec<NearDuplicateCluster> {
+	let mut entries: Vec<&Entry> = entries.iter().collect();
+	entries.sort_by_key(|entry| (entry.created_at, entry.id));
+	let mut parents: Vec<usize> = (0..entries.len()).collect();
+	let mut links: Vec<(usize, f32)> = Vec::new();
+	for (left, first) in entries.iter().enumerate() {
+		for (offset, second) in entries[left + 1..].iter().enumerate() {
+			if first.tenant != second.tenant
+				|| first.collection != second.collection
+				|| first.meaning.len() != second.meaning.len()
+			{
+				continue;
+			}
+			let similarity = cosine_similarity(&first.meaning, &second.meaning);
+			if similarity >= threshold {
+				let root = union(&mut parents, left, left + 1 + offset);
+				links.push((root, similarity));
+			}
+		}
+	}
+
+	let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+	for index in 0..entries.len() {
+		let root = find(&mut parents, index);
+		members.entry(root).or_default().push(index);
+	}
+	let mut min_similarity: HashMap<usize, f32> = HashMap::new();
+	for (root, similarity) in links {
+		let root = find(&mut parents, root);
+		let lowest = min_similarity.entry(root).or_insert(similarity);
+		*lowest = lowest.min(similarity);
+	}
+	let mut clusters: Vec<(usize, NearDuplicateCluster)> = members
+		.into_values()
+		.filter(|indexes| indexes.len() > 1)
+		.map(|indexes| {
+			let root = find(&mut parents, indexes[0]);
+			let cluster = NearDuplicateCluster {
+				collection: entries[indexes[0]].collection.clone(),
+				ids: indexes.iter().map(|index| entries[*index].id).collect(),
+				min_similarity: min_similarity[&root],
+			};
+			(indexes[0], cluster)
+		})
+		.collect();
+	clusters.sort_by_key(|(oldest, _)| *oldest);
+	clusters.into_iter().map(|(_, cluster)| cluster).collect()
+}
+
+fn find(parents: &mut [usize], index: usize) -> usize {
+	let mut root = index;
+	while parents[root] != root {
+		root = parents[root];
+	}
+	let mut current = index;
+	while parents[current] != root {
+		let next = parents[current];
+		parents[current] = root;
+		current = next;
+	}
+	root
+}
+
+/// Join the sets holding `first` and `second`, returning the new root
+fn union(parents: &mut [usize], first: usize, second: usize) -> usize {
+	let first = find(parents, first);
+	let second = find(parents, second);
+	let root = first.min(second);
+	parents[first.max(second)] = root;
+	root
+}
+
+/// 128-bit FNV-1a, whose output never changes across releases or platforms
+pub(crate) struct Fnv128(u128);
+
+impl Fnv128 {
+	const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+	const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+	pub(crate) fn new() -> Self {
+		Self(Self::OFFSET_BASIS)
+	}
+
+	pub(crate) fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 ^= u128::from(*byte);
+			self.0 = self.0.wrapping_mul(Self::PRIME);
+		}
+	}
+
+	/// The hash as 32 hex digits
+	pub(crate) fn finish_hex(&self) -> String {
+		format!("{:032x}", self.0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_content_hash_ignores_case_and_whitespace() {
+		let policy = DedupPolicy {
+			enabled: true,
+			..DedupPolicy::default()
+		};
+		let first = Entry::new(vec![0.1], "  User prefers\tcold brew ".to_string());
+		let second = Entry::new(vec![0.2], "user PREFERS cold  brew".to_string());
+		let other = Entry::new(vec![0.1], "User prefers hot tea".to_string());
+
+		assert_eq!(policy.content_hash(&first), policy.content_hash(&second));
+		assert_ne!(policy.content_hash(&first), policy.content_hash(&other));
+		assert_eq!(policy.content_hash(&first).len(), 32);
+	}
+
+	#[test]
+	fn test_cluster_near_duplicates_links_similar_meanings() {
+		let base = chrono::Utc::now();
+		let entry = |meaning: Vec<f32>, seconds: i64| {
+			let mut entry = Entry::new(meaning, "Entry".to_string());
+			entry.created_at = base + chrono::Duration::seconds(seconds);
+			entry
+		};
+		let first = entry(vec![1.0, 0.0], 0);
+		let close = entry(vec![0.99, 0.05], 1);
+		let chained = entry(vec![0.96, 0.2], 2);
+		let distant = entry(vec![0.0, 1.0], 3);
+		let archived = Entry {
+			collection: "archive".to_string(),
+			..entry(vec![1.0, 0.0], 4)
+		};
+		let shorter = entry(vec![1.0], 5);
+
+		let clusters = cluster_near_duplicates(
+			&[
+				distant.clone(),
+				chained.clone(),
+				archived,
+				first.clone(),
+				shorter,
+				close.clone(),
+			],
+			0.98,
+		);
+
+		assert_eq!(clusters.len(), 1);
+		assert_eq!(clusters[0].ids, vec![first.id, close.id, chained.id]);
+		assert!(clusters[0].min_similarity >= 0.98);
+		assert!(cluster_near_duplicates(&[first, distant], 0.98).is_empty());
+	}
+
+	#[test]
+	fn test_merge_strategy_picks_the_survivor() {
+		let older = Entry::new(vec![0.1], "Older".to_string());
+		let mut newer = Entry::new(vec![0.1], "Newer".to_string());
+		newer.created_at = older.created_at + chrono::Duration::seconds(1);
+		let entries = [newer, older];
+
+		assert_eq!(MergeStrategy::KeepFirst.survivor(&entries), 0);
+		assert_eq!(MergeStrategy::KeepOldest.survivor(&entries), 1);
+		assert_eq!(MergeStrategy::KeepNewest.survivor(&entries), 0);
+	}
+
+	#[test]
+	fn test_content_hash_can_include_context() {
+		let policy = DedupPolicy {
+			enabled: true,
+			include_context: true,
+			..DedupPolicy::default()
+		};
+		let first = Entry::new(vec![0.1], "Note".to_string()).with_context(json!({"a": 1, "b": 2}));
+		let reordered =
+			Entry::new(vec![0.1], "Note".to_string()).with_context(json!({"b": 2, "a": 1}));
+		let changed = Entry::new(vec![0.1], "Note".to_string()).with_context(json!({"a": 2}));
+
+		assert_eq!(policy.content_hash(&first), policy.content_hash(&reordered));
+		assert_ne!(policy.content_hash(&first), policy.content_hash(&changed));
+	}
+}
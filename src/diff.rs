@@ -0,0 +1,480 @@
+use crate::storage::{EntryRevision, RevisionOperation, StorageError, StorageResult};
+use crate::types::cosine_similarity;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Largest token-grid the word diff solves exactly before replacing the whole text
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Field-by-field comparison of two revisions of one entry
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RevisionDiff {
+	/// Entry both revisions belong to
+	pub entry_id: Uuid,
+	/// Older side of the comparison
+	pub from_revision: Uuid,
+	/// Newer side of the comparison
+	pub to_revision: Uuid,
+	/// Operation that recorded the newer revision
+	pub operation: RevisionOperation,
+	/// When the older revision was recorded
+	pub from_recorded_at: DateTime<Utc>,
+	/// When the newer revision was recorded
+	pub to_recorded_at: DateTime<Utc>,
+	/// Word-level expression diff; a single `Equal` span when unchanged
+	pub expression: Vec<TextChange>,
+	/// RFC 6902 operations turning the older context into the newer one
+	pub context: Vec<JsonPatchOperation>,
+	/// Outgoing relations present only in the newer revision
+	pub relations_added: Vec<Uuid>,
+	/// Outgoing relations present only in the older revision
+	pub relations_removed: Vec<Uuid>,
+	/// `1 - cosine similarity` between the vectors, or `None` when dimensions differ
+	pub meaning_drift: Option<f32>,
+}
+
+impl RevisionDiff {
+	/// Compare `from` with `to`
+	pub fn between(from: &EntryRevision, to: &EntryRevision) -> Self {
+		let (old, new) = (&from.snapshot, &to.snapshot);
+		let relations_added = new
+			.relations
+			.iter()
+			.filter(|id| !old.relations.contains(id))
+			.copied()
+			.collect();
+		let relations_removed = old
+			.relations
+			.iter()
+			.filter(|id| !new.relations.contains(id))
+			.copied()
+			.collect();
+		let meaning_drift = (old.meaning.len() == new.meaning.len())
+			.then(|| 1.0 - cosine_similarity(&old.meaning, &new.meaning));
+		Self {
+			entry_id: to.entry_id,
+			from_revision: from.revision_id,
+			to_revision: to.revision_id,
+			operation: to.operation,
+			from_recorded_at: from.recorded_at,
+			to_recorded_at: to.recorded_at,
+			expression: diff_words(&old.expression, &new.expression),
+			context: json_patch(&old.context, &new.context),
+			relations_added,
+			relations_removed,
+			meaning_drift,
+		}
+	}
+
+	/// Whether the expression text differs
+	pub fn expression_changed(&self) -> bool {
+		self.expression
+			.iter()
+			.any(|change| !matches!(change, TextChange::Equal(_)))
+	}
+
+	/// Whether any compared field differs
+	pub fn is_empty(&self) -> bool {
+		!self.expression_changed()
+			&& self.context.is_empty()
+			&& self.relations_added.is_empty()
+			&& self.relations_removed.is_empty()
+			&& self
+				.meaning_drift
+				.is_some_and(|drift| drift.abs() <= f32::EPSILON)
+	}
+}
+
+/// One span of a word-level text diff
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", content = "text", rename_all = "lowercase")]
+pub enum TextChange {
+	/// Text present in both versions
+	Equal(String),
+	/// Text only in the newer version
+	Insert(String),
+	/// Text only in the older version
+	Delete(String),
+}
+
+/// One RFC 6902 JSON Patch operation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum JsonPatchOperation {
+	/// Add a value at `path`
+	Add {
+		/// JSON Pointer to the new location
+		path: String,
+		/// Value to insert
+		value: Value,
+	},
+	/// Remove the value at `path`
+	Remove {
+		/// JSON Pointer to the removed location
+		path: String,
+	},
+	/// Replace the value at `path`
+	Replace {
+		/// JSON Pointer to the replaced location
+		path: String,
+		/// Replacement value
+		value: Value,
+	},
+}
+
+/// Word-level diff of two strings; whitespace runs are kept as their own tokens
+pub fn diff_words(old: &str, new: &str) -> Vec<TextChange> {
+	let old_tokens = tokenize(old);
+	let new_tokens = tokenize(new);
+	let prefix = old_tokens
+		.iter()
+		.zip(&new_tokens)
+		.take_while(|(left, right)| left == right)
+		.count();
+	let suffix = old_tokens[prefix..]
+		.iter()
+		.rev()
+		.zip(new_tokens[prefix..].iter().rev())
+		.take_while(|(left, right)| left == right)
+		.count();
+	let old_middle = &old_tokens[prefix..old_tokens.len() - suffix];
+	let new_middle = &new_tokens[prefix..new_tokens.len() - suffix];
+
+	let mut changes = Vec::new();
+	push_change(
+		&mut changes,
+		TextChange::Equal(old_tokens[..prefix].concat()),
+	);
+	if old_middle.len().saturating_mul(new_middle.len()) > MAX_DIFF_CELLS {
+		push_change(&mut changes, TextChange::Delete(old_middle.concat()));
+		push_change(&mut changes, TextChange::Insert(new_middle.concat()));
+	} else {
+		for change in lcs_diff(old_middle, new_middle) {
+			push_change(&mut changes, change);
+		}
+	}
+	push_change(
+		&mut changes,
+		TextChange::Equal(old_tokens[old_tokens.len() - suffix..].concat()),
+	);
+	if changes.is_empty() {
+		changes.push(TextChange::Equal(String::new()));
+	}
+	changes
+}
+
+fn tokenize(text: &str) -> Vec<&str> {
+	let mut tokens = Vec::new();
+	let mut start = 0;
+	let mut previous_whitespace = None;
+	for (index, character) in text.char_indices() {
+		let whitespace = character.is_whitespace();
+		if previous_whitespace.is_some_and(|previous| previous != whitespace) {
+			tokens.push(&text[start..index]);
+			start = index;
+		}
+		previous_whitespace = Some(whitespace);
+	}
+	if start < text.len() {
+		tokens.push(&text[start..]);
+	}
+	tokens
+}
+
+fn lcs_diff(old: &[&str], new: &[&str]) -> Vec<TextChange> {
+	let width = new.len() + 1;
+	let mut lengths = vec![0_u32; (old.len() + 1) * width];
+	for i in (0..old.len()).rev() {
+		for j in (0..new.len()).rev() {
+			lengths[i * width + j] = if old[i] == new[j] {
+				lengths[(i + 1) * width + j + 1] + 1
+			} else {
+				lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+			};
+		}
+	}
+
+	let mut changes = Vec::new();
+	let (mut i, mut j) = (0, 0);
+	while i < old.len() && j < new.len() {
+		if old[i] == new[j] {
+			changes.push(TextChange::Equal(old[i].to_string()));
+			i += 1;
+			j += 1;
+		} else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+			changes.push(TextChange::Delete(old[i].to_string()));
+			i += 1;
+		} else {
+			changes.push(TextChange::Insert(new[j].to_string()));
+			j += 1;
+		}
+	}
+	changes.extend(
+		old[i..]
+			.iter()
+			.map(|token| TextChange::Delete(token.to_string())),
+	);
+	changes.extend(
+		new[j..]
+			.iter()
+			.map(|token| TextChange::Insert(token.to_string())),
+	);
+	changes
+}
+
+/// Append `change`, merging it into the previous span of the same kind
+fn push_change(changes: &mut Vec<TextChange>, change: TextChange) {
+	let text = match &change {
+		TextChange::Equal(text) | TextChange::Insert(text) | TextChange::Delete(text) => text,
+	};
+	if text.is_empty() {
+		return;
+	}
+	match (changes.last_mut(), &change) {
+		(Some(TextChange::Equal(previous)), TextChange::Equal(text))
+		| (Some(TextChange::Insert(previous)), TextChange::Insert(text))
+		| (Some(TextChange::Delete(previous)), TextChange::Delete(text)) => previous.push_str(text),
+		_ => changes.push(change),
+	}
+}
+
+/// RFC 6902 operations that transform `old` into `new`
+///
+/// Objects are compared key by key and arrays element by element, with
+/// trailing elements added or removed; other changes replace the value.
+pub fn json_patch(old: &Value, new: &Value) -> Vec<JsonPatchOperation> {
+	let mut operations = Vec::new();
+	diff_values(String::new(), old, new, &mut operations);
+	operations
+}
+
+fn diff_values(path: String, old: &Value, new: &Value, operations: &mut Vec<JsonPatchOperation>) {
+	match (old, new) {
+		_ if old == new => {}
+		(Value::Object(old_map), Value::Object(new_map)) => {
+			for (key, old_value) in old_map {
+				let child = format!("{path}/{}", escape_pointer(key));
+				match new_map.get(key) {
+					Some(new_value) => diff_values(child, old_value, new_value, operations),
+					None => operations.push(JsonPatchOperation::Remove { path: child }),
+				}
+			}
+			for (key, new_value) in new_map {
+				if !old_map.contains_key(key) {
+					operations.push(JsonPatchOperation::Add {
+						path: format!("{path}/{}", escape_pointer(key)),
+						value: new_value.clone(),
+					});
+				}
+			}
+		}
+		(Value::Array(old_items), Value::Array(new_items)) => {
+			let shared = old_items.len().min(new_items.len());
+			for index in 0..shared {
+				diff_values(
+					format!("{path}/{index}"),
+					&old_items[index],
+					&new_items[index],
+					operations,
+				);
+			}
+			for index in (shared..old_items.len()).rev() {
+				operations.push(JsonPatchOperation::Remove {
+					path: format!("{path}/{index}"),
+				});
+			}
+			for (index, value) in new_items.iter().enumerate().skip(shared) {
+				operations.push(JsonPatchOperation::Add {
+					path: format!("{path}/{index}"),
+					value: value.clone(),
+				});
+			}
+		}
+		_ => operations.push(JsonPatchOperation::Replace {
+			path,
+			value: new.clone(),
+		}),
+	}
+}
+
+fn escape_pointer(key: &str) -> String {
+	key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Apply RFC 6902 `operations` to `target` in order
+///
+/// Fails with `InvalidArgument` when a path does not resolve; `target` may
+/// then be partially patched.
+pub fn apply_json_patch(
+	target: &mut Value,
+	operations: &[JsonPatchOperation],
+) -> StorageResult<()> {
+	for operation in operations {
+		match operation {
+			JsonPatchOperation::Add { path, value } => {
+				let Some((parent, key)) = split_pointer(path) else {
+					*target = value.clone();
+					continue;
+				};
+				match target.pointer_mut(parent) {
+					Some(Value::Object(map)) => {
+						map.insert(key, value.clone());
+					}
+					Some(Value::Array(items)) => {
+						let index = if key == "-" {
+							items.len()
+						} else {
+							array_index(&key, items.len() + 1, path)?
+						};
+						items.insert(index, value.clone());
+					}
+					_ => return Err(missing_path(path)),
+				}
+			}
+			JsonPatchOperation::Remove { path } => {
+				let (parent, key) = split_pointer(path).ok_or_else(|| missing_path(path))?;
+				match target.pointer_mut(parent) {
+					Some(Value::Object(map)) => {
+						map.remove(&key).ok_or_else(|| missing_path(path))?;
+					}
+					Some(Value::Array(items)) => {
+						let index = array_index(&key, items.len(), path)?;
+						items.remove(index);
+					}
+					_ => return Err(missing_path(path)),
+				}
+			}
+			JsonPatchOperation::Replace { path, value } => {
+				*target.pointer_mut(path).ok_or_else(|| missing_path(path))? = value.clone();
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Split a JSON Pointer into its parent pointer and unescaped final token
+fn split_pointer(path: &str) -> Option<(&str, String)> {
+	let (parent, key) = path.rsplit_once('/')?;
+	Some((parent, key.replace("~1", "/").replace("~0", "~")))
+}
+
+fn array_index(key: &str, bound: usize, path: &str) -> StorageResult<usize> {
+	key.parse::<usize>()
+		.ok()
+		.filter(|index| *index < bound)
+		.ok_or_else(|| missing_path(path))
+}
+
+fn missing_path(path: &str) -> StorageError {
+	StorageError::InvalidArgument(format!("JSON Patch path does not resolve: {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::types::Entry;
+	use serde_json::json;
+
+	fn revision(entry: &Entry, operation: RevisionOperation) -> EntryRevision {
+		EntryRevision {
+			revision_id: Uuid::new_v4(),
+			entry_id: entry.id,
+			operation,
+			snapshot: entry.clone(),
+			recorded_at: Utc::now(),
+		}
+	}
+
+	#[test]
+	fn test_word_diff_keeps_unchanged_spans() {
+		let changes = diff_words("the quick brown fox", "the slow brown fox jumps");
+
+		assert_eq!(
+			changes,
+			vec![
+				TextChange::Equal("the ".to_string()),
+				TextChange::Delete("quick".to_string()),
+				TextChange::Insert("slow".to_string()),
+				TextChange::Equal(" brown fox".to_string()),
+				TextChange::Insert(" jumps".to_string()),
+			]
+		);
+		assert_eq!(
+			diff_words("same", "same"),
+			vec![TextChange::Equal("same".to_string())]
+		);
+	}
+
+	#[test]
+	fn test_json_patch_follows_rfc_6902_paths() {
+		let old = json!({"a/b": 1, "keep": true, "tags": ["x", "y", "z"], "gone": null});
+		let new = json!({"a/b": 2, "keep": true, "tags": ["x", "q"], "new": {"n": 1}});
+
+		let patch = json_patch(&old, &new);
+
+		assert_eq!(
+			serde_json::to_value(&patch).unwrap(),
+			json!([
+				{"op": "replace", "path": "/a~1b", "value": 2},
+				{"op": "remove", "path": "/gone"},
+				{"op": "replace", "path": "/tags/1", "value": "q"},
+				{"op": "remove", "path": "/tags/2"},
+				{"op": "add", "path": "/new", "value": {"n": 1}},
+			])
+		);
+		assert!(json_patch(&old, &old).is_empty());
+		assert_eq!(
+			json_patch(&json!(null), &json!({"a": 1})),
+			vec![JsonPatchOperation::Replace {
+				path: String::new(),
+				value: json!({"a": 1})
+			}]
+		);
+	}
+
+	#[test]
+	fn test_apply_json_patch_round_trips_generated_patches() {
+		let old = json!({"a/b": 1, "tags": ["x", "y", "z"], "gone": null, "n": {"k": [1]}});
+		let new = json!({"a/b": 2, "tags": ["x"], "n": {"k": [1, 2, 3]}, "new": true});
+		let mut patched = old.clone();
+
+		apply_json_patch(&mut patched, &json_patch(&old, &new)).unwrap();
+
+		assert_eq!(patched, new);
+		assert!(apply_json_patch(
+			&mut patched,
+			&[JsonPatchOperation::Remove {
+				path: "/missing".to_string()
+			}]
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn test_revision_diff_compares_every_field() {
+		let kept = Uuid::new_v4();
+		let dropped = Uuid::new_v4();
+		let added = Uuid::new_v4();
+		let mut entry = Entry::new(vec![1.0, 0.0], "Original text".to_string())
+			.with_context(json!({"source": "chat"}));
+		entry.relations = vec![kept, dropped];
+		let from = revision(&entry, RevisionOperation::Insert);
+		entry.expression = "Revised text".to_string();
+		entry.context = json!({"source": "email"});
+		entry.relations = vec![kept, added];
+		entry.meaning = vec![0.0, 1.0];
+		let to = revision(&entry, RevisionOperation::Update);
+
+		let diff = RevisionDiff::between(&from, &to);
+
+		assert!(diff.expression_changed());
+		assert_eq!(diff.context.len(), 1);
+		assert_eq!(diff.relations_added, vec![added]);
+		assert_eq!(diff.relations_removed, vec![dropped]);
+		assert_eq!(diff.meaning_drift, Some(1.0));
+		assert!(!diff.is_empty());
+		assert!(RevisionDiff::between(&to, &to).is_empty());
+	}
+}
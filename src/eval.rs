@@ -0,0 +1,300 @@
+use crate::query::{DecayRanking, FeedbackBoost, HybridWeights, Query};
+use crate::storage::{StorageError, StorageResult};
+use crate::ContextDB;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// A query and the relevance judgments (qrels) its results are scored against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalCase {
+	/// Label shown in reports
+	pub name: String,
+	/// Query to run; its limit, offset, and cursor are replaced by the cutoff
+	pub query: Query,
+	/// Relevance grade of each judged entry; 0 marks an entry as not relevant
+	pub judgments: BTreeMap<Uuid, u32>,
+}
+
+/// Ranking settings applied on top of every case's query
+///
+/// Fields left unset keep what the case's query asks for, so two configs can
+/// be compared over the same judgments.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvalConfig {
+	/// Semantic and lexical weights for hybrid queries
+	pub hybrid_weights: Option<HybridWeights>,
+	/// Minimum similarity for queries with a meaning filter
+	pub threshold: Option<f32>,
+	/// Most entries kept by vector search for queries with a meaning filter
+	pub top_k: Option<usize>,
+	/// Rank by relevance, recency, and importance
+	pub decay: Option<DecayRanking>,
+	/// Re-rank by recorded relevance feedback
+	pub feedback_boost: Option<FeedbackBoost>,
+	/// Replace chunk hits with their documents
+	pub collapse_chunks: Option<bool>,
+}
+
+/// Retrieval quality of one ranking, or the mean over several
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvalMetrics {
+	/// Share of the relevant entries found in the top k
+	pub recall: f32,
+	/// Share of the top k that is relevant
+	pub precision: f32,
+	/// Inverse rank of the first relevant entry, or 0; averaged, this is MRR
+	pub reciprocal_rank: f32,
+	/// Graded gain of the top k relative to an ideal ranking
+	pub ndcg: f32,
+}
+
+/// Metrics for one case
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseEvaluation {
+	/// The case's label
+	pub name: String,
+	/// Entries the query returned, in rank order
+	pub retrieved: Vec<Uuid>,
+	/// Metrics at the report's cutoff
+	pub metrics: EvalMetrics,
+}
+
+/// Metrics for every case under one config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalReport {
+	/// Rank cutoff the metrics are computed at
+	pub k: usize,
+	/// Per-case metrics in case order
+	pub cases: Vec<CaseEvaluation>,
+	/// Mean of the per-case metrics
+	pub mean: EvalMetrics,
+}
+
+/// Two configs evaluated over the same cases
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalComparison {
+	/// Report for the first config
+	pub baseline: EvalReport,
+	/// Report for the second config
+	pub candidate: EvalReport,
+	/// Candidate mean minus baseline mean
+	pub delta: EvalMetrics,
+}
+
+impl EvalConfig {
+	/// `query` with this config's settings applied
+	pub fn apply(&self, query: &Query) -> Query {
+		let mut query = query.clone();
+		if let Some(weights) = self.hybrid_weights {
+			query.hybrid_weights = Some(weights);
+		}
+		if let Some(meaning) = query.meaning.as_mut() {
+			if self.threshold.is_some() {
+				meaning.threshold = self.threshold;
+			}
+			if self.top_k.is_some() {
+				meaning.top_k = self.top_k;
+			}
+		}
+		if let Some(decay) = self.decay {
+			query.decay = Some(decay);
+		}
+		if let Some(boost) = &self.feedback_boost {
+			query.feedback_boost = Some(boost.clone());
+		}
+		if let Some(collapse) = self.collapse_chunks {
+			query.collapse_chunks = collapse;
+		}
+		query
+	}
+}
+
+impl EvalMetrics {
+	/// Score the top `k` of `retrieved` against `judgments`
+	///
+	/// nDCG uses each entry's grade as its gain, discounted by
+	/// `log2(rank + 1)`. Unjudged entries count as not relevant.
+	pub fn measure(retrieved: &[Uuid], judgments: &BTreeMap<Uuid, u32>, k: usize) -> Self {
+		let grade = |id: &Uuid| judgments.get(id).copied().unwrap_or(0);
+		let top = &retrieved[..retrieved.len().min(k)];
+		let relevant = judgments.values().filter(|grade| **grade > 0).count();
+		let hits = top.iter().filter(|id| grade(id) > 0).count();
+		let reciprocal_rank = top
+			.iter()
+			.position(|id| grade(id) > 0)
+			.map_or(0.0, |index| 1.0 / (index + 1) as f32);
+
+		let discounted = |grades: &mut dyn Iterator<Item = u32>| -> f32 {
+			grades
+				.enumerate()
+				.map(|(index, grade)| grade as f32 / ((index + 2) as f32).log2())
+				.sum()
+		};
+		let dcg = discounted(&mut top.iter().map(grade));
+		let mut ideal: Vec<u32> = judgments.values().copied().collect();
+		ideal.sort_unstable_by(|left, right| right.cmp(left));
+		let ideal_dcg = discounted(&mut ideal.into_iter().take(k));
+
+		Self {
+			recall: ratio(hits, relevant),
+			precision: ratio(hits, k),
+			reciprocal_rank,
+			ndcg: if ideal_dcg > 0.0 {
+				dcg / ideal_dcg
+			} else {
+				0.0
+			},
+		}
+	}
+
+	/// Mean of each metric over `metrics`
+	pub fn mean<'a>(metrics: impl IntoIterator<Item = &'a EvalMetrics>) -> Self {
+		let mut sum = Self::default();
+		let mut count = 0;
+		for metric in metrics {
+			sum.recall += metric.recall;
+			sum.precision += metric.precision;
+			sum.reciprocal_rank += metric.reciprocal_rank;
+			sum.ndcg += metric.ndcg;
+			count += 1;
+		}
+		if count == 0 {
+			return sum;
+		}
+		let count = count as f32;
+		Self {
+			recall: sum.recall / count,
+			precision: sum.precision / count,
+			reciprocal_rank: sum.reciprocal_rank / count,
+			ndcg: sum.ndcg / count,
+		}
+	}
+
+	/// Each metric of `self` minus the same metric of `other`
+	pub fn minus(&self, other: &Self) -> Self {
+		Self {
+			recall: self.recall - other.recall,
+			precision: self.precision - other.precision,
+			reciprocal_rank: self.reciprocal_rank - other.reciprocal_rank,
+			ndcg: self.ndcg - other.ndcg,
+		}
+	}
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f32 {
+	if denominator == 0 {
+		0.0
+	} else {
+		numerator as f32 / denominator as f32
+	}
+}
+
+/// Run each case's query under `config` and score its top `k` results
+///
+/// Queries run through [`ContextDB::execute`] without access tracking, from
+/// the first result, limited to `k`. Fails when `k` is zero, there are no
+/// cases, or a case judges no entry relevant.
+pub(crate) fn evaluate(
+	db: &ContextDB,
+	cases: &[EvalCase],
+	k: usize,
+	config: &EvalConfig,
+) -> StorageResult<EvalReport> {
+	if k == 0 {
+		return Err(StorageError::InvalidArgument(
+			"Evaluation cutoff k must be positive".to_string(),
+		));
+	}
+	if cases.is_empty() {
+		return Err(StorageError::InvalidArgument(
+			"Evaluation needs at least one case".to_string(),
+		));
+	}
+	if let Some(case) = cases
+		.iter()
+		.find(|case| !case.judgments.values().any(|grade| *grade > 0))
+	{
+		return Err(StorageError::InvalidArgument(format!(
+			"Evaluation case '{}' judges no entry relevant",
+			case.name
+		)));
+	}
+
+	let mut evaluations = Vec::with_capacity(cases.len());
+	for case in cases {
+		let mut query = config.apply(&case.query).without_access_tracking();
+		query.limit = Some(k);
+		query.offset = 0;
+		query.cursor = None;
+		let retrieved: Vec<Uuid> = db
+			.execute(&query)?
+			.results
+			.iter()
+			.map(|result| result.entry.id)
+			.collect();
+		let metrics = EvalMetrics::measure(&retrieved, &case.judgments, k);
+		evaluations.push(CaseEvaluation {
+			name: case.name.clone(),
+			retrieved,
+			metrics,
+		});
+	}
+	let mean = EvalMetrics::mean(evaluations.iter().map(|case| &case.metrics));
+	Ok(EvalReport {
+		k,
+		cases: evaluations,
+		mean,
+	})
+}
+
+/// Evaluate `baseline` and `candidate` over the same cases
+pub(crate) fn compare(
+	db: &ContextDB,
+	cases: &[EvalCase],
+	k: usize,
+	baseline: &EvalConfig,
+	candidate: &EvalConfig,
+) -> StorageResult<EvalComparison> {
+	let baseline = evaluate(db, cases, k, baseline)?;
+	let candidate = evaluate(db, cases, k, candidate)?;
+	let delta = candidate.mean.minus(&baseline.mean);
+	Ok(EvalComparison {
+		baseline,
+		candidate,
+		delta,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_measure_scores_graded_rankings() {
+		let ids: Vec<Uuid> = (0..4).map(|_| Uuid::new_v4()).collect();
+		let judgments = BTreeMap::from([(ids[0], 2), (ids[1], 1), (ids[2], 0)]);
+
+		let ideal = EvalMetrics::measure(&[ids[0], ids[1]], &judgments, 2);
+		let late = EvalMetrics::measure(&[ids[3], ids[2], ids[1]], &judgments, 3);
+		let missed = EvalMetrics::measure(&[ids[2]], &judgments, 1);
+
+		assert_eq!(ideal.recall, 1.0);
+		assert_eq!(ideal.precision, 1.0);
+		assert_eq!(ideal.reciprocal_rank, 1.0);
+		assert!((ideal.ndcg - 1.0).abs() < 1e-6);
+		assert_eq!(late.recall, 0.5);
+		assert!((late.precision - 1.0 / 3.0).abs() < 1e-6);
+		assert!((late.reciprocal_rank - 1.0 / 3.0).abs() < 1e-6);
+		// DCG 1 / log2(4) = 0.5 against an ideal of 2 + 1 / log2(3)
+		let expected = 0.5 / (2.0 + 1.0 / 3f32.log2());
+		assert!((late.ndcg - expected).abs() < 1e-6);
+		assert_eq!(missed, EvalMetrics::default());
+
+		let mean = EvalMetrics::mean([&ideal, &missed]);
+		assert_eq!(mean.recall, 0.5);
+		assert_eq!(mean.reciprocal_rank, 0.5);
+		assert_eq!(mean.minus(&ideal).precision, -0.5);
+	}
+}
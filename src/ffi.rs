@@ -27,6 +27,8 @@ pub const CONTEXTDB_STATUS_INVALID_ARGUMENT: i32 = 1;
 pub const CONTEXTDB_STATUS_NOT_FOUND: i32 = 2;
 pub const CONTEXTDB_STATUS_DATABASE: i32 = 3;
 pub const CONTEXTDB_STATUS_PANIC: i32 = 4;
+pub const CONTEXTDB_STATUS_CONFLICT: i32 = 5;
+pub const CONTEXTDB_STATUS_DUPLICATE: i32 = 6;
 
 #[derive(serde::Deserialize)]
 struct InsertRequest {
@@ -97,6 +99,8 @@ fn value_guard<T>(fallback: T, operation: impl FnOnce() -> T) -> T {
 fn storage_status(error: crate::StorageError) -> (i32, String) {
 	let code = match &error {
 		crate::StorageError::NotFound(_) => CONTEXTDB_STATUS_NOT_FOUND,
+		crate::StorageError::Conflict { .. } => CONTEXTDB_STATUS_CONFLICT,
+		crate::StorageError::Duplicate { .. } => CONTEXTDB_STATUS_DUPLICATE,
 		crate::StorageError::InvalidDimensions | crate::StorageError::InvalidArgument(_) => {
 			CONTEXTDB_STATUS_INVALID_ARGUMENT
 		}
@@ -131,7 +135,7 @@ pub extern "C" fn contextdb_last_error_message() -> *mut c_char {
 
 #[no_mangle]
 pub extern "C" fn contextdb_abi_version() -> u32 {
-	1
+	2
 }
 
 #[no_mangle]
@@ -248,6 +252,37 @@ pub unsafe extern "C" fn contextdb_update_json(
 	})
 }
 
+#[no_mangle]
+/// Update an entry from its complete JSON representation if its stored
+/// version is still `expected_version`.
+///
+/// Returns `CONTEXTDB_STATUS_CONFLICT` when another write changed it first.
+///
+/// # Safety
+/// `handle` and `json` must be valid pointers.
+pub unsafe extern "C" fn contextdb_update_if_version_json(
+	handle: *mut ContextDBHandle,
+	json: *const c_char,
+	expected_version: u64,
+) -> i32 {
+	status_guard(|| {
+		if handle.is_null() {
+			return Err((
+				CONTEXTDB_STATUS_INVALID_ARGUMENT,
+				"handle was null".to_string(),
+			));
+		}
+		let json = cstr_to_string(json, "json")
+			.map_err(|message| (CONTEXTDB_STATUS_INVALID_ARGUMENT, message))?;
+		let entry: Entry = serde_json::from_str(&json)
+			.map_err(|error| (CONTEXTDB_STATUS_INVALID_ARGUMENT, error.to_string()))?;
+		(&mut *handle)
+			.db
+			.update_if_version(&entry, expected_version)
+			.map_err(storage_status)
+	})
+}
+
 #[no_mangle]
 /// Delete an entry by UUID.
 ///
@@ -703,7 +738,7 @@ mod tests {
 
 	#[test]
 	fn test_json_ffi_crud_and_query_round_trip() {
-		assert_eq!(contextdb_abi_version(), 1);
+		assert_eq!(contextdb_abi_version(), 2);
 		let handle = contextdb_open(ptr::null());
 		assert!(!handle.is_null());
 		let request = CString::new(
@@ -867,6 +902,34 @@ mod tests {
 		);
 		assert_eq!(contextdb_last_error_code(), CONTEXTDB_STATUS_NOT_FOUND);
 
+		entry.relations.clear();
+		let read_version = entry.version;
+		entry.expression = "first writer".to_string();
+		let update = CString::new(serde_json::to_string(&entry).unwrap()).unwrap();
+		assert_eq!(
+			unsafe { contextdb_update_if_version_json(handle, update.as_ptr(), read_version) },
+			CONTEXTDB_STATUS_OK
+		);
+		entry.expression = "stale writer".to_string();
+		let update = CString::new(serde_json::to_string(&entry).unwrap()).unwrap();
+		assert_eq!(
+			unsafe { contextdb_update_if_version_json(handle, update.as_ptr(), read_version) },
+			CONTEXTDB_STATUS_CONFLICT
+		);
+		assert_eq!(contextdb_last_error_code(), CONTEXTDB_STATUS_CONFLICT);
+
+		// The stale serialized version does not make a plain update conditional.
+		entry.expression = "last writer".to_string();
+		let update = CString::new(serde_json::to_string(&entry).unwrap()).unwrap();
+		assert_eq!(
+			unsafe { contextdb_update_json(handle, update.as_ptr()) },
+			CONTEXTDB_STATUS_OK
+		);
+		assert_eq!(
+			unsafe { (&*handle).db.get(id).unwrap().expression },
+			"last writer"
+		);
+
 		unsafe { contextdb_close(handle) };
 	}
 }
@@ -0,0 +1,350 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+/// Structural statistics for the directed relation graph
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphStats {
+	/// Entries considered as graph nodes
+	pub entry_count: usize,
+	/// Directed relation edges between entries in the graph, including self-relations
+	pub relation_count: usize,
+	/// Number of weakly connected components, including isolated entries
+	pub component_count: usize,
+	/// Size of the largest weakly connected component
+	pub largest_component_size: usize,
+	/// Weakly connected components that contain at least one relation, largest first
+	pub components: Vec<GraphComponent>,
+	/// Number of entries by outgoing-relation count
+	pub out_degree_distribution: BTreeMap<usize, usize>,
+	/// Number of entries by incoming-relation count
+	pub in_degree_distribution: BTreeMap<usize, usize>,
+	/// Entries with the highest combined degree
+	pub most_connected: Vec<EntryDegree>,
+	/// Entries with neither incoming nor outgoing relations
+	pub isolated_entries: Vec<Uuid>,
+	/// Entries that are relation targets but have no outgoing relations
+	pub sink_entries: Vec<Uuid>,
+	/// Relations from an entry in the graph to a target outside it, such as a
+	/// missing or trashed entry, as `(from, to)` pairs in ascending order
+	pub dangling_relations: Vec<(Uuid, Uuid)>,
+	/// Entries that relate to themselves
+	///
+	/// Self-relations count toward `relation_count` and `cycles` but not toward
+	/// degrees or components.
+	pub self_related_entries: Vec<Uuid>,
+	/// Directed cycles: strongly connected components of two or more entries,
+	/// then each self-related entry on its own
+	pub cycles: Vec<Vec<Uuid>>,
+}
+
+impl GraphStats {
+	/// Whether the directed relation graph contains no cycles
+	pub fn is_acyclic(&self) -> bool {
+		self.cycles.is_empty()
+	}
+
+	/// Compute statistics for `ids`, keeping the `top` most connected entries
+	///
+	/// Edges from an entry outside `ids` are ignored, and edges from an entry in
+	/// `ids` to one outside it are reported as dangling.
+	pub fn compute(ids: &HashSet<Uuid>, edges: &[(Uuid, Uuid)], top: usize) -> Self {
+		let mut nodes: Vec<Uuid> = ids.iter().copied().collect();
+		nodes.sort();
+		let mut outgoing: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+		let mut incoming: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+		let mut relation_count = 0;
+		let mut dangling_relations = Vec::new();
+		let mut self_related_entries = Vec::new();
+		for &(from, to) in edges {
+			if !ids.contains(&from) {
+				continue;
+			}
+			if !ids.contains(&to) {
+				dangling_relations.push((from, to));
+				continue;
+			}
+			relation_count += 1;
+			if from == to {
+				self_related_entries.push(from);
+				continue;
+			}
+			outgoing.entry(from).or_default().push(to);
+			incoming.entry(to).or_default().push(from);
+		}
+		for targets in outgoing.values_mut() {
+			targets.sort();
+		}
+		dangling_relations.sort();
+		self_related_entries.sort();
+		self_related_entries.dedup();
+
+		let out_degree = |id: &Uuid| outgoing.get(id).map_or(0, Vec::len);
+		let in_degree = |id: &Uuid| incoming.get(id).map_or(0, Vec::len);
+
+		let mut out_degree_distribution = BTreeMap::new();
+		let mut in_degree_distribution = BTreeMap::new();
+		let mut isolated_entries = Vec::new();
+		let mut sink_entries = Vec::new();
+		let mut degrees = Vec::with_capacity(nodes.len());
+		for id in &nodes {
+			let (out_count, in_count) = (out_degree(id), in_degree(id));
+			*out_degree_distribution.entry(out_count).or_insert(0) += 1;
+			*in_degree_distribution.entry(in_count).or_insert(0) += 1;
+			match (in_count, out_count) {
+				(0, 0) => isolated_entries.push(*id),
+				(_, 0) => sink_entries.push(*id),
+				_ => {}
+			}
+			degrees.push(EntryDegree {
+				entry_id: *id,
+				in_degree: in_count,
+				out_degree: out_count,
+			});
+		}
+		degrees.retain(|degree| degree.total() > 0);
+		degrees.sort_by(|left, right| {
+			right
+				.total()
+				.cmp(&left.total())
+				.then_with(|| left.entry_id.cmp(&right.entry_id))
+		});
+		degrees.truncate(top);
+
+		let mut cycles = directed_cycles(&nodes, &outgoing);
+		cycles.extend(self_related_entries.iter().map(|id| vec![*id]));
+
+		let components = weakly_connected_components(&nodes, &outgoing, &incoming);
+		let component_count = components.len();
+		let largest_component_size = components.iter().map(Vec::len).max().unwrap_or(0);
+		let components = components
+			.into_iter()
+			.filter(|component| component.len() > 1)
+			.map(|entry_ids| GraphComponent {
+				size: entry_ids.len(),
+				entry_ids,
+			})
+			.collect();
+
+		Self {
+			entry_count: nodes.len(),
+			relation_count,
+			component_count,
+			largest_component_size,
+			components,
+			out_degree_distribution,
+			in_degree_distribution,
+			most_connected: degrees,
+			isolated_entries,
+			sink_entries,
+			dangling_relations,
+			cycles,
+			self_related_entries,
+		}
+	}
+}
+
+/// One weakly connected group of related entries
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GraphComponent {
+	/// Number of entries in the component
+	pub size: usize,
+	/// Member entries in ascending UUID order
+	pub entry_ids: Vec<Uuid>,
+}
+
+/// Relation counts for one entry
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EntryDegree {
+	/// Entry being described
+	pub entry_id: Uuid,
+	/// Relations pointing at the entry
+	pub in_degree: usize,
+	/// Relations leaving the entry
+	pub out_degree: usize,
+}
+
+impl EntryDegree {
+	/// Combined incoming and outgoing relation count
+	pub fn total(&self) -> usize {
+		self.in_degree + self.out_degree
+	}
+}
+
+fn weakly_connected_components(
+	nodes: &[Uuid],
+	outgoing: &HashMap<Uuid, Vec<Uuid>>,
+	incoming: &HashMap<Uuid, Vec<Uuid>>,
+) -> Vec<Vec<Uuid>> {
+	let mut visited: HashSet<Uuid> = HashSet::with_capacity(nodes.len());
+	let mut components = Vec::new();
+	for &start in nodes {
+		if !visited.insert(start) {
+			continue;
+		}
+		let mut component = vec![start];
+		let mut stack = vec![start];
+		while let Some(current) = stack.pop() {
+			let neighbors = outgoing
+				.get(&current)
+				.into_iter()
+				.chain(incoming.get(&current))
+				.flatten();
+			for &neighbor in neighbors {
+				if visited.insert(neighbor) {
+					component.push(neighbor);
+					stack.push(neighbor);
+				}
+			}
+		}
+		component.sort();
+		components.push(component);
+	}
+	components.sort_by(|left, right| right.len().cmp(&left.len()).then(left[0].cmp(&right[0])));
+	components
+}
+
+/// Tarjan's strongly connected components, iterative to avoid deep recursion
+fn directed_cycles(nodes: &[Uuid], outgoing: &HashMap<Uuid, Vec<Uuid>>) -> Vec<Vec<Uuid>> {
+	let mut next_index = 0;
+	let mut index: HashMap<Uuid, usize> = HashMap::new();
+	let mut low_link: HashMap<Uuid, usize> = HashMap::new();
+	let mut on_stack: HashSet<Uuid> = HashSet::new();
+	let mut stack: Vec<Uuid> = Vec::new();
+	let mut cycles = Vec::new();
+	let empty = Vec::new();
+
+	for &root in nodes {
+		if index.contains_key(&root) {
+			continue;
+		}
+		let mut work: Vec<(Uuid, usize)> = vec![(root, 0)];
+		while let Some(&mut (node, ref mut next_child)) = work.last_mut() {
+			if *next_child == 0 && !index.contains_key(&node) {
+				index.insert(node, next_index);
+				low_link.insert(node, next_index);
+				next_index += 1;
+				stack.push(node);
+				on_stack.insert(node);
+			}
+			let children = outgoing.get(&node).unwrap_or(&empty);
+			if let Some(&child) = children.get(*next_child) {
+				*next_child += 1;
+				if !index.contains_key(&child) {
+					work.push((child, 0));
+				} else if on_stack.contains(&child) {
+					let lowest = low_link[&node].min(index[&child]);
+					low_link.insert(node, lowest);
+				}
+				continue;
+			}
+			work.pop();
+			if let Some(&(parent, _)) = work.last() {
+				let lowest = low_link[&parent].min(low_link[&node]);
+				low_link.insert(parent, lowest);
+			}
+			if low_link[&node] == index[&node] {
+				let mut component = Vec::new();
+				while let Some(member) = stack.pop() {
+					on_stack.remove(&member);
+					component.push(member);
+					if member == node {
+						break;
+					}
+				}
+				if component.len() > 1 {
+					component.sort();
+					cycles.push(component);
+				}
+			}
+		}
+	}
+	cycles.sort();
+	cycles
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn ids(count: usize) -> Vec<Uuid> {
+		let mut ids: Vec<Uuid> = (0..count).map(|_| Uuid::new_v4()).collect();
+		ids.sort();
+		ids
+	}
+
+	#[test]
+	fn test_empty_graph_has_no_components() {
+		let stats = GraphStats::compute(&HashSet::new(), &[], 5);
+
+		assert_eq!(stats.entry_count, 0);
+		assert_eq!(stats.component_count, 0);
+		assert!(stats.is_acyclic());
+	}
+
+	#[test]
+	fn test_components_isolated_and_sink_entries() {
+		let ids = ids(5);
+		let set: HashSet<Uuid> = ids.iter().copied().collect();
+		let edges = [(ids[0], ids[1]), (ids[1], ids[2]), (ids[3], ids[2])];
+
+		let stats = GraphStats::compute(&set, &edges, 10);
+
+		assert_eq!(stats.relation_count, 3);
+		assert_eq!(stats.component_count, 2);
+		assert_eq!(stats.largest_component_size, 4);
+		assert_eq!(stats.components.len(), 1);
+		assert_eq!(stats.isolated_entries, vec![ids[4]]);
+		assert_eq!(stats.sink_entries, vec![ids[2]]);
+		assert_eq!(stats.out_degree_distribution[&0], 2);
+		assert_eq!(stats.in_degree_distribution[&2], 1);
+		assert_eq!(stats.most_connected[0].entry_id, ids[1]);
+		assert_eq!(stats.most_connected.len(), 4);
+		assert!(stats.is_acyclic());
+	}
+
+	#[test]
+	fn test_cycles_are_detected_as_strongly_connected_components() {
+		let ids = ids(4);
+		let set: HashSet<Uuid> = ids.iter().copied().collect();
+		let edges = [
+			(ids[0], ids[1]),
+			(ids[1], ids[2]),
+			(ids[2], ids[0]),
+			(ids[2], ids[3]),
+		];
+
+		let stats = GraphStats::compute(&set, &edges, 1);
+
+		assert_eq!(stats.cycles, vec![vec![ids[0], ids[1], ids[2]]]);
+		assert!(!stats.is_acyclic());
+		assert_eq!(stats.most_connected.len(), 1);
+		assert_eq!(stats.most_connected[0].entry_id, ids[2]);
+	}
+
+	#[test]
+	fn test_edges_leaving_the_node_set_are_dangling() {
+		let ids = ids(3);
+		let set: HashSet<Uuid> = [ids[0]].into_iter().collect();
+
+		let stats = GraphStats::compute(&set, &[(ids[0], ids[1]), (ids[2], ids[0])], 5);
+
+		assert_eq!(stats.relation_count, 0);
+		assert_eq!(stats.isolated_entries, vec![ids[0]]);
+		assert_eq!(stats.dangling_relations, vec![(ids[0], ids[1])]);
+	}
+
+	#[test]
+	fn test_self_relations_are_reported_as_cycles() {
+		let ids = ids(2);
+		let set: HashSet<Uuid> = ids.iter().copied().collect();
+
+		let stats = GraphStats::compute(&set, &[(ids[0], ids[0]), (ids[0], ids[1])], 5);
+
+		assert_eq!(stats.relation_count, 2);
+		assert_eq!(stats.self_related_entries, vec![ids[0]]);
+		assert_eq!(stats.cycles, vec![vec![ids[0]]]);
+		assert!(!stats.is_acyclic());
+		assert_eq!(stats.most_connected[0].out_degree, 1);
+	}
+}
@@ -0,0 +1,302 @@
+use crate::dedup::MergeStrategy;
+use crate::patch::EntryPatch;
+use crate::storage::{EmbeddingProfile, StorageBackend, StorageResult};
+use crate::types::Entry;
+use uuid::Uuid;
+
+/// Mutation passed to hooks registered on [`crate::ContextDB`]
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum Mutation<'a> {
+	/// Entries being inserted
+	///
+	/// After-hooks see only the entries written under their own IDs; those the
+	/// deduplication policy dropped or merged arrive as [`Mutation::Deduplicated`].
+	Insert(&'a [Entry]),
+	/// Inserted entries the deduplication policy dropped or merged, as
+	/// `(inserted, existing)` ID pairs; only after-hooks see this
+	Deduplicated(&'a [(Uuid, Uuid)]),
+	/// Entries being updated, as supplied by the caller
+	Update(&'a [Entry]),
+	/// Entries being inserted or updated, depending on whether their IDs exist
+	Upsert(&'a [Entry]),
+	/// Entry being partially updated
+	Patch {
+		/// Entry being patched
+		entry_id: Uuid,
+		/// Fields being changed
+		patch: &'a EntryPatch,
+	},
+	/// Entries being deleted
+	Delete(&'a [Uuid]),
+	/// Entries being erased together with their history
+	Purge(&'a [Uuid]),
+	/// Trashed entries being removed permanently
+	EmptyTrash(&'a [Uuid]),
+	/// Entries being merged into one survivor
+	Merge {
+		/// Entries being merged, including the survivor
+		entry_ids: &'a [Uuid],
+		/// How the survivor is chosen
+		strategy: MergeStrategy,
+	},
+	/// Directed relations being added
+	Relate(&'a [(Uuid, Uuid)]),
+	/// Directed relations being removed
+	Unrelate(&'a [(Uuid, Uuid)]),
+	/// Entry being restored from a revision, or from its deletion when `revision_id` is `None`
+	Revert {
+		/// Entry being restored
+		entry_id: Uuid,
+		/// Revision being restored
+		revision_id: Option<Uuid>,
+	},
+	/// Every vector in a collection being replaced under a new embedding profile
+	MigrateEmbeddings {
+		/// Collection being migrated
+		collection: &'a str,
+		/// Profile being adopted
+		profile: &'a EmbeddingProfile,
+		/// Replacement vector for each entry
+		replacements: &'a [(Uuid, Vec<f32>)],
+	},
+}
+
+impl Mutation<'_> {
+	/// IDs of the entries whose stored state the mutation changes
+	///
+	/// For relation changes these are the source entries, and for deduplicated
+	/// inserts the existing entries that hold the content.
+	pub fn entry_ids(&self) -> Vec<Uuid> {
+		match self {
+			Mutation::Insert(entries) | Mutation::Update(entries) | Mutation::Upsert(entries) => {
+				entries.iter().map(|entry| entry.id).collect()
+			}
+			Mutation::Delete(ids)
+			| Mutation::Purge(ids)
+			| Mutation::EmptyTrash(ids)
+			| Mutation::Merge { entry_ids: ids, .. } => ids.to_vec(),
+			Mutation::Relate(edges) | Mutation::Unrelate(edges) => {
+				let mut ids: Vec<Uuid> = edges.iter().map(|(from, _)| *from).collect();
+				ids.sort();
+				ids.dedup();
+				ids
+			}
+			Mutation::Deduplicated(pairs) => {
+				let mut ids: Vec<Uuid> = pairs.iter().map(|(_, existing)| *existing).collect();
+				ids.sort();
+				ids.dedup();
+				ids
+			}
+			Mutation::Patch { entry_id, .. } | Mutation::Revert { entry_id, .. } => {
+				vec![*entry_id]
+			}
+			Mutation::MigrateEmbeddings { replacements, .. } => {
+				replacements.iter().map(|(id, _)| *id).collect()
+			}
+		}
+	}
+}
+
+/// Handle returned when registering a hook, used to remove it again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HookId(u64);
+
+type BeforeHook = Box<dyn FnMut(&dyn StorageBackend, &Mutation<'_>) -> StorageResult<()> + Send>;
+type AfterHook = Box<dyn FnMut(&mut dyn StorageBackend, &Mutation<'_>) + Send>;
+
+/// Registered mutation hooks, run in registration order
+#[derive(Default)]
+pub(crate) struct Hooks {
+	next_id: u64,
+	before: Vec<(HookId, BeforeHook)>,
+	after: Vec<(HookId, AfterHook)>,
+}
+
+impl Hooks {
+	fn next_id(&mut self) -> HookId {
+		self.next_id += 1;
+		HookId(self.next_id)
+	}
+
+	pub(crate) fn add_before(&mut self, hook: BeforeHook) -> HookId {
+		let id = self.next_id();
+		self.before.push((id, hook));
+		id
+	}
+
+	pub(crate) fn add_after(&mut self, hook: AfterHook) -> HookId {
+		let id = self.next_id();
+		self.after.push((id, hook));
+		id
+	}
+
+	pub(crate) fn remove(&mut self, id: HookId) -> bool {
+		let count = self.before.len() + self.after.len();
+		self.before.retain(|(hook_id, _)| *hook_id != id);
+		self.after.retain(|(hook_id, _)| *hook_id != id);
+		self.before.len() + self.after.len() != count
+	}
+
+	/// Run before-hooks until one vetoes the mutation
+	pub(crate) fn run_before(
+		&mut self,
+		storage: &dyn StorageBackend,
+		mutation: &Mutation<'_>,
+	) -> StorageResult<()> {
+		for (_, hook) in &mut self.before {
+			hook(storage, mutation)?;
+		}
+		Ok(())
+	}
+
+	/// Run after-hooks once the mutation has committed
+	pub(crate) fn run_after(&mut self, storage: &mut dyn StorageBackend, mutation: &Mutation<'_>) {
+		for (_, hook) in &mut self.after {
+			hook(storage, mutation);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::dedup::{DedupPolicy, DuplicateAction};
+	use crate::storage::StorageError;
+	use crate::ContextDB;
+	use std::sync::{Arc, Mutex};
+
+	#[test]
+	fn test_after_hooks_observe_committed_mutations() {
+		let mut db = ContextDB::in_memory().unwrap();
+		let seen = Arc::new(Mutex::new(Vec::new()));
+		let log = Arc::clone(&seen);
+		db.after_mutation(move |_, mutation| {
+			let kind = match mutation {
+				Mutation::Insert(_) => "insert",
+				Mutation::Update(_) => "update",
+				Mutation::Delete(_) => "delete",
+				_ => "other",
+			};
+			log.lock().unwrap().push((kind, mutation.entry_ids()));
+		});
+
+		let mut entry = Entry::new(vec![0.1], "Observed".to_string());
+		db.insert(&entry).unwrap();
+		entry.expression = "Observed again".to_string();
+		entry.updated_at = chrono::Utc::now();
+		db.update(&entry).unwrap();
+		assert!(db.insert(&entry).is_err());
+		db.delete(entry.id).unwrap();
+
+		assert_eq!(
+			*seen.lock().unwrap(),
+			vec![
+				("insert", vec![entry.id]),
+				("update", vec![entry.id]),
+				("delete", vec![entry.id]),
+			]
+		);
+	}
+
+	#[test]
+	fn test_after_hooks_see_skipped_duplicates_apart_from_inserts() {
+		let mut db = ContextDB::in_memory().unwrap();
+		db.set_dedup_policy(&DedupPolicy {
+			enabled: true,
+			on_duplicate: DuplicateAction::Skip,
+			..DedupPolicy::default()
+		})
+		.unwrap();
+		let original = Entry::new(vec![0.1], "Same words".to_string());
+		db.insert(&original).unwrap();
+		let seen = Arc::new(Mutex::new(Vec::new()));
+		let log = Arc::clone(&seen);
+		db.after_mutation(move |_, mutation| {
+			let event = match mutation {
+				Mutation::Insert(entries) => {
+					("insert", entries.iter().map(|entry| entry.id).collect())
+				}
+				Mutation::Deduplicated(pairs) => (
+					"deduplicated",
+					pairs
+						.iter()
+						.flat_map(|(inserted, existing)| [*inserted, *existing])
+						.collect(),
+				),
+				_ => ("other", Vec::new()),
+			};
+			log.lock().unwrap().push(event);
+		});
+
+		let duplicate = Entry::new(vec![0.2], "  same WORDS ".to_string());
+		let fresh = Entry::new(vec![0.3], "Other words".to_string());
+		db.insert(&duplicate).unwrap();
+		db.insert_batch(&[duplicate.clone(), fresh.clone()])
+			.unwrap();
+
+		assert_eq!(db.count().unwrap(), 2);
+		assert_eq!(
+			*seen.lock().unwrap(),
+			vec![
+				("deduplicated", vec![duplicate.id, original.id]),
+				("insert", vec![fresh.id]),
+				("deduplicated", vec![duplicate.id, original.id]),
+			]
+		);
+	}
+
+	#[test]
+	fn test_before_hook_can_veto_mutation() {
+		let mut db = ContextDB::in_memory().unwrap();
+		let after_calls = Arc::new(Mutex::new(0));
+		let counter = Arc::clone(&after_calls);
+		let veto = db.before_mutation(|_, mutation| match mutation {
+			Mutation::Insert(entries)
+				if entries.iter().any(|entry| entry.expression.is_empty()) =>
+			{
+				Err(StorageError::InvalidArgument(
+					"Expression must not be empty".to_string(),
+				))
+			}
+			_ => Ok(()),
+		});
+		db.after_mutation(move |_, _| *counter.lock().unwrap() += 1);
+
+		let empty = Entry::new(vec![0.1], String::new());
+		assert!(matches!(
+			db.insert(&empty),
+			Err(StorageError::InvalidArgument(_))
+		));
+		assert_eq!(db.count().unwrap(), 0);
+		assert_eq!(*after_calls.lock().unwrap(), 0);
+
+		assert!(db.remove_hook(veto));
+		assert!(!db.remove_hook(veto));
+		db.insert(&empty).unwrap();
+		assert_eq!(*after_calls.lock().unwrap(), 1);
+	}
+
+	#[test]
+	fn test_after_hook_can_write_without_reentering_hooks() {
+		let mut db = ContextDB::in_memory().unwrap();
+		let anchor = Entry::new(vec![1.0, 0.0], "Anchor".to_string());
+		db.insert(&anchor).unwrap();
+		let calls = Arc::new(Mutex::new(0));
+		let counter = Arc::clone(&calls);
+		db.after_mutation(move |storage, mutation| {
+			*counter.lock().unwrap() += 1;
+			if let Mutation::Insert(entries) = mutation {
+				for entry in *entries {
+					storage.relate(entry.id, anchor.id).unwrap();
+				}
+			}
+		});
+
+		let memory = Entry::new(vec![0.9, 0.1], "Memory".to_string());
+		db.insert(&memory).unwrap();
+
+		assert_eq!(db.get(memory.id).unwrap().relations, vec![anchor.id]);
+		assert_eq!(*calls.lock().unwrap(), 1);
+	}
+}
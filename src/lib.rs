@@ -43,25 +43,55 @@
 //! # }
 //! ```
 
+mod chunk;
+mod dedup;
+mod diff;
+mod eval;
+mod graph;
+mod hooks;
+mod pack;
+mod patch;
 mod query;
 mod storage;
 mod types;
 
+pub use chunk::{
+	chunk_document_id, chunk_next_id, ChunkUnit, ChunkedDocument, Chunker, CHUNK_CONTEXT_KEY,
+};
+pub use dedup::{
+	cluster_near_duplicates, normalize_expression, DedupPolicy, DuplicateAction, MergeStrategy,
+	NearDuplicateCluster,
+};
+pub use diff::{
+	apply_json_patch, diff_words, json_patch, JsonPatchOperation, RevisionDiff, TextChange,
+};
+pub use eval::{CaseEvaluation, EvalCase, EvalComparison, EvalConfig, EvalMetrics, EvalReport};
+pub use graph::{EntryDegree, GraphComponent, GraphStats};
+pub use hooks::{HookId, Mutation};
+pub use pack::{
+	ContextPack, DropReason, DroppedEntry, PackOptions, PackedEntry, TokenCounter,
+	WhitespaceTokenCounter,
+};
+pub use patch::{merge_patch, EntryPatch};
 pub use query::{
-	ContextFilter, ExpressionFilter, HybridWeights, MeaningFilter, Query, QueryCursor,
-	QueryExecution, QueryFilterIdentity, QueryOrder, QueryPaginationPlan, QueryPlan,
-	QueryPlanOrdering, QueryPlanStep, QueryPlanStrategy, QueryPrimaryOrder, QueryRankingMode,
-	QueryResult, QueryTieBreaker, RelationFilter, TemporalFilter,
+	ContextFilter, DecayAnchor, DecayRanking, ExpressionFilter, FeedbackBoost, HybridWeights,
+	MeaningFilter, Query, QueryCursor, QueryExecution, QueryFilterIdentity, QueryOrder,
+	QueryPaginationPlan, QueryPlan, QueryPlanOrdering, QueryPlanStep, QueryPlanStrategy,
+	QueryPrimaryOrder, QueryRankingMode, QueryResult, QueryTieBreaker, RelationFilter,
+	TemporalFilter,
 };
 pub use storage::{
-	EmbeddingProfile, EntryRevision, IntegrityIssue, IntegrityReport, RevisionOperation,
-	SqliteStorage, StorageBackend, StorageError, StorageResult,
+	AccessPolicy, AccessStats, Change, ChangeOperation, CollectionInfo, CompactionReport,
+	DeletedEntry, EmbeddingProfile, EntryRevision, Feedback, FeedbackSignal, IntegrityIssue,
+	IntegrityReport, RetentionPolicy, RevisionOperation, SqliteStorage, StorageBackend,
+	StorageError, StorageResult, Tombstone, TrashPolicy, UpsertOutcome,
 };
-pub use types::{cosine_similarity, Entry};
+pub use types::{cosine_similarity, Entry, DEFAULT_COLLECTION, DEFAULT_TENANT};
 
 #[cfg(feature = "ffi")]
 pub mod ffi;
 
+use hooks::Hooks;
 use std::path::Path;
 
 /// Main ContextDB interface
@@ -69,6 +99,7 @@ use std::path::Path;
 /// Uses a trait-based storage backend, allowing you to swap SQLite, PostgreSQL, MySQL, etc.
 pub struct ContextDB {
 	storage: Box<dyn StorageBackend>,
+	hooks: Hooks,
 }
 
 impl ContextDB {
@@ -76,6 +107,7 @@ impl ContextDB {
 	pub fn in_memory() -> StorageResult<Self> {
 		Ok(Self {
 			storage: Box::new(SqliteStorage::in_memory()?),
+			hooks: Hooks::default(),
 		})
 	}
 
@@ -83,6 +115,7 @@ impl ContextDB {
 	pub fn new<P: AsRef<Path>>(path: P) -> StorageResult<Self> {
 		Ok(Self {
 			storage: Box::new(SqliteStorage::new(path)?),
+			hooks: Hooks::default(),
 		})
 	}
 
@@ -92,52 +125,386 @@ impl ContextDB {
 	pub fn with_backend<B: StorageBackend + 'static>(backend: B) -> Self {
 		Self {
 			storage: Box::new(backend),
+			hooks: Hooks::default(),
+		}
+	}
+
+	/// Scope this handle to one tenant
+	///
+	/// Reads see only the tenant's entries, revisions, relations, changes, and
+	/// tombstones, and writes must carry the tenant. Database-wide maintenance
+	/// calls such as `integrity_check` and `backup` remain unscoped.
+	pub fn with_tenant(mut self, tenant: &str) -> StorageResult<Self> {
+		self.storage.scope_to_tenant(tenant)?;
+		Ok(self)
+	}
+
+	/// Tenant this handle is scoped to, if any
+	pub fn tenant(&self) -> Option<&str> {
+		self.storage.tenant_scope()
+	}
+
+	/// Register a hook that runs before each mutation and can veto it by returning an error
+	///
+	/// The hook sees the database state before the mutation.
+	pub fn before_mutation<F>(&mut self, hook: F) -> HookId
+	where
+		F: FnMut(&dyn StorageBackend, &Mutation<'_>) -> StorageResult<()> + Send + 'static,
+	{
+		self.hooks.add_before(Box::new(hook))
+	}
+
+	/// Register a hook that runs after each committed mutation
+	///
+	/// Writes the hook makes through the storage backend do not trigger hooks.
+	pub fn after_mutation<F>(&mut self, hook: F) -> HookId
+	where
+		F: FnMut(&mut dyn StorageBackend, &Mutation<'_>) + Send + 'static,
+	{
+		self.hooks.add_after(Box::new(hook))
+	}
+
+	/// Remove a registered hook, returning whether it existed
+	pub fn remove_hook(&mut self, id: HookId) -> bool {
+		self.hooks.remove(id)
+	}
+
+	fn mutate<T>(
+		&mut self,
+		mutation: Mutation<'_>,
+		write: impl FnOnce(&mut dyn StorageBackend) -> StorageResult<T>,
+	) -> StorageResult<T> {
+		self.hooks.run_before(self.storage.as_ref(), &mutation)?;
+		let result = write(self.storage.as_mut())?;
+		self.hooks.run_after(self.storage.as_mut(), &mutation);
+		Ok(result)
+	}
+
+	/// Run an insert's hooks, telling after-hooks which entries were written
+	/// and which the deduplication policy dropped or merged
+	fn insert_entries(&mut self, entries: &[Entry]) -> StorageResult<Vec<uuid::Uuid>> {
+		let mutation = Mutation::Insert(entries);
+		self.hooks.run_before(self.storage.as_ref(), &mutation)?;
+		let ids = self.storage.insert_batch_returning_ids(entries)?;
+		let duplicates: Vec<(uuid::Uuid, uuid::Uuid)> = entries
+			.iter()
+			.zip(&ids)
+			.filter(|(entry, id)| entry.id != **id)
+			.map(|(entry, id)| (entry.id, *id))
+			.collect();
+		if duplicates.is_empty() {
+			self.hooks.run_after(self.storage.as_mut(), &mutation);
+			return Ok(ids);
+		}
+		let written: Vec<Entry> = entries
+			.iter()
+			.zip(&ids)
+			.filter(|(entry, id)| entry.id == **id)
+			.map(|(entry, _)| entry.clone())
+			.collect();
+		if !written.is_empty() {
+			self.hooks
+				.run_after(self.storage.as_mut(), &Mutation::Insert(&written));
 		}
+		self.hooks
+			.run_after(self.storage.as_mut(), &Mutation::Deduplicated(&duplicates));
+		Ok(ids)
 	}
 
 	/// Insert a new entry into the database
 	pub fn insert(&mut self, entry: &Entry) -> StorageResult<()> {
-		self.storage.insert(entry)
+		self.insert_entries(std::slice::from_ref(entry)).map(|_| ())
 	}
 
 	/// Insert multiple entries atomically
 	pub fn insert_batch(&mut self, entries: &[Entry]) -> StorageResult<()> {
-		self.storage.insert_batch(entries)
+		self.insert_entries(entries).map(|_| ())
+	}
+
+	/// Insert an entry and return the ID it is stored under
+	///
+	/// Under a deduplication policy that skips or merges, this is the ID of
+	/// the existing entry when `entry` duplicates one.
+	pub fn insert_returning_id(&mut self, entry: &Entry) -> StorageResult<uuid::Uuid> {
+		let mut ids = self.insert_batch_returning_ids(std::slice::from_ref(entry))?;
+		Ok(ids.remove(0))
+	}
+
+	/// Insert multiple entries atomically and return the ID each is stored under
+	pub fn insert_batch_returning_ids(
+		&mut self,
+		entries: &[Entry],
+	) -> StorageResult<Vec<uuid::Uuid>> {
+		self.insert_entries(entries)
+	}
+
+	/// Insert an entry, or update it if its ID already exists
+	pub fn upsert(&mut self, entry: &Entry) -> StorageResult<UpsertOutcome> {
+		self.mutate(Mutation::Upsert(std::slice::from_ref(entry)), |storage| {
+			storage.upsert(entry)
+		})
+	}
+
+	/// Insert or update multiple entries atomically
+	pub fn upsert_batch(&mut self, entries: &[Entry]) -> StorageResult<Vec<UpsertOutcome>> {
+		self.mutate(Mutation::Upsert(entries), |storage| {
+			storage.upsert_batch(entries)
+		})
+	}
+
+	/// Insert an entry, or update the one stored under its `external_id`
+	///
+	/// Returns the ID the entry is stored under and what the write did.
+	pub fn upsert_by_external_id(
+		&mut self,
+		entry: &Entry,
+	) -> StorageResult<(uuid::Uuid, UpsertOutcome)> {
+		self.mutate(Mutation::Upsert(std::slice::from_ref(entry)), |storage| {
+			storage.upsert_by_external_id(entry)
+		})
+	}
+
+	/// Insert or update multiple entries by `external_id` atomically
+	pub fn upsert_batch_by_external_id(
+		&mut self,
+		entries: &[Entry],
+	) -> StorageResult<Vec<(uuid::Uuid, UpsertOutcome)>> {
+		self.mutate(Mutation::Upsert(entries), |storage| {
+			storage.upsert_batch_by_external_id(entries)
+		})
 	}
 
 	/// Get an entry by its ID
 	pub fn get(&self, id: uuid::Uuid) -> StorageResult<Entry> {
-		self.storage.get(id)
+		let entry = self.storage.get(id)?;
+		self.storage.record_access(&[entry.id])?;
+		Ok(entry)
+	}
+
+	/// Get the entry keyed by `external_id` in this handle's tenant
+	pub fn get_by_external_id(&self, external_id: &str) -> StorageResult<Option<Entry>> {
+		let entry = self.storage.get_by_external_id(external_id)?;
+		if let Some(entry) = &entry {
+			self.storage.record_access(&[entry.id])?;
+		}
+		Ok(entry)
 	}
 
 	/// Execute a query and return matching entries
 	pub fn query(&self, query: &Query) -> StorageResult<Vec<QueryResult>> {
-		self.storage.query(query)
+		let results = self.storage.query(query)?;
+		self.record_access(query, results.iter().map(|result| result.entry.id))?;
+		Ok(results)
 	}
 
 	/// Execute a query and return both rows and top-level execution provenance
 	pub fn execute(&self, query: &Query) -> StorageResult<QueryExecution> {
-		self.storage.execute(query)
+		let execution = self.storage.execute(query)?;
+		let ids = execution.results.iter().map(|result| result.entry.id);
+		self.record_access(query, ids)?;
+		Ok(execution)
+	}
+
+	/// Score each case's top `k` results under `config` against its judgments
+	///
+	/// Evaluation reads do not count as accesses.
+	pub fn evaluate(
+		&self,
+		cases: &[EvalCase],
+		k: usize,
+		config: &EvalConfig,
+	) -> StorageResult<EvalReport> {
+		eval::evaluate(self, cases, k, config)
+	}
+
+	/// Evaluate two configs over the same cases and report the change in their means
+	pub fn compare_configs(
+		&self,
+		cases: &[EvalCase],
+		k: usize,
+		baseline: &EvalConfig,
+		candidate: &EvalConfig,
+	) -> StorageResult<EvalComparison> {
+		eval::compare(self, cases, k, baseline, candidate)
+	}
+
+	/// Run a query and pack the best-ranked entries that fit a token budget
+	///
+	/// `counter` measures each entry's expression. The returned pack reports
+	/// every entry that was left out and why.
+	pub fn pack_context(
+		&self,
+		query: &Query,
+		options: &PackOptions,
+		counter: &impl TokenCounter,
+	) -> StorageResult<ContextPack> {
+		let pack = pack::pack_context(self.storage.as_ref(), query, options, counter)?;
+		self.record_access(query, pack.entries.iter().map(|packed| packed.entry.id))?;
+		Ok(pack)
+	}
+
+	/// Record that a query returned `ids`, unless it opted out of tracking
+	fn record_access(
+		&self,
+		query: &Query,
+		ids: impl Iterator<Item = uuid::Uuid>,
+	) -> StorageResult<()> {
+		if query.skip_access_tracking {
+			return Ok(());
+		}
+		self.storage.record_access(&ids.collect::<Vec<_>>())
 	}
 
 	/// Update an existing entry
 	pub fn update(&mut self, entry: &Entry) -> StorageResult<()> {
-		self.storage.update(entry)
+		self.mutate(Mutation::Update(std::slice::from_ref(entry)), |storage| {
+			storage.update(entry)
+		})
+	}
+
+	/// Update an entry only if its stored version equals `expected_version`
+	pub fn update_if_version(&mut self, entry: &Entry, expected_version: u64) -> StorageResult<()> {
+		self.mutate(Mutation::Update(std::slice::from_ref(entry)), |storage| {
+			storage.update_if_version(entry, expected_version)
+		})
 	}
 
 	/// Update multiple entries atomically
 	pub fn update_batch(&mut self, entries: &[Entry]) -> StorageResult<()> {
-		self.storage.update_batch(entries)
+		self.mutate(Mutation::Update(entries), |storage| {
+			storage.update_batch(entries)
+		})
+	}
+
+	/// Apply a partial update to an entry and return the result
+	pub fn patch(&mut self, id: uuid::Uuid, patch: &EntryPatch) -> StorageResult<Entry> {
+		self.mutate(
+			Mutation::Patch {
+				entry_id: id,
+				patch,
+			},
+			|storage| storage.patch(id, patch),
+		)
+	}
+
+	/// Apply a partial update only if the stored version equals `expected_version`
+	pub fn patch_if_version(
+		&mut self,
+		id: uuid::Uuid,
+		patch: &EntryPatch,
+		expected_version: u64,
+	) -> StorageResult<Entry> {
+		self.mutate(
+			Mutation::Patch {
+				entry_id: id,
+				patch,
+			},
+			|storage| storage.patch_if_version(id, patch, expected_version),
+		)
 	}
 
 	/// Delete an entry by ID
 	pub fn delete(&mut self, id: uuid::Uuid) -> StorageResult<()> {
-		self.storage.delete(id)
+		self.mutate(Mutation::Delete(&[id]), |storage| storage.delete(id))
+	}
+
+	/// Delete an entry only if its stored version equals `expected_version`
+	pub fn delete_if_version(
+		&mut self,
+		id: uuid::Uuid,
+		expected_version: u64,
+	) -> StorageResult<()> {
+		self.mutate(Mutation::Delete(&[id]), |storage| {
+			storage.delete_if_version(id, expected_version)
+		})
 	}
 
 	/// Delete multiple entries atomically
 	pub fn delete_batch(&mut self, ids: &[uuid::Uuid]) -> StorageResult<()> {
-		self.storage.delete_batch(ids)
+		self.mutate(Mutation::Delete(ids), |storage| storage.delete_batch(ids))
+	}
+
+	/// Erase an entry and its revision history, leaving only a tombstone
+	pub fn purge(&mut self, id: uuid::Uuid) -> StorageResult<()> {
+		self.mutate(Mutation::Purge(&[id]), |storage| storage.purge(id))
+	}
+
+	/// Erase multiple entries atomically
+	pub fn purge_batch(&mut self, ids: &[uuid::Uuid]) -> StorageResult<()> {
+		self.mutate(Mutation::Purge(ids), |storage| storage.purge_batch(ids))
+	}
+
+	/// Erase every current entry matching a query, returning the purged IDs
+	pub fn purge_matching(&mut self, query: &Query) -> StorageResult<Vec<uuid::Uuid>> {
+		storage::purge_matching_with(
+			self,
+			query,
+			|db| db.storage.as_ref(),
+			|db, ids| db.purge_batch(ids),
+		)
+	}
+
+	/// Delete every expired entry in batches of 500, returning the deleted IDs
+	pub fn sweep_expired(&mut self) -> StorageResult<Vec<uuid::Uuid>> {
+		self.sweep_expired_in_batches(500)
+	}
+
+	/// Delete every expired entry, `batch_size` per transaction, returning the deleted IDs
+	///
+	/// Each batch runs the delete hooks and records delete revisions.
+	pub fn sweep_expired_in_batches(
+		&mut self,
+		batch_size: usize,
+	) -> StorageResult<Vec<uuid::Uuid>> {
+		storage::sweep_expired_with(
+			self,
+			batch_size,
+			|db| db.storage.as_ref(),
+			|db, ids| db.delete_batch(ids),
+		)
+	}
+
+	/// Return up to `limit` changes after sequence number `seq`
+	pub fn changes_since(&self, seq: u64, limit: usize) -> StorageResult<Vec<Change>> {
+		self.storage.changes_since(seq, limit)
+	}
+
+	/// Return tombstones for purged entries
+	pub fn tombstones(&self) -> StorageResult<Vec<Tombstone>> {
+		self.storage.tombstones()
+	}
+
+	/// Reclaim space so erased bytes no longer remain in the database files
+	pub fn vacuum(&mut self) -> StorageResult<()> {
+		self.storage.vacuum()
+	}
+
+	/// Add a directed relation without rewriting the source entry
+	pub fn relate(&mut self, from: uuid::Uuid, to: uuid::Uuid) -> StorageResult<()> {
+		self.mutate(Mutation::Relate(&[(from, to)]), |storage| {
+			storage.relate(from, to)
+		})
+	}
+
+	/// Add multiple directed relations atomically
+	pub fn relate_batch(&mut self, edges: &[(uuid::Uuid, uuid::Uuid)]) -> StorageResult<()> {
+		self.mutate(Mutation::Relate(edges), |storage| {
+			storage.relate_batch(edges)
+		})
+	}
+
+	/// Remove a directed relation without rewriting the source entry
+	pub fn unrelate(&mut self, from: uuid::Uuid, to: uuid::Uuid) -> StorageResult<()> {
+		self.mutate(Mutation::Unrelate(&[(from, to)]), |storage| {
+			storage.unrelate(from, to)
+		})
+	}
+
+	/// Remove multiple directed relations atomically
+	pub fn unrelate_batch(&mut self, edges: &[(uuid::Uuid, uuid::Uuid)]) -> StorageResult<()> {
+		self.mutate(Mutation::Unrelate(edges), |storage| {
+			storage.unrelate_batch(edges)
+		})
 	}
 
 	/// Count total entries in the database
@@ -155,12 +522,12 @@ impl ContextDB {
 		self.storage.backup_to(destination.as_ref())
 	}
 
-	/// Read the configured embedding profile
+	/// Read the default collection's embedding profile
 	pub fn embedding_profile(&self) -> StorageResult<Option<EmbeddingProfile>> {
 		self.storage.embedding_profile()
 	}
 
-	/// Configure the database-wide embedding model, version, and dimensions
+	/// Configure the default collection's embedding model, version, and dimensions
 	pub fn set_embedding_profile(&mut self, profile: &EmbeddingProfile) -> StorageResult<()> {
 		self.storage.set_embedding_profile(profile)
 	}
@@ -173,13 +540,60 @@ impl ContextDB {
 		self.storage.adopt_legacy_embedding_profile(profile)
 	}
 
-	/// Atomically replace every stored vector and change the embedding profile
+	/// Atomically replace every vector in the default collection and change its embedding profile
 	pub fn migrate_embeddings(
 		&mut self,
 		profile: &EmbeddingProfile,
 		replacements: &[(uuid::Uuid, Vec<f32>)],
 	) -> StorageResult<()> {
-		self.storage.migrate_embeddings(profile, replacements)
+		self.mutate(
+			Mutation::MigrateEmbeddings {
+				collection: DEFAULT_COLLECTION,
+				profile,
+				replacements,
+			},
+			|storage| storage.migrate_embeddings(profile, replacements),
+		)
+	}
+
+	/// List collections with their entry counts and embedding profiles
+	pub fn collections(&self) -> StorageResult<Vec<CollectionInfo>> {
+		self.storage.collections()
+	}
+
+	/// Read a collection's embedding profile
+	pub fn collection_embedding_profile(
+		&self,
+		collection: &str,
+	) -> StorageResult<Option<EmbeddingProfile>> {
+		self.storage.collection_embedding_profile(collection)
+	}
+
+	/// Configure a collection's embedding model, version, and dimensions
+	pub fn set_collection_embedding_profile(
+		&mut self,
+		collection: &str,
+		profile: &EmbeddingProfile,
+	) -> StorageResult<()> {
+		self.storage
+			.set_collection_embedding_profile(collection, profile)
+	}
+
+	/// Atomically replace every vector in a collection and change its embedding profile
+	pub fn migrate_collection_embeddings(
+		&mut self,
+		collection: &str,
+		profile: &EmbeddingProfile,
+		replacements: &[(uuid::Uuid, Vec<f32>)],
+	) -> StorageResult<()> {
+		self.mutate(
+			Mutation::MigrateEmbeddings {
+				collection,
+				profile,
+				replacements,
+			},
+			|storage| storage.migrate_collection_embeddings(collection, profile, replacements),
+		)
 	}
 
 	/// Return durable revision history for an entry
@@ -187,11 +601,216 @@ impl ContextDB {
 		self.storage.revisions(id)
 	}
 
+	/// Return the stored revision retention policy
+	pub fn retention_policy(&self) -> StorageResult<RetentionPolicy> {
+		self.storage.retention_policy()
+	}
+
+	/// Store the revision retention policy
+	pub fn set_retention_policy(&mut self, policy: &RetentionPolicy) -> StorageResult<()> {
+		self.storage.set_retention_policy(policy)
+	}
+
+	/// Compact revision history with the stored retention policy
+	pub fn compact_revisions(&mut self) -> StorageResult<CompactionReport> {
+		self.storage.compact_revisions()
+	}
+
+	/// Compact revision history with an explicit retention policy
+	pub fn compact_revisions_with(
+		&mut self,
+		policy: &RetentionPolicy,
+	) -> StorageResult<CompactionReport> {
+		self.storage.compact_revisions_with(policy)
+	}
+
+	/// Compare two revisions of an entry field by field
+	pub fn diff_revisions(
+		&self,
+		id: uuid::Uuid,
+		from_revision: uuid::Uuid,
+		to_revision: uuid::Uuid,
+	) -> StorageResult<RevisionDiff> {
+		let revisions = self.storage.revisions(id)?;
+		let find = |revision_id: uuid::Uuid| {
+			revisions
+				.iter()
+				.find(|revision| revision.revision_id == revision_id)
+				.ok_or(StorageError::NotFound(revision_id))
+		};
+		Ok(RevisionDiff::between(
+			find(from_revision)?,
+			find(to_revision)?,
+		))
+	}
+
+	/// Restore an entry to one of its revisions, recording a new revision
+	pub fn revert(
+		&mut self,
+		entry_id: uuid::Uuid,
+		revision_id: uuid::Uuid,
+	) -> StorageResult<Entry> {
+		self.mutate(
+			Mutation::Revert {
+				entry_id,
+				revision_id: Some(revision_id),
+			},
+			|storage| storage.revert(entry_id, revision_id),
+		)
+	}
+
+	/// Return the trash policy that decides whether deletes are soft
+	pub fn trash_policy(&self) -> StorageResult<TrashPolicy> {
+		self.storage.trash_policy()
+	}
+
+	/// Store the trash policy
+	pub fn set_trash_policy(&mut self, policy: &TrashPolicy) -> StorageResult<()> {
+		self.storage.set_trash_policy(policy)
+	}
+
+	/// Return the policy that decides whether reads record entry access
+	pub fn access_policy(&self) -> StorageResult<AccessPolicy> {
+		self.storage.access_policy()
+	}
+
+	/// Store the access-tracking policy
+	pub fn set_access_policy(&mut self, policy: &AccessPolicy) -> StorageResult<()> {
+		self.storage.set_access_policy(policy)
+	}
+
+	/// Return when an entry was last returned by a tracked read and how often
+	pub fn access_stats(&self, entry_id: uuid::Uuid) -> StorageResult<Option<AccessStats>> {
+		self.storage.access_stats(entry_id)
+	}
+
+	/// Return visible entries that no tracked read has returned, oldest first
+	pub fn never_accessed(&self) -> StorageResult<Vec<Entry>> {
+		self.storage.never_accessed()
+	}
+
+	/// Record whether an entry returned for a query helped
+	///
+	/// Pass `Query::fingerprint` of the query that returned the entry, or
+	/// any other stable key for the search.
+	pub fn record_feedback(
+		&mut self,
+		query_fingerprint: &str,
+		entry_id: uuid::Uuid,
+		signal: FeedbackSignal,
+	) -> StorageResult<Feedback> {
+		self.storage
+			.record_feedback(query_fingerprint, entry_id, signal)
+	}
+
+	/// Return the feedback recorded for an entry, oldest first
+	pub fn feedback(&self, entry_id: uuid::Uuid) -> StorageResult<Vec<Feedback>> {
+		self.storage.feedback(entry_id)
+	}
+
+	/// Return the deduplication policy applied to inserts
+	pub fn dedup_policy(&self) -> StorageResult<DedupPolicy> {
+		self.storage.dedup_policy()
+	}
+
+	/// Store the deduplication policy, rehashing stored entries under it
+	///
+	/// Fails with `Duplicate` when enabling it finds two live entries with the
+	/// same content.
+	pub fn set_dedup_policy(&mut self, policy: &DedupPolicy) -> StorageResult<()> {
+		self.storage.set_dedup_policy(policy)
+	}
+
+	/// Cluster entries whose meaning similarity reaches `threshold`
+	pub fn find_near_duplicates(&self, threshold: f32) -> StorageResult<Vec<NearDuplicateCluster>> {
+		self.storage.find_near_duplicates(threshold)
+	}
+
+	/// Merge entries into one survivor and remove the rest
+	///
+	/// Removed entries follow the trash policy, so a soft-deleting database
+	/// keeps them restorable.
+	pub fn merge(&mut self, ids: &[uuid::Uuid], strategy: MergeStrategy) -> StorageResult<Entry> {
+		self.mutate(
+			Mutation::Merge {
+				entry_ids: ids,
+				strategy,
+			},
+			|storage| storage.merge(ids, strategy),
+		)
+	}
+
+	/// Return entries in the trash, oldest deletion first
+	pub fn list_deleted(&self) -> StorageResult<Vec<DeletedEntry>> {
+		self.storage.list_deleted()
+	}
+
+	/// Move an entry out of the trash together with its relations
+	pub fn restore_deleted(&mut self, entry_id: uuid::Uuid) -> StorageResult<Entry> {
+		self.mutate(
+			Mutation::Revert {
+				entry_id,
+				revision_id: None,
+			},
+			|storage| storage.restore_deleted(entry_id),
+		)
+	}
+
+	/// Permanently remove trashed entries whose grace period has ended, returning their IDs
+	pub fn empty_trash(&mut self) -> StorageResult<Vec<uuid::Uuid>> {
+		let grace = chrono::Duration::days(i64::from(self.trash_policy()?.grace_period_days));
+		let cutoff = chrono::Utc::now() - grace;
+		let ids: Vec<uuid::Uuid> = self
+			.list_deleted()?
+			.into_iter()
+			.filter(|deleted| deleted.deleted_at <= cutoff)
+			.map(|deleted| deleted.entry.id)
+			.collect();
+		self.remove_from_trash(&ids)?;
+		Ok(ids)
+	}
+
+	/// Permanently remove specific entries from the trash, ignoring the grace period
+	pub fn remove_from_trash(&mut self, ids: &[uuid::Uuid]) -> StorageResult<()> {
+		if ids.is_empty() {
+			return Ok(());
+		}
+		self.mutate(Mutation::EmptyTrash(ids), |storage| {
+			storage.remove_from_trash(ids)
+		})
+	}
+
+	/// Restore a deleted entry from its deletion revision
+	pub fn undelete(&mut self, entry_id: uuid::Uuid) -> StorageResult<Entry> {
+		self.mutate(
+			Mutation::Revert {
+				entry_id,
+				revision_id: None,
+			},
+			|storage| storage.undelete(entry_id),
+		)
+	}
+
 	/// Create an index for a frequently filtered JSON Pointer context path
 	pub fn create_context_index(&mut self, path: &str) -> StorageResult<String> {
 		self.storage.create_context_index(path)
 	}
 
+	/// Create a context-path index covering only one collection's entries
+	pub fn create_collection_context_index(
+		&mut self,
+		collection: &str,
+		path: &str,
+	) -> StorageResult<String> {
+		self.storage
+			.create_collection_context_index(collection, path)
+	}
+
+	/// Summarize connected components, degrees, isolated entries, and cycles in the relation graph
+	pub fn graph_stats(&self, top: usize) -> StorageResult<GraphStats> {
+		self.storage.graph_stats(top)
+	}
+
 	/// Restore a SQLite snapshot into a new destination database
 	pub fn restore<P: AsRef<Path>, Q: AsRef<Path>>(
 		backup: P,
@@ -296,6 +915,258 @@ mod tests {
 		assert_eq!(db.count().unwrap(), 0);
 	}
 
+	#[test]
+	fn test_empty_trash_respects_grace_period() {
+		let mut db = ContextDB::in_memory().unwrap();
+		db.set_trash_policy(&TrashPolicy {
+			soft_delete: true,
+			grace_period_days: 30,
+		})
+		.unwrap();
+		let entry = Entry::new(vec![0.1], "Trashed".to_string());
+		db.insert(&entry).unwrap();
+		db.delete(entry.id).unwrap();
+
+		assert!(db.empty_trash().unwrap().is_empty());
+		assert_eq!(db.list_deleted().unwrap().len(), 1);
+
+		db.set_trash_policy(&TrashPolicy {
+			soft_delete: true,
+			grace_period_days: 0,
+		})
+		.unwrap();
+		assert_eq!(db.empty_trash().unwrap(), vec![entry.id]);
+		assert!(db.list_deleted().unwrap().is_empty());
+		assert!(matches!(
+			db.restore_deleted(entry.id),
+			Err(StorageError::NotFound(_))
+		));
+	}
+
+	#[test]
+	fn test_insert_returning_id_reports_the_deduplicated_entry() {
+		let mut db = ContextDB::in_memory().unwrap();
+		db.set_dedup_policy(&DedupPolicy {
+			enabled: true,
+			..DedupPolicy::default()
+		})
+		.unwrap();
+		let entry = Entry::new(vec![0.1], "Remember this".to_string());
+		let repeated = Entry::new(vec![0.2], "remember   this".to_string());
+
+		assert_eq!(db.insert_returning_id(&entry).unwrap(), entry.id);
+		assert_eq!(db.insert_returning_id(&repeated).unwrap(), entry.id);
+		db.insert(&repeated).unwrap();
+		assert_eq!(db.count().unwrap(), 1);
+	}
+
+	#[test]
+	fn test_collapsing_query_returns_documents_with_their_best_chunk() {
+		let mut db = ContextDB::in_memory().unwrap();
+		let document = Entry::new(
+			vec![0.5, 0.5],
+			"Shipping takes a week.\n\nRefunds take three days.".to_string(),
+		);
+		let chunked = Chunker::new(ChunkUnit::Paragraphs, 1)
+			.chunk_document(document.clone(), |text| match text.contains("Refunds") {
+				true => vec![1.0, 0.0],
+				false => vec![0.0, 1.0],
+			})
+			.unwrap();
+		let standalone = Entry::new(vec![0.9, 0.1], "Refund policy FAQ".to_string());
+		db.insert_batch(&chunked.entries()).unwrap();
+		db.insert(&standalone).unwrap();
+		let query = Query::new().with_meaning(vec![1.0, 0.0], None);
+
+		let chunks = db.query(&query).unwrap();
+		let collapsed = db
+			.execute(&query.clone().collapsing_chunks().with_limit(2))
+			.unwrap();
+
+		assert_eq!(chunks.len(), 4);
+		assert_eq!(chunks[0].entry.id, chunked.chunks[1].id);
+		let ids: Vec<_> = collapsed
+			.results
+			.iter()
+			.map(|result| result.entry.id)
+			.collect();
+		assert_eq!(ids, vec![document.id, standalone.id]);
+		let best = collapsed.results[0].best_chunk.as_ref().unwrap();
+		assert_eq!(best.id, chunked.chunks[1].id);
+		assert_eq!(collapsed.results[0].similarity_score, Some(1.0));
+		assert!(collapsed.results[1].best_chunk.is_none());
+		assert!(collapsed
+			.plan
+			.steps
+			.iter()
+			.any(|step| step.strategy == QueryPlanStrategy::ChunkCollapse
+				&& step.candidates_before == 4
+				&& step.candidates_after == 2));
+	}
+
+	#[test]
+	fn test_pack_context_fits_ranked_and_related_entries_into_a_budget() {
+		let mut db = ContextDB::in_memory().unwrap();
+		let base = Utc::now();
+		let entry = |meaning: Vec<f32>, text: &str, seconds: i64| {
+			let mut entry = Entry::new(meaning, text.to_string());
+			entry.created_at = base - chrono::Duration::seconds(10 - seconds);
+			entry
+		};
+		let background = entry(vec![0.0, 1.0], "Order placed on Monday", 0);
+		let best =
+			entry(vec![1.0, 0.0], "Refund approved for the order", 2).add_relation(background.id);
+		let long = entry(
+			vec![0.9, 0.1],
+			"Refund policy covers every order placed within thirty days",
+			1,
+		);
+		let short = entry(vec![0.8, 0.2], "Refunds take days", 3);
+		db.insert_batch(&[
+			background.clone(),
+			best.clone(),
+			long.clone(),
+			short.clone(),
+		])
+		.unwrap();
+		let query = Query::new().with_meaning(vec![1.0, 0.0], Some(0.5));
+
+		let ranked = db
+			.pack_context(&query, &PackOptions::new(8), &WhitespaceTokenCounter)
+			.unwrap();
+		let related = db
+			.pack_context(
+				&query,
+				&PackOptions::new(12).with_related().chronological(),
+				&|text: &str| text.split_whitespace().count(),
+			)
+			.unwrap();
+
+		assert_eq!(
+			ranked.expressions(),
+			vec!["Refund approved for the order", "Refunds take days"]
+		);
+		assert_eq!(ranked.used_tokens, 8);
+		assert_eq!(ranked.dropped.len(), 1);
+		assert_eq!(ranked.dropped[0].id, long.id);
+		assert_eq!(ranked.dropped[0].rank, Some(1));
+		assert_eq!(ranked.dropped[0].reason, DropReason::ExceedsBudget);
+
+		let ids: Vec<_> = related
+			.entries
+			.iter()
+			.map(|packed| packed.entry.id)
+			.collect();
+		assert_eq!(ids, vec![background.id, best.id, short.id]);
+		assert_eq!(related.entries[0].related_to, Some(best.id));
+		assert_eq!(related.entries[0].rank, None);
+		assert_eq!(related.used_tokens, 12);
+		assert_eq!(related.dropped[0].reason, DropReason::BudgetExhausted);
+	}
+
+	#[test]
+	fn test_tracked_reads_record_access_without_revisions() {
+		let mut db = ContextDB::in_memory().unwrap();
+		let earlier = |entry: Entry| Entry {
+			created_at: entry.created_at - chrono::Duration::hours(10),
+			updated_at: entry.updated_at - chrono::Duration::hours(10),
+			..entry
+		};
+		let read = earlier(Entry::new(vec![1.0, 0.0], "Read".to_string()));
+		let cold = earlier(Entry::new(vec![0.0, 1.0], "Cold".to_string()));
+		db.insert_batch(&[read.clone(), cold.clone()]).unwrap();
+		db.get(read.id).unwrap();
+		assert_eq!(db.access_stats(read.id).unwrap(), None);
+		db.set_access_policy(&AccessPolicy { track: true }).unwrap();
+
+		db.get(read.id).unwrap();
+		db.query(&Query::new().with_meaning(vec![1.0, 0.0], Some(0.9)))
+			.unwrap();
+		db.query(&Query::new().without_access_tracking()).unwrap();
+
+		let stats = db.access_stats(read.id).unwrap().unwrap();
+		assert_eq!(stats.access_count, 2);
+		assert_eq!(db.access_stats(cold.id).unwrap(), None);
+		let never: Vec<_> = db
+			.never_accessed()
+			.unwrap()
+			.iter()
+			.map(|entry| entry.id)
+			.collect();
+		assert_eq!(never, vec![cold.id]);
+		assert_eq!(db.get(read.id).unwrap().version, 1);
+		assert_eq!(db.revisions(read.id).unwrap().len(), 1);
+
+		let ranked = db
+			.query(
+				&Query::new()
+					.with_decay(
+						DecayRanking::new(0.0, 1.0, 0.0, 1.0)
+							.anchored_at(DecayAnchor::LastAccessedAt),
+					)
+					.without_access_tracking(),
+			)
+			.unwrap();
+		assert_eq!(ranked[0].entry.id, read.id);
+		assert!(ranked[0].combined_score.unwrap() > 0.99);
+		assert!(ranked[1].combined_score.unwrap() < 0.01);
+	}
+
+	#[test]
+	fn test_evaluate_compares_query_configs_against_judgments() {
+		let mut db = ContextDB::in_memory().unwrap();
+		let db_entry = |meaning: Vec<f32>, text: &str| Entry::new(meaning, text.to_string());
+		let exact = db_entry(vec![1.0, 0.0], "Invoice reminder for the unpaid invoice");
+		let related = db_entry(vec![0.7, 0.7], "Invoice overdue policy");
+		let noise = db_entry(
+			vec![0.95, 0.3],
+			"Weekly team digest with travel plans, lunch menus, and one invoice",
+		);
+		db.set_access_policy(&AccessPolicy { track: true }).unwrap();
+		db.insert_batch(&[exact.clone(), related.clone(), noise.clone()])
+			.unwrap();
+		let cases = vec![EvalCase {
+			name: "invoices".to_string(),
+			query: Query::new()
+				.with_meaning(vec![1.0, 0.0], None)
+				.with_expression(ExpressionFilter::FullText("invoice".to_string()))
+				.with_limit(1),
+			judgments: [(exact.id, 2), (related.id, 1), (noise.id, 0)].into(),
+		}];
+		let lexical = EvalConfig {
+			hybrid_weights: Some(HybridWeights {
+				semantic: 0.0,
+				lexical: 1.0,
+			}),
+			..EvalConfig::default()
+		};
+		let semantic = EvalConfig {
+			hybrid_weights: Some(HybridWeights {
+				semantic: 1.0,
+				lexical: 0.0,
+			}),
+			..EvalConfig::default()
+		};
+
+		let report = db.evaluate(&cases, 2, &EvalConfig::default()).unwrap();
+		let comparison = db.compare_configs(&cases, 3, &lexical, &semantic).unwrap();
+
+		assert_eq!(report.cases[0].retrieved.len(), 2);
+		assert_eq!(report.mean.recall, 1.0);
+		assert_eq!(report.mean.reciprocal_rank, 1.0);
+		assert!((comparison.baseline.mean.ndcg - 1.0).abs() < 1e-6);
+		assert_eq!(comparison.candidate.cases[0].retrieved[1], noise.id);
+		assert!(comparison.delta.ndcg < 0.0);
+		assert_eq!(comparison.delta.recall, 0.0);
+		assert!(db.access_stats(exact.id).unwrap().is_none());
+		assert!(db.evaluate(&cases, 0, &EvalConfig::default()).is_err());
+		let unjudged = EvalCase {
+			judgments: [(noise.id, 0)].into(),
+			..cases[0].clone()
+		};
+		assert!(db.evaluate(&[unjudged], 2, &semantic).is_err());
+	}
+
 	#[test]
 	fn test_delete_nonexistent_entry() {
 		let mut db = ContextDB::in_memory().unwrap();
@@ -303,6 +1174,28 @@ mod tests {
 		assert!(db.delete(fake_id).is_err());
 	}
 
+	#[test]
+	fn test_diff_revisions() {
+		let mut db = ContextDB::in_memory().unwrap();
+		let mut entry = Entry::new(vec![0.1], "Before".to_string());
+		db.insert(&entry).unwrap();
+		entry.expression = "After".to_string();
+		entry.updated_at = Utc::now();
+		db.update(&entry).unwrap();
+		let revisions = db.revisions(entry.id).unwrap();
+
+		let diff = db
+			.diff_revisions(entry.id, revisions[0].revision_id, revisions[1].revision_id)
+			.unwrap();
+
+		assert!(diff.expression_changed());
+		assert_eq!(diff.operation, RevisionOperation::Update);
+		assert!(matches!(
+			db.diff_revisions(entry.id, revisions[0].revision_id, uuid::Uuid::new_v4()),
+			Err(StorageError::NotFound(_))
+		));
+	}
+
 	// ==================== Semantic Query Tests ====================
 
 	#[test]
@@ -0,0 +1,241 @@
+use crate::query::Query;
+use crate::storage::{StorageBackend, StorageError, StorageResult};
+use crate::types::Entry;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Counts the tokens a piece of text takes up in a prompt
+///
+/// Any `Fn(&str) -> usize` is a counter, so a model's tokenizer can be
+/// plugged in with a closure.
+pub trait TokenCounter {
+	/// Tokens in `text`
+	fn count(&self, text: &str) -> usize;
+}
+
+impl<F: Fn(&str) -> usize> TokenCounter for F {
+	fn count(&self, text: &str) -> usize {
+		self(text)
+	}
+}
+
+/// Counts whitespace-separated words, a rough stand-in for a model tokenizer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WhitespaceTokenCounter;
+
+impl TokenCounter for WhitespaceTokenCounter {
+	fn count(&self, text: &str) -> usize {
+		text.split_whitespace().count()
+	}
+}
+
+/// How query results are packed into a token budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackOptions {
+	/// Most tokens the packed expressions may take together
+	pub budget: usize,
+	/// Follow each packed entry's outgoing relations and pack their targets after it
+	pub include_related: bool,
+	/// Order packed entries by creation time instead of rank
+	pub chronological: bool,
+}
+
+/// Why an entry was left out of a [`ContextPack`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropReason {
+	/// The entry alone takes more tokens than the whole budget
+	ExceedsBudget,
+	/// The entry did not fit in what was left of the budget
+	BudgetExhausted,
+}
+
+/// An entry placed in a [`ContextPack`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackedEntry {
+	/// The packed entry
+	pub entry: Entry,
+	/// Tokens its expression takes
+	pub tokens: usize,
+	/// Position in the query results, or `None` for a related entry
+	pub rank: Option<usize>,
+	/// Packed entry whose relation pulled this one in
+	pub related_to: Option<Uuid>,
+}
+
+/// An entry left out of a [`ContextPack`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DroppedEntry {
+	/// The dropped entry's ID
+	pub id: Uuid,
+	/// Tokens its expression takes
+	pub tokens: usize,
+	/// Position in the query results, or `None` for a related entry
+	pub rank: Option<usize>,
+	/// Packed entry whose relation pulled this one in
+	pub related_to: Option<Uuid>,
+	/// Why it was left out
+	pub reason: DropReason,
+}
+
+/// Query results packed into a token budget
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextPack {
+	/// Packed entries in rank or chronological order
+	pub entries: Vec<PackedEntry>,
+	/// Entries that did not fit, in the order they were considered
+	pub dropped: Vec<DroppedEntry>,
+	/// Tokens the packed entries take together
+	pub used_tokens: usize,
+	/// The budget the entries were packed into
+	pub budget: usize,
+}
+
+impl PackOptions {
+	/// Pack results by rank into `budget` tokens without related entries
+	pub fn new(budget: usize) -> Self {
+		Self {
+			budget,
+			include_related: false,
+			chronological: false,
+		}
+	}
+
+	/// Pack the targets of each packed entry's relations after it
+	pub fn with_related(mut self) -> Self {
+		self.include_related = true;
+		self
+	}
+
+	/// Order packed entries by creation time
+	pub fn chronological(mut self) -> Self {
+		self.chronological = true;
+		self
+	}
+}
+
+/// Run `query` and pack its results greedily by rank into the budget
+///
+/// An entry that does not fit is reported as dropped and packing moves on, so
+/// a smaller entry further down may still fit. Each entry is considered once,
+/// whether it is reached by rank or by relation. Relation targets that are not
+/// visible are skipped.
+pub(crate) fn pack_context(
+	storage: &dyn StorageBackend,
+	query: &Query,
+	options: &PackOptions,
+	counter: &dyn TokenCounter,
+) -> StorageResult<ContextPack> {
+	let mut pack = ContextPack {
+		entries: Vec::new(),
+		dropped: Vec::new(),
+		used_tokens: 0,
+		budget: options.budget,
+	};
+	let mut seen = HashSet::new();
+	for (rank, result) in storage.query(query)?.into_iter().enumerate() {
+		if !seen.insert(result.entry.id) {
+			continue;
+		}
+		let entry = result.entry;
+		if !pack.place(entry.clone(), Some(rank), None, counter) || !options.include_related {
+			continue;
+		}
+		for target in &entry.relations {
+			if !seen.insert(*target) {
+				continue;
+			}
+			let related = match storage.get(*target) {
+				Ok(related) => related,
+				Err(StorageError::NotFound(_)) => continue,
+				Err(error) => return Err(error),
+			};
+			pack.place(related, None, Some(entry.id), counter);
+		}
+	}
+	if options.chronological {
+		pack.entries
+			.sort_by_key(|packed| (packed.entry.created_at, packed.entry.id));
+	}
+	Ok(pack)
+}
+
+impl ContextPack {
+	/// Expressions of the packed entries, in order
+	pub fn expressions(&self) -> Vec<&str> {
+		self.entries
+			.iter()
+			.map(|packed| packed.entry.expression.as_str())
+			.collect()
+	}
+
+	/// Pack `entry` if it fits, otherwise record why not
+	fn place(
+		&mut self,
+		entry: Entry,
+		rank: Option<usize>,
+		related_to: Option<Uuid>,
+		counter: &dyn TokenCounter,
+	) -> bool {
+		let tokens = counter.count(&entry.expression);
+		let reason = if tokens > self.budget {
+			DropReason::ExceedsBudget
+		} else if tokens > self.budget - self.used_tokens {
+			DropReason::BudgetExhausted
+		} else {
+			self.used_tokens += tokens;
+			self.entries.push(PackedEntry {
+				entry,
+				tokens,
+				rank,
+				related_to,
+			});
+			return true;
+		};
+		self.dropped.push(DroppedEntry {
+			id: entry.id,
+			tokens,
+			rank,
+			related_to,
+			reason,
+		});
+		false
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_place_reports_why_entries_do_not_fit() {
+		let mut pack = ContextPack {
+			entries: Vec::new(),
+			dropped: Vec::new(),
+			used_tokens: 0,
+			budget: 5,
+		};
+		let entry = |text: &str| Entry::new(vec![0.1], text.to_string());
+		let counter = WhitespaceTokenCounter;
+
+		assert!(pack.place(entry("one two three"), Some(0), None, &counter));
+		assert!(!pack.place(entry("one two three"), Some(1), None, &counter));
+		assert!(!pack.place(
+			entry("one two three four five six"),
+			Some(2),
+			None,
+			&counter
+		));
+		assert!(pack.place(entry("one two"), Some(3), None, &counter));
+
+		assert_eq!(pack.used_tokens, 5);
+		assert_eq!(pack.expressions(), vec!["one two three", "one two"]);
+		let reasons: Vec<_> = pack.dropped.iter().map(|dropped| dropped.reason).collect();
+		assert_eq!(
+			reasons,
+			vec![DropReason::BudgetExhausted, DropReason::ExceedsBudget]
+		);
+		assert_eq!(pack.dropped[1].tokens, 6);
+	}
+}
@@ -0,0 +1,143 @@
+use crate::types::Entry;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Partial update applied to a stored entry
+///
+/// Unset fields keep their stored values. `context` is an RFC 7396 JSON Merge
+/// Patch: object members are merged recursively, `null` members are removed,
+/// and any other value replaces the target.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EntryPatch {
+	/// Replacement expression
+	pub expression: Option<String>,
+	/// Replacement meaning vector
+	pub meaning: Option<Vec<f32>>,
+	/// Merge patch applied to the stored context
+	pub context: Option<Value>,
+}
+
+impl EntryPatch {
+	/// Create an empty patch
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Replace the expression
+	pub fn with_expression(mut self, expression: impl Into<String>) -> Self {
+		self.expression = Some(expression.into());
+		self
+	}
+
+	/// Replace the meaning vector
+	pub fn with_meaning(mut self, meaning: Vec<f32>) -> Self {
+		self.meaning = Some(meaning);
+		self
+	}
+
+	/// Merge-patch the context
+	pub fn with_context_patch(mut self, patch: Value) -> Self {
+		self.context = Some(patch);
+		self
+	}
+
+	/// Whether the patch sets no field
+	pub fn is_empty(&self) -> bool {
+		self.expression.is_none() && self.meaning.is_none() && self.context.is_none()
+	}
+
+	/// Return `entry` with the patch applied, leaving timestamps untouched
+	pub fn apply(&self, entry: &Entry) -> Entry {
+		let mut patched = entry.clone();
+		if let Some(expression) = &self.expression {
+			patched.expression = expression.clone();
+		}
+		if let Some(meaning) = &self.meaning {
+			patched.meaning = meaning.clone();
+		}
+		if let Some(patch) = &self.context {
+			merge_patch(&mut patched.context, patch);
+		}
+		patched
+	}
+}
+
+/// Apply an RFC 7396 JSON Merge Patch to `target`
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+	let Value::Object(members) = patch else {
+		*target = patch.clone();
+		return;
+	};
+	if !target.is_object() {
+		*target = Value::Object(Map::new());
+	}
+	if let Value::Object(object) = target {
+		for (key, value) in members {
+			if value.is_null() {
+				object.remove(key);
+			} else {
+				merge_patch(object.entry(key.clone()).or_insert(Value::Null), value);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_merge_patch_follows_rfc_7396() {
+		let mut target = json!({
+			"title": "Goodbye!",
+			"author": {"givenName": "John", "familyName": "Doe"},
+			"tags": ["example", "sample"],
+			"content": "This will be unchanged"
+		});
+		merge_patch(
+			&mut target,
+			&json!({
+				"title": "Hello!",
+				"phoneNumber": "+01-123-456-7890",
+				"author": {"familyName": null},
+				"tags": ["example"]
+			}),
+		);
+		assert_eq!(
+			target,
+			json!({
+				"title": "Hello!",
+				"author": {"givenName": "John"},
+				"tags": ["example"],
+				"content": "This will be unchanged",
+				"phoneNumber": "+01-123-456-7890"
+			})
+		);
+
+		let mut scalar = json!("text");
+		merge_patch(&mut scalar, &json!({"a": {"b": null, "c": 1}}));
+		assert_eq!(scalar, json!({"a": {"c": 1}}));
+		merge_patch(&mut scalar, &json!(null));
+		assert_eq!(scalar, Value::Null);
+	}
+
+	#[test]
+	fn test_entry_patch_changes_only_supplied_fields() {
+		let entry = Entry::new(vec![0.1, 0.2], "Original".to_string())
+			.with_context(json!({"status": "open", "owner": "ana"}));
+		let patch: EntryPatch =
+			serde_json::from_value(json!({"context": {"status": "closed", "owner": null}}))
+				.unwrap();
+
+		let patched = patch.apply(&entry);
+		assert_eq!(patched.expression, "Original");
+		assert_eq!(patched.meaning, entry.meaning);
+		assert_eq!(patched.context, json!({"status": "closed"}));
+		assert_eq!(patched.updated_at, entry.updated_at);
+
+		assert!(EntryPatch::new().is_empty());
+		assert!(serde_json::from_value::<EntryPatch>(json!({"expresion": "typo"})).is_err());
+	}
+}
@@ -1,3 +1,4 @@
+use crate::dedup::Fnv128;
 use crate::types::Entry;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -39,6 +40,57 @@ pub struct Query {
 
 	/// Whether to explain why results matched
 	pub explain: bool,
+
+	/// Evaluate against the state reconstructed from revision history at this time
+	pub as_of: Option<DateTime<Utc>>,
+
+	/// Collections to search; empty searches every collection
+	pub collections: Vec<String>,
+
+	/// Whether to return entries whose `expires_at` has passed
+	pub include_expired: bool,
+
+	/// Whether to replace chunk hits with their parent document
+	pub collapse_chunks: bool,
+
+	/// Rank by relevance, recency, and importance instead of relevance alone
+	pub decay: Option<DecayRanking>,
+
+	/// Whether to leave the returned entries' access records untouched
+	pub skip_access_tracking: bool,
+
+	/// Re-rank results by the relevance feedback recorded for them
+	pub feedback_boost: Option<FeedbackBoost>,
+}
+
+/// Prefix hashed into every [`Query::fingerprint`]; bumping it orphans stored feedback
+const FINGERPRINT_VERSION: &str = "query-fingerprint/1";
+
+/// Hash `value` as JSON with object keys sorted, whatever the map's own order
+fn write_canonical_json(hash: &mut Fnv128, value: &serde_json::Value) {
+	match value {
+		serde_json::Value::Array(items) => {
+			hash.write(b"[");
+			for item in items {
+				write_canonical_json(hash, item);
+				hash.write(b",");
+			}
+			hash.write(b"]");
+		}
+		serde_json::Value::Object(map) => {
+			let mut keys: Vec<&String> = map.keys().collect();
+			keys.sort();
+			hash.write(b"{");
+			for key in keys {
+				hash.write(serde_json::Value::from(key.as_str()).to_string().as_bytes());
+				hash.write(b":");
+				write_canonical_json(hash, &map[key]);
+				hash.write(b",");
+			}
+			hash.write(b"}");
+		}
+		scalar => hash.write(scalar.to_string().as_bytes()),
+	}
 }
 
 /// Cursor for continuing a deterministically ordered query
@@ -57,6 +109,51 @@ pub struct HybridWeights {
 	pub lexical: f32,
 }
 
+/// Timestamp that recency decay measures an entry's age from
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DecayAnchor {
+	/// When the entry was created
+	CreatedAt,
+	/// When the entry was last updated
+	#[default]
+	UpdatedAt,
+	/// When a tracked read last returned the entry, or `updated_at` if none has
+	LastAccessedAt,
+}
+
+/// Weights and half-life for ranking by relevance, recency, and importance
+///
+/// The score is the weighted mean of relevance, recency, and importance, each
+/// between 0 and 1. Relevance is the hybrid score, normalized similarity, or
+/// normalized BM25 relevance, whichever the query produces, and 1 when it
+/// produces none. Recency halves every `half_life_hours` of age. Entries
+/// without an importance count as 0.5.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct DecayRanking {
+	/// Weight applied to relevance
+	pub relevance: f32,
+	/// Weight applied to recency
+	pub recency: f32,
+	/// Weight applied to importance
+	pub importance: f32,
+	/// Hours of age after which recency falls to one half
+	pub half_life_hours: f32,
+	/// Timestamp age is measured from
+	pub anchor: DecayAnchor,
+}
+
+/// Re-ranking of results by recorded relevance feedback
+///
+/// Each result's ranking score moves by `weight × (up − down) / (up + down + 1)`,
+/// so the boost approaches `±weight` as one-sided feedback accumulates.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FeedbackBoost {
+	/// Largest amount feedback can add to or subtract from a ranking score
+	pub weight: f32,
+	/// Count only feedback recorded for this query fingerprint; `None` counts all
+	pub fingerprint: Option<String>,
+}
+
 /// Deterministic ordering for queries without semantic ranking
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub enum QueryOrder {
@@ -181,6 +278,14 @@ pub struct QueryResult {
 
 	/// Structured execution details when explanation was requested
 	pub plan: Option<QueryPlan>,
+
+	/// Best-ranked chunk of a document returned by a collapsing query
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub best_chunk: Option<Entry>,
+
+	/// Amount recorded feedback added to the ranking score, when re-ranked by feedback
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub feedback_boost: Option<f32>,
 }
 
 /// Results and execution provenance for one query
@@ -213,6 +318,12 @@ pub enum QueryPlanStrategy {
 	DeterministicSort,
 	/// Cursor, offset, and limit application
 	Pagination,
+	/// Reconstruction of entry state from revision snapshots
+	RevisionReplay,
+	/// Replacement of chunk hits with their parent documents
+	ChunkCollapse,
+	/// Re-ranking by recorded relevance feedback
+	FeedbackRerank,
 }
 
 /// Query component handled by an execution step
@@ -240,6 +351,20 @@ pub enum QueryFilterIdentity {
 	Ordering,
 	/// Cursor, offset, and limit
 	Pagination,
+	/// Point-in-time revision state
+	AsOf,
+	/// Collection scope
+	Collection,
+	/// Tenant scope of the storage handle
+	Tenant,
+	/// Exclusion of expired entries
+	Expiry,
+	/// Exclusion of entries in the trash
+	Trash,
+	/// Collapsing of chunks into their documents
+	Chunks,
+	/// Relevance feedback boost
+	Feedback,
 }
 
 /// One measured stage of query execution
@@ -271,6 +396,70 @@ pub enum QueryRankingMode {
 		/// Lexical component weight
 		lexical_weight: f32,
 	},
+	/// Weighted relevance, time-decayed recency, and importance
+	Decayed {
+		/// Relevance component weight
+		relevance_weight: f32,
+		/// Recency component weight
+		recency_weight: f32,
+		/// Importance component weight
+		importance_weight: f32,
+		/// Hours of age after which recency falls to one half
+		half_life_hours: f32,
+		/// Timestamp age is measured from
+		anchor: DecayAnchor,
+	},
+	/// Relevance or decayed score moved by recorded relevance feedback
+	FeedbackBoosted {
+		/// Most the feedback boost can move a score
+		feedback_weight: f32,
+		/// Whether the boost moves the decayed score rather than relevance
+		decayed: bool,
+	},
+}
+
+impl QueryRankingMode {
+	/// Ranking that produces the final order of `query`'s results
+	pub(crate) fn of(query: &Query) -> Self {
+		let has_full_text = matches!(query.expression, Some(ExpressionFilter::FullText(_)));
+		if let Some(boost) = &query.feedback_boost {
+			QueryRankingMode::FeedbackBoosted {
+				feedback_weight: boost.weight,
+				decayed: query.decay.is_some(),
+			}
+		} else if let Some(decay) = query.decay {
+			decay.ranking_mode()
+		} else if query.meaning.is_some() && has_full_text {
+			let weights = query.hybrid_weights.unwrap_or(HybridWeights {
+				semantic: 1.0,
+				lexical: 1.0,
+			});
+			QueryRankingMode::Hybrid {
+				semantic_weight: weights.semantic,
+				lexical_weight: weights.lexical,
+			}
+		} else if query.meaning.is_some() {
+			QueryRankingMode::CosineSimilarity
+		} else if has_full_text {
+			QueryRankingMode::Bm25
+		} else {
+			QueryRankingMode::None
+		}
+	}
+
+	/// Primary ordering this ranking sorts by
+	pub(crate) fn primary_order(&self, order: QueryOrder) -> QueryPrimaryOrder {
+		match self {
+			QueryRankingMode::Hybrid { .. } => QueryPrimaryOrder::CombinedScoreDescending,
+			QueryRankingMode::Decayed { .. } => QueryPrimaryOrder::DecayedScoreDescending,
+			QueryRankingMode::FeedbackBoosted { .. } => {
+				QueryPrimaryOrder::FeedbackBoostedScoreDescending
+			}
+			QueryRankingMode::CosineSimilarity => QueryPrimaryOrder::SimilarityDescending,
+			QueryRankingMode::Bm25 => QueryPrimaryOrder::Bm25Descending,
+			QueryRankingMode::None => QueryPrimaryOrder::Configured(order),
+		}
+	}
 }
 
 /// Typed primary ordering used after filtering
@@ -284,6 +473,10 @@ pub enum QueryPrimaryOrder {
 	Bm25Descending,
 	/// Descending weighted hybrid score
 	CombinedScoreDescending,
+	/// Descending weighted relevance, recency, and importance
+	DecayedScoreDescending,
+	/// Descending relevance or decayed score plus feedback boost
+	FeedbackBoostedScoreDescending,
 }
 
 /// Deterministic tie-breaker used by every ordering mode
@@ -344,30 +537,8 @@ pub struct QueryPlan {
 
 impl QueryPlan {
 	pub(crate) fn fallback(backend: &str, query: &Query, result_count: usize) -> Self {
-		let ranking_mode = if query.meaning.is_some()
-			&& matches!(query.expression, Some(ExpressionFilter::FullText(_)))
-		{
-			let weights = query.hybrid_weights.unwrap_or(HybridWeights {
-				semantic: 1.0,
-				lexical: 1.0,
-			});
-			QueryRankingMode::Hybrid {
-				semantic_weight: weights.semantic,
-				lexical_weight: weights.lexical,
-			}
-		} else if query.meaning.is_some() {
-			QueryRankingMode::CosineSimilarity
-		} else if matches!(query.expression, Some(ExpressionFilter::FullText(_))) {
-			QueryRankingMode::Bm25
-		} else {
-			QueryRankingMode::None
-		};
-		let primary = match ranking_mode {
-			QueryRankingMode::Hybrid { .. } => QueryPrimaryOrder::CombinedScoreDescending,
-			QueryRankingMode::CosineSimilarity => QueryPrimaryOrder::SimilarityDescending,
-			QueryRankingMode::Bm25 => QueryPrimaryOrder::Bm25Descending,
-			QueryRankingMode::None => QueryPrimaryOrder::Configured(query.order),
-		};
+		let ranking_mode = QueryRankingMode::of(query);
+		let primary = ranking_mode.primary_order(query.order);
 		Self {
 			backend: backend.to_string(),
 			candidate_filters: Vec::new(),
@@ -407,6 +578,13 @@ impl Query {
 			order: QueryOrder::default(),
 			hybrid_weights: None,
 			explain: false,
+			as_of: None,
+			collections: Vec::new(),
+			include_expired: false,
+			collapse_chunks: false,
+			decay: None,
+			skip_access_tracking: false,
+			feedback_boost: None,
 		}
 	}
 
@@ -484,11 +662,171 @@ impl Query {
 		self
 	}
 
+	/// Rank by relevance, recency, and importance
+	pub fn with_decay(mut self, decay: DecayRanking) -> Self {
+		self.decay = Some(decay);
+		self
+	}
+
+	/// Leave the access records of returned entries untouched
+	///
+	/// Use it for maintenance scans that should not count as retrievals.
+	pub fn without_access_tracking(mut self) -> Self {
+		self.skip_access_tracking = true;
+		self
+	}
+
+	/// Boost or demote results by recorded feedback, up to `weight`
+	///
+	/// With a fingerprint, only feedback recorded for that query counts.
+	pub fn with_feedback_boost(mut self, weight: f32, fingerprint: Option<String>) -> Self {
+		self.feedback_boost = Some(FeedbackBoost {
+			weight,
+			fingerprint,
+		});
+		self
+	}
+
+	/// Stable fingerprint of what the query asks for, for recording feedback
+	///
+	/// Only the meaning, expression, context, relation, and temporal filters
+	/// and the set of collections are hashed, so paging, explaining, ranking
+	/// settings, and fields added later leave it unchanged.
+	pub fn fingerprint(&self) -> String {
+		let mut collections = self.collections.clone();
+		collections.sort();
+		collections.dedup();
+		let parts = [
+			("meaning", serde_json::to_value(&self.meaning)),
+			("expression", serde_json::to_value(&self.expression)),
+			("context", serde_json::to_value(&self.context)),
+			("relations", serde_json::to_value(&self.relations)),
+			("temporal", serde_json::to_value(&self.temporal)),
+			("collections", serde_json::to_value(&collections)),
+		];
+		let mut hash = Fnv128::new();
+		hash.write(FINGERPRINT_VERSION.as_bytes());
+		for (name, value) in parts {
+			hash.write(&[0]);
+			hash.write(name.as_bytes());
+			hash.write(&[0]);
+			write_canonical_json(&mut hash, &value.unwrap_or_default());
+		}
+		hash.finish_hex()
+	}
+
 	/// Enable explanations
 	pub fn with_explanation(mut self) -> Self {
 		self.explain = true;
 		self
 	}
+
+	/// Evaluate the query against the database as it was at `timestamp`
+	///
+	/// Entries are reconstructed from their latest revision at or before
+	/// `timestamp`, so entries deleted since then are included and later
+	/// edits are ignored. Times before history that revision compaction
+	/// pruned cannot be replayed and fail with `InvalidArgument`.
+	pub fn as_of(mut self, timestamp: DateTime<Utc>) -> Self {
+		self.as_of = Some(timestamp);
+		self
+	}
+
+	/// Restrict the query to one collection, adding to any already targeted
+	pub fn in_collection(mut self, collection: impl Into<String>) -> Self {
+		let collection = collection.into();
+		if !self.collections.contains(&collection) {
+			self.collections.push(collection);
+		}
+		self
+	}
+
+	/// Restrict the query to several collections
+	pub fn in_collections<I, S>(self, collections: I) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>,
+	{
+		collections
+			.into_iter()
+			.fold(self, |query, collection| query.in_collection(collection))
+	}
+
+	/// Also return entries that have expired but not yet been swept
+	pub fn including_expired(mut self) -> Self {
+		self.include_expired = true;
+		self
+	}
+
+	/// Return each chunk's document instead of the chunk, once per document
+	///
+	/// The document takes the place of its best-ranked chunk, which is
+	/// attached as `QueryResult::best_chunk`.
+	pub fn collapsing_chunks(mut self) -> Self {
+		self.collapse_chunks = true;
+		self
+	}
+}
+
+impl DecayRanking {
+	/// Weight relevance, recency, and importance with a recency half-life in hours
+	///
+	/// Age is measured from `updated_at` unless [`Self::from`] picks another anchor.
+	pub fn new(relevance: f32, recency: f32, importance: f32, half_life_hours: f32) -> Self {
+		Self {
+			relevance,
+			recency,
+			importance,
+			half_life_hours,
+			anchor: DecayAnchor::default(),
+		}
+	}
+
+	/// Measure age from `anchor`
+	pub fn anchored_at(mut self, anchor: DecayAnchor) -> Self {
+		self.anchor = anchor;
+		self
+	}
+
+	/// Recency of `entry` at `now`, halving every `half_life_hours` of age
+	///
+	/// `last_accessed_at` is the entry's last tracked read, used by
+	/// [`DecayAnchor::LastAccessedAt`].
+	pub fn recency_of(
+		&self,
+		entry: &Entry,
+		last_accessed_at: Option<DateTime<Utc>>,
+		now: DateTime<Utc>,
+	) -> f32 {
+		let anchor = match self.anchor {
+			DecayAnchor::CreatedAt => entry.created_at,
+			DecayAnchor::UpdatedAt => entry.updated_at,
+			DecayAnchor::LastAccessedAt => last_accessed_at.unwrap_or(entry.updated_at),
+		};
+		let age_hours = (now - anchor).num_milliseconds().max(0) as f64 / 3_600_000.0;
+		0.5_f64.powf(age_hours / f64::from(self.half_life_hours)) as f32
+	}
+
+	/// Importance of `entry`, or 0.5 when it has none
+	pub fn importance_of(&self, entry: &Entry) -> f32 {
+		entry.importance.unwrap_or(0.5)
+	}
+
+	/// Weighted mean of the three components
+	pub fn score(&self, relevance: f32, recency: f32, importance: f32) -> f32 {
+		(self.relevance * relevance + self.recency * recency + self.importance * importance)
+			/ (self.relevance + self.recency + self.importance)
+	}
+
+	pub(crate) fn ranking_mode(&self) -> QueryRankingMode {
+		QueryRankingMode::Decayed {
+			relevance_weight: self.relevance,
+			recency_weight: self.recency,
+			importance_weight: self.importance,
+			half_life_hours: self.half_life_hours,
+			anchor: self.anchor,
+		}
+	}
 }
 
 impl Default for Query {
@@ -518,6 +856,7 @@ mod tests {
 		assert!(query.cursor.is_none());
 		assert_eq!(query.order, QueryOrder::CreatedAtAsc);
 		assert!(!query.explain);
+		assert!(query.as_of.is_none());
 	}
 
 	#[test]
@@ -795,12 +1134,83 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_decay_ranking_halves_recency_each_half_life() {
+		let now = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+		let mut entry = Entry::new(vec![0.1], "Note".to_string()).with_importance(1.0);
+		entry.created_at = now - chrono::Duration::hours(48);
+		entry.updated_at = now - chrono::Duration::hours(24);
+		let decay = DecayRanking::new(1.0, 1.0, 1.0, 24.0);
+
+		let created = decay.anchored_at(DecayAnchor::CreatedAt);
+		let accessed = decay.anchored_at(DecayAnchor::LastAccessedAt);
+
+		assert!((decay.recency_of(&entry, None, now) - 0.5).abs() < 1e-6);
+		assert!((created.recency_of(&entry, None, now) - 0.25).abs() < 1e-6);
+		assert!((accessed.recency_of(&entry, Some(now), now) - 1.0).abs() < 1e-6);
+		assert!((accessed.recency_of(&entry, None, now) - 0.5).abs() < 1e-6);
+		assert!((decay.score(0.0, 0.5, 1.0) - 0.5).abs() < 1e-6);
+		entry.importance = None;
+		assert_eq!(decay.importance_of(&entry), 0.5);
+		assert_eq!(
+			Query::new().with_decay(decay).decay.unwrap().anchor,
+			DecayAnchor::UpdatedAt
+		);
+	}
+
+	#[test]
+	fn test_fingerprint_ignores_paging_and_presentation() {
+		let query = Query::new()
+			.with_meaning(vec![0.5, 0.5], Some(0.2))
+			.with_context(ContextFilter::PathEquals(
+				"/team".to_string(),
+				serde_json::json!({"name": "core", "region": "eu"}),
+			))
+			.in_collections(vec!["notes".to_string(), "docs".to_string()]);
+		let fingerprint = query.fingerprint();
+
+		assert_eq!(fingerprint.len(), 32);
+		assert_eq!(query.clone().with_offset(20).fingerprint(), fingerprint);
+		assert_eq!(query.clone().with_explanation().fingerprint(), fingerprint);
+		assert_eq!(query.clone().with_limit(3).fingerprint(), fingerprint);
+		assert_eq!(
+			query
+				.clone()
+				.in_collections(vec!["docs".to_string(), "notes".to_string()])
+				.fingerprint(),
+			fingerprint
+		);
+		assert_ne!(
+			query
+				.clone()
+				.with_meaning(vec![0.5, 0.5], Some(0.3))
+				.fingerprint(),
+			fingerprint
+		);
+	}
+
 	#[test]
 	fn test_query_with_explanation() {
 		let query = Query::new().with_explanation();
 		assert!(query.explain);
 	}
 
+	#[test]
+	fn test_query_as_of() {
+		let dt = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+		let query = Query::new().as_of(dt);
+		assert_eq!(query.as_of, Some(dt));
+	}
+
+	#[test]
+	fn test_query_in_collections_deduplicates() {
+		let query = Query::new()
+			.in_collection("notes")
+			.in_collections(["tasks", "notes"]);
+		assert_eq!(query.collections, vec!["notes", "tasks"]);
+		assert!(Query::new().collections.is_empty());
+	}
+
 	#[test]
 	fn test_query_builder_chain() {
 		let vector = vec![0.1, 0.2, 0.3];
@@ -875,6 +1285,8 @@ mod tests {
 			combined_score: None,
 			explanation: Some("Matched by semantic search".to_string()),
 			plan: None,
+			best_chunk: None,
+			feedback_boost: None,
 		};
 
 		assert_eq!(result.entry.id, entry.id);
@@ -892,6 +1304,8 @@ mod tests {
 			combined_score: None,
 			explanation: None,
 			plan: None,
+			best_chunk: None,
+			feedback_boost: None,
 		};
 
 		assert!(result.similarity_score.is_none());
@@ -1,5 +1,9 @@
+use crate::dedup::{DedupPolicy, MergeStrategy, NearDuplicateCluster};
+use crate::graph::GraphStats;
+use crate::patch::EntryPatch;
 use crate::query::{Query, QueryExecution, QueryPlan, QueryResult};
 use crate::types::Entry;
+use chrono::{DateTime, Utc};
 use std::path::Path;
 use thiserror::Error;
 use uuid::Uuid;
@@ -21,6 +25,24 @@ pub enum StorageError {
 	#[error("Invalid argument: {0}")]
 	InvalidArgument(String),
 
+	#[error("Version conflict for entry {id}: expected {expected}, found {actual}")]
+	Conflict {
+		/// Entry whose version did not match
+		id: Uuid,
+		/// Version the caller expected
+		expected: u64,
+		/// Version currently stored
+		actual: u64,
+	},
+
+	#[error("Entry {id} duplicates existing entry {existing}")]
+	Duplicate {
+		/// Entry that was rejected
+		id: Uuid,
+		/// Stored entry with the same content hash
+		existing: Uuid,
+	},
+
 	#[error("Storage backend error: {0}")]
 	Backend(Box<dyn std::error::Error + Send + Sync>),
 }
@@ -61,6 +83,19 @@ pub struct EmbeddingProfile {
 	pub dimensions: usize,
 }
 
+/// Summary of one named collection
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CollectionInfo {
+	/// Collection name
+	pub name: String,
+	/// Number of live entries in the collection
+	pub entries: usize,
+	/// Vector dimensions required by the collection, once established
+	pub vector_dimension: Option<usize>,
+	/// Embedding profile configured for the collection
+	pub embedding_profile: Option<EmbeddingProfile>,
+}
+
 /// Mutation recorded in an entry's durable revision history
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum RevisionOperation {
@@ -72,6 +107,188 @@ pub enum RevisionOperation {
 	Update,
 	/// Entry deletion
 	Delete,
+	/// Outgoing relations added without rewriting the entry
+	Relate,
+	/// Outgoing relations removed without rewriting the entry
+	Unrelate,
+	/// Entry restored from an earlier revision snapshot
+	Revert,
+}
+
+/// What an upsert did to one entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum UpsertOutcome {
+	/// No entry with the ID existed, so it was inserted
+	Inserted,
+	/// The existing entry was updated
+	Updated,
+	/// The existing entry already matched, so nothing was written
+	Unchanged,
+}
+
+/// Mutation published on the change feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ChangeOperation {
+	/// Entry creation
+	Insert,
+	/// Entry update
+	Update,
+	/// Entry deletion
+	Delete,
+	/// Outgoing relations added
+	Relate,
+	/// Outgoing relations removed
+	Unrelate,
+	/// Entry restored from an earlier revision snapshot
+	Revert,
+	/// Entry and its history erased
+	Purge,
+}
+
+/// One committed mutation in the change feed
+///
+/// Changes carry no entry content; read current state with
+/// [`StorageBackend::get`] or history with [`StorageBackend::revisions`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Change {
+	/// Global sequence number, strictly increasing and never reused
+	pub seq: u64,
+	/// Entry that changed
+	pub entry_id: Uuid,
+	/// Kind of mutation
+	pub operation: ChangeOperation,
+	/// Revision recorded by the mutation, if any
+	pub revision_id: Option<Uuid>,
+	/// When the mutation was recorded
+	pub recorded_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Content-free record that an entry was purged
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Tombstone {
+	/// Purged entry
+	pub entry_id: Uuid,
+	/// When the purge committed
+	pub purged_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Which revisions history compaction keeps
+///
+/// Each entry's latest revision is always kept. With neither `keep_last` nor
+/// `keep_for_days` set, every revision is kept.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+	/// Keep this many of each entry's most recent revisions
+	pub keep_last: Option<usize>,
+	/// Keep revisions recorded within this many days
+	pub keep_for_days: Option<u32>,
+	/// Keep delete revisions regardless of age or count
+	pub keep_deletes: bool,
+	/// Store retained revisions as JSON Patch deltas against the previous revision
+	pub delta_encoding: bool,
+}
+
+impl Default for RetentionPolicy {
+	fn default() -> Self {
+		Self {
+			keep_last: None,
+			keep_for_days: None,
+			keep_deletes: true,
+			delta_encoding: false,
+		}
+	}
+}
+
+/// How `delete` treats entries
+///
+/// With `soft_delete`, deleted entries move to a trash where they stay hidden
+/// but restorable, together with their relations, until the trash is emptied.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TrashPolicy {
+	/// Move deleted entries to the trash instead of removing them
+	pub soft_delete: bool,
+	/// Days a trashed entry stays restorable before emptying the trash removes it
+	pub grace_period_days: u32,
+}
+
+impl Default for TrashPolicy {
+	fn default() -> Self {
+		Self {
+			soft_delete: false,
+			grace_period_days: 30,
+		}
+	}
+}
+
+/// Whether reads record which entries they returned
+///
+/// Access records live beside entries, so recording one never changes an
+/// entry's version or `updated_at` and never creates a revision.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AccessPolicy {
+	/// Record the last access time and access count of entries returned by reads
+	pub track: bool,
+}
+
+/// When an entry was last returned by a read, and how often it has been
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct AccessStats {
+	/// Entry that was read
+	pub entry_id: Uuid,
+	/// When a read last returned the entry
+	pub last_accessed_at: DateTime<Utc>,
+	/// Reads that returned the entry while tracking was on
+	pub access_count: u64,
+}
+
+/// Whether a retrieved entry helped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackSignal {
+	/// The entry was useful for the query
+	Positive,
+	/// The entry was not useful for the query
+	Negative,
+}
+
+/// Relevance feedback recorded for an entry returned by a query
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Feedback {
+	/// Fingerprint of the query that returned the entry, usually `Query::fingerprint`
+	pub query_fingerprint: String,
+	/// Entry the feedback is about
+	pub entry_id: Uuid,
+	/// Whether the entry helped
+	pub signal: FeedbackSignal,
+	/// When the feedback was recorded
+	pub recorded_at: DateTime<Utc>,
+}
+
+/// Entry waiting in the trash
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DeletedEntry {
+	/// Entry as it was when deleted, with relations to current entries
+	pub entry: Entry,
+	/// When the entry was moved to the trash
+	pub deleted_at: DateTime<Utc>,
+}
+
+/// Outcome of one revision-history compaction
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CompactionReport {
+	/// Revisions present before compaction
+	pub revisions_examined: usize,
+	/// Revisions removed by the retention policy
+	pub revisions_removed: usize,
+	/// Retained revisions stored as deltas after compaction
+	pub delta_revisions: usize,
+	/// Stored snapshot bytes before compaction
+	pub bytes_before: usize,
+	/// Stored snapshot bytes after compaction
+	pub bytes_after: usize,
 }
 
 /// Immutable snapshot of an entry at a mutation boundary
@@ -99,6 +316,56 @@ pub trait StorageBackend: Send {
 	/// Insert entries atomically
 	fn insert_batch(&mut self, entries: &[Entry]) -> StorageResult<()>;
 
+	/// Insert entries atomically, returning the ID each entry is stored under
+	///
+	/// An entry dropped or merged by the deduplication policy reports the ID of
+	/// the entry that already holds its content.
+	fn insert_batch_returning_ids(&mut self, entries: &[Entry]) -> StorageResult<Vec<Uuid>> {
+		self.insert_batch(entries)?;
+		Ok(entries.iter().map(|entry| entry.id).collect())
+	}
+
+	/// Insert an entry, or update it if its ID already exists
+	fn upsert(&mut self, entry: &Entry) -> StorageResult<UpsertOutcome> {
+		let mut outcomes = self.upsert_batch(std::slice::from_ref(entry))?;
+		Ok(outcomes.remove(0))
+	}
+
+	/// Insert or update entries atomically, returning one outcome per entry
+	fn upsert_batch(&mut self, _entries: &[Entry]) -> StorageResult<Vec<UpsertOutcome>> {
+		Err(StorageError::Database(
+			"Upserts are not supported by this backend".to_string(),
+		))
+	}
+
+	/// Upsert an entry matched by its `external_id` instead of its `id`
+	fn upsert_by_external_id(&mut self, entry: &Entry) -> StorageResult<(Uuid, UpsertOutcome)> {
+		let mut outcomes = self.upsert_batch_by_external_id(std::slice::from_ref(entry))?;
+		Ok(outcomes.remove(0))
+	}
+
+	/// Upsert entries matched by `external_id`, returning each stored ID and outcome
+	///
+	/// An entry whose key is already stored in its tenant takes that entry's
+	/// ID and `created_at`; other entries are inserted under their own IDs.
+	fn upsert_batch_by_external_id(
+		&mut self,
+		_entries: &[Entry],
+	) -> StorageResult<Vec<(Uuid, UpsertOutcome)>> {
+		Err(StorageError::Database(
+			"External IDs are not supported by this backend".to_string(),
+		))
+	}
+
+	/// Get the entry keyed by `external_id`, if one is visible
+	///
+	/// Keys are unique per tenant; an unscoped handle looks in the default tenant.
+	fn get_by_external_id(&self, _external_id: &str) -> StorageResult<Option<Entry>> {
+		Err(StorageError::Database(
+			"External IDs are not supported by this backend".to_string(),
+		))
+	}
+
 	/// Get an entry by ID
 	fn get(&self, id: Uuid) -> StorageResult<Entry>;
 
@@ -121,12 +388,120 @@ pub trait StorageBackend: Send {
 	/// Update entries atomically
 	fn update_batch(&mut self, entries: &[Entry]) -> StorageResult<()>;
 
+	/// Update an entry only if its stored version equals `expected_version`
+	///
+	/// Fails with [`StorageError::Conflict`] when another write got there first.
+	fn update_if_version(&mut self, _entry: &Entry, _expected_version: u64) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Conditional writes are not supported by this backend".to_string(),
+		))
+	}
+
+	/// Apply a partial update to an entry and return the result
+	///
+	/// A patch that changes nothing records no revision.
+	fn patch(&mut self, id: Uuid, patch: &EntryPatch) -> StorageResult<Entry> {
+		let existing = self.get(id)?;
+		let mut patched = patch.apply(&existing);
+		if patched.expression == existing.expression
+			&& patched.meaning == existing.meaning
+			&& patched.context == existing.context
+		{
+			return Ok(existing);
+		}
+		patched.updated_at = existing.updated_at.max(chrono::Utc::now());
+		self.update(&patched)?;
+		Ok(patched)
+	}
+
+	/// Apply a partial update only if the stored version equals `expected_version`
+	fn patch_if_version(
+		&mut self,
+		_id: Uuid,
+		_patch: &EntryPatch,
+		_expected_version: u64,
+	) -> StorageResult<Entry> {
+		Err(StorageError::Database(
+			"Conditional writes are not supported by this backend".to_string(),
+		))
+	}
+
 	/// Delete an entry by ID
 	fn delete(&mut self, id: Uuid) -> StorageResult<()>;
 
+	/// Delete an entry only if its stored version equals `expected_version`
+	fn delete_if_version(&mut self, _id: Uuid, _expected_version: u64) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Conditional writes are not supported by this backend".to_string(),
+		))
+	}
+
 	/// Delete entries atomically
 	fn delete_batch(&mut self, ids: &[Uuid]) -> StorageResult<()>;
 
+	/// Erase an entry, its revision history, and its edges, leaving only a tombstone
+	fn purge(&mut self, id: Uuid) -> StorageResult<()> {
+		self.purge_batch(&[id])
+	}
+
+	/// Erase entries atomically, leaving only tombstones
+	fn purge_batch(&mut self, _ids: &[Uuid]) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Purging is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Erase every current entry matching `query`, returning the purged IDs
+	fn purge_matching(&mut self, query: &Query) -> StorageResult<Vec<Uuid>> {
+		purge_matching_with(
+			self,
+			query,
+			|storage| storage,
+			|storage, ids| storage.purge_batch(ids),
+		)
+	}
+
+	/// Return tombstones for purged entries, oldest first
+	fn tombstones(&self) -> StorageResult<Vec<Tombstone>> {
+		Err(StorageError::Database(
+			"Purging is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Reclaim space so purged and deleted bytes no longer remain in storage files
+	fn vacuum(&mut self) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Vacuuming is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Return up to `limit` IDs of entries expired at `now`, soonest expiry first
+	fn expired_ids(&self, _now: DateTime<Utc>, _limit: usize) -> StorageResult<Vec<Uuid>> {
+		Err(StorageError::Database(
+			"Entry expiry is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Delete every entry expired now, `batch_size` per transaction, returning the deleted IDs
+	///
+	/// Each batch records delete revisions like `delete_batch`. Entries that
+	/// expire while the sweep runs are left for the next sweep.
+	fn sweep_expired(&mut self, batch_size: usize) -> StorageResult<Vec<Uuid>> {
+		sweep_expired_with(
+			self,
+			batch_size,
+			|storage| storage,
+			|storage, ids| storage.delete_batch(ids),
+		)
+	}
+
+	/// Return up to `limit` changes with a sequence number greater than `seq`, oldest first
+	fn changes_since(&self, _seq: u64, _limit: usize) -> StorageResult<Vec<Change>> {
+		Err(StorageError::Database(
+			"Change feeds are not supported by this backend".to_string(),
+		))
+	}
+
 	/// Count total entries
 	fn count(&self) -> StorageResult<usize>;
 
@@ -160,12 +535,266 @@ pub trait StorageBackend: Send {
 		))
 	}
 
+	/// List collections that hold entries or have an embedding profile, by name
+	fn collections(&self) -> StorageResult<Vec<CollectionInfo>> {
+		Err(StorageError::Database(
+			"Collections are not supported by this backend".to_string(),
+		))
+	}
+
+	/// Read the embedding profile configured for a collection
+	fn collection_embedding_profile(
+		&self,
+		_collection: &str,
+	) -> StorageResult<Option<EmbeddingProfile>> {
+		Err(StorageError::Database(
+			"Collections are not supported by this backend".to_string(),
+		))
+	}
+
+	/// Configure a collection's embedding profile, rejecting incompatible stored data
+	fn set_collection_embedding_profile(
+		&mut self,
+		_collection: &str,
+		_profile: &EmbeddingProfile,
+	) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Collections are not supported by this backend".to_string(),
+		))
+	}
+
+	/// Atomically replace every vector in a collection and change its embedding profile
+	fn migrate_collection_embeddings(
+		&mut self,
+		_collection: &str,
+		_profile: &EmbeddingProfile,
+		_replacements: &[(Uuid, Vec<f32>)],
+	) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Collections are not supported by this backend".to_string(),
+		))
+	}
+
 	/// Return durable revision history for an entry
 	fn revisions(&self, id: Uuid) -> StorageResult<Vec<EntryRevision>>;
 
+	/// Return the stored revision retention policy
+	fn retention_policy(&self) -> StorageResult<RetentionPolicy> {
+		Ok(RetentionPolicy::default())
+	}
+
+	/// Store the retention policy used by [`StorageBackend::compact_revisions`]
+	fn set_retention_policy(&mut self, _policy: &RetentionPolicy) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Revision retention is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Compact revision history with the stored retention policy
+	fn compact_revisions(&mut self) -> StorageResult<CompactionReport> {
+		let policy = self.retention_policy()?;
+		self.compact_revisions_with(&policy)
+	}
+
+	/// Compact revision history with an explicit retention policy
+	fn compact_revisions_with(
+		&mut self,
+		_policy: &RetentionPolicy,
+	) -> StorageResult<CompactionReport> {
+		Err(StorageError::Database(
+			"Revision retention is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Restore an entry to the snapshot stored in one of its revisions
+	fn revert(&mut self, _entry_id: Uuid, _revision_id: Uuid) -> StorageResult<Entry> {
+		Err(StorageError::Database(
+			"Reverting revisions is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Restore a deleted entry from the snapshot recorded by its deletion
+	fn undelete(&mut self, _entry_id: Uuid) -> StorageResult<Entry> {
+		Err(StorageError::Database(
+			"Reverting revisions is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Return the stored trash policy
+	fn trash_policy(&self) -> StorageResult<TrashPolicy> {
+		Ok(TrashPolicy::default())
+	}
+
+	/// Store the trash policy that decides whether deletes are soft
+	fn set_trash_policy(&mut self, _policy: &TrashPolicy) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Soft delete is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Return the stored access-tracking policy
+	fn access_policy(&self) -> StorageResult<AccessPolicy> {
+		Ok(AccessPolicy::default())
+	}
+
+	/// Store the access-tracking policy
+	fn set_access_policy(&mut self, _policy: &AccessPolicy) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Access tracking is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Record that a read returned these entries, if the access policy tracks reads
+	///
+	/// Backends without access tracking ignore reads.
+	fn record_access(&self, _ids: &[Uuid]) -> StorageResult<()> {
+		Ok(())
+	}
+
+	/// Return when an entry was last read and how often, if it ever was
+	fn access_stats(&self, _id: Uuid) -> StorageResult<Option<AccessStats>> {
+		Err(StorageError::Database(
+			"Access tracking is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Return visible entries that no tracked read has returned, oldest first
+	fn never_accessed(&self) -> StorageResult<Vec<Entry>> {
+		Err(StorageError::Database(
+			"Access tracking is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Record whether an entry returned for a query helped
+	fn record_feedback(
+		&mut self,
+		_query_fingerprint: &str,
+		_entry_id: Uuid,
+		_signal: FeedbackSignal,
+	) -> StorageResult<Feedback> {
+		Err(StorageError::Database(
+			"Relevance feedback is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Return the feedback recorded for an entry, oldest first
+	fn feedback(&self, _entry_id: Uuid) -> StorageResult<Vec<Feedback>> {
+		Err(StorageError::Database(
+			"Relevance feedback is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Return the stored deduplication policy
+	fn dedup_policy(&self) -> StorageResult<DedupPolicy> {
+		Ok(DedupPolicy::default())
+	}
+
+	/// Store the deduplication policy and rehash stored entries under it
+	fn set_dedup_policy(&mut self, _policy: &DedupPolicy) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Deduplication is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Cluster visible entries whose meaning similarity reaches `threshold`
+	fn find_near_duplicates(&self, _threshold: f32) -> StorageResult<Vec<NearDuplicateCluster>> {
+		Err(StorageError::Database(
+			"Near-duplicate detection is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Merge entries into the one `strategy` picks and remove the rest
+	///
+	/// The survivor gains the others' relations and contexts, and relations
+	/// that pointed at a removed entry point at the survivor instead.
+	fn merge(&mut self, _ids: &[Uuid], _strategy: MergeStrategy) -> StorageResult<Entry> {
+		Err(StorageError::Database(
+			"Merging entries is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Return entries in the trash, oldest deletion first
+	fn list_deleted(&self) -> StorageResult<Vec<DeletedEntry>> {
+		Err(StorageError::Database(
+			"Soft delete is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Move an entry out of the trash, bringing back its relations in both directions
+	fn restore_deleted(&mut self, _id: Uuid) -> StorageResult<Entry> {
+		Err(StorageError::Database(
+			"Soft delete is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Permanently remove entries from the trash, keeping their revision history
+	fn remove_from_trash(&mut self, _ids: &[Uuid]) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Soft delete is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Restrict this handle to one tenant's entries
+	///
+	/// Once scoped, reads see only that tenant's entries, revisions, and
+	/// relations, and writes must carry the tenant. A handle cannot be
+	/// rescoped to a different tenant.
+	fn scope_to_tenant(&mut self, _tenant: &str) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Tenant scoping is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Tenant this handle is scoped to, if any
+	fn tenant_scope(&self) -> Option<&str> {
+		None
+	}
+
 	/// Create a selective SQLite-style index for a JSON Pointer context path
 	fn create_context_index(&mut self, path: &str) -> StorageResult<String>;
 
+	/// Create a context-path index covering only one collection's entries
+	fn create_collection_context_index(
+		&mut self,
+		_collection: &str,
+		_path: &str,
+	) -> StorageResult<String> {
+		Err(StorageError::Database(
+			"Collections are not supported by this backend".to_string(),
+		))
+	}
+
+	/// Add a directed relation, leaving the entry's other fields untouched
+	fn relate(&mut self, from: Uuid, to: Uuid) -> StorageResult<()> {
+		self.relate_batch(&[(from, to)])
+	}
+
+	/// Add directed relations atomically; existing relations are left unchanged
+	fn relate_batch(&mut self, _edges: &[(Uuid, Uuid)]) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Relation management is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Remove a directed relation, leaving the entry's other fields untouched
+	fn unrelate(&mut self, from: Uuid, to: Uuid) -> StorageResult<()> {
+		self.unrelate_batch(&[(from, to)])
+	}
+
+	/// Remove directed relations atomically; missing relations are ignored
+	fn unrelate_batch(&mut self, _edges: &[(Uuid, Uuid)]) -> StorageResult<()> {
+		Err(StorageError::Database(
+			"Relation management is not supported by this backend".to_string(),
+		))
+	}
+
+	/// Summarize the relation graph, keeping the `top` most connected entries
+	fn graph_stats(&self, _top: usize) -> StorageResult<GraphStats> {
+		Err(StorageError::Database(
+			"Graph statistics are not supported by this backend".to_string(),
+		))
+	}
+
 	/// Get backend name for debugging
 	fn backend_name(&self) -> &str;
 }
@@ -176,6 +805,52 @@ pub mod sqlite;
 // Re-export for convenience
 pub use sqlite::SqliteStorage;
 
+/// Purge the entries `storage_of(target)` finds for `query` through `purge`
+///
+/// Shared by [`StorageBackend::purge_matching`] and `ContextDB::purge_matching`,
+/// which purges through its hooks.
+pub(crate) fn purge_matching_with<T: ?Sized, S: StorageBackend + ?Sized>(
+	target: &mut T,
+	query: &Query,
+	storage_of: impl Fn(&T) -> &S,
+	mut purge: impl FnMut(&mut T, &[Uuid]) -> StorageResult<()>,
+) -> StorageResult<Vec<Uuid>> {
+	let ids: Vec<Uuid> = storage_of(target)
+		.query(query)?
+		.into_iter()
+		.map(|result| result.entry.id)
+		.collect();
+	purge(target, &ids)?;
+	Ok(ids)
+}
+
+/// Delete entries expired now in batches of `batch_size` through `delete`
+///
+/// Shared by [`StorageBackend::sweep_expired`] and `ContextDB`'s sweeps, which
+/// delete through their hooks.
+pub(crate) fn sweep_expired_with<T: ?Sized, S: StorageBackend + ?Sized>(
+	target: &mut T,
+	batch_size: usize,
+	storage_of: impl Fn(&T) -> &S,
+	mut delete: impl FnMut(&mut T, &[Uuid]) -> StorageResult<()>,
+) -> StorageResult<Vec<Uuid>> {
+	if batch_size == 0 {
+		return Err(StorageError::InvalidArgument(
+			"Sweep batch size must be positive".to_string(),
+		));
+	}
+	let now = Utc::now();
+	let mut swept = Vec::new();
+	loop {
+		let ids = storage_of(target).expired_ids(now, batch_size)?;
+		if ids.is_empty() {
+			return Ok(swept);
+		}
+		delete(target, &ids)?;
+		swept.extend(ids);
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
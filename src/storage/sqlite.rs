@@ -1,17 +1,26 @@
+use crate::chunk::chunk_document_id;
+use crate::dedup::{
+	cluster_near_duplicates, DedupPolicy, DuplicateAction, MergeStrategy, NearDuplicateCluster,
+};
+use crate::diff::{apply_json_patch, json_patch, JsonPatchOperation};
+use crate::graph::GraphStats;
+use crate::patch::{merge_patch, EntryPatch};
 use crate::query::{
-	ContextFilter, ExpressionFilter, Query, QueryExecution, QueryFilterIdentity, QueryOrder,
-	QueryPaginationPlan, QueryPlan, QueryPlanOrdering, QueryPlanStep, QueryPlanStrategy,
-	QueryPrimaryOrder, QueryRankingMode, QueryResult, QueryTieBreaker, RelationFilter,
+	ContextFilter, DecayAnchor, ExpressionFilter, Query, QueryExecution, QueryFilterIdentity,
+	QueryOrder, QueryPaginationPlan, QueryPlan, QueryPlanOrdering, QueryPlanStep,
+	QueryPlanStrategy, QueryRankingMode, QueryResult, QueryTieBreaker, RelationFilter,
 	TemporalFilter,
 };
 use crate::storage::{
-	EmbeddingProfile, EntryRevision, IntegrityIssue, IntegrityReport, RevisionOperation,
-	StorageBackend, StorageError, StorageResult,
+	AccessPolicy, AccessStats, Change, ChangeOperation, CollectionInfo, CompactionReport,
+	DeletedEntry, EmbeddingProfile, EntryRevision, Feedback, FeedbackSignal, IntegrityIssue,
+	IntegrityReport, RetentionPolicy, RevisionOperation, StorageBackend, StorageError,
+	StorageResult, Tombstone, TrashPolicy, UpsertOutcome,
 };
-use crate::types::Entry;
+use crate::types::{Entry, DEFAULT_COLLECTION, DEFAULT_TENANT};
 use chrono::{DateTime, Utc};
 use regex::Regex;
-use rusqlite::{params, Connection, OpenFlags, Transaction};
+use rusqlite::{params, Connection, OpenFlags, Transaction, TransactionBehavior};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::Path;
 use std::time::Duration;
@@ -20,16 +29,60 @@ use uuid::Uuid;
 /// SQLite-backed storage for ContextDB entries
 pub struct SqliteStorage {
 	conn: Connection,
+	/// Tenant every read and write is restricted to, when scoped
+	tenant: Option<String>,
+}
+
+const SCHEMA_VERSION: i64 = 12;
+
+/// Columns decoded by [`SqliteStorage::read_entry_row`], in order
+const ENTRY_COLUMNS: &str = "id, meaning, expression, context, created_at, updated_at, version, \
+	collection, tenant, expires_at, external_id, importance";
+
+/// Batch left to write once duplicates are resolved
+struct DedupResolution {
+	/// Entries to insert
+	inserts: Vec<Entry>,
+	/// Stored entries whose context absorbed a duplicate, at their next version
+	merged: Vec<Entry>,
+	/// ID each input entry is stored under, in input order
+	ids: Vec<Uuid>,
+}
+
+/// Chunks set aside while collapsing query results into documents
+#[derive(Default)]
+struct CollapsedChunks {
+	/// Best-ranked chunk of each returned document
+	best_chunks: HashMap<Uuid, Entry>,
+	/// Documents that took the place of a chunk hit
+	placed_by_chunk: HashSet<Uuid>,
+}
+
+/// Raw column values of one `entries` row
+struct EntryRow {
+	id: String,
+	meaning: Vec<u8>,
+	expression: String,
+	context: String,
+	created_at: String,
+	updated_at: String,
+	version: i64,
+	collection: String,
+	tenant: String,
+	expires_at: Option<String>,
+	external_id: Option<String>,
+	importance: Option<f64>,
 }
 
-const SCHEMA_VERSION: i64 = 2;
+/// Longest run of delta-encoded revisions between full snapshots
+const MAX_DELTA_CHAIN: usize = 15;
 
 impl SqliteStorage {
 	/// Create a new storage instance with an in-memory database
 	pub fn in_memory() -> StorageResult<Self> {
 		let conn =
 			Connection::open_in_memory().map_err(|e| StorageError::Database(e.to_string()))?;
-		let mut storage = Self { conn };
+		let mut storage = Self { conn, tenant: None };
 		storage.initialize()?;
 		Ok(storage)
 	}
@@ -41,7 +94,7 @@ impl SqliteStorage {
 			.map_err(|error| StorageError::Database(error.to_string()))?;
 		conn.pragma_update(None, "synchronous", "NORMAL")
 			.map_err(|error| StorageError::Database(error.to_string()))?;
-		let mut storage = Self { conn };
+		let mut storage = Self { conn, tenant: None };
 		storage.initialize()?;
 		Ok(storage)
 	}
@@ -79,7 +132,15 @@ impl SqliteStorage {
                 expression TEXT NOT NULL,
                 context TEXT NOT NULL,
                 created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
+                updated_at TEXT NOT NULL,
+				version INTEGER NOT NULL DEFAULT 1,
+				collection TEXT NOT NULL DEFAULT 'default',
+				tenant TEXT NOT NULL DEFAULT 'default',
+				expires_at TEXT,
+				deleted_at TEXT,
+				content_hash TEXT,
+				external_id TEXT,
+				importance REAL
             );
             
             CREATE TABLE IF NOT EXISTS relations (
@@ -101,10 +162,43 @@ impl SqliteStorage {
 				entry_id TEXT NOT NULL,
 				operation TEXT NOT NULL,
 				snapshot TEXT NOT NULL,
-				recorded_at TEXT NOT NULL
+				recorded_at TEXT NOT NULL,
+				encoding TEXT NOT NULL DEFAULT 'full'
 			);
 			CREATE INDEX IF NOT EXISTS idx_entry_revisions_entry
 			ON entry_revisions(entry_id, recorded_at, revision_id);
+
+			CREATE TABLE IF NOT EXISTS change_log (
+				seq INTEGER PRIMARY KEY AUTOINCREMENT,
+				entry_id TEXT NOT NULL,
+				operation TEXT NOT NULL,
+				revision_id TEXT,
+				recorded_at TEXT NOT NULL,
+				tenant TEXT NOT NULL DEFAULT 'default'
+			);
+
+			CREATE TABLE IF NOT EXISTS entry_tombstones (
+				entry_id TEXT PRIMARY KEY,
+				purged_at TEXT NOT NULL,
+				tenant TEXT NOT NULL DEFAULT 'default'
+			);
+
+			CREATE TABLE IF NOT EXISTS entry_feedback (
+				id INTEGER PRIMARY KEY AUTOINCREMENT,
+				query_fingerprint TEXT NOT NULL,
+				entry_id TEXT NOT NULL,
+				signal TEXT NOT NULL,
+				recorded_at TEXT NOT NULL,
+				FOREIGN KEY (entry_id) REFERENCES entries(id) ON DELETE CASCADE
+			);
+			CREATE INDEX IF NOT EXISTS idx_entry_feedback_entry ON entry_feedback(entry_id);
+
+			CREATE TABLE IF NOT EXISTS entry_access (
+				entry_id TEXT PRIMARY KEY,
+				last_accessed_at TEXT NOT NULL,
+				access_count INTEGER NOT NULL,
+				FOREIGN KEY (entry_id) REFERENCES entries(id) ON DELETE CASCADE
+			);
             
             CREATE INDEX IF NOT EXISTS idx_entries_created_at ON entries(created_at);
             CREATE INDEX IF NOT EXISTS idx_entries_updated_at ON entries(updated_at);
@@ -125,12 +219,114 @@ impl SqliteStorage {
 			)));
 		}
 		if version < SCHEMA_VERSION {
-			self.migrate_legacy_schema()?;
+			self.upgrade_schema(version)?;
 		}
 		self.initialize_search_index()?;
 		Ok(())
 	}
 
+	/// Bring an older schema up to [`SCHEMA_VERSION`]
+	///
+	/// Column additions are idempotent, so an interrupted upgrade can be retried.
+	fn upgrade_schema(&mut self, version: i64) -> StorageResult<()> {
+		self.add_column_if_missing("entries", "version", "INTEGER NOT NULL DEFAULT 1")?;
+		self.add_column_if_missing("entries", "collection", "TEXT NOT NULL DEFAULT 'default'")?;
+		self.add_column_if_missing("entries", "tenant", "TEXT NOT NULL DEFAULT 'default'")?;
+		self.add_column_if_missing("entries", "expires_at", "TEXT")?;
+		self.add_column_if_missing("entries", "deleted_at", "TEXT")?;
+		self.add_column_if_missing("entries", "content_hash", "TEXT")?;
+		self.add_column_if_missing("entries", "external_id", "TEXT")?;
+		self.add_column_if_missing("entries", "importance", "REAL")?;
+		self.add_column_if_missing(
+			"entry_revisions",
+			"encoding",
+			"TEXT NOT NULL DEFAULT 'full'",
+		)?;
+		self.add_column_if_missing("change_log", "tenant", "TEXT NOT NULL DEFAULT 'default'")?;
+		self.add_column_if_missing(
+			"entry_tombstones",
+			"tenant",
+			"TEXT NOT NULL DEFAULT 'default'",
+		)?;
+		if (2..12).contains(&version) {
+			self.backfill_history_tenants()?;
+		}
+		self.conn
+			.execute_batch(
+				"CREATE INDEX IF NOT EXISTS idx_entries_collection ON entries(collection);
+				 CREATE INDEX IF NOT EXISTS idx_entries_tenant ON entries(tenant);
+				 CREATE INDEX IF NOT EXISTS idx_entries_expires_at ON entries(expires_at)
+					WHERE expires_at IS NOT NULL;
+				 CREATE INDEX IF NOT EXISTS idx_entries_deleted_at ON entries(deleted_at)
+					WHERE deleted_at IS NOT NULL;
+				 CREATE UNIQUE INDEX IF NOT EXISTS idx_entries_content_hash
+					ON entries(tenant, collection, content_hash)
+					WHERE content_hash IS NOT NULL AND deleted_at IS NULL;
+				 CREATE UNIQUE INDEX IF NOT EXISTS idx_entries_external_id
+					ON entries(tenant, external_id)
+					WHERE external_id IS NOT NULL AND deleted_at IS NULL;
+				 CREATE INDEX IF NOT EXISTS idx_change_log_tenant ON change_log(tenant, seq);",
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		if version < 2 {
+			return self.migrate_legacy_schema();
+		}
+		self.conn
+			.pragma_update(None, "user_version", SCHEMA_VERSION)
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	/// Attribute change-log rows and tombstones written before version 12 to
+	/// their entry's tenant
+	///
+	/// The tenant comes from the entry row, or else from the newest full
+	/// revision snapshot. History with neither, such as that of entries purged
+	/// before the upgrade, gets an empty tenant that only unscoped handles see.
+	fn backfill_history_tenants(&self) -> StorageResult<()> {
+		self.conn
+			.execute_batch(
+				"UPDATE change_log SET tenant = COALESCE(
+					(SELECT tenant FROM entries WHERE id = change_log.entry_id),
+					(SELECT json_extract(snapshot, '$.tenant') FROM entry_revisions
+					 WHERE entry_id = change_log.entry_id AND encoding = 'full'
+					 ORDER BY recorded_at DESC LIMIT 1),
+					''
+				 );
+				 UPDATE entry_tombstones SET tenant = COALESCE(
+					(SELECT tenant FROM change_log
+					 WHERE entry_id = entry_tombstones.entry_id ORDER BY seq DESC LIMIT 1),
+					''
+				 );",
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn add_column_if_missing(
+		&self,
+		table: &str,
+		column: &str,
+		definition: &str,
+	) -> StorageResult<()> {
+		let exists: bool = self
+			.conn
+			.query_row(
+				&format!(
+					"SELECT EXISTS(SELECT 1 FROM pragma_table_info('{table}') WHERE name = ?1)"
+				),
+				params![column],
+				|row| row.get(0),
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		if !exists {
+			self.conn
+				.execute_batch(&format!(
+					"ALTER TABLE {table} ADD COLUMN {column} {definition}"
+				))
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+		}
+		Ok(())
+	}
+
 	fn initialize_search_index(&self) -> StorageResult<()> {
 		self.conn
 			.execute_batch(
@@ -156,7 +352,7 @@ impl SqliteStorage {
 	}
 
 	fn migrate_legacy_schema(&mut self) -> StorageResult<()> {
-		let dimension = self.validate_existing_vectors()?;
+		let dimensions = self.validate_existing_vectors()?;
 		let existing_entries = self.get_all_entries()?;
 		let invalid_relations: i64 = self
 			.conn
@@ -200,8 +396,8 @@ impl SqliteStorage {
 			"#,
 			)
 			.map_err(|error| StorageError::Database(error.to_string()))?;
-		if let Some(dimension) = dimension {
-			Self::set_vector_dimension(&transaction, dimension)?;
+		for (collection, dimension) in &dimensions {
+			Self::set_vector_dimension(&transaction, collection, *dimension)?;
 		}
 		for entry in &existing_entries {
 			Self::record_revision(&transaction, entry, RevisionOperation::Snapshot)?;
@@ -214,10 +410,14 @@ impl SqliteStorage {
 			.map_err(|error| StorageError::Database(error.to_string()))
 	}
 
-	fn validate_existing_vectors(&self) -> StorageResult<Option<usize>> {
+	/// Validate every stored entry, returning the vector dimension used by each collection
+	fn validate_existing_vectors(&self) -> StorageResult<HashMap<String, usize>> {
 		let mut statement = self
 			.conn
-			.prepare("SELECT id, meaning, context, created_at, updated_at FROM entries ORDER BY id")
+			.prepare(
+				"SELECT id, meaning, context, created_at, updated_at, collection
+				 FROM entries ORDER BY id",
+			)
 			.map_err(|error| StorageError::Database(error.to_string()))?;
 		let rows = statement
 			.query_map([], |row| {
@@ -227,12 +427,13 @@ impl SqliteStorage {
 					row.get::<_, String>(2)?,
 					row.get::<_, String>(3)?,
 					row.get::<_, String>(4)?,
+					row.get::<_, String>(5)?,
 				))
 			})
 			.map_err(|error| StorageError::Database(error.to_string()))?;
-		let mut expected_dimension = None;
+		let mut dimensions = HashMap::new();
 		for row in rows {
-			let (id, bytes, context, created_at, updated_at) =
+			let (id, bytes, context, created_at, updated_at, collection) =
 				row.map_err(|error| StorageError::Database(error.to_string()))?;
 			Uuid::parse_str(&id).map_err(|error| {
 				StorageError::Database(format!("Entry has invalid UUID {id}: {error}"))
@@ -250,23 +451,38 @@ impl SqliteStorage {
 			DateTime::parse_from_rfc3339(&updated_at).map_err(|error| {
 				StorageError::Database(format!("Entry {id} has invalid updated_at: {error}"))
 			})?;
-			match expected_dimension {
-				Some(dimension) if dimension != vector.len() => {
-					return Err(StorageError::InvalidDimensions);
-				}
-				None => expected_dimension = Some(vector.len()),
-				_ => {}
+			if *dimensions.entry(collection).or_insert(vector.len()) != vector.len() {
+				return Err(StorageError::InvalidDimensions);
 			}
 		}
-		Ok(expected_dimension)
+		Ok(dimensions)
+	}
+
+	/// Metadata key holding `key` for `collection`
+	///
+	/// The default collection keeps the unprefixed keys written before
+	/// collections existed.
+	fn metadata_key(collection: &str, key: &str) -> String {
+		if collection == DEFAULT_COLLECTION {
+			key.to_string()
+		} else {
+			format!("collection/{collection}/{key}")
+		}
 	}
 
-	fn set_vector_dimension(transaction: &Transaction<'_>, dimension: usize) -> StorageResult<()> {
+	fn set_vector_dimension(
+		transaction: &Transaction<'_>,
+		collection: &str,
+		dimension: usize,
+	) -> StorageResult<()> {
 		transaction
 			.execute(
-				"INSERT INTO contextdb_metadata (key, value) VALUES ('vector_dimension', ?1)
+				"INSERT INTO contextdb_metadata (key, value) VALUES (?1, ?2)
 				 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
-				params![dimension.to_string()],
+				params![
+					Self::metadata_key(collection, "vector_dimension"),
+					dimension.to_string()
+				],
 			)
 			.map_err(|error| StorageError::Database(error.to_string()))?;
 		Ok(())
@@ -289,70 +505,513 @@ impl SqliteStorage {
 		entry: &Entry,
 		operation: RevisionOperation,
 	) -> StorageResult<()> {
-		let operation = match operation {
-			RevisionOperation::Snapshot => "snapshot",
-			RevisionOperation::Insert => "insert",
-			RevisionOperation::Update => "update",
-			RevisionOperation::Delete => "delete",
+		let revision_id = Uuid::new_v4();
+		let recorded_at = Utc::now();
+		// Edge edits only change relations, so they are stored against the
+		// previous revision instead of repeating the whole entry.
+		let edge_edit = matches!(
+			operation,
+			RevisionOperation::Relate | RevisionOperation::Unrelate
+		);
+		let has_base = edge_edit
+			&& transaction
+				.query_row(
+					"SELECT EXISTS(SELECT 1 FROM entry_revisions WHERE entry_id = ?1)",
+					params![entry.id.to_string()],
+					|row| row.get::<_, bool>(0),
+				)
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+		let (encoding, snapshot) = if has_base {
+			let payload = serde_json::json!({
+				"relations": entry.relations,
+				"updated_at": entry.updated_at,
+				"version": entry.version,
+			});
+			("relations", payload.to_string())
+		} else {
+			("full", serde_json::to_string(entry)?)
 		};
 		transaction
 			.execute(
 				"INSERT INTO entry_revisions
-				 (revision_id, entry_id, operation, snapshot, recorded_at)
-				 VALUES (?1, ?2, ?3, ?4, ?5)",
+				 (revision_id, entry_id, operation, snapshot, recorded_at, encoding)
+				 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
 				params![
-					Uuid::new_v4().to_string(),
+					revision_id.to_string(),
 					entry.id.to_string(),
-					operation,
-					serde_json::to_string(entry)?,
-					Utc::now().to_rfc3339(),
+					Self::revision_operation_name(operation),
+					snapshot,
+					recorded_at.to_rfc3339(),
+					encoding,
+				],
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+
+		let change = match operation {
+			// Legacy migration snapshots describe existing data, not a mutation.
+			RevisionOperation::Snapshot => return Ok(()),
+			RevisionOperation::Insert => ChangeOperation::Insert,
+			RevisionOperation::Update => ChangeOperation::Update,
+			RevisionOperation::Delete => ChangeOperation::Delete,
+			RevisionOperation::Relate => ChangeOperation::Relate,
+			RevisionOperation::Unrelate => ChangeOperation::Unrelate,
+			RevisionOperation::Revert => ChangeOperation::Revert,
+		};
+		Self::record_change(
+			transaction,
+			entry.id,
+			&entry.tenant,
+			change,
+			Some(revision_id),
+			recorded_at,
+		)
+	}
+
+	fn record_change(
+		transaction: &Transaction<'_>,
+		entry_id: Uuid,
+		tenant: &str,
+		operation: ChangeOperation,
+		revision_id: Option<Uuid>,
+		recorded_at: DateTime<Utc>,
+	) -> StorageResult<()> {
+		transaction
+			.execute(
+				"INSERT INTO change_log (entry_id, operation, revision_id, recorded_at, tenant)
+				 VALUES (?1, ?2, ?3, ?4, ?5)",
+				params![
+					entry_id.to_string(),
+					Self::change_operation_name(operation),
+					revision_id.map(|id| id.to_string()),
+					recorded_at.to_rfc3339(),
+					tenant,
 				],
 			)
 			.map_err(|error| StorageError::Database(error.to_string()))?;
 		Ok(())
 	}
 
+	fn change_operation_name(operation: ChangeOperation) -> &'static str {
+		match operation {
+			ChangeOperation::Insert => "insert",
+			ChangeOperation::Update => "update",
+			ChangeOperation::Delete => "delete",
+			ChangeOperation::Relate => "relate",
+			ChangeOperation::Unrelate => "unrelate",
+			ChangeOperation::Revert => "revert",
+			ChangeOperation::Purge => "purge",
+		}
+	}
+
+	fn parse_change_operation(operation: &str) -> StorageResult<ChangeOperation> {
+		match operation {
+			"insert" => Ok(ChangeOperation::Insert),
+			"update" => Ok(ChangeOperation::Update),
+			"delete" => Ok(ChangeOperation::Delete),
+			"relate" => Ok(ChangeOperation::Relate),
+			"unrelate" => Ok(ChangeOperation::Unrelate),
+			"revert" => Ok(ChangeOperation::Revert),
+			"purge" => Ok(ChangeOperation::Purge),
+			other => Err(StorageError::Database(format!(
+				"Unknown change operation: {other}"
+			))),
+		}
+	}
+
+	fn revision_operation_name(operation: RevisionOperation) -> &'static str {
+		match operation {
+			RevisionOperation::Snapshot => "snapshot",
+			RevisionOperation::Insert => "insert",
+			RevisionOperation::Update => "update",
+			RevisionOperation::Delete => "delete",
+			RevisionOperation::Relate => "relate",
+			RevisionOperation::Unrelate => "unrelate",
+			RevisionOperation::Revert => "revert",
+		}
+	}
+
+	fn parse_revision_operation(operation: &str) -> StorageResult<RevisionOperation> {
+		match operation {
+			"snapshot" => Ok(RevisionOperation::Snapshot),
+			"insert" => Ok(RevisionOperation::Insert),
+			"update" => Ok(RevisionOperation::Update),
+			"delete" => Ok(RevisionOperation::Delete),
+			"relate" => Ok(RevisionOperation::Relate),
+			"unrelate" => Ok(RevisionOperation::Unrelate),
+			"revert" => Ok(RevisionOperation::Revert),
+			other => Err(StorageError::Database(format!(
+				"Unknown revision operation: {other}"
+			))),
+		}
+	}
+
 	fn validate_revisions(&self) -> StorageResult<()> {
+		self.load_revisions(None).map(|_| ())
+	}
+
+	/// Load and decode revisions, optionally for one entry, ordered by entry and rowid
+	///
+	/// Delta-encoded rows are JSON Patches against the previous row of the same
+	/// entry. Relations-encoded rows hold only the relations, `updated_at`, and
+	/// `version` an edge edit changed, and take the rest from the previous row.
+	fn load_revisions(&self, entry_id: Option<Uuid>) -> StorageResult<Vec<StoredRevision>> {
+		let sql = format!(
+			"SELECT rowid, revision_id, entry_id, operation, encoding, snapshot, recorded_at
+			 FROM entry_revisions {}
+			 ORDER BY entry_id, rowid",
+			if entry_id.is_some() {
+				"WHERE entry_id = ?1"
+			} else {
+				""
+			}
+		);
 		let mut statement = self
 			.conn
-			.prepare(
-				"SELECT revision_id, entry_id, operation, snapshot, recorded_at
-				 FROM entry_revisions ORDER BY revision_id",
-			)
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		let rows = statement
-			.query_map([], |row| {
-				Ok((
-					row.get::<_, String>(0)?,
-					row.get::<_, String>(1)?,
-					row.get::<_, String>(2)?,
-					row.get::<_, String>(3)?,
-					row.get::<_, String>(4)?,
-				))
-			})
+			.prepare(&sql)
 			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let map_row = |row: &rusqlite::Row<'_>| {
+			Ok((
+				row.get::<_, i64>(0)?,
+				row.get::<_, String>(1)?,
+				row.get::<_, String>(2)?,
+				row.get::<_, String>(3)?,
+				row.get::<_, String>(4)?,
+				row.get::<_, String>(5)?,
+				row.get::<_, String>(6)?,
+			))
+		};
+		let rows = match entry_id {
+			Some(id) => statement.query_map(params![id.to_string()], map_row),
+			None => statement.query_map([], map_row),
+		}
+		.map_err(|error| StorageError::Database(error.to_string()))?;
+
+		let mut revisions: Vec<StoredRevision> = Vec::new();
 		for row in rows {
-			let (revision_id, entry_id, operation, snapshot, recorded_at) =
+			let (rowid, revision_id, entry_id, operation, encoding, snapshot, recorded_at) =
 				row.map_err(|error| StorageError::Database(error.to_string()))?;
-			Uuid::parse_str(&revision_id)
+			let revision_id = Uuid::parse_str(&revision_id)
 				.map_err(|error| StorageError::Database(error.to_string()))?;
-			Uuid::parse_str(&entry_id)
+			let entry_id = Uuid::parse_str(&entry_id)
 				.map_err(|error| StorageError::Database(error.to_string()))?;
-			if !matches!(
-				operation.as_str(),
-				"snapshot" | "insert" | "update" | "delete"
-			) {
-				return Err(StorageError::Database(format!(
-					"Unknown revision operation: {operation}"
-				)));
+			let base = revisions
+				.last()
+				.filter(|previous| previous.revision.entry_id == entry_id)
+				.map(|previous| &previous.document);
+			let document = match encoding.as_str() {
+				"full" => serde_json::from_str(&snapshot)?,
+				"relations" => {
+					let mut document = base
+						.ok_or_else(|| {
+							StorageError::Database(format!(
+								"Relations revision {revision_id} has no preceding revision"
+							))
+						})?
+						.clone();
+					let changed: serde_json::Map<String, serde_json::Value> =
+						serde_json::from_str(&snapshot)?;
+					let fields = document.as_object_mut().ok_or_else(|| {
+						StorageError::Database(format!(
+							"Revision before {revision_id} is not an entry object"
+						))
+					})?;
+					fields.extend(changed);
+					document
+				}
+				"delta" => {
+					let mut document = base
+						.ok_or_else(|| {
+							StorageError::Database(format!(
+								"Delta revision {revision_id} has no preceding revision"
+							))
+						})?
+						.clone();
+					let patch: Vec<JsonPatchOperation> = serde_json::from_str(&snapshot)?;
+					apply_json_patch(&mut document, &patch).map_err(|error| {
+						StorageError::Database(format!(
+							"Delta revision {revision_id} does not apply: {error}"
+						))
+					})?;
+					document
+				}
+				other => {
+					return Err(StorageError::Database(format!(
+						"Unknown revision encoding: {other}"
+					)))
+				}
+			};
+			revisions.push(StoredRevision {
+				rowid,
+				relations_only: encoding == "relations",
+				delta: encoding != "full",
+				stored_bytes: snapshot.len(),
+				revision: EntryRevision {
+					revision_id,
+					entry_id,
+					operation: Self::parse_revision_operation(&operation)?,
+					snapshot: serde_json::from_value(document.clone())?,
+					recorded_at: DateTime::parse_from_rfc3339(&recorded_at)
+						.map_err(|error| StorageError::Database(error.to_string()))?
+						.with_timezone(&Utc),
+				},
+				document,
+			});
+		}
+		Ok(revisions)
+	}
+
+	/// Validate a collection or tenant name
+	fn validate_name(kind: &str, name: &str) -> StorageResult<()> {
+		let valid = !name.is_empty()
+			&& name.len() <= 64
+			&& name
+				.bytes()
+				.all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.'));
+		if !valid {
+			return Err(StorageError::InvalidArgument(format!(
+				"Invalid {kind} name {name:?}: use 1-64 ASCII letters, digits, '-', '_' or '.'"
+			)));
+		}
+		Ok(())
+	}
+
+	/// Read an entry of this handle's tenant, including an expired one
+	fn read_scoped(&self, id: Uuid) -> StorageResult<Entry> {
+		let entry = Self::read_entry(&self.conn, id)?;
+		if !self.in_scope(&entry) {
+			return Err(StorageError::NotFound(id));
+		}
+		Ok(entry)
+	}
+
+	/// Whether `entry` belongs to the tenant this handle is scoped to
+	fn in_scope(&self, entry: &Entry) -> bool {
+		match &self.tenant {
+			Some(tenant) => entry.tenant == *tenant,
+			None => true,
+		}
+	}
+
+	/// Tenant to record a purge of `id` under
+	///
+	/// A purged entry may already be gone from `entries`, so its newest
+	/// revision answers for it.
+	fn purged_tenant(&self, id: Uuid) -> StorageResult<String> {
+		if let Some(tenant) = &self.tenant {
+			return Ok(tenant.clone());
+		}
+		match self.conn.query_row(
+			"SELECT tenant FROM entries WHERE id = ?1",
+			params![id.to_string()],
+			|row| row.get(0),
+		) {
+			Ok(tenant) => return Ok(tenant),
+			Err(rusqlite::Error::QueryReturnedNoRows) => {}
+			Err(error) => return Err(StorageError::Database(error.to_string())),
+		}
+		Ok(self.revisions(id)?.last().map_or_else(
+			|| DEFAULT_TENANT.to_string(),
+			|revision| revision.snapshot.tenant.clone(),
+		))
+	}
+
+	fn tenant_of(conn: &Connection, id: Uuid) -> StorageResult<Option<String>> {
+		match conn.query_row(
+			"SELECT tenant FROM entries WHERE id = ?1 AND deleted_at IS NULL",
+			params![id.to_string()],
+			|row| row.get(0),
+		) {
+			Ok(tenant) => Ok(Some(tenant)),
+			Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+			Err(error) => Err(StorageError::Database(error.to_string())),
+		}
+	}
+
+	/// Require every relation of `entries` to target an entry of the same tenant
+	///
+	/// Targets may be other members of `entries`. A target owned by another
+	/// tenant is reported as missing so its existence does not leak.
+	fn check_relation_tenants(conn: &Connection, entries: &[Entry]) -> StorageResult<()> {
+		let batch: HashMap<Uuid, &str> = entries
+			.iter()
+			.map(|entry| (entry.id, entry.tenant.as_str()))
+			.collect();
+		for entry in entries {
+			for target in &entry.relations {
+				let tenant = match batch.get(target) {
+					Some(tenant) => Some(tenant.to_string()),
+					None => Self::tenant_of(conn, *target)?,
+				};
+				if tenant.as_deref() != Some(entry.tenant.as_str()) {
+					return Err(StorageError::NotFound(*target));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Apply `policy` to a batch about to be inserted through `conn`
+	///
+	/// Entries are compared with earlier batch members and with live stored
+	/// entries of the same tenant and collection. Relations that pointed at a
+	/// dropped entry are redirected to the entry that kept its content.
+	fn resolve_duplicates(
+		conn: &Connection,
+		policy: &DedupPolicy,
+		entries: &[Entry],
+	) -> StorageResult<DedupResolution> {
+		let mut inserts: Vec<Entry> = Vec::with_capacity(entries.len());
+		let mut kept: HashMap<(String, String, String), usize> = HashMap::new();
+		let mut stored: Vec<(serde_json::Value, Entry)> = Vec::new();
+		let mut redirects: HashMap<Uuid, Uuid> = HashMap::new();
+		let mut ids = Vec::with_capacity(entries.len());
+		for entry in entries {
+			let content_hash = policy.content_hash(entry);
+			let key = (entry.tenant.clone(), entry.collection.clone(), content_hash);
+			let existing = match kept.get(&key) {
+				Some(index) => Some(inserts[*index].id),
+				None => Self::live_duplicate(conn, entry, &key.2)?,
+			};
+			let Some(existing) = existing else {
+				kept.insert(key, inserts.len());
+				ids.push(entry.id);
+				inserts.push(entry.clone());
+				continue;
+			};
+			match policy.on_duplicate {
+				DuplicateAction::Reject => {
+					return Err(StorageError::Duplicate {
+						id: entry.id,
+						existing,
+					});
+				}
+				DuplicateAction::Skip => {}
+				DuplicateAction::MergeContext => {
+					let target = match kept.get(&key) {
+						Some(index) => &mut inserts[*index],
+						None => {
+							let position =
+								match stored.iter().position(|(_, merged)| merged.id == existing) {
+									Some(position) => position,
+									None => {
+										let mut merged = Self::read_entry(conn, existing)?;
+										let original = merged.context.clone();
+										merged.version += 1;
+										merged.updated_at = merged.updated_at.max(Utc::now());
+										stored.push((original, merged));
+										stored.len() - 1
+									}
+								};
+							&mut stored[position].1
+						}
+					};
+					merge_patch(&mut target.context, &entry.context);
+				}
 			}
-			serde_json::from_str::<Entry>(&snapshot)?;
-			DateTime::parse_from_rfc3339(&recorded_at)
+			redirects.insert(entry.id, existing);
+			ids.push(existing);
+		}
+
+		for entry in &mut inserts {
+			let id = entry.id;
+			let mut seen = HashSet::new();
+			entry.relations = entry
+				.relations
+				.iter()
+				.map(|target| *redirects.get(target).unwrap_or(target))
+				.filter(|target| *target != id && seen.insert(*target))
+				.collect();
+		}
+		let merged = stored
+			.into_iter()
+			.filter(|(original, merged)| *original != merged.context)
+			.map(|(_, merged)| merged)
+			.collect();
+		Ok(DedupResolution {
+			inserts,
+			merged,
+			ids,
+		})
+	}
+
+	/// Reject writes that would move a stored entry to another tenant
+	fn check_same_tenant(entry: &Entry, stored: &Entry) -> StorageResult<()> {
+		if entry.tenant != stored.tenant {
+			return Err(StorageError::InvalidArgument(
+				"Entries cannot move between tenants".to_string(),
+			));
+		}
+		Ok(())
+	}
+
+	fn has_trashed_entries(&self) -> StorageResult<bool> {
+		self.conn
+			.query_row(
+				"SELECT EXISTS(SELECT 1 FROM entries WHERE deleted_at IS NOT NULL)",
+				[],
+				|row| row.get(0),
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn is_trashed(conn: &Connection, id: Uuid) -> StorageResult<bool> {
+		conn.query_row(
+			"SELECT EXISTS(SELECT 1 FROM entries WHERE id = ?1 AND deleted_at IS NOT NULL)",
+			params![id.to_string()],
+			|row| row.get(0),
+		)
+		.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	/// Hide entries in the trash, recording a delete revision for each
+	///
+	/// The rows and their relations stay in place so a restore can bring both back.
+	fn move_to_trash(&mut self, snapshots: &[Entry]) -> StorageResult<()> {
+		let transaction = self
+			.conn
+			.transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Self::trash_rows(&transaction, snapshots)?;
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn trash_rows(transaction: &Transaction<'_>, snapshots: &[Entry]) -> StorageResult<()> {
+		let now = Utc::now().to_rfc3339();
+		for snapshot in snapshots {
+			let rows_affected = transaction
+				.execute(
+					"UPDATE entries SET deleted_at = ?1, version = version + 1
+					 WHERE id = ?2 AND version = ?3 AND deleted_at IS NULL",
+					params![now, snapshot.id.to_string(), snapshot.version as i64],
+				)
 				.map_err(|error| StorageError::Database(error.to_string()))?;
+			Self::check_version_write(transaction, snapshot.id, snapshot.version, rows_affected)?;
+			Self::record_revision(transaction, snapshot, RevisionOperation::Delete)?;
 		}
 		Ok(())
 	}
 
+	/// SQL predicate hiding entries expired at `now`, when any exist
+	fn expiry_predicate(&self, now: DateTime<Utc>) -> StorageResult<Option<String>> {
+		let now = now.to_rfc3339();
+		let any_expired: bool = self
+			.conn
+			.query_row(
+				"SELECT EXISTS(SELECT 1 FROM entries WHERE expires_at <= ?1)",
+				params![now],
+				|row| row.get(0),
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(any_expired.then(|| {
+			format!(
+				"(expires_at IS NULL OR expires_at > {})",
+				Self::sql_string_literal(&now)
+			)
+		}))
+	}
+
 	fn validate_vector(vector: &[f32]) -> StorageResult<()> {
 		if vector.is_empty() || vector.iter().any(|value| !value.is_finite()) {
 			return Err(StorageError::InvalidDimensions);
@@ -360,6 +1019,15 @@ impl SqliteStorage {
 		Ok(())
 	}
 
+	fn validate_retention_policy(policy: &RetentionPolicy) -> StorageResult<()> {
+		if policy.keep_last == Some(0) {
+			return Err(StorageError::InvalidArgument(
+				"keep_last must be greater than zero".to_string(),
+			));
+		}
+		Ok(())
+	}
+
 	fn validate_embedding_profile(profile: &EmbeddingProfile) -> StorageResult<()> {
 		if profile.model.trim().is_empty() || profile.dimensions == 0 {
 			return Err(StorageError::InvalidDimensions);
@@ -378,11 +1046,13 @@ impl SqliteStorage {
 
 	fn inspect_embedding_metadata(
 		&self,
+		collection: &str,
 	) -> StorageResult<(Option<EmbeddingProfile>, Option<usize>)> {
-		let model = self.metadata_value("embedding_model")?;
-		let version = self.metadata_value("embedding_model_version")?;
+		let model = self.metadata_value(&Self::metadata_key(collection, "embedding_model"))?;
+		let version =
+			self.metadata_value(&Self::metadata_key(collection, "embedding_model_version"))?;
 		let dimensions = self
-			.metadata_value("vector_dimension")?
+			.metadata_value(&Self::metadata_key(collection, "vector_dimension"))?
 			.map(|value| {
 				value.parse::<usize>().map_err(|error| {
 					StorageError::Database(format!(
@@ -434,32 +1104,36 @@ impl SqliteStorage {
 
 	fn write_embedding_profile(
 		transaction: &Transaction<'_>,
+		collection: &str,
 		profile: &EmbeddingProfile,
 	) -> StorageResult<()> {
-		Self::set_vector_dimension(transaction, profile.dimensions)?;
+		Self::set_vector_dimension(transaction, collection, profile.dimensions)?;
 		transaction
 			.execute(
-				"INSERT INTO contextdb_metadata (key, value) VALUES ('embedding_model', ?1)
+				"INSERT INTO contextdb_metadata (key, value) VALUES (?1, ?2)
 				 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
-				params![&profile.model],
+				params![
+					Self::metadata_key(collection, "embedding_model"),
+					&profile.model
+				],
 			)
 			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let version_key = Self::metadata_key(collection, "embedding_model_version");
 		match &profile.version {
 			Some(version) => {
 				transaction
 					.execute(
-						"INSERT INTO contextdb_metadata (key, value)
-						 VALUES ('embedding_model_version', ?1)
+						"INSERT INTO contextdb_metadata (key, value) VALUES (?1, ?2)
 						 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
-						params![version],
+						params![version_key, version],
 					)
 					.map_err(|error| StorageError::Database(error.to_string()))?;
 			}
 			None => {
 				transaction
 					.execute(
-						"DELETE FROM contextdb_metadata WHERE key = 'embedding_model_version'",
-						[],
+						"DELETE FROM contextdb_metadata WHERE key = ?1",
+						params![version_key],
 					)
 					.map_err(|error| StorageError::Database(error.to_string()))?;
 			}
@@ -467,10 +1141,10 @@ impl SqliteStorage {
 		Ok(())
 	}
 
-	fn stored_vector_dimension(&self) -> StorageResult<Option<usize>> {
+	fn stored_vector_dimension(&self, collection: &str) -> StorageResult<Option<usize>> {
 		let metadata_result = self.conn.query_row(
-			"SELECT value FROM contextdb_metadata WHERE key = 'vector_dimension'",
-			[],
+			"SELECT value FROM contextdb_metadata WHERE key = ?1",
+			params![Self::metadata_key(collection, "vector_dimension")],
 			|row| row.get::<_, String>(0),
 		);
 		match metadata_result {
@@ -490,8 +1164,8 @@ impl SqliteStorage {
 		}
 
 		let result = self.conn.query_row(
-			"SELECT meaning FROM entries ORDER BY id LIMIT 1",
-			[],
+			"SELECT meaning FROM entries WHERE collection = ?1 ORDER BY id LIMIT 1",
+			params![collection],
 			|row| row.get::<_, Vec<u8>>(0),
 		);
 
@@ -508,17 +1182,115 @@ impl SqliteStorage {
 		}
 	}
 
-	fn validate_entry(&self, entry: &Entry) -> StorageResult<()> {
-		Self::validate_vector(&entry.meaning)?;
-		if self
-			.stored_vector_dimension()?
-			.is_some_and(|dimension| dimension != entry.meaning.len())
-		{
-			return Err(StorageError::InvalidDimensions);
-		}
-		if entry.relations.contains(&entry.id) {
-			return Err(StorageError::InvalidArgument(
-				"An entry cannot relate to itself".to_string(),
+	/// Names of collections that hold entries or have stored metadata, sorted
+	fn collection_names(&self) -> StorageResult<Vec<String>> {
+		let mut statement = self
+			.conn
+			.prepare(
+				"SELECT collection FROM entries
+				 UNION
+				 SELECT substr(key, 12, instr(substr(key, 12), '/') - 1)
+				 FROM contextdb_metadata WHERE key LIKE 'collection/%/%'
+				 UNION
+				 SELECT ?1 FROM contextdb_metadata
+				 WHERE key IN ('vector_dimension', 'embedding_model', 'embedding_model_version')
+				 ORDER BY 1",
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let rows = statement
+			.query_map(params![DEFAULT_COLLECTION], |row| row.get::<_, String>(0))
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		rows.collect::<Result<_, _>>()
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn collection_count(&self, collection: &str) -> StorageResult<usize> {
+		self.conn
+			.query_row(
+				"SELECT COUNT(*) FROM entries WHERE collection = ?1",
+				params![collection],
+				|row| row.get(0),
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	/// Check that entries written together agree with their collections' dimensions
+	///
+	/// Returns the dimension each collection without one establishes once the
+	/// entries are written.
+	fn check_batch_dimensions(&self, entries: &[Entry]) -> StorageResult<Vec<(String, usize)>> {
+		let mut dimensions: HashMap<&str, usize> = HashMap::new();
+		let mut established = Vec::new();
+		for entry in entries {
+			let expected = match dimensions.get(entry.collection.as_str()) {
+				Some(dimension) => *dimension,
+				None => {
+					let dimension = match self.stored_vector_dimension(&entry.collection)? {
+						Some(dimension) => dimension,
+						None => {
+							established.push((entry.collection.clone(), entry.meaning.len()));
+							entry.meaning.len()
+						}
+					};
+					dimensions.insert(&entry.collection, dimension);
+					dimension
+				}
+			};
+			if entry.meaning.len() != expected {
+				return Err(StorageError::InvalidDimensions);
+			}
+		}
+		Ok(established)
+	}
+
+	fn set_vector_dimensions(
+		transaction: &Transaction<'_>,
+		dimensions: &[(String, usize)],
+	) -> StorageResult<()> {
+		for (collection, dimension) in dimensions {
+			Self::set_vector_dimension(transaction, collection, *dimension)?;
+		}
+		Ok(())
+	}
+
+	fn validate_entry(&self, entry: &Entry) -> StorageResult<()> {
+		Self::validate_vector(&entry.meaning)?;
+		Self::validate_name("collection", &entry.collection)?;
+		Self::validate_name("tenant", &entry.tenant)?;
+		if entry
+			.external_id
+			.as_deref()
+			.is_some_and(|external_id| external_id.trim().is_empty())
+		{
+			return Err(StorageError::InvalidArgument(
+				"External ID cannot be empty".to_string(),
+			));
+		}
+		if entry
+			.importance
+			.is_some_and(|importance| !(0.0..=1.0).contains(&importance))
+		{
+			return Err(StorageError::InvalidArgument(
+				"Importance must be between 0 and 1".to_string(),
+			));
+		}
+		if let Some(tenant) = &self.tenant {
+			if entry.tenant != *tenant {
+				return Err(StorageError::InvalidArgument(format!(
+					"Entry tenant {} does not match the handle's tenant {tenant}",
+					entry.tenant
+				)));
+			}
+		}
+		if self
+			.stored_vector_dimension(&entry.collection)?
+			.is_some_and(|dimension| dimension != entry.meaning.len())
+		{
+			return Err(StorageError::InvalidDimensions);
+		}
+		if entry.relations.contains(&entry.id) {
+			return Err(StorageError::InvalidArgument(
+				"An entry cannot relate to itself".to_string(),
 			));
 		}
 		if entry
@@ -546,13 +1318,30 @@ impl SqliteStorage {
 				"A query cannot use both cursor and offset pagination".to_string(),
 			));
 		}
+		if query.collapse_chunks && query.as_of.is_some() {
+			return Err(StorageError::InvalidArgument(
+				"Chunk collapsing cannot be combined with as_of".to_string(),
+			));
+		}
+		for collection in &query.collections {
+			Self::validate_name("collection", collection)?;
+		}
 		if let Some(meaning) = &query.meaning {
 			Self::validate_vector(&meaning.vector)?;
-			if self
-				.stored_vector_dimension()?
-				.is_some_and(|dimension| dimension != meaning.vector.len())
-			{
-				return Err(StorageError::InvalidDimensions);
+			// Vectors from different embedding profiles are not comparable, so
+			// every searched collection must use the query's dimensions.
+			let collections = if query.collections.is_empty() {
+				self.collection_names()?
+			} else {
+				query.collections.clone()
+			};
+			for collection in &collections {
+				if self
+					.stored_vector_dimension(collection)?
+					.is_some_and(|dimension| dimension != meaning.vector.len())
+				{
+					return Err(StorageError::InvalidDimensions);
+				}
 			}
 			if meaning.threshold.is_some_and(|threshold| {
 				!threshold.is_finite() || !(0.0..=1.0).contains(&threshold)
@@ -567,6 +1356,40 @@ impl SqliteStorage {
 				));
 			}
 		}
+		if let Some(decay) = query.decay {
+			let weights = [decay.relevance, decay.recency, decay.importance];
+			if weights
+				.iter()
+				.any(|weight| !weight.is_finite() || *weight < 0.0)
+				|| weights.iter().sum::<f32>() == 0.0
+			{
+				return Err(StorageError::InvalidArgument(
+					"Decay weights must be finite, non-negative, and have a positive sum"
+						.to_string(),
+				));
+			}
+			if !decay.half_life_hours.is_finite() || decay.half_life_hours <= 0.0 {
+				return Err(StorageError::InvalidArgument(
+					"Decay half-life must be finite and positive".to_string(),
+				));
+			}
+		}
+		if let Some(boost) = &query.feedback_boost {
+			if !boost.weight.is_finite() || boost.weight < 0.0 {
+				return Err(StorageError::InvalidArgument(
+					"Feedback boost weight must be finite and non-negative".to_string(),
+				));
+			}
+			if boost
+				.fingerprint
+				.as_deref()
+				.is_some_and(|fingerprint| fingerprint.trim().is_empty())
+			{
+				return Err(StorageError::InvalidArgument(
+					"Query fingerprint cannot be empty".to_string(),
+				));
+			}
+		}
 		if let Some(weights) = query.hybrid_weights {
 			if !weights.semantic.is_finite()
 				|| !weights.lexical.is_finite()
@@ -598,364 +1421,870 @@ impl SqliteStorage {
 		Ok(())
 	}
 
-	fn validate_relation_targets(&self, entry: &Entry) -> StorageResult<()> {
-		for relation_id in &entry.relations {
-			let exists: bool = self
-				.conn
-				.query_row(
-					"SELECT EXISTS(SELECT 1 FROM entries WHERE id = ?1)",
-					params![relation_id.to_string()],
-					|row| row.get(0),
-				)
-				.map_err(|error| StorageError::Database(error.to_string()))?;
-			if !exists {
-				return Err(StorageError::NotFound(*relation_id));
+	/// Insert or update each of `entries` inside `transaction`, which must hold the write lock
+	fn upsert_rows(
+		transaction: &Transaction<'_>,
+		entries: &[Entry],
+	) -> StorageResult<Vec<UpsertOutcome>> {
+		let mut outcomes = Vec::with_capacity(entries.len());
+		let mut stored_versions = Vec::with_capacity(entries.len());
+		for entry in entries {
+			let outcome = match Self::read_entry(transaction, entry.id) {
+				Ok(existing) => {
+					Self::check_same_tenant(entry, &existing)?;
+					if entry.created_at != existing.created_at
+						|| entry.updated_at < existing.updated_at
+					{
+						return Err(StorageError::Database(
+							"Updates must preserve created_at and advance updated_at monotonically"
+								.to_string(),
+						));
+					}
+					stored_versions.push(existing.version);
+					if Self::same_entry_state(entry, &existing) {
+						UpsertOutcome::Unchanged
+					} else {
+						UpsertOutcome::Updated
+					}
+				}
+				Err(StorageError::NotFound(_)) => {
+					if Self::is_trashed(transaction, entry.id)? {
+						return Err(StorageError::InvalidArgument(format!(
+							"Entry {} is in the trash; restore it before writing it",
+							entry.id
+						)));
+					}
+					stored_versions.push(0);
+					UpsertOutcome::Inserted
+				}
+				Err(error) => return Err(error),
+			};
+			outcomes.push(outcome);
+		}
+		Self::check_relation_tenants(transaction, entries)?;
+
+		let mut written = Vec::with_capacity(entries.len());
+		for ((entry, outcome), stored_version) in entries.iter().zip(&outcomes).zip(stored_versions)
+		{
+			let entry = Entry {
+				version: stored_version + 1,
+				..entry.clone()
+			};
+			match outcome {
+				UpsertOutcome::Inserted => Self::insert_entry_row(transaction, &entry)?,
+				UpsertOutcome::Updated => {
+					Self::update_entry_row(transaction, &entry, stored_version)?
+				}
+				UpsertOutcome::Unchanged => continue,
 			}
+			written.push((entry, *outcome));
 		}
-		Ok(())
+		for (entry, outcome) in &written {
+			let operation = match outcome {
+				UpsertOutcome::Inserted => RevisionOperation::Insert,
+				_ => RevisionOperation::Update,
+			};
+			Self::write_relations(transaction, entry)?;
+			Self::record_revision(transaction, entry, operation)?;
+		}
+		Ok(outcomes)
 	}
 
-	/// Get all entries from the database
-	fn get_all_entries(&self) -> StorageResult<Vec<Entry>> {
-		let mut stmt = self
-			.conn
-			.prepare(
-				"SELECT id, meaning, expression, context, created_at, updated_at
-				 FROM entries ORDER BY id",
+	/// Insert an entry row; relations are written separately by [`Self::write_relations`]
+	fn insert_entry_row(transaction: &Transaction<'_>, entry: &Entry) -> StorageResult<()> {
+		let meaning_bytes = vector_codec::serialize(&entry.meaning).map_err(|error| {
+			StorageError::Database(format!("Failed to serialize vector: {error}"))
+		})?;
+		let content_hash = Self::content_hash_in(transaction, entry)?;
+		Self::release_expired_content_hash(transaction, entry, content_hash.as_deref())?;
+		Self::release_expired_external_id(transaction, entry)?;
+		transaction
+			.execute(
+				"INSERT INTO entries
+				 (id, meaning, expression, context, created_at, updated_at, version, collection, tenant,
+					expires_at, content_hash, external_id, importance)
+				 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+				params![
+					entry.id.to_string(),
+					meaning_bytes,
+					&entry.expression,
+					serde_json::to_string(&entry.context)?,
+					entry.created_at.to_rfc3339(),
+					entry.updated_at.to_rfc3339(),
+					entry.version as i64,
+					&entry.collection,
+					&entry.tenant,
+					entry.expires_at.map(|expires_at| expires_at.to_rfc3339()),
+					content_hash,
+					&entry.external_id,
+					entry.importance,
+				],
 			)
-			.map_err(|e| StorageError::Database(e.to_string()))?;
+			.map_err(|error| {
+				Self::unique_write_error(transaction, entry, content_hash.as_deref(), error)
+			})?;
+		Ok(())
+	}
 
-		let rows = stmt
-			.query_map([], |row| {
-				Ok((
-					row.get::<_, String>(0)?,
-					row.get::<_, Vec<u8>>(1)?,
-					row.get::<_, String>(2)?,
-					row.get::<_, String>(3)?,
-					row.get::<_, String>(4)?,
-					row.get::<_, String>(5)?,
-				))
-			})
-			.map_err(|e| StorageError::Database(e.to_string()))?;
-		let mut entries = Vec::new();
-		for row in rows {
-			let (id, meaning, expression, context, created_at, updated_at) =
-				row.map_err(|error| StorageError::Database(error.to_string()))?;
-			let id = Uuid::parse_str(&id)
-				.map_err(|error| StorageError::Database(format!("Invalid entry UUID: {error}")))?;
-			entries.push(Entry {
-				id,
-				meaning: vector_codec::deserialize(&meaning).map_err(|error| {
-					StorageError::Database(format!("Entry {id} has invalid vector: {error}"))
-				})?,
-				expression,
-				context: serde_json::from_str(&context)?,
-				created_at: DateTime::parse_from_rfc3339(&created_at)
-					.map_err(|error| StorageError::Database(error.to_string()))?
-					.with_timezone(&Utc),
-				updated_at: DateTime::parse_from_rfc3339(&updated_at)
-					.map_err(|error| StorageError::Database(error.to_string()))?
-					.with_timezone(&Utc),
-				relations: Vec::new(),
-			});
-		}
+	/// Overwrite an existing entry row stored at `expected_version` with `entry`
+	///
+	/// Fails with `NotFound` when the row is absent and `Conflict` when another
+	/// write changed its version first.
+	fn update_entry_row(
+		transaction: &Transaction<'_>,
+		entry: &Entry,
+		expected_version: u64,
+	) -> StorageResult<()> {
+		let meaning_bytes = vector_codec::serialize(&entry.meaning).map_err(|error| {
+			StorageError::Database(format!("Failed to serialize vector: {error}"))
+		})?;
+		let content_hash = Self::content_hash_in(transaction, entry)?;
+		Self::release_expired_content_hash(transaction, entry, content_hash.as_deref())?;
+		Self::release_expired_external_id(transaction, entry)?;
+		let rows_affected = transaction
+			.execute(
+				"UPDATE entries
+				 SET meaning = ?1, expression = ?2, context = ?3, updated_at = ?4, version = ?5,
+					collection = ?6, expires_at = ?7, content_hash = ?8, external_id = ?9,
+					importance = ?10
+				 WHERE id = ?11 AND version = ?12",
+				params![
+					meaning_bytes,
+					&entry.expression,
+					serde_json::to_string(&entry.context)?,
+					entry.updated_at.to_rfc3339(),
+					entry.version as i64,
+					&entry.collection,
+					entry.expires_at.map(|expires_at| expires_at.to_rfc3339()),
+					content_hash,
+					&entry.external_id,
+					entry.importance,
+					entry.id.to_string(),
+					expected_version as i64,
+				],
+			)
+			.map_err(|error| {
+				Self::unique_write_error(transaction, entry, content_hash.as_deref(), error)
+			})?;
+		Self::check_version_write(transaction, entry.id, expected_version, rows_affected)
+	}
 
-		let mut relation_statement = self
-			.conn
-			.prepare("SELECT from_id, to_id FROM relations ORDER BY from_id, to_id")
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		let relation_rows = relation_statement
-			.query_map([], |row| {
-				Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
-			})
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		let mut relations: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
-		for row in relation_rows {
-			let (from_id, to_id) =
-				row.map_err(|error| StorageError::Database(error.to_string()))?;
-			let from_id = Uuid::parse_str(&from_id)
-				.map_err(|error| StorageError::Database(error.to_string()))?;
-			let to_id = Uuid::parse_str(&to_id)
-				.map_err(|error| StorageError::Database(error.to_string()))?;
-			relations.entry(from_id).or_default().push(to_id);
-		}
-		for entry in &mut entries {
-			entry.relations = relations.remove(&entry.id).unwrap_or_default();
-		}
-		Ok(entries)
+	/// Take `content_hash` back from expired entries of `entry`'s tenant and
+	/// collection so the unique index lets `entry` have it
+	///
+	/// An expired entry is hidden until swept, so it is no duplicate. Updating
+	/// it later hashes it again.
+	fn release_expired_content_hash(
+		conn: &Connection,
+		entry: &Entry,
+		content_hash: Option<&str>,
+	) -> StorageResult<()> {
+		let Some(content_hash) = content_hash else {
+			return Ok(());
+		};
+		conn.execute(
+			"UPDATE entries SET content_hash = NULL
+			 WHERE tenant = ?1 AND collection = ?2 AND content_hash = ?3 AND id <> ?4
+				AND expires_at <= ?5",
+			params![
+				&entry.tenant,
+				&entry.collection,
+				content_hash,
+				entry.id.to_string(),
+				Utc::now().to_rfc3339()
+			],
+		)
+		.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(())
 	}
 
-	fn matches_expression(
-		&self,
-		expression: &str,
-		filter: &ExpressionFilter,
-	) -> StorageResult<bool> {
-		match filter {
-			ExpressionFilter::Equals(s) => Ok(expression == s),
-			ExpressionFilter::Contains(s) => {
-				Ok(expression.to_lowercase().contains(&s.to_lowercase()))
-			}
-			ExpressionFilter::StartsWith(s) => Ok(expression.starts_with(s)),
-			ExpressionFilter::Matches(pattern) => {
-				let regex = Regex::new(pattern)
-					.map_err(|e| StorageError::InvalidArgument(format!("Invalid regex: {e}")))?;
-				Ok(regex.is_match(expression))
-			}
-			ExpressionFilter::FullText(_) => Ok(true),
-		}
+	/// Take `entry`'s external ID back from expired entries of its tenant
+	///
+	/// Like its content hash, an expired entry gives up its key to the next
+	/// write that needs it, and an update that restores the key must not clash.
+	fn release_expired_external_id(conn: &Connection, entry: &Entry) -> StorageResult<()> {
+		let Some(external_id) = entry.external_id.as_deref() else {
+			return Ok(());
+		};
+		conn.execute(
+			"UPDATE entries SET external_id = NULL
+			 WHERE tenant = ?1 AND external_id = ?2 AND id <> ?3 AND expires_at <= ?4",
+			params![
+				&entry.tenant,
+				external_id,
+				entry.id.to_string(),
+				Utc::now().to_rfc3339()
+			],
+		)
+		.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(())
 	}
 
-	fn matches_context(&self, context: &serde_json::Value, filter: &ContextFilter) -> bool {
-		match filter {
-			ContextFilter::PathExists(path) => {
-				// Simple path checking - in production use jsonpath
-				context.pointer(path).is_some()
-			}
-			ContextFilter::PathEquals(path, value) => context.pointer(path) == Some(value),
-			ContextFilter::PathContains(path, value) => {
-				if let Some(arr) = context.pointer(path).and_then(|v| v.as_array()) {
-					arr.contains(value)
-				} else {
-					false
-				}
-			}
-			ContextFilter::And(filters) => filters.iter().all(|f| self.matches_context(context, f)),
-			ContextFilter::Or(filters) => filters.iter().any(|f| self.matches_context(context, f)),
-		}
+	/// Store `entry`'s content hash after a partial write changed its content
+	fn write_content_hash(transaction: &Transaction<'_>, entry: &Entry) -> StorageResult<()> {
+		let content_hash = Self::content_hash_in(transaction, entry)?;
+		Self::release_expired_content_hash(transaction, entry, content_hash.as_deref())?;
+		transaction
+			.execute(
+				"UPDATE entries SET content_hash = ?1 WHERE id = ?2",
+				params![content_hash, entry.id.to_string()],
+			)
+			.map_err(|error| {
+				Self::unique_write_error(transaction, entry, content_hash.as_deref(), error)
+			})?;
+		Ok(())
 	}
 
-	fn matches_temporal(&self, entry: &Entry, filter: &TemporalFilter) -> bool {
-		match filter {
-			TemporalFilter::CreatedAfter(dt) => entry.created_at > *dt,
-			TemporalFilter::CreatedBefore(dt) => entry.created_at < *dt,
-			TemporalFilter::CreatedBetween(start, end) => {
-				entry.created_at > *start && entry.created_at < *end
-			}
-			TemporalFilter::UpdatedAfter(dt) => entry.updated_at > *dt,
-			TemporalFilter::UpdatedBefore(dt) => entry.updated_at < *dt,
+	fn dedup_policy_in(conn: &Connection) -> StorageResult<DedupPolicy> {
+		match conn.query_row(
+			"SELECT value FROM contextdb_metadata WHERE key = 'dedup_policy'",
+			[],
+			|row| row.get::<_, String>(0),
+		) {
+			Ok(policy) => Ok(serde_json::from_str(&policy)?),
+			Err(rusqlite::Error::QueryReturnedNoRows) => Ok(DedupPolicy::default()),
+			Err(error) => Err(StorageError::Database(error.to_string())),
 		}
 	}
 
-	fn load_relation_index(&self) -> StorageResult<RelationIndex> {
-		let mut stmt = self
-			.conn
-			.prepare("SELECT from_id, to_id FROM relations")
-			.map_err(|e| StorageError::Database(e.to_string()))?;
-
-		let mut adjacency: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
-		let mut related_ids: HashSet<Uuid> = HashSet::new();
+	/// Hash of `entry`'s content when the stored dedup policy is enabled
+	fn content_hash_in(conn: &Connection, entry: &Entry) -> StorageResult<Option<String>> {
+		let policy = Self::dedup_policy_in(conn)?;
+		Ok(policy.enabled.then(|| policy.content_hash(entry)))
+	}
 
-		let rows = stmt
-			.query_map([], |row| {
-				let from_id_str: String = row.get(0)?;
-				let to_id_str: String = row.get(1)?;
-				let from_id =
-					Uuid::parse_str(&from_id_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
-				let to_id =
-					Uuid::parse_str(&to_id_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
-				Ok((from_id, to_id))
-			})
-			.map_err(|e| StorageError::Database(e.to_string()))?;
+	/// Live, unexpired entry other than `entry` with its tenant, collection,
+	/// and content hash
+	fn live_duplicate(
+		conn: &Connection,
+		entry: &Entry,
+		content_hash: &str,
+	) -> StorageResult<Option<Uuid>> {
+		let id: String = match conn.query_row(
+			"SELECT id FROM entries
+			 WHERE tenant = ?1 AND collection = ?2 AND content_hash = ?3
+				AND deleted_at IS NULL AND id <> ?4
+				AND (expires_at IS NULL OR expires_at > ?5)",
+			params![
+				&entry.tenant,
+				&entry.collection,
+				content_hash,
+				entry.id.to_string(),
+				Utc::now().to_rfc3339()
+			],
+			|row| row.get(0),
+		) {
+			Ok(id) => id,
+			Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+			Err(error) => return Err(StorageError::Database(error.to_string())),
+		};
+		Uuid::parse_str(&id)
+			.map(Some)
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
 
-		for row in rows {
-			let (from_id, to_id) = row.map_err(|e| StorageError::Database(e.to_string()))?;
-			adjacency.entry(from_id).or_default().push(to_id);
-			related_ids.insert(from_id);
-		}
+	/// Live, unexpired entry of `tenant` keyed by `external_id`
+	fn entry_with_external_id(
+		conn: &Connection,
+		tenant: &str,
+		external_id: &str,
+	) -> StorageResult<Option<Uuid>> {
+		let id: String = match conn.query_row(
+			"SELECT id FROM entries
+			 WHERE tenant = ?1 AND external_id = ?2 AND deleted_at IS NULL
+				AND (expires_at IS NULL OR expires_at > ?3)",
+			params![tenant, external_id, Utc::now().to_rfc3339()],
+			|row| row.get(0),
+		) {
+			Ok(id) => id,
+			Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+			Err(error) => return Err(StorageError::Database(error.to_string())),
+		};
+		Uuid::parse_str(&id)
+			.map(Some)
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
 
-		Ok(RelationIndex {
-			adjacency,
-			related_ids,
-		})
+	/// Report a write of `entry` that hit a unique entry index as `Duplicate`
+	///
+	/// The existing entry holds either the same external ID or the same content hash.
+	fn unique_write_error(
+		conn: &Connection,
+		entry: &Entry,
+		content_hash: Option<&str>,
+		error: rusqlite::Error,
+	) -> StorageError {
+		let unique_violation = matches!(
+			&error,
+			rusqlite::Error::SqliteFailure(failure, _)
+				if failure.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE
+		);
+		if !unique_violation {
+			return StorageError::Database(error.to_string());
+		}
+		let keyed = entry.external_id.as_deref().and_then(|external_id| {
+			Self::entry_with_external_id(conn, &entry.tenant, external_id)
+				.ok()
+				.flatten()
+				.filter(|existing| *existing != entry.id)
+		});
+		let hashed = || {
+			content_hash.and_then(|content_hash| {
+				Self::live_duplicate(conn, entry, content_hash)
+					.ok()
+					.flatten()
+			})
+		};
+		match keyed.or_else(hashed) {
+			Some(existing) => StorageError::Duplicate {
+				id: entry.id,
+				existing,
+			},
+			None => StorageError::Database(error.to_string()),
+		}
 	}
 
-	fn direct_relations(&self, index: &RelationIndex, id: Uuid) -> HashSet<Uuid> {
-		index
-			.adjacency
-			.get(&id)
-			.map(|ids| ids.iter().copied().collect())
-			.unwrap_or_default()
+	/// Bump an entry's `updated_at` and version, guarded by `expected_version`
+	fn touch_entry_row(transaction: &Transaction<'_>, entry: &Entry) -> StorageResult<()> {
+		let expected_version = entry.version - 1;
+		let rows_affected = transaction
+			.execute(
+				"UPDATE entries SET updated_at = ?1, version = ?2 WHERE id = ?3 AND version = ?4",
+				params![
+					entry.updated_at.to_rfc3339(),
+					entry.version as i64,
+					entry.id.to_string(),
+					expected_version as i64,
+				],
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Self::check_version_write(transaction, entry.id, expected_version, rows_affected)
 	}
 
-	fn within_distance_relations(
-		&self,
-		index: &RelationIndex,
-		from: Uuid,
-		max_hops: usize,
-	) -> HashSet<Uuid> {
-		if max_hops == 0 {
-			return HashSet::new();
+	/// Turn a version-guarded write that matched no row into `NotFound` or `Conflict`
+	fn check_version_write(
+		transaction: &Transaction<'_>,
+		id: Uuid,
+		expected: u64,
+		rows_affected: usize,
+	) -> StorageResult<()> {
+		if rows_affected > 0 {
+			return Ok(());
+		}
+		let actual: Option<i64> = transaction
+			.query_row(
+				"SELECT version FROM entries WHERE id = ?1 AND deleted_at IS NULL",
+				params![id.to_string()],
+				|row| row.get(0),
+			)
+			.map(Some)
+			.or_else(|error| match error {
+				rusqlite::Error::QueryReturnedNoRows => Ok(None),
+				other => Err(other),
+			})
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		match actual {
+			None => Err(StorageError::NotFound(id)),
+			Some(actual) => Err(StorageError::Conflict {
+				id,
+				expected,
+				actual: actual as u64,
+			}),
 		}
+	}
 
-		let mut visited: HashSet<Uuid> = HashSet::new();
-		let mut results: HashSet<Uuid> = HashSet::new();
-		let mut queue: VecDeque<(Uuid, usize)> = VecDeque::new();
+	/// Replace an entry's outgoing relation edges
+	fn write_relations(transaction: &Transaction<'_>, entry: &Entry) -> StorageResult<()> {
+		let id = entry.id.to_string();
+		// Edges to trashed entries are hidden from `entry`, so keep them for a restore.
+		transaction
+			.execute(
+				"DELETE FROM relations WHERE from_id = ?1
+				 AND to_id NOT IN (SELECT id FROM entries WHERE deleted_at IS NOT NULL)",
+				params![id],
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		for relation_id in &entry.relations {
+			transaction
+				.execute(
+					"INSERT OR IGNORE INTO relations (from_id, to_id) VALUES (?1, ?2)",
+					params![id, relation_id.to_string()],
+				)
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+		}
+		Ok(())
+	}
 
-		visited.insert(from);
-		queue.push_back((from, 0));
+	fn validate_relation_targets(&self, entry: &Entry) -> StorageResult<()> {
+		Self::check_relation_tenants(&self.conn, std::slice::from_ref(entry))
+	}
 
-		while let Some((current, hops)) = queue.pop_front() {
-			if hops >= max_hops {
-				continue;
+	/// Add or remove directed edges, touching only relations, `updated_at`, and history
+	fn change_relations(
+		&mut self,
+		edges: &[(Uuid, Uuid)],
+		operation: RevisionOperation,
+	) -> StorageResult<()> {
+		if edges.is_empty() {
+			return Ok(());
+		}
+		let mut unique_edges = HashSet::with_capacity(edges.len());
+		for &(from, to) in edges {
+			if from == to {
+				return Err(StorageError::InvalidArgument(
+					"An entry cannot relate to itself".to_string(),
+				));
 			}
-
-			if let Some(neighbors) = index.adjacency.get(&current) {
-				for &neighbor in neighbors {
-					if visited.insert(neighbor) {
-						let next_hops = hops + 1;
-						results.insert(neighbor);
-						queue.push_back((neighbor, next_hops));
-					}
-				}
+			if !unique_edges.insert((from, to)) {
+				return Err(StorageError::InvalidArgument(format!(
+					"Duplicate relation in batch: {from} -> {to}"
+				)));
 			}
 		}
 
-		results
-	}
-
-	fn generate_explanation(
-		&self,
-		_entry: &Entry,
-		query: &Query,
-		similarity_score: Option<f32>,
-		lexical_score: Option<f32>,
-		combined_score: Option<f32>,
-	) -> String {
-		let mut parts = vec!["Plan: SQLite candidate filtering".to_string()];
-
-		if let Some(score) = similarity_score {
-			parts.push(format!("Semantic similarity: {:.2}%", score * 100.0));
+		// Look up only the entries the edges touch, under the write lock.
+		let scope = self.tenant.clone();
+		let transaction = self
+			.conn
+			.transaction_with_behavior(TransactionBehavior::Immediate)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let mut sources: Vec<Uuid> = edges.iter().map(|(from, _)| *from).collect();
+		sources.sort();
+		sources.dedup();
+		let mut snapshots: HashMap<Uuid, Entry> = HashMap::with_capacity(sources.len());
+		for source in &sources {
+			let snapshot = Self::read_entry(&transaction, *source)?;
+			if scope
+				.as_ref()
+				.is_some_and(|tenant| snapshot.tenant != *tenant)
+			{
+				return Err(StorageError::NotFound(*source));
+			}
+			snapshots.insert(*source, snapshot);
 		}
-		if let Some(score) = lexical_score {
-			parts.push(format!("Normalized BM25 relevance: {:.2}%", score * 100.0));
+		if operation == RevisionOperation::Relate {
+			for &(from, to) in edges {
+				let tenant = &snapshots.get(&from).expect("sources were loaded").tenant;
+				if Self::tenant_of(&transaction, to)?.as_ref() != Some(tenant) {
+					return Err(StorageError::NotFound(to));
+				}
+			}
 		}
-		if let Some(score) = combined_score {
-			parts.push(format!("Combined relevance: {:.2}%", score * 100.0));
+		let mut changed = HashSet::new();
+		for &(from, to) in edges {
+			let snapshot = snapshots.get_mut(&from).expect("sources were loaded");
+			let position = snapshot.relations.iter().position(|id| *id == to);
+			match (operation, position) {
+				(RevisionOperation::Relate, None) => snapshot.relations.push(to),
+				(RevisionOperation::Unrelate, Some(position)) => {
+					snapshot.relations.remove(position);
+				}
+				_ => continue,
+			}
+			changed.insert(from);
 		}
 
-		if query.expression.is_some() {
-			parts.push("Matched expression filter".to_string());
+		let now = Utc::now();
+		for &(from, to) in edges {
+			let sql = match operation {
+				RevisionOperation::Relate => {
+					"INSERT OR IGNORE INTO relations (from_id, to_id) VALUES (?1, ?2)"
+				}
+				_ => "DELETE FROM relations WHERE from_id = ?1 AND to_id = ?2",
+			};
+			transaction
+				.execute(sql, params![from.to_string(), to.to_string()])
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+		}
+		for source in sources.iter().filter(|id| changed.contains(*id)) {
+			let snapshot = snapshots.get_mut(source).expect("sources were loaded");
+			snapshot.updated_at = snapshot.updated_at.max(now);
+			snapshot.version += 1;
+			Self::touch_entry_row(&transaction, snapshot)?;
+			Self::record_revision(&transaction, snapshot, operation)?;
 		}
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
 
-		if query.context.is_some() {
-			parts.push("Matched context filter".to_string());
+	/// Update an entry, first requiring its stored version to equal `expected_version`
+	fn update_versioned(
+		&mut self,
+		entry: &Entry,
+		expected_version: Option<u64>,
+	) -> StorageResult<()> {
+		self.validate_entry(entry)?;
+		self.validate_relation_targets(entry)?;
+		let existing = self.read_scoped(entry.id)?;
+		Self::check_same_tenant(entry, &existing)?;
+		Self::check_expected_version(&existing, expected_version)?;
+		if entry.created_at != existing.created_at || entry.updated_at < existing.updated_at {
+			return Err(StorageError::Database(
+				"Updates must preserve created_at and advance updated_at monotonically".to_string(),
+			));
 		}
+		let entry = &Entry {
+			version: existing.version + 1,
+			..entry.clone()
+		};
+		let established = self.check_batch_dimensions(std::slice::from_ref(entry))?;
+		let transaction = self
+			.conn
+			.transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Self::update_entry_row(&transaction, entry, existing.version)?;
+		// Entry relations are directed outgoing edges.
+		Self::write_relations(&transaction, entry)?;
+		Self::record_revision(&transaction, entry, RevisionOperation::Update)?;
+		Self::set_vector_dimensions(&transaction, &established)?;
 
-		if query.temporal.is_some() {
-			parts.push("Matched temporal filter".to_string());
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn check_expected_version(
+		existing: &Entry,
+		expected_version: Option<u64>,
+	) -> StorageResult<()> {
+		match expected_version {
+			Some(expected) if expected != existing.version => Err(StorageError::Conflict {
+				id: existing.id,
+				expected,
+				actual: existing.version,
+			}),
+			_ => Ok(()),
 		}
+	}
 
-		if query.relations.is_some() {
-			parts.push("Matched relation filter".to_string());
+	/// Apply a patch, first requiring the stored version to equal `expected_version`
+	fn patch_versioned(
+		&mut self,
+		id: Uuid,
+		patch: &EntryPatch,
+		expected_version: Option<u64>,
+	) -> StorageResult<Entry> {
+		let existing = self.read_scoped(id)?;
+		Self::check_expected_version(&existing, expected_version)?;
+		let mut patched = patch.apply(&existing);
+		let expression_changed = patched.expression != existing.expression;
+		let meaning_changed = patched.meaning != existing.meaning;
+		let context_changed = patched.context != existing.context;
+		if !expression_changed && !meaning_changed && !context_changed {
+			return Ok(existing);
 		}
+		patched.updated_at = existing.updated_at.max(Utc::now());
+		patched.version = existing.version + 1;
+		self.validate_entry(&patched)?;
 
-		parts.join(", ")
+		// Write only the changed columns so the FTS trigger fires only for
+		// expression changes and unchanged vectors are not re-encoded.
+		let id_text = id.to_string();
+		let transaction = self
+			.conn
+			.transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Self::touch_entry_row(&transaction, &patched)?;
+		if expression_changed {
+			transaction
+				.execute(
+					"UPDATE entries SET expression = ?1 WHERE id = ?2",
+					params![patched.expression, id_text],
+				)
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+		}
+		if meaning_changed {
+			let meaning_bytes = vector_codec::serialize(&patched.meaning).map_err(|error| {
+				StorageError::Database(format!("Failed to serialize vector: {error}"))
+			})?;
+			transaction
+				.execute(
+					"UPDATE entries SET meaning = ?1 WHERE id = ?2",
+					params![meaning_bytes, id_text],
+				)
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+		}
+		if context_changed {
+			transaction
+				.execute(
+					"UPDATE entries SET context = ?1 WHERE id = ?2",
+					params![serde_json::to_string(&patched.context)?, id_text],
+				)
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+		}
+		if expression_changed || context_changed {
+			Self::write_content_hash(&transaction, &patched)?;
+		}
+		Self::record_revision(&transaction, &patched, RevisionOperation::Update)?;
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(patched)
 	}
 
-	fn get_entry_ids(&self) -> StorageResult<HashSet<Uuid>> {
-		let mut stmt = self
+	/// Delete an entry, first requiring its stored version to equal `expected_version`
+	fn delete_versioned(&mut self, id: Uuid, expected_version: Option<u64>) -> StorageResult<()> {
+		let snapshot = self.read_scoped(id)?;
+		Self::check_expected_version(&snapshot, expected_version)?;
+		if self.trash_policy()?.soft_delete {
+			return self.move_to_trash(std::slice::from_ref(&snapshot));
+		}
+		let id_str = id.to_string();
+		let transaction = self
 			.conn
-			.prepare("SELECT id FROM entries")
-			.map_err(|e| StorageError::Database(e.to_string()))?;
+			.transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
 
-		let rows = stmt
-			.query_map([], |row| {
-				let id_str: String = row.get(0)?;
-				Uuid::parse_str(&id_str).map_err(|_| rusqlite::Error::InvalidQuery)
-			})
+		// Delete relations first
+		transaction
+			.execute(
+				"DELETE FROM relations WHERE from_id = ?1 OR to_id = ?1",
+				params![id_str],
+			)
 			.map_err(|e| StorageError::Database(e.to_string()))?;
 
-		let mut ids = HashSet::new();
-		for row in rows {
-			let id = row.map_err(|e| StorageError::Database(e.to_string()))?;
-			ids.insert(id);
-		}
+		// Delete entry
+		let rows_affected = transaction
+			.execute(
+				"DELETE FROM entries WHERE id = ?1 AND version = ?2",
+				params![id_str, snapshot.version as i64],
+			)
+			.map_err(|e| StorageError::Database(e.to_string()))?;
+		Self::check_version_write(&transaction, id, snapshot.version, rows_affected)?;
+		Self::record_revision(&transaction, &snapshot, RevisionOperation::Delete)?;
 
-		Ok(ids)
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))
 	}
 
-	#[allow(clippy::manual_repeat_n)]
-	fn get_entries_by_ids(&self, ids: &HashSet<Uuid>) -> StorageResult<Vec<Entry>> {
-		if ids.is_empty() {
-			return Ok(Vec::new());
+	/// Make a revision snapshot current again and record it as a `Revert`
+	///
+	/// The snapshot is validated like a write: relation targets must exist and
+	/// the vector must match the current dimension and embedding migration.
+	fn restore_revision(&mut self, revision: EntryRevision) -> StorageResult<Entry> {
+		if Self::is_trashed(&self.conn, revision.entry_id)? {
+			return Err(StorageError::InvalidArgument(format!(
+				"Entry {} is in the trash; restore it before reverting it",
+				revision.entry_id
+			)));
 		}
-		let mut id_values: Vec<String> = ids.iter().map(Uuid::to_string).collect();
-		id_values.sort();
-		let mut entries = Vec::with_capacity(ids.len());
-		for id_chunk in id_values.chunks(900) {
-			let placeholders = std::iter::repeat("?")
-				.take(id_chunk.len())
-				.collect::<Vec<_>>()
-				.join(",");
-			let mut statement = self
-				.conn
-				.prepare(&format!(
-					"SELECT id, meaning, expression, context, created_at, updated_at
-					 FROM entries WHERE id IN ({placeholders}) ORDER BY id"
-				))
-				.map_err(|error| StorageError::Database(error.to_string()))?;
-			let rows = statement
-				.query_map(rusqlite::params_from_iter(id_chunk), |row| {
-					Ok((
-						row.get::<_, String>(0)?,
-						row.get::<_, Vec<u8>>(1)?,
-						row.get::<_, String>(2)?,
-						row.get::<_, String>(3)?,
-						row.get::<_, String>(4)?,
-						row.get::<_, String>(5)?,
-					))
-				})
-				.map_err(|error| StorageError::Database(error.to_string()))?;
-			for row in rows {
-				let (id, meaning, expression, context, created_at, updated_at) =
-					row.map_err(|error| StorageError::Database(error.to_string()))?;
-				let id = Uuid::parse_str(&id)
-					.map_err(|error| StorageError::Database(error.to_string()))?;
-				entries.push(Entry {
-					id,
-					meaning: vector_codec::deserialize(&meaning).map_err(|error| {
-						StorageError::Database(format!("Entry {id} has invalid vector: {error}"))
-					})?,
-					expression,
-					context: serde_json::from_str(&context)?,
-					created_at: DateTime::parse_from_rfc3339(&created_at)
-						.map_err(|error| StorageError::Database(error.to_string()))?
-						.with_timezone(&Utc),
-					updated_at: DateTime::parse_from_rfc3339(&updated_at)
-						.map_err(|error| StorageError::Database(error.to_string()))?
-						.with_timezone(&Utc),
-					relations: Vec::new(),
-				});
+		let migrated_at_key =
+			Self::metadata_key(&revision.snapshot.collection, "embeddings_migrated_at");
+		if let Some(migrated_at) = self.metadata_value(&migrated_at_key)? {
+			let migrated_at = DateTime::parse_from_rfc3339(&migrated_at)
+				.map_err(|error| StorageError::Database(error.to_string()))?
+				.with_timezone(&Utc);
+			if revision.recorded_at < migrated_at {
+				return Err(StorageError::InvalidArgument(format!(
+					"Revision {} predates the embedding migration at {}",
+					revision.revision_id,
+					migrated_at.to_rfc3339()
+				)));
+			}
+		}
+		let mut entry = revision.snapshot;
+		let existing = match self.read_scoped(entry.id) {
+			Ok(existing) => Some(existing),
+			Err(StorageError::NotFound(_)) => None,
+			Err(error) => return Err(error),
+		};
+		let now = Utc::now();
+		entry.updated_at = match &existing {
+			Some(current) => current.updated_at.max(now),
+			None => entry.updated_at.max(now),
+		};
+		// Versions keep increasing across delete and undelete so stale
+		// conditional writes cannot match a restored entry.
+		entry.version = match &existing {
+			Some(current) => current.version + 1,
+			None => {
+				self.load_revisions(Some(entry.id))?
+					.iter()
+					.map(|stored| stored.revision.snapshot.version)
+					.max()
+					.unwrap_or(0) + 1
 			}
+		};
+		self.validate_entry(&entry)?;
+		self.validate_relation_targets(&entry)?;
+		let established = self.check_batch_dimensions(std::slice::from_ref(&entry))?;
+
+		let transaction = self
+			.conn
+			.transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		if let Some(current) = &existing {
+			Self::update_entry_row(&transaction, &entry, current.version)?;
+		} else {
+			Self::insert_entry_row(&transaction, &entry)?;
+		}
+		Self::write_relations(&transaction, &entry)?;
+		Self::record_revision(&transaction, &entry, RevisionOperation::Revert)?;
+		Self::set_vector_dimensions(&transaction, &established)?;
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(entry)
+	}
+
+	/// Read one entry and its outgoing relations through `conn`
+	fn read_entry(conn: &Connection, id: Uuid) -> StorageResult<Entry> {
+		Self::read_entry_where(conn, id, "deleted_at IS NULL")
+	}
+
+	/// Read an entry in the trash
+	fn read_trashed_entry(conn: &Connection, id: Uuid) -> StorageResult<Entry> {
+		Self::read_entry_where(conn, id, "deleted_at IS NOT NULL")
+	}
+
+	fn read_entry_where(conn: &Connection, id: Uuid, condition: &str) -> StorageResult<Entry> {
+		let id_str = id.to_string();
+
+		let mut stmt = conn
+			.prepare(&format!(
+				"SELECT {ENTRY_COLUMNS} FROM entries WHERE id = ?1 AND {condition}"
+			))
+			.map_err(|e| StorageError::Database(e.to_string()))?;
+
+		let row = stmt
+			.query_row(params![id_str], Self::read_entry_row)
+			.map_err(|error| match error {
+				rusqlite::Error::QueryReturnedNoRows => StorageError::NotFound(id),
+				other => StorageError::Database(other.to_string()),
+			})?;
+		// Relations are filled below
+		let entry = Self::entry_from_row(row)?;
+
+		// Get relations, leaving out edges to entries in the trash
+		let mut rel_stmt = conn
+			.prepare(
+				"SELECT relation.to_id FROM relations AS relation
+				 JOIN entries AS target ON target.id = relation.to_id
+				 WHERE relation.from_id = ?1 AND target.deleted_at IS NULL
+				 ORDER BY relation.to_id",
+			)
+			.map_err(|e| StorageError::Database(e.to_string()))?;
+
+		let relation_rows = rel_stmt
+			.query_map(params![id_str], |row| {
+				let to_id_str: String = row.get(0)?;
+				Uuid::parse_str(&to_id_str).map_err(|_| rusqlite::Error::InvalidQuery)
+			})
+			.map_err(|e| StorageError::Database(e.to_string()))?;
+		let mut relations = Vec::new();
+		for row in relation_rows {
+			relations.push(row.map_err(|error| StorageError::Database(error.to_string()))?);
+		}
+
+		Ok(Entry { relations, ..entry })
+	}
+
+	/// Whether writing `entry` would leave the stored entry unchanged
+	fn same_entry_state(entry: &Entry, stored: &Entry) -> bool {
+		let mut relations = entry.relations.clone();
+		relations.sort();
+		entry.meaning == stored.meaning
+			&& entry.expression == stored.expression
+			&& entry.context == stored.context
+			&& entry.collection == stored.collection
+			&& entry.expires_at == stored.expires_at
+			&& entry.external_id == stored.external_id
+			&& entry.importance == stored.importance
+			&& entry.updated_at == stored.updated_at
+			&& relations == stored.relations
+	}
+
+	/// Read the [`ENTRY_COLUMNS`] of one `entries` row
+	fn read_entry_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<EntryRow> {
+		Ok(EntryRow {
+			id: row.get(0)?,
+			meaning: row.get(1)?,
+			expression: row.get(2)?,
+			context: row.get(3)?,
+			created_at: row.get(4)?,
+			updated_at: row.get(5)?,
+			version: row.get(6)?,
+			collection: row.get(7)?,
+			tenant: row.get(8)?,
+			expires_at: row.get(9)?,
+			external_id: row.get(10)?,
+			importance: row.get(11)?,
+		})
+	}
+
+	/// Decode an `entries` row into an entry without relations
+	fn entry_from_row(row: EntryRow) -> StorageResult<Entry> {
+		let id = Uuid::parse_str(&row.id)
+			.map_err(|error| StorageError::Database(format!("Invalid entry UUID: {error}")))?;
+		Ok(Entry {
+			id,
+			meaning: vector_codec::deserialize(&row.meaning).map_err(|error| {
+				StorageError::Database(format!("Entry {id} has invalid vector: {error}"))
+			})?,
+			expression: row.expression,
+			context: serde_json::from_str(&row.context)?,
+			created_at: DateTime::parse_from_rfc3339(&row.created_at)
+				.map_err(|error| StorageError::Database(error.to_string()))?
+				.with_timezone(&Utc),
+			updated_at: DateTime::parse_from_rfc3339(&row.updated_at)
+				.map_err(|error| StorageError::Database(error.to_string()))?
+				.with_timezone(&Utc),
+			relations: Vec::new(),
+			version: u64::try_from(row.version).map_err(|_| {
+				StorageError::Database(format!("Entry {id} has invalid version {}", row.version))
+			})?,
+			collection: row.collection,
+			tenant: row.tenant,
+			expires_at: row
+				.expires_at
+				.map(|expires_at| {
+					DateTime::parse_from_rfc3339(&expires_at)
+						.map(|expires_at| expires_at.with_timezone(&Utc))
+						.map_err(|error| StorageError::Database(error.to_string()))
+				})
+				.transpose()?,
+			external_id: row.external_id,
+			importance: row.importance.map(|importance| importance as f32),
+		})
+	}
+
+	/// Get all entries from the database
+	fn get_all_entries(&self) -> StorageResult<Vec<Entry>> {
+		let mut stmt = self
+			.conn
+			.prepare(&format!("SELECT {ENTRY_COLUMNS} FROM entries ORDER BY id"))
+			.map_err(|e| StorageError::Database(e.to_string()))?;
+
+		let rows = stmt
+			.query_map([], Self::read_entry_row)
+			.map_err(|e| StorageError::Database(e.to_string()))?;
+		let mut entries = Vec::new();
+		for row in rows {
+			entries.push(Self::entry_from_row(
+				row.map_err(|error| StorageError::Database(error.to_string()))?,
+			)?);
 		}
 
+		let mut relation_statement = self
+			.conn
+			.prepare("SELECT from_id, to_id FROM relations ORDER BY from_id, to_id")
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let relation_rows = relation_statement
+			.query_map([], |row| {
+				Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+			})
+			.map_err(|error| StorageError::Database(error.to_string()))?;
 		let mut relations: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
-		for id_chunk in id_values.chunks(900) {
-			let placeholders = std::iter::repeat("?")
-				.take(id_chunk.len())
-				.collect::<Vec<_>>()
-				.join(",");
-			let mut relation_statement = self
-				.conn
-				.prepare(&format!(
-					"SELECT from_id, to_id FROM relations
-					 WHERE from_id IN ({placeholders}) ORDER BY from_id, to_id"
-				))
+		for row in relation_rows {
+			let (from_id, to_id) =
+				row.map_err(|error| StorageError::Database(error.to_string()))?;
+			let from_id = Uuid::parse_str(&from_id)
 				.map_err(|error| StorageError::Database(error.to_string()))?;
-			let relation_rows = relation_statement
-				.query_map(rusqlite::params_from_iter(id_chunk), |row| {
-					Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
-				})
+			let to_id = Uuid::parse_str(&to_id)
 				.map_err(|error| StorageError::Database(error.to_string()))?;
-			for row in relation_rows {
-				let (from_id, to_id) =
-					row.map_err(|error| StorageError::Database(error.to_string()))?;
-				let from_id = Uuid::parse_str(&from_id)
-					.map_err(|error| StorageError::Database(error.to_string()))?;
-				let to_id = Uuid::parse_str(&to_id)
-					.map_err(|error| StorageError::Database(error.to_string()))?;
-				relations.entry(from_id).or_default().push(to_id);
-			}
+			relations.entry(from_id).or_default().push(to_id);
 		}
 		for entry in &mut entries {
 			entry.relations = relations.remove(&entry.id).unwrap_or_default();
@@ -963,218 +2292,221 @@ impl SqliteStorage {
 		Ok(entries)
 	}
 
-	fn query_expression_ids(&self, filter: &ExpressionFilter) -> StorageResult<HashSet<Uuid>> {
+	fn matches_expression(
+		&self,
+		expression: &str,
+		filter: &ExpressionFilter,
+	) -> StorageResult<bool> {
 		match filter {
-			ExpressionFilter::Equals(value) => self.query_ids_with_params(
-				"SELECT id FROM entries WHERE expression = ?1",
-				rusqlite::params![value],
-			),
-			ExpressionFilter::Contains(value) => {
-				let lowered = value.to_lowercase();
-				self.query_ids_with_params(
-					"SELECT id FROM entries WHERE INSTR(LOWER(expression), ?1) > 0",
-					rusqlite::params![lowered],
-				)
-			}
-			ExpressionFilter::StartsWith(value) => {
-				let prefix_len = value.chars().count() as i64;
-				self.query_ids_with_params(
-					"SELECT id FROM entries WHERE SUBSTR(expression, 1, ?2) = ?1",
-					rusqlite::params![value, prefix_len],
-				)
+			ExpressionFilter::Equals(s) => Ok(expression == s),
+			ExpressionFilter::Contains(s) => {
+				Ok(expression.to_lowercase().contains(&s.to_lowercase()))
 			}
-			ExpressionFilter::Matches(value) => {
-				let _ = Regex::new(value)
+			ExpressionFilter::StartsWith(s) => Ok(expression.starts_with(s)),
+			ExpressionFilter::Matches(pattern) => {
+				let regex = Regex::new(pattern)
 					.map_err(|e| StorageError::InvalidArgument(format!("Invalid regex: {e}")))?;
-				self.get_entry_ids()
-			}
-			ExpressionFilter::FullText(value) => {
-				Ok(self.full_text_scores(value)?.into_keys().collect())
+				Ok(regex.is_match(expression))
 			}
+			ExpressionFilter::FullText(_) => Ok(true),
 		}
 	}
 
-	fn full_text_scores(&self, query: &str) -> StorageResult<HashMap<Uuid, f32>> {
-		let mut statement = self
-			.conn
-			.prepare(
-				"SELECT id, bm25(entries_fts) AS score
-				 FROM entries_fts WHERE entries_fts MATCH ?1 ORDER BY score, id",
-			)
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		let rows = statement
-			.query_map(params![query], |row| {
-				Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
-			})
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		let mut raw_scores = Vec::new();
-		for row in rows {
-			let (id, score) = row.map_err(|error| StorageError::Database(error.to_string()))?;
-			let id =
-				Uuid::parse_str(&id).map_err(|error| StorageError::Database(error.to_string()))?;
-			raw_scores.push((id, -score as f32));
-		}
-		let min = raw_scores
-			.iter()
-			.map(|(_, score)| *score)
-			.reduce(f32::min)
-			.unwrap_or(0.0);
-		let max = raw_scores
-			.iter()
-			.map(|(_, score)| *score)
-			.reduce(f32::max)
-			.unwrap_or(0.0);
-		Ok(raw_scores
-			.into_iter()
-			.map(|(id, score)| {
-				let normalized = if max > min {
-					(score - min) / (max - min)
+	fn matches_context(&self, context: &serde_json::Value, filter: &ContextFilter) -> bool {
+		match filter {
+			ContextFilter::PathExists(path) => {
+				// Simple path checking - in production use jsonpath
+				context.pointer(path).is_some()
+			}
+			ContextFilter::PathEquals(path, value) => context.pointer(path) == Some(value),
+			ContextFilter::PathContains(path, value) => {
+				if let Some(arr) = context.pointer(path).and_then(|v| v.as_array()) {
+					arr.contains(value)
 				} else {
-					1.0
-				};
-				(id, normalized)
-			})
-			.collect())
+					false
+				}
+			}
+			ContextFilter::And(filters) => filters.iter().all(|f| self.matches_context(context, f)),
+			ContextFilter::Or(filters) => filters.iter().any(|f| self.matches_context(context, f)),
+		}
 	}
 
-	fn query_temporal_ids(&self, filter: &TemporalFilter) -> StorageResult<HashSet<Uuid>> {
+	fn matches_temporal(&self, entry: &Entry, filter: &TemporalFilter) -> bool {
 		match filter {
-			TemporalFilter::CreatedAfter(dt) => self.query_ids_with_params(
-				"SELECT id FROM entries WHERE created_at > ?1",
-				rusqlite::params![dt.to_rfc3339()],
-			),
-			TemporalFilter::CreatedBefore(dt) => self.query_ids_with_params(
-				"SELECT id FROM entries WHERE created_at < ?1",
-				rusqlite::params![dt.to_rfc3339()],
-			),
-			TemporalFilter::CreatedBetween(start, end) => self.query_ids_with_params(
-				"SELECT id FROM entries WHERE created_at > ?1 AND created_at < ?2",
-				rusqlite::params![start.to_rfc3339(), end.to_rfc3339()],
-			),
-			TemporalFilter::UpdatedAfter(dt) => self.query_ids_with_params(
-				"SELECT id FROM entries WHERE updated_at > ?1",
-				rusqlite::params![dt.to_rfc3339()],
-			),
-			TemporalFilter::UpdatedBefore(dt) => self.query_ids_with_params(
-				"SELECT id FROM entries WHERE updated_at < ?1",
-				rusqlite::params![dt.to_rfc3339()],
-			),
+			TemporalFilter::CreatedAfter(dt) => entry.created_at > *dt,
+			TemporalFilter::CreatedBefore(dt) => entry.created_at < *dt,
+			TemporalFilter::CreatedBetween(start, end) => {
+				entry.created_at > *start && entry.created_at < *end
+			}
+			TemporalFilter::UpdatedAfter(dt) => entry.updated_at > *dt,
+			TemporalFilter::UpdatedBefore(dt) => entry.updated_at < *dt,
 		}
 	}
 
-	fn query_context_ids(&self, filter: &ContextFilter) -> StorageResult<HashSet<Uuid>> {
-		match filter {
-			ContextFilter::PathExists(path) => {
-				let path = Self::sql_string_literal(&Self::json_pointer_to_sqlite_path(path)?);
-				self.query_ids_with_params(
-					&format!("SELECT id FROM entries WHERE json_type(context, {path}) IS NOT NULL"),
-					[],
-				)
-			}
-			ContextFilter::PathEquals(path, value) => {
-				let path = Self::sql_string_literal(&Self::json_pointer_to_sqlite_path(path)?);
-				self.query_ids_with_params(
-					&format!(
-						"SELECT id FROM entries
-						 WHERE json_extract(context, {path}) = json_extract(?1, '$')"
-					),
-					params![serde_json::to_string(value)?],
-				)
-			}
-			ContextFilter::PathContains(path, value) => self.query_ids_with_params(
-				"SELECT entry.id FROM entries AS entry
-				 WHERE EXISTS (
-					SELECT 1 FROM json_each(entry.context, ?1)
-					WHERE json_each.value = json_extract(?2, '$')
-				 )",
-				params![
-					Self::json_pointer_to_sqlite_path(path)?,
-					serde_json::to_string(value)?
-				],
+	fn load_relation_index(&self) -> StorageResult<RelationIndex> {
+		// Only follow edges between current entries, and for scoped handles
+		// only edges whose both ends belong to their tenant.
+		let tenant = match &self.tenant {
+			Some(tenant) => format!(
+				" AND f.tenant = {tenant} AND t.tenant = {tenant}",
+				tenant = Self::sql_string_literal(tenant)
 			),
-			ContextFilter::And(filters) => {
-				let mut ids = self.get_entry_ids()?;
-				for filter in filters {
-					let matching = self.query_context_ids(filter)?;
-					ids = ids.intersection(&matching).copied().collect();
-				}
-				Ok(ids)
+			None => String::new(),
+		};
+		let sql = format!(
+			"SELECT r.from_id, r.to_id FROM relations r
+			 JOIN entries f ON f.id = r.from_id
+			 JOIN entries t ON t.id = r.to_id
+			 WHERE f.deleted_at IS NULL AND t.deleted_at IS NULL{tenant}"
+		);
+		let mut stmt = self
+			.conn
+			.prepare(&sql)
+			.map_err(|e| StorageError::Database(e.to_string()))?;
+
+		let mut adjacency: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+		let mut related_ids: HashSet<Uuid> = HashSet::new();
+
+		let rows = stmt
+			.query_map([], |row| {
+				let from_id_str: String = row.get(0)?;
+				let to_id_str: String = row.get(1)?;
+				let from_id =
+					Uuid::parse_str(&from_id_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
+				let to_id =
+					Uuid::parse_str(&to_id_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
+				Ok((from_id, to_id))
+			})
+			.map_err(|e| StorageError::Database(e.to_string()))?;
+
+		for row in rows {
+			let (from_id, to_id) = row.map_err(|e| StorageError::Database(e.to_string()))?;
+			adjacency.entry(from_id).or_default().push(to_id);
+			related_ids.insert(from_id);
+		}
+
+		Ok(RelationIndex {
+			adjacency,
+			related_ids,
+		})
+	}
+
+	fn direct_relations(&self, index: &RelationIndex, id: Uuid) -> HashSet<Uuid> {
+		index
+			.adjacency
+			.get(&id)
+			.map(|ids| ids.iter().copied().collect())
+			.unwrap_or_default()
+	}
+
+	fn within_distance_relations(
+		&self,
+		index: &RelationIndex,
+		from: Uuid,
+		max_hops: usize,
+	) -> HashSet<Uuid> {
+		if max_hops == 0 {
+			return HashSet::new();
+		}
+
+		let mut visited: HashSet<Uuid> = HashSet::new();
+		let mut results: HashSet<Uuid> = HashSet::new();
+		let mut queue: VecDeque<(Uuid, usize)> = VecDeque::new();
+
+		visited.insert(from);
+		queue.push_back((from, 0));
+
+		while let Some((current, hops)) = queue.pop_front() {
+			if hops >= max_hops {
+				continue;
 			}
-			ContextFilter::Or(filters) => {
-				let mut ids = HashSet::new();
-				for filter in filters {
-					ids.extend(self.query_context_ids(filter)?);
+
+			if let Some(neighbors) = index.adjacency.get(&current) {
+				for &neighbor in neighbors {
+					if visited.insert(neighbor) {
+						let next_hops = hops + 1;
+						results.insert(neighbor);
+						queue.push_back((neighbor, next_hops));
+					}
 				}
-				Ok(ids)
 			}
 		}
+
+		results
 	}
 
-	fn json_pointer_to_sqlite_path(pointer: &str) -> StorageResult<String> {
-		if pointer.is_empty() {
-			return Ok("$".to_string());
+	fn generate_explanation(
+		&self,
+		entry: &Entry,
+		query: &Query,
+		similarity_score: Option<f32>,
+		lexical_score: Option<f32>,
+		combined_score: Option<f32>,
+		recency: Option<f32>,
+	) -> String {
+		let mut parts = vec!["Plan: SQLite candidate filtering".to_string()];
+		if let Some(as_of) = query.as_of {
+			parts.push(format!("Evaluated as of {}", as_of.to_rfc3339()));
 		}
-		if !pointer.starts_with('/') {
-			return Err(StorageError::InvalidArgument(format!(
-				"Invalid JSON Pointer: {pointer}"
-			)));
+
+		if let Some(score) = similarity_score {
+			parts.push(format!("Semantic similarity: {:.2}%", score * 100.0));
 		}
-		let mut path = "$".to_string();
-		for raw_segment in pointer[1..].split('/') {
-			let segment = raw_segment.replace("~1", "/").replace("~0", "~");
-			if let Ok(index) = segment.parse::<usize>() {
-				path.push_str(&format!("[{index}]"));
-			} else {
-				path.push('.');
-				path.push_str(&serde_json::to_string(&segment)?);
+		if let Some(score) = lexical_score {
+			parts.push(format!("Normalized BM25 relevance: {:.2}%", score * 100.0));
+		}
+		if let Some((decay, recency)) = query.decay.zip(recency) {
+			let anchor = match decay.anchor {
+				DecayAnchor::CreatedAt => "created_at",
+				DecayAnchor::UpdatedAt => "updated_at",
+				DecayAnchor::LastAccessedAt => "last access",
+			};
+			parts.push(format!("Recency since {anchor}: {:.2}%", recency * 100.0));
+			parts.push(format!(
+				"Importance: {:.2}%",
+				decay.importance_of(entry) * 100.0
+			));
+			if let Some(score) = combined_score {
+				parts.push(format!("Decayed score: {:.2}%", score * 100.0));
 			}
+		} else if let Some(score) = combined_score {
+			parts.push(format!("Combined relevance: {:.2}%", score * 100.0));
 		}
-		Ok(path)
-	}
 
-	fn sql_string_literal(value: &str) -> String {
-		format!("'{}'", value.replace('\'', "''"))
-	}
+		if query.expression.is_some() {
+			parts.push("Matched expression filter".to_string());
+		}
 
-	fn query_relation_ids(&self, filter: &RelationFilter) -> StorageResult<HashSet<Uuid>> {
-		match filter {
-			RelationFilter::DirectlyRelatedTo(id) => {
-				let id_str = id.to_string();
-				self.query_ids_with_params(
-					"SELECT to_id AS id FROM relations WHERE from_id = ?1",
-					rusqlite::params![id_str],
-				)
-			}
-			RelationFilter::WithinDistance { from, max_hops } => {
-				let index = self.load_relation_index()?;
-				Ok(self.within_distance_relations(&index, *from, *max_hops))
-			}
-			RelationFilter::HasRelations => self.query_ids_with_params(
-				"SELECT DISTINCT from_id AS id FROM relations",
-				rusqlite::params![],
-			),
-			RelationFilter::NoRelations => {
-				let all_ids = self.get_entry_ids()?;
-				let related_ids = self.query_relation_ids(&RelationFilter::HasRelations)?;
-				Ok(all_ids
-					.difference(&related_ids)
-					.copied()
-					.collect::<HashSet<_>>())
-			}
+		if query.context.is_some() {
+			parts.push("Matched context filter".to_string());
+		}
+
+		if query.temporal.is_some() {
+			parts.push("Matched temporal filter".to_string());
+		}
+
+		if query.relations.is_some() {
+			parts.push("Matched relation filter".to_string());
 		}
+
+		parts.join(", ")
 	}
 
-	fn query_ids_with_params<P>(&self, sql: &str, params: P) -> StorageResult<HashSet<Uuid>>
-	where
-		P: rusqlite::Params,
-	{
+	fn get_entry_ids(&self) -> StorageResult<HashSet<Uuid>> {
+		let sql = match &self.tenant {
+			Some(tenant) => format!(
+				"SELECT id FROM entries WHERE deleted_at IS NULL AND tenant = {}",
+				Self::sql_string_literal(tenant)
+			),
+			None => "SELECT id FROM entries WHERE deleted_at IS NULL".to_string(),
+		};
 		let mut stmt = self
 			.conn
-			.prepare(sql)
+			.prepare(&sql)
 			.map_err(|e| StorageError::Database(e.to_string()))?;
 
 		let rows = stmt
-			.query_map(params, |row| {
+			.query_map([], |row| {
 				let id_str: String = row.get(0)?;
 				Uuid::parse_str(&id_str).map_err(|_| rusqlite::Error::InvalidQuery)
 			})
@@ -1189,310 +2521,117 @@ impl SqliteStorage {
 		Ok(ids)
 	}
 
-	fn intersect_candidate_ids(
-		candidate_ids: &mut Option<HashSet<Uuid>>,
-		ids: HashSet<Uuid>,
-		total_entries: usize,
-	) -> (usize, usize) {
-		let before = candidate_ids.as_ref().map_or(total_entries, HashSet::len);
-		let narrowed = match candidate_ids.take() {
-			Some(existing) => existing.intersection(&ids).copied().collect(),
-			None => ids,
-		};
-		let after = narrowed.len();
-		*candidate_ids = Some(narrowed);
-		(before, after)
-	}
-}
-
-impl StorageBackend for SqliteStorage {
-	fn insert(&mut self, entry: &Entry) -> StorageResult<()> {
-		self.validate_entry(entry)?;
-		self.validate_relation_targets(entry)?;
-		let establishes_dimension = self.stored_vector_dimension()?.is_none();
-		let id = entry.id.to_string();
-		let meaning_bytes = vector_codec::serialize(&entry.meaning)
-			.map_err(|e| StorageError::Database(format!("Failed to serialize vector: {}", e)))?;
-		let context_json = serde_json::to_string(&entry.context)?;
-
-		let transaction = self
-			.conn
-			.transaction()
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-
-		transaction
-			.execute(
-				"INSERT INTO entries (id, meaning, expression, context, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-				params![
-					id,
-					meaning_bytes,
-					&entry.expression,
-					context_json,
-					entry.created_at.to_rfc3339(),
-					entry.updated_at.to_rfc3339(),
-				],
-			)
-			.map_err(|e| StorageError::Database(e.to_string()))?;
-
-		// Insert relations
-		for relation_id in &entry.relations {
-			transaction
-				.execute(
-					"INSERT OR IGNORE INTO relations (from_id, to_id) VALUES (?1, ?2)",
-					params![id, relation_id.to_string()],
-				)
-				.map_err(|e| StorageError::Database(e.to_string()))?;
+	#[allow(clippy::manual_repeat_n)]
+	fn get_entries_by_ids(&self, ids: &HashSet<Uuid>) -> StorageResult<Vec<Entry>> {
+		if ids.is_empty() {
+			return Ok(Vec::new());
 		}
-		Self::record_revision(&transaction, entry, RevisionOperation::Insert)?;
-		if establishes_dimension {
-			Self::set_vector_dimension(&transaction, entry.meaning.len())?;
+		let mut id_values: Vec<String> = ids.iter().map(Uuid::to_string).collect();
+		id_values.sort();
+		let mut entries = Vec::with_capacity(ids.len());
+		for id_chunk in id_values.chunks(900) {
+			let placeholders = std::iter::repeat("?")
+				.take(id_chunk.len())
+				.collect::<Vec<_>>()
+				.join(",");
+			let mut statement = self
+				.conn
+				.prepare(&format!(
+					"SELECT {ENTRY_COLUMNS} FROM entries
+					 WHERE id IN ({placeholders}) AND deleted_at IS NULL ORDER BY id"
+				))
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+			let rows = statement
+				.query_map(rusqlite::params_from_iter(id_chunk), Self::read_entry_row)
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+			for row in rows {
+				entries.push(Self::entry_from_row(
+					row.map_err(|error| StorageError::Database(error.to_string()))?,
+				)?);
+			}
 		}
 
-		transaction
-			.commit()
-			.map_err(|error| StorageError::Database(error.to_string()))
-	}
-
-	fn insert_batch(&mut self, entries: &[Entry]) -> StorageResult<()> {
-		if entries.is_empty() {
-			return Ok(());
-		}
-
-		let stored_dimension = self.stored_vector_dimension()?;
-		let expected_dimension = stored_dimension.unwrap_or(entries[0].meaning.len());
-		let mut batch_ids = HashSet::with_capacity(entries.len());
-		for entry in entries {
-			self.validate_entry(entry)?;
-			if entry.meaning.len() != expected_dimension {
-				return Err(StorageError::InvalidDimensions);
-			}
-			if !batch_ids.insert(entry.id) {
-				return Err(StorageError::Database(format!(
-					"Duplicate entry ID in batch: {}",
-					entry.id
-				)));
-			}
-		}
-
-		let existing_ids = self.get_entry_ids()?;
-		for entry in entries {
-			for relation_id in &entry.relations {
-				if !existing_ids.contains(relation_id) && !batch_ids.contains(relation_id) {
-					return Err(StorageError::NotFound(*relation_id));
-				}
-			}
-		}
-
-		let transaction = self
-			.conn
-			.transaction()
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		for entry in entries {
-			let meaning_bytes = vector_codec::serialize(&entry.meaning).map_err(|error| {
-				StorageError::Database(format!("Failed to serialize vector: {error}"))
-			})?;
-			let context_json = serde_json::to_string(&entry.context)?;
-			transaction
-				.execute(
-					"INSERT INTO entries (id, meaning, expression, context, created_at, updated_at)
-					 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-					params![
-						entry.id.to_string(),
-						meaning_bytes,
-						&entry.expression,
-						context_json,
-						entry.created_at.to_rfc3339(),
-						entry.updated_at.to_rfc3339(),
-					],
-				)
+		let mut relations: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+		for id_chunk in id_values.chunks(900) {
+			let placeholders = std::iter::repeat("?")
+				.take(id_chunk.len())
+				.collect::<Vec<_>>()
+				.join(",");
+			let mut relation_statement = self
+				.conn
+				.prepare(&format!(
+					"SELECT relation.from_id, relation.to_id FROM relations AS relation
+					 JOIN entries AS target ON target.id = relation.to_id
+					 WHERE relation.from_id IN ({placeholders}) AND target.deleted_at IS NULL
+					 ORDER BY relation.from_id, relation.to_id"
+				))
 				.map_err(|error| StorageError::Database(error.to_string()))?;
-		}
-		for entry in entries {
-			for relation_id in &entry.relations {
-				transaction
-					.execute(
-						"INSERT INTO relations (from_id, to_id) VALUES (?1, ?2)",
-						params![entry.id.to_string(), relation_id.to_string()],
-					)
+			let relation_rows = relation_statement
+				.query_map(rusqlite::params_from_iter(id_chunk), |row| {
+					Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+				})
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+			for row in relation_rows {
+				let (from_id, to_id) =
+					row.map_err(|error| StorageError::Database(error.to_string()))?;
+				let from_id = Uuid::parse_str(&from_id)
+					.map_err(|error| StorageError::Database(error.to_string()))?;
+				let to_id = Uuid::parse_str(&to_id)
 					.map_err(|error| StorageError::Database(error.to_string()))?;
+				relations.entry(from_id).or_default().push(to_id);
 			}
-			Self::record_revision(&transaction, entry, RevisionOperation::Insert)?;
-		}
-		if stored_dimension.is_none() {
-			Self::set_vector_dimension(&transaction, expected_dimension)?;
 		}
-		transaction
-			.commit()
-			.map_err(|error| StorageError::Database(error.to_string()))
-	}
-
-	fn get(&self, id: Uuid) -> StorageResult<Entry> {
-		let id_str = id.to_string();
-
-		let mut stmt = self
-			.conn
-			.prepare(
-				"SELECT id, meaning, expression, context, created_at, updated_at
-             FROM entries WHERE id = ?1",
-			)
-			.map_err(|e| StorageError::Database(e.to_string()))?;
-
-		let entry = stmt
-			.query_row(params![id_str], |row| {
-				let meaning_bytes: Vec<u8> = row.get(1)?;
-				let meaning: Vec<f32> = vector_codec::deserialize(&meaning_bytes)
-					.map_err(|_| rusqlite::Error::InvalidQuery)?;
-
-				let context_json: String = row.get(3)?;
-				let context: serde_json::Value = serde_json::from_str(&context_json)
-					.map_err(|_| rusqlite::Error::InvalidQuery)?;
-
-				let created_at_str: String = row.get(4)?;
-				let created_at = DateTime::parse_from_rfc3339(&created_at_str)
-					.map_err(|_| rusqlite::Error::InvalidQuery)?
-					.with_timezone(&Utc);
-
-				let updated_at_str: String = row.get(5)?;
-				let updated_at = DateTime::parse_from_rfc3339(&updated_at_str)
-					.map_err(|_| rusqlite::Error::InvalidQuery)?
-					.with_timezone(&Utc);
-
-				Ok(Entry {
-					id,
-					meaning,
-					expression: row.get(2)?,
-					context,
-					created_at,
-					updated_at,
-					relations: Vec::new(), // Will be filled below
-				})
-			})
-			.map_err(|error| match error {
-				rusqlite::Error::QueryReturnedNoRows => StorageError::NotFound(id),
-				other => StorageError::Database(other.to_string()),
-			})?;
-
-		// Get relations
-		let mut rel_stmt = self
-			.conn
-			.prepare("SELECT to_id FROM relations WHERE from_id = ?1 ORDER BY to_id")
-			.map_err(|e| StorageError::Database(e.to_string()))?;
-
-		let relation_rows = rel_stmt
-			.query_map(params![id_str], |row| {
-				let to_id_str: String = row.get(0)?;
-				Uuid::parse_str(&to_id_str).map_err(|_| rusqlite::Error::InvalidQuery)
-			})
-			.map_err(|e| StorageError::Database(e.to_string()))?;
-		let mut relations = Vec::new();
-		for row in relation_rows {
-			relations.push(row.map_err(|error| StorageError::Database(error.to_string()))?);
+		for entry in &mut entries {
+			entry.relations = relations.remove(&entry.id).unwrap_or_default();
 		}
-
-		Ok(Entry { relations, ..entry })
-	}
-
-	fn query(&self, query: &Query) -> StorageResult<Vec<QueryResult>> {
-		self.execute(query).map(|execution| execution.results)
+		Ok(entries)
 	}
 
-	fn execute(&self, query: &Query) -> StorageResult<QueryExecution> {
-		self.validate_query(query)?;
-		let lexical_scores = match &query.expression {
-			Some(ExpressionFilter::FullText(value)) => self.full_text_scores(value)?,
-			_ => HashMap::new(),
-		};
-		let total_entries = self.count()?;
-		let mut candidate_ids: Option<HashSet<Uuid>> = None;
-		let mut steps = Vec::new();
-		let mut candidate_filters = Vec::new();
-
-		if let Some(ref expr_filter) = query.expression {
-			let ids = self.query_expression_ids(expr_filter)?;
-			candidate_filters.push("expression".to_string());
-			if !matches!(expr_filter, ExpressionFilter::Matches(_)) {
-				let (before, after) =
-					Self::intersect_candidate_ids(&mut candidate_ids, ids, total_entries);
-				let (strategy, filter) = match expr_filter {
-					ExpressionFilter::Equals(_) => (
-						QueryPlanStrategy::SqlPredicate,
-						QueryFilterIdentity::ExpressionEquals,
-					),
-					ExpressionFilter::Contains(_) => (
-						QueryPlanStrategy::SqlPredicate,
-						QueryFilterIdentity::ExpressionContains,
-					),
-					ExpressionFilter::StartsWith(_) => (
-						QueryPlanStrategy::SqlPredicate,
-						QueryFilterIdentity::ExpressionStartsWith,
-					),
-					ExpressionFilter::FullText(_) => (
-						QueryPlanStrategy::Fts5,
-						QueryFilterIdentity::ExpressionFullText,
-					),
-					ExpressionFilter::Matches(_) => unreachable!(),
-				};
-				steps.push(QueryPlanStep {
-					strategy,
-					filter: Some(filter),
-					candidates_before: before,
-					candidates_after: after,
-				});
+	fn query_expression_ids(&self, filter: &ExpressionFilter) -> StorageResult<HashSet<Uuid>> {
+		match filter {
+			ExpressionFilter::Equals(value) => self.query_ids_with_params(
+				"SELECT id FROM entries WHERE expression = ?1",
+				rusqlite::params![value],
+			),
+			ExpressionFilter::Contains(value) => {
+				let lowered = value.to_lowercase();
+				self.query_ids_with_params(
+					"SELECT id FROM entries WHERE INSTR(LOWER(expression), ?1) > 0",
+					rusqlite::params![lowered],
+				)
+			}
+			ExpressionFilter::StartsWith(value) => {
+				let prefix_len = value.chars().count() as i64;
+				self.query_ids_with_params(
+					"SELECT id FROM entries WHERE SUBSTR(expression, 1, ?2) = ?1",
+					rusqlite::params![value, prefix_len],
+				)
+			}
+			ExpressionFilter::Matches(value) => {
+				let _ = Regex::new(value)
+					.map_err(|e| StorageError::InvalidArgument(format!("Invalid regex: {e}")))?;
+				self.get_entry_ids()
+			}
+			ExpressionFilter::FullText(value) => {
+				Ok(self.full_text_scores(value, None)?.into_keys().collect())
 			}
 		}
+	}
 
-		if let Some(ref context_filter) = query.context {
-			let ids = self.query_context_ids(context_filter)?;
-			candidate_filters.push("context".to_string());
-			let (before, after) =
-				Self::intersect_candidate_ids(&mut candidate_ids, ids, total_entries);
-			steps.push(QueryPlanStep {
-				strategy: QueryPlanStrategy::JsonPredicate,
-				filter: Some(QueryFilterIdentity::Context),
-				candidates_before: before,
-				candidates_after: after,
-			});
-		}
-
-		if let Some(ref temporal_filter) = query.temporal {
-			let ids = self.query_temporal_ids(temporal_filter)?;
-			candidate_filters.push("temporal".to_string());
-			let (before, after) =
-				Self::intersect_candidate_ids(&mut candidate_ids, ids, total_entries);
-			steps.push(QueryPlanStep {
-				strategy: QueryPlanStrategy::SqlPredicate,
-				filter: Some(QueryFilterIdentity::Temporal),
-				candidates_before: before,
-				candidates_after: after,
-			});
-		}
-
-		if let Some(ref relation_filter) = query.relations {
-			let ids = self.query_relation_ids(relation_filter)?;
-			candidate_filters.push("relations".to_string());
-			let (before, after) =
-				Self::intersect_candidate_ids(&mut candidate_ids, ids, total_entries);
-			steps.push(QueryPlanStep {
-				strategy: QueryPlanStrategy::GraphTraversal,
-				filter: Some(QueryFilterIdentity::Relations),
-				candidates_before: before,
-				candidates_after: after,
-			});
-		}
-
-		// Start with filtered entries if possible
-		let mut results = match candidate_ids {
-			Some(ref ids) => self.get_entries_by_ids(ids)?,
-			None => self.get_all_entries()?,
-		};
-		let candidates_loaded = results.len();
-
-		let relation_index = if query.relations.is_some() {
-			Some(self.load_relation_index()?)
-		} else {
-			None
-		};
+	/// Shared ranking, filtering, ordering, and pagination for loaded candidates
+	fn finish_query(
+		&self,
+		query: &Query,
+		mut results: Vec<Entry>,
+		candidates: QueryCandidates,
+	) -> StorageResult<QueryExecution> {
+		let QueryCandidates {
+			candidates_loaded,
+			relation_index,
+			lexical_scores,
+			mut steps,
+			candidate_filters,
+		} = candidates;
 
 		// Context is not SQL-indexed yet, so narrow candidates before ranking.
 		if let Some(ref ctx_filter) = query.context {
@@ -1612,6 +2751,35 @@ impl StorageBackend for SqliteStorage {
 				ordering.then_with(|| left.id.cmp(&right.id))
 			});
 		}
+		// Recency and decayed score of each entry under decayed ranking
+		let decayed_scores: HashMap<Uuid, (f32, f32)> = match query.decay {
+			Some(decay) => {
+				let now = query.as_of.unwrap_or_else(Utc::now);
+				let last_accessed = match decay.anchor {
+					DecayAnchor::LastAccessedAt => self.last_accessed_times()?,
+					DecayAnchor::CreatedAt | DecayAnchor::UpdatedAt => HashMap::new(),
+				};
+				results
+					.iter()
+					.map(|entry| {
+						let relevance = Self::normalized_relevance(query, entry, &lexical_scores);
+						let recency =
+							decay.recency_of(entry, last_accessed.get(&entry.id).copied(), now);
+						let score = decay.score(relevance, recency, decay.importance_of(entry));
+						(entry.id, (recency, score))
+					})
+					.collect()
+			}
+			None => HashMap::new(),
+		};
+		if query.decay.is_some() {
+			results.sort_by(|left, right| {
+				decayed_scores[&right.id]
+					.1
+					.total_cmp(&decayed_scores[&left.id].1)
+					.then_with(|| left.id.cmp(&right.id))
+			});
+		}
 		steps.push(QueryPlanStep {
 			strategy: QueryPlanStrategy::DeterministicSort,
 			filter: Some(QueryFilterIdentity::Ordering),
@@ -1619,43 +2787,75 @@ impl StorageBackend for SqliteStorage {
 			candidates_after: results.len(),
 		});
 
-		let has_full_text = matches!(
-			query.expression.as_ref(),
-			Some(ExpressionFilter::FullText(_))
-		);
-		let ranking_mode = if query.meaning.is_some() && has_full_text {
-			let weights = query.hybrid_weights.unwrap_or(crate::query::HybridWeights {
-				semantic: 1.0,
-				lexical: 1.0,
-			});
-			QueryRankingMode::Hybrid {
-				semantic_weight: weights.semantic,
-				lexical_weight: weights.lexical,
+		// Positive count, negative count, and boost of each re-ranked entry
+		let mut feedback_boosts: HashMap<Uuid, (u32, u32, f32)> = HashMap::new();
+		if let Some(boost) = &query.feedback_boost {
+			let counts = self.feedback_counts(boost.fingerprint.as_deref())?;
+			let before = results.len();
+			let mut boosted = Vec::with_capacity(results.len());
+			for entry in results {
+				let base = match decayed_scores.get(&entry.id) {
+					Some((_, score)) => *score,
+					None => Self::normalized_relevance(query, &entry, &lexical_scores),
+				};
+				let (positive, negative) = counts.get(&entry.id).copied().unwrap_or_default();
+				let amount = boost.weight * (positive as f32 - negative as f32)
+					/ (positive + negative + 1) as f32;
+				feedback_boosts.insert(entry.id, (positive, negative, amount));
+				boosted.push((base + amount, entry));
 			}
-		} else if query.meaning.is_some() {
-			QueryRankingMode::CosineSimilarity
-		} else if has_full_text {
-			QueryRankingMode::Bm25
-		} else {
-			QueryRankingMode::None
-		};
-		let primary = match ranking_mode {
-			QueryRankingMode::Hybrid { .. } => QueryPrimaryOrder::CombinedScoreDescending,
-			QueryRankingMode::CosineSimilarity => QueryPrimaryOrder::SimilarityDescending,
-			QueryRankingMode::Bm25 => QueryPrimaryOrder::Bm25Descending,
-			QueryRankingMode::None => QueryPrimaryOrder::Configured(query.order),
-		};
-		let ranking = match ranking_mode {
-			QueryRankingMode::Hybrid { .. } => "weighted semantic and BM25".to_string(),
-			QueryRankingMode::CosineSimilarity => "cosine similarity".to_string(),
-			QueryRankingMode::Bm25 => "BM25".to_string(),
-			QueryRankingMode::None => format!("{:?} with UUID tie-breaker", query.order),
-		};
-		let matches_before_pagination = results.len();
-
-		// Apply pagination after filtering and ordering.
-		if let Some(cursor) = query.cursor {
-			let position = results
+			// A stable sort keeps the existing order between equally boosted entries.
+			boosted.sort_by(|left, right| right.0.total_cmp(&left.0));
+			results = boosted.into_iter().map(|(_, entry)| entry).collect();
+			steps.push(QueryPlanStep {
+				strategy: QueryPlanStrategy::FeedbackRerank,
+				filter: Some(QueryFilterIdentity::Feedback),
+				candidates_before: before,
+				candidates_after: results.len(),
+			});
+		}
+
+		let mut collapsed = CollapsedChunks::default();
+		if query.collapse_chunks {
+			let before = results.len();
+			results = self.collapse_chunks(results, &mut collapsed)?;
+			steps.push(QueryPlanStep {
+				strategy: QueryPlanStrategy::ChunkCollapse,
+				filter: Some(QueryFilterIdentity::Chunks),
+				candidates_before: before,
+				candidates_after: results.len(),
+			});
+		}
+
+		let ranking_mode = QueryRankingMode::of(query);
+		let primary = ranking_mode.primary_order(query.order);
+		let ranking = match ranking_mode {
+			QueryRankingMode::Hybrid { .. } => "weighted semantic and BM25".to_string(),
+			QueryRankingMode::Decayed {
+				half_life_hours, ..
+			} => format!(
+				"weighted relevance, recency with {half_life_hours}h half-life, and importance"
+			),
+			QueryRankingMode::FeedbackBoosted {
+				feedback_weight,
+				decayed,
+			} => format!(
+				"{} plus relevance feedback boost up to {feedback_weight}",
+				if decayed {
+					"decayed score"
+				} else {
+					"relevance"
+				}
+			),
+			QueryRankingMode::CosineSimilarity => "cosine similarity".to_string(),
+			QueryRankingMode::Bm25 => "BM25".to_string(),
+			QueryRankingMode::None => format!("{:?} with UUID tie-breaker", query.order),
+		};
+		let matches_before_pagination = results.len();
+
+		// Apply pagination after filtering and ordering.
+		if let Some(cursor) = query.cursor {
+			let position = results
 				.iter()
 				.position(|entry| entry.id == cursor.after)
 				.ok_or_else(|| {
@@ -1705,12 +2905,18 @@ impl StorageBackend for SqliteStorage {
 		let query_results: Vec<QueryResult> = results
 			.into_iter()
 			.map(|entry| {
+				let best_chunk = collapsed.best_chunks.remove(&entry.id);
+				// A document placed by a chunk hit is scored as that chunk.
+				let hit = match &best_chunk {
+					Some(chunk) if collapsed.placed_by_chunk.contains(&entry.id) => chunk,
+					_ => &entry,
+				};
 				let similarity_score = query
 					.meaning
 					.as_ref()
-					.map(|m| crate::types::cosine_similarity(&entry.meaning, &m.vector));
-				let lexical_score = lexical_scores.get(&entry.id).copied();
-				let combined_score =
+					.map(|m| crate::types::cosine_similarity(&hit.meaning, &m.vector));
+				let lexical_score = lexical_scores.get(&hit.id).copied();
+				let hybrid_score =
 					similarity_score
 						.zip(lexical_score)
 						.map(|(semantic, lexical)| {
@@ -1723,15 +2929,26 @@ impl StorageBackend for SqliteStorage {
 								+ weights.lexical * lexical)
 								/ (weights.semantic + weights.lexical)
 						});
+				let decayed = decayed_scores.get(&hit.id).copied();
+				let combined_score = decayed.map(|(_, score)| score).or(hybrid_score);
+
+				let feedback = feedback_boosts.get(&hit.id).copied();
 
 				let explanation = if query.explain {
-					Some(self.generate_explanation(
-						&entry,
+					let mut explanation = self.generate_explanation(
+						hit,
 						query,
 						similarity_score,
 						lexical_score,
 						combined_score,
-					))
+						decayed.map(|(recency, _)| recency),
+					);
+					if let Some((positive, negative, amount)) = feedback {
+						explanation.push_str(&format!(
+							", Feedback: {positive} positive, {negative} negative, boost {amount:+.3}"
+						));
+					}
+					Some(explanation)
 				} else {
 					None
 				};
@@ -1743,6 +2960,8 @@ impl StorageBackend for SqliteStorage {
 					combined_score,
 					explanation,
 					plan: result_plan.clone(),
+					best_chunk,
+					feedback_boost: feedback.map(|(_, _, amount)| amount),
 				}
 			})
 			.collect();
@@ -1753,1371 +2972,5420 @@ impl StorageBackend for SqliteStorage {
 		})
 	}
 
-	fn update(&mut self, entry: &Entry) -> StorageResult<()> {
-		self.validate_entry(entry)?;
-		self.validate_relation_targets(entry)?;
-		let existing = self.get(entry.id)?;
-		if entry.created_at != existing.created_at || entry.updated_at < existing.updated_at {
-			return Err(StorageError::Database(
-				"Updates must preserve created_at and advance updated_at monotonically".to_string(),
-			));
+	/// Positive and negative feedback counts per entry, optionally for one query fingerprint
+	fn feedback_counts(
+		&self,
+		fingerprint: Option<&str>,
+	) -> StorageResult<HashMap<Uuid, (u32, u32)>> {
+		let mut statement = self
+			.conn
+			.prepare(
+				"SELECT entry_id, SUM(signal = 'positive'), SUM(signal = 'negative')
+				 FROM entry_feedback
+				 WHERE ?1 IS NULL OR query_fingerprint = ?1
+				 GROUP BY entry_id",
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let rows = statement
+			.query_map(params![fingerprint], |row| {
+				Ok((
+					row.get::<_, String>(0)?,
+					row.get::<_, u32>(1)?,
+					row.get::<_, u32>(2)?,
+				))
+			})
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let mut counts = HashMap::new();
+		for row in rows {
+			let (id, positive, negative) =
+				row.map_err(|error| StorageError::Database(error.to_string()))?;
+			let id =
+				Uuid::parse_str(&id).map_err(|error| StorageError::Database(error.to_string()))?;
+			counts.insert(id, (positive, negative));
 		}
-		let id = entry.id.to_string();
-		let meaning_bytes = vector_codec::serialize(&entry.meaning)
-			.map_err(|e| StorageError::Database(format!("Failed to serialize vector: {}", e)))?;
-		let context_json = serde_json::to_string(&entry.context)?;
+		Ok(counts)
+	}
 
-		let transaction = self
+	fn feedback_signal_name(signal: FeedbackSignal) -> &'static str {
+		match signal {
+			FeedbackSignal::Positive => "positive",
+			FeedbackSignal::Negative => "negative",
+		}
+	}
+
+	/// Last tracked read of every entry that has one
+	fn last_accessed_times(&self) -> StorageResult<HashMap<Uuid, DateTime<Utc>>> {
+		let mut statement = self
 			.conn
-			.transaction()
+			.prepare("SELECT entry_id, last_accessed_at FROM entry_access")
 			.map_err(|error| StorageError::Database(error.to_string()))?;
-		let rows_affected = transaction
-			.execute(
-				"UPDATE entries 
-             SET meaning = ?1, expression = ?2, context = ?3, updated_at = ?4
-             WHERE id = ?5",
-				params![
-					meaning_bytes,
-					&entry.expression,
-					context_json,
-					entry.updated_at.to_rfc3339(),
-					id,
-				],
-			)
-			.map_err(|e| StorageError::Database(e.to_string()))?;
-		if rows_affected == 0 {
-			return Err(StorageError::NotFound(entry.id));
+		let rows = statement
+			.query_map([], |row| {
+				Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+			})
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let mut times = HashMap::new();
+		for row in rows {
+			let (id, accessed_at) =
+				row.map_err(|error| StorageError::Database(error.to_string()))?;
+			let id =
+				Uuid::parse_str(&id).map_err(|error| StorageError::Database(error.to_string()))?;
+			let accessed_at = DateTime::parse_from_rfc3339(&accessed_at)
+				.map_err(|error| StorageError::Database(error.to_string()))?
+				.with_timezone(&Utc);
+			times.insert(id, accessed_at);
 		}
+		Ok(times)
+	}
 
-		// Entry relations are directed outgoing edges.
-		transaction
-			.execute("DELETE FROM relations WHERE from_id = ?1", params![id])
-			.map_err(|e| StorageError::Database(e.to_string()))?;
-
-		for relation_id in &entry.relations {
-			transaction
-				.execute(
-					"INSERT OR IGNORE INTO relations (from_id, to_id) VALUES (?1, ?2)",
-					params![id, relation_id.to_string()],
-				)
-				.map_err(|e| StorageError::Database(e.to_string()))?;
+	/// Relevance of `entry` between 0 and 1, as weighed by decay and feedback re-ranking
+	///
+	/// Similarity is mapped from -1..=1 onto 0..=1, as in hybrid scoring.
+	fn normalized_relevance(
+		query: &Query,
+		entry: &Entry,
+		lexical_scores: &HashMap<Uuid, f32>,
+	) -> f32 {
+		let similarity = query.meaning.as_ref().map(|meaning| {
+			(crate::types::cosine_similarity(&entry.meaning, &meaning.vector) + 1.0) / 2.0
+		});
+		match (similarity, lexical_scores.get(&entry.id)) {
+			(Some(semantic), Some(lexical)) => {
+				let weights = query.hybrid_weights.unwrap_or(crate::query::HybridWeights {
+					semantic: 1.0,
+					lexical: 1.0,
+				});
+				(weights.semantic * semantic + weights.lexical * lexical)
+					/ (weights.semantic + weights.lexical)
+			}
+			(Some(semantic), None) => semantic,
+			(None, Some(lexical)) => *lexical,
+			(None, None) => 1.0,
 		}
-		Self::record_revision(&transaction, entry, RevisionOperation::Update)?;
+	}
 
-		transaction
-			.commit()
-			.map_err(|error| StorageError::Database(error.to_string()))
+	/// Replace chunk hits with their documents, each at its first position
+	///
+	/// Chunks whose document is not visible are returned as they are.
+	fn collapse_chunks(
+		&self,
+		results: Vec<Entry>,
+		collapsed: &mut CollapsedChunks,
+	) -> StorageResult<Vec<Entry>> {
+		let mut documents = Vec::with_capacity(results.len());
+		let mut seen = HashSet::new();
+		for entry in results {
+			let Some(document_id) = chunk_document_id(&entry) else {
+				if seen.insert(entry.id) {
+					documents.push(entry);
+				}
+				continue;
+			};
+			if seen.contains(&document_id) {
+				collapsed.best_chunks.entry(document_id).or_insert(entry);
+				continue;
+			}
+			match self.get(document_id) {
+				Ok(document) => {
+					seen.insert(document_id);
+					collapsed.placed_by_chunk.insert(document_id);
+					collapsed.best_chunks.insert(document_id, entry);
+					documents.push(document);
+				}
+				Err(StorageError::NotFound(_)) => documents.push(entry),
+				Err(error) => return Err(error),
+			}
+		}
+		Ok(documents)
 	}
 
-	fn update_batch(&mut self, entries: &[Entry]) -> StorageResult<()> {
-		if entries.is_empty() {
-			return Ok(());
+	/// Execute `query` against entry state reconstructed from revision history
+	fn execute_as_of(&self, query: &Query, as_of: DateTime<Utc>) -> StorageResult<QueryExecution> {
+		let mut results = self.entries_as_of(as_of)?;
+		let mut candidate_filters = vec!["as_of".to_string()];
+		let mut steps = vec![QueryPlanStep {
+			strategy: QueryPlanStrategy::RevisionReplay,
+			filter: Some(QueryFilterIdentity::AsOf),
+			candidates_before: results.len(),
+			candidates_after: results.len(),
+		}];
+		let relation_index = query
+			.relations
+			.is_some()
+			.then(|| RelationIndex::from_entries(&results));
+		if !query.include_expired && results.iter().any(|entry| entry.is_expired_at(as_of)) {
+			let before = results.len();
+			results.retain(|entry| !entry.is_expired_at(as_of));
+			candidate_filters.push("expiry".to_string());
+			steps.push(QueryPlanStep {
+				strategy: QueryPlanStrategy::RevisionReplay,
+				filter: Some(QueryFilterIdentity::Expiry),
+				candidates_before: before,
+				candidates_after: results.len(),
+			});
 		}
-		let mut ids = HashSet::with_capacity(entries.len());
-		for entry in entries {
-			self.validate_entry(entry)?;
-			self.validate_relation_targets(entry)?;
-			if !ids.insert(entry.id) {
-				return Err(StorageError::Database(format!(
-					"Duplicate entry ID in batch: {}",
-					entry.id
+		if !query.collections.is_empty() {
+			let before = results.len();
+			results.retain(|entry| query.collections.contains(&entry.collection));
+			candidate_filters.push("collection".to_string());
+			steps.push(QueryPlanStep {
+				strategy: QueryPlanStrategy::RevisionReplay,
+				filter: Some(QueryFilterIdentity::Collection),
+				candidates_before: before,
+				candidates_after: results.len(),
+			});
+		}
+		let lexical_scores = match &query.expression {
+			Some(ExpressionFilter::FullText(value)) => {
+				let scores = self.full_text_scores_as_of(&results, value)?;
+				let before = results.len();
+				results.retain(|entry| scores.contains_key(&entry.id));
+				candidate_filters.push("expression".to_string());
+				steps.push(QueryPlanStep {
+					strategy: QueryPlanStrategy::Fts5,
+					filter: Some(QueryFilterIdentity::ExpressionFullText),
+					candidates_before: before,
+					candidates_after: results.len(),
+				});
+				scores
+			}
+			_ => HashMap::new(),
+		};
+		let candidates_loaded = results.len();
+		self.finish_query(
+			query,
+			results,
+			QueryCandidates {
+				candidates_loaded,
+				relation_index,
+				lexical_scores,
+				steps,
+				candidate_filters,
+			},
+		)
+	}
+
+	/// Reconstruct live entries from their latest revision at or before `as_of`
+	///
+	/// Entries whose latest revision is a delete are absent, and relations to
+	/// entries that were absent at that time are dropped.
+	fn entries_as_of(&self, as_of: DateTime<Utc>) -> StorageResult<Vec<Entry>> {
+		if let Some(horizon) = self.revision_horizon()? {
+			if as_of < horizon {
+				return Err(StorageError::InvalidArgument(format!(
+					"Revision history before {} has been compacted, so as_of cannot replay earlier state",
+					horizon.to_rfc3339()
 				)));
 			}
 		}
-		let existing_ids = self.get_entry_ids()?;
-		if let Some(missing) = ids.difference(&existing_ids).next() {
-			return Err(StorageError::NotFound(*missing));
-		}
-		for entry in entries {
-			let existing = self.get(entry.id)?;
-			if entry.created_at != existing.created_at || entry.updated_at < existing.updated_at {
-				return Err(StorageError::Database(
-					"Updates must preserve created_at and advance updated_at monotonically"
-						.to_string(),
-				));
+		let mut latest: HashMap<Uuid, StoredRevision> = HashMap::new();
+		for stored in self.load_revisions(None)? {
+			if stored.revision.recorded_at > as_of {
+				continue;
+			}
+			let newer = match latest.get(&stored.revision.entry_id) {
+				Some(current) => stored.order_key() > current.order_key(),
+				None => true,
+			};
+			if newer {
+				latest.insert(stored.revision.entry_id, stored);
 			}
 		}
 
-		let transaction = self
-			.conn
-			.transaction()
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		for entry in entries {
-			let meaning_bytes = vector_codec::serialize(&entry.meaning).map_err(|error| {
-				StorageError::Database(format!("Failed to serialize vector: {error}"))
-			})?;
-			let context_json = serde_json::to_string(&entry.context)?;
-			transaction
-				.execute(
-					"UPDATE entries
-					 SET meaning = ?1, expression = ?2, context = ?3, updated_at = ?4
-					 WHERE id = ?5",
-					params![
-						meaning_bytes,
-						&entry.expression,
-						context_json,
-						entry.updated_at.to_rfc3339(),
-						entry.id.to_string(),
-					],
-				)
-				.map_err(|error| StorageError::Database(error.to_string()))?;
-			transaction
-				.execute(
-					"DELETE FROM relations WHERE from_id = ?1",
-					params![entry.id.to_string()],
-				)
-				.map_err(|error| StorageError::Database(error.to_string()))?;
-		}
-		for entry in entries {
-			for relation_id in &entry.relations {
-				transaction
-					.execute(
-						"INSERT INTO relations (from_id, to_id) VALUES (?1, ?2)",
-						params![entry.id.to_string(), relation_id.to_string()],
-					)
-					.map_err(|error| StorageError::Database(error.to_string()))?;
-			}
-			Self::record_revision(&transaction, entry, RevisionOperation::Update)?;
+		let mut entries: Vec<Entry> = latest
+			.into_values()
+			.filter(|stored| stored.revision.operation != RevisionOperation::Delete)
+			.map(|stored| stored.revision.snapshot)
+			.filter(|entry| self.in_scope(entry))
+			.collect();
+		let live: HashSet<Uuid> = entries.iter().map(|entry| entry.id).collect();
+		for entry in &mut entries {
+			entry.relations.retain(|target| live.contains(target));
 		}
-		transaction
-			.commit()
-			.map_err(|error| StorageError::Database(error.to_string()))
+		entries.sort_by_key(|entry| entry.id);
+		Ok(entries)
 	}
 
-	fn delete(&mut self, id: Uuid) -> StorageResult<()> {
-		let snapshot = self.get(id)?;
-		let id_str = id.to_string();
-		let transaction = self
+	/// Earliest time `as_of` can replay in this handle's scope
+	///
+	/// Compaction records it per tenant whenever it prunes revisions that
+	/// were followed by kept ones.
+	fn revision_horizon(&self) -> StorageResult<Option<DateTime<Utc>>> {
+		let key = self
+			.tenant
+			.as_ref()
+			.map(|tenant| format!("revision_horizon/{tenant}"));
+		let horizon: Option<String> = self
 			.conn
-			.transaction()
+			.query_row(
+				"SELECT MAX(value) FROM contextdb_metadata
+				 WHERE key = ?1 OR (?1 IS NULL AND key LIKE 'revision_horizon/%')",
+				params![key],
+				|row| row.get(0),
+			)
 			.map_err(|error| StorageError::Database(error.to_string()))?;
+		horizon
+			.map(|horizon| {
+				DateTime::parse_from_rfc3339(&horizon)
+					.map(|horizon| horizon.with_timezone(&Utc))
+					.map_err(|error| StorageError::Database(error.to_string()))
+			})
+			.transpose()
+	}
 
-		// Delete relations first
-		transaction
-			.execute(
-				"DELETE FROM relations WHERE from_id = ?1 OR to_id = ?1",
-				params![id_str],
+	/// BM25 scores for `entries` using a temporary FTS5 index
+	fn full_text_scores_as_of(
+		&self,
+		entries: &[Entry],
+		query: &str,
+	) -> StorageResult<HashMap<Uuid, f32>> {
+		self.conn
+			.execute_batch(
+				"DROP TABLE IF EXISTS temp.entries_fts_as_of;
+				 CREATE VIRTUAL TABLE temp.entries_fts_as_of USING fts5(id UNINDEXED, expression);",
 			)
-			.map_err(|e| StorageError::Database(e.to_string()))?;
-
-		// Delete entry
-		let rows_affected = transaction
-			.execute("DELETE FROM entries WHERE id = ?1", params![id_str])
-			.map_err(|e| StorageError::Database(e.to_string()))?;
-
-		if rows_affected == 0 {
-			return Err(StorageError::NotFound(id));
-		}
-		Self::record_revision(&transaction, &snapshot, RevisionOperation::Delete)?;
-
-		transaction
-			.commit()
-			.map_err(|error| StorageError::Database(error.to_string()))
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let scores = entries
+			.iter()
+			.try_for_each(|entry| {
+				self.conn
+					.execute(
+						"INSERT INTO temp.entries_fts_as_of(id, expression) VALUES (?1, ?2)",
+						params![entry.id.to_string(), entry.expression],
+					)
+					.map(|_| ())
+					.map_err(|error| StorageError::Database(error.to_string()))
+			})
+			.and_then(|()| self.full_text_scores_in("entries_fts_as_of", query, None));
+		self.conn
+			.execute_batch("DROP TABLE temp.entries_fts_as_of")
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		scores
 	}
 
-	fn delete_batch(&mut self, ids: &[Uuid]) -> StorageResult<()> {
-		if ids.is_empty() {
-			return Ok(());
-		}
-		let unique_ids: HashSet<Uuid> = ids.iter().copied().collect();
-		if unique_ids.len() != ids.len() {
-			return Err(StorageError::Database(
-				"Duplicate entry ID in delete batch".to_string(),
-			));
-		}
-		let existing_ids = self.get_entry_ids()?;
-		if let Some(missing) = unique_ids.difference(&existing_ids).next() {
-			return Err(StorageError::NotFound(*missing));
-		}
-		let snapshots: Vec<Entry> = ids
-			.iter()
-			.map(|id| self.get(*id))
-			.collect::<StorageResult<_>>()?;
+	/// BM25 scores for stored entries, normalized within the collections `scope` selects
+	fn full_text_scores(
+		&self,
+		query: &str,
+		scope: Option<&str>,
+	) -> StorageResult<HashMap<Uuid, f32>> {
+		self.full_text_scores_in("entries_fts", query, scope)
+	}
 
-		let transaction = self
+	fn full_text_scores_in(
+		&self,
+		table: &str,
+		query: &str,
+		scope: Option<&str>,
+	) -> StorageResult<HashMap<Uuid, f32>> {
+		let scope = match scope {
+			Some(predicate) => {
+				format!("AND rowid IN (SELECT rowid FROM entries WHERE {predicate})")
+			}
+			None => String::new(),
+		};
+		let mut statement = self
 			.conn
-			.transaction()
+			.prepare(&format!(
+				"SELECT id, bm25({table}) AS score
+				 FROM {table} WHERE {table} MATCH ?1 {scope} ORDER BY score, id"
+			))
 			.map_err(|error| StorageError::Database(error.to_string()))?;
-		for (id, snapshot) in ids.iter().zip(&snapshots) {
-			transaction
-				.execute("DELETE FROM entries WHERE id = ?1", params![id.to_string()])
-				.map_err(|error| StorageError::Database(error.to_string()))?;
-			Self::record_revision(&transaction, snapshot, RevisionOperation::Delete)?;
-		}
-		transaction
-			.commit()
-			.map_err(|error| StorageError::Database(error.to_string()))
-	}
-
-	fn count(&self) -> StorageResult<usize> {
-		let count: i64 = self
-			.conn
-			.query_row("SELECT COUNT(*) FROM entries", [], |row| row.get(0))
-			.map_err(|e| StorageError::Database(e.to_string()))?;
-		Ok(count as usize)
-	}
-
-	fn integrity_check(&self) -> StorageResult<IntegrityReport> {
-		let mut report = IntegrityReport::default();
-		let quick_check: String = self
-			.conn
-			.query_row("PRAGMA quick_check", [], |row| row.get(0))
+		let rows = statement
+			.query_map(params![query], |row| {
+				Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+			})
 			.map_err(|error| StorageError::Database(error.to_string()))?;
-		if quick_check != "ok" {
-			report.issues.push(IntegrityIssue {
-				area: "sqlite".to_string(),
-				message: quick_check,
-			});
+		let mut raw_scores = Vec::new();
+		for row in rows {
+			let (id, score) = row.map_err(|error| StorageError::Database(error.to_string()))?;
+			let id =
+				Uuid::parse_str(&id).map_err(|error| StorageError::Database(error.to_string()))?;
+			raw_scores.push((id, -score as f32));
 		}
-
-		let mut foreign_key_statement = self
-			.conn
-			.prepare("PRAGMA foreign_key_check")
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		let foreign_key_rows = foreign_key_statement
-			.query_map([], |row| {
-				Ok((
-					row.get::<_, String>(0)?,
-					row.get::<_, i64>(1)?,
-					row.get::<_, String>(2)?,
-				))
+		let min = raw_scores
+			.iter()
+			.map(|(_, score)| *score)
+			.reduce(f32::min)
+			.unwrap_or(0.0);
+		let max = raw_scores
+			.iter()
+			.map(|(_, score)| *score)
+			.reduce(f32::max)
+			.unwrap_or(0.0);
+		Ok(raw_scores
+			.into_iter()
+			.map(|(id, score)| {
+				let normalized = if max > min {
+					(score - min) / (max - min)
+				} else {
+					1.0
+				};
+				(id, normalized)
 			})
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		for row in foreign_key_rows {
-			let (table, row_id, parent) =
-				row.map_err(|error| StorageError::Database(error.to_string()))?;
-			report.issues.push(IntegrityIssue {
-				area: "foreign_key".to_string(),
-				message: format!("{table} row {row_id} references missing {parent}"),
-			});
+			.collect())
+	}
+
+	fn query_temporal_ids(&self, filter: &TemporalFilter) -> StorageResult<HashSet<Uuid>> {
+		match filter {
+			TemporalFilter::CreatedAfter(dt) => self.query_ids_with_params(
+				"SELECT id FROM entries WHERE created_at > ?1",
+				rusqlite::params![dt.to_rfc3339()],
+			),
+			TemporalFilter::CreatedBefore(dt) => self.query_ids_with_params(
+				"SELECT id FROM entries WHERE created_at < ?1",
+				rusqlite::params![dt.to_rfc3339()],
+			),
+			TemporalFilter::CreatedBetween(start, end) => self.query_ids_with_params(
+				"SELECT id FROM entries WHERE created_at > ?1 AND created_at < ?2",
+				rusqlite::params![start.to_rfc3339(), end.to_rfc3339()],
+			),
+			TemporalFilter::UpdatedAfter(dt) => self.query_ids_with_params(
+				"SELECT id FROM entries WHERE updated_at > ?1",
+				rusqlite::params![dt.to_rfc3339()],
+			),
+			TemporalFilter::UpdatedBefore(dt) => self.query_ids_with_params(
+				"SELECT id FROM entries WHERE updated_at < ?1",
+				rusqlite::params![dt.to_rfc3339()],
+			),
 		}
+	}
 
-		let actual_dimension = match self.validate_existing_vectors() {
-			Ok(dimension) => dimension,
-			Err(error) => {
-				report.issues.push(IntegrityIssue {
-					area: "entries".to_string(),
-					message: error.to_string(),
-				});
-				None
+	/// IDs matching a context filter; `scope` is appended to each `entries` predicate
+	fn query_context_ids(
+		&self,
+		filter: &ContextFilter,
+		scope: &str,
+	) -> StorageResult<HashSet<Uuid>> {
+		match filter {
+			ContextFilter::PathExists(path) => {
+				let path = Self::sql_string_literal(&Self::json_pointer_to_sqlite_path(path)?);
+				self.query_ids_with_params(
+					&format!(
+						"SELECT id FROM entries WHERE json_type(context, {path}) IS NOT NULL{scope}"
+					),
+					[],
+				)
 			}
-		};
-		if let Err(error) = self.validate_revisions() {
-			report.issues.push(IntegrityIssue {
-				area: "revisions".to_string(),
-				message: error.to_string(),
-			});
-		}
-		if let Err(error) = self.conn.execute(
-			"INSERT INTO entries_fts(entries_fts) VALUES ('integrity-check')",
-			[],
-		) {
-			report.issues.push(IntegrityIssue {
-				area: "full_text".to_string(),
-				message: format!("FTS5 integrity check failed: {error}"),
-			});
-		}
-		let entry_count = self.count()?;
-		let search_count: usize = self
-			.conn
-			.query_row("SELECT COUNT(*) FROM entries_fts", [], |row| row.get(0))
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		if entry_count != search_count {
-			report.issues.push(IntegrityIssue {
-				area: "full_text".to_string(),
-				message: format!(
-					"Search index contains {search_count} rows for {entry_count} entries"
-				),
-			});
-		}
-		let search_mismatch_count: usize = self
-			.conn
-			.query_row(
-				"SELECT
-					(SELECT COUNT(*)
-					 FROM entries AS entry
-					 LEFT JOIN entries_fts AS search ON search.rowid = entry.rowid
-					 WHERE search.rowid IS NULL
-						OR search.id <> entry.id
-						OR search.expression <> entry.expression)
-					+
-					(SELECT COUNT(*)
-					 FROM entries_fts AS search
-					 LEFT JOIN entries AS entry ON entry.rowid = search.rowid
-					 WHERE entry.rowid IS NULL)",
-				[],
-				|row| row.get(0),
-			)
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		if search_mismatch_count > 0 {
-			report.issues.push(IntegrityIssue {
-				area: "full_text".to_string(),
-				message: format!(
-					"Search index contains {search_mismatch_count} stale or mismatched rows"
+			ContextFilter::PathEquals(path, value) => {
+				let path = Self::sql_string_literal(&Self::json_pointer_to_sqlite_path(path)?);
+				self.query_ids_with_params(
+					&format!(
+						"SELECT id FROM entries
+						 WHERE json_extract(context, {path}) = json_extract(?1, '$'){scope}"
+					),
+					params![serde_json::to_string(value)?],
+				)
+			}
+			ContextFilter::PathContains(path, value) => self.query_ids_with_params(
+				&format!(
+					"SELECT entry.id FROM entries AS entry
+					 WHERE EXISTS (
+						SELECT 1 FROM json_each(entry.context, ?1)
+						WHERE json_each.value = json_extract(?2, '$')
+					 ){scope}"
 				),
-			});
-		}
-		match self.inspect_embedding_metadata() {
-			Err(error) => report.issues.push(IntegrityIssue {
-				area: "metadata".to_string(),
-				message: error.to_string(),
-			}),
-			Ok((_, metadata_dimension)) => {
-				if entry_count > 0 && metadata_dimension.is_none() {
-					report.issues.push(IntegrityIssue {
-						area: "metadata".to_string(),
-						message: "Stored entries are missing vector-dimension metadata".to_string(),
-					});
+				params![
+					Self::json_pointer_to_sqlite_path(path)?,
+					serde_json::to_string(value)?
+				],
+			),
+			ContextFilter::And(filters) => {
+				let mut ids = self.get_entry_ids()?;
+				for filter in filters {
+					let matching = self.query_context_ids(filter, scope)?;
+					ids = ids.intersection(&matching).copied().collect();
 				}
-				if let (Some(metadata_dimension), Some(actual_dimension)) =
-					(metadata_dimension, actual_dimension)
-				{
-					if metadata_dimension != actual_dimension {
-						report.issues.push(IntegrityIssue {
-							area: "metadata".to_string(),
-							message: format!(
-								"Stored vector dimension {metadata_dimension} does not match data dimension {actual_dimension}"
-							),
-						});
-					}
+				Ok(ids)
+			}
+			ContextFilter::Or(filters) => {
+				let mut ids = HashSet::new();
+				for filter in filters {
+					ids.extend(self.query_context_ids(filter, scope)?);
 				}
+				Ok(ids)
 			}
 		}
-
-		Ok(report)
 	}
 
-	fn backup_to(&self, destination: &Path) -> StorageResult<()> {
-		if destination.exists() {
-			return Err(StorageError::Database(format!(
-				"Backup destination already exists: {}",
-				destination.display()
-			)));
+	/// Create an expression index on a context path, partial to `collection` when given
+	fn create_context_index_in(
+		&mut self,
+		collection: Option<&str>,
+		path: &str,
+	) -> StorageResult<String> {
+		let sqlite_path = Self::json_pointer_to_sqlite_path(path)?;
+		let key = match collection {
+			Some(collection) => format!("{collection}\0{path}"),
+			None => path.to_string(),
+		};
+		let mut hash = 0xcbf29ce484222325_u64;
+		for byte in key.as_bytes() {
+			hash ^= u64::from(*byte);
+			hash = hash.wrapping_mul(0x100000001b3);
 		}
-		let mut destination_connection = Connection::open(destination)
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		let backup = rusqlite::backup::Backup::new(&self.conn, &mut destination_connection)
+		let name = format!("idx_entries_context_{hash:016x}");
+		let path_literal = Self::sql_string_literal(&sqlite_path);
+		let scope = match collection {
+			Some(collection) => format!(
+				" WHERE collection = {}",
+				Self::sql_string_literal(collection)
+			),
+			None => String::new(),
+		};
+		self.conn
+			.execute(
+				&format!("CREATE INDEX IF NOT EXISTS {name} ON entries(json_extract(context, {path_literal})){scope}"),
+				[],
+			)
 			.map_err(|error| StorageError::Database(error.to_string()))?;
-		backup
-			.run_to_completion(64, Duration::from_millis(10), None)
-			.map_err(|error| StorageError::Database(error.to_string()))
+		Ok(name)
 	}
 
-	fn embedding_profile(&self) -> StorageResult<Option<EmbeddingProfile>> {
-		self.inspect_embedding_metadata()
-			.map(|(profile, _)| profile)
+	/// SQL predicate restricting `entries` rows to the query's collections
+	///
+	/// Names are inlined as literals so SQLite can match per-collection
+	/// partial context indexes.
+	fn collection_predicate(collections: &[String]) -> Option<String> {
+		match collections {
+			[] => None,
+			[collection] => Some(format!(
+				"collection = {}",
+				Self::sql_string_literal(collection)
+			)),
+			collections => Some(format!(
+				"collection IN ({})",
+				collections
+					.iter()
+					.map(|collection| Self::sql_string_literal(collection))
+					.collect::<Vec<_>>()
+					.join(", ")
+			)),
+		}
 	}
 
-	fn set_embedding_profile(&mut self, profile: &EmbeddingProfile) -> StorageResult<()> {
-		Self::validate_embedding_profile(profile)?;
-		if self
-			.stored_vector_dimension()?
-			.is_some_and(|dimension| dimension != profile.dimensions)
-		{
-			return Err(StorageError::InvalidDimensions);
+	fn json_pointer_to_sqlite_path(pointer: &str) -> StorageResult<String> {
+		if pointer.is_empty() {
+			return Ok("$".to_string());
 		}
-		if self.count()? > 0 {
-			match self.embedding_profile()? {
-				Some(current) if current == *profile => return Ok(()),
-				Some(_) => {
-					return Err(StorageError::Database(
-						"Changing the embedding profile requires an explicit embedding migration"
-							.to_string(),
-					));
-				}
-				None => {
-					return Err(StorageError::Database(
-						"Populated legacy data must be explicitly adopted before assigning an embedding profile"
-							.to_string(),
-					));
-				}
+		if !pointer.starts_with('/') {
+			return Err(StorageError::InvalidArgument(format!(
+				"Invalid JSON Pointer: {pointer}"
+			)));
+		}
+		let mut path = "$".to_string();
+		for raw_segment in pointer[1..].split('/') {
+			let segment = raw_segment.replace("~1", "/").replace("~0", "~");
+			if let Ok(index) = segment.parse::<usize>() {
+				path.push_str(&format!("[{index}]"));
+			} else {
+				path.push('.');
+				path.push_str(&serde_json::to_string(&segment)?);
+			}
+		}
+		Ok(path)
+	}
+
+	fn sql_string_literal(value: &str) -> String {
+		format!("'{}'", value.replace('\'', "''"))
+	}
+
+	fn query_relation_ids(&self, filter: &RelationFilter) -> StorageResult<HashSet<Uuid>> {
+		match filter {
+			RelationFilter::DirectlyRelatedTo(id) => {
+				let id_str = id.to_string();
+				self.query_ids_with_params(
+					"SELECT to_id AS id FROM relations WHERE from_id = ?1",
+					rusqlite::params![id_str],
+				)
+			}
+			RelationFilter::WithinDistance { from, max_hops } => {
+				let index = self.load_relation_index()?;
+				Ok(self.within_distance_relations(&index, *from, *max_hops))
+			}
+			RelationFilter::HasRelations => self.query_ids_with_params(
+				"SELECT DISTINCT from_id AS id FROM relations",
+				rusqlite::params![],
+			),
+			RelationFilter::NoRelations => {
+				let all_ids = self.get_entry_ids()?;
+				let related_ids = self.query_relation_ids(&RelationFilter::HasRelations)?;
+				Ok(all_ids
+					.difference(&related_ids)
+					.copied()
+					.collect::<HashSet<_>>())
 			}
 		}
+	}
+
+	fn query_ids_with_params<P>(&self, sql: &str, params: P) -> StorageResult<HashSet<Uuid>>
+	where
+		P: rusqlite::Params,
+	{
+		let mut stmt = self
+			.conn
+			.prepare(sql)
+			.map_err(|e| StorageError::Database(e.to_string()))?;
+
+		let rows = stmt
+			.query_map(params, |row| {
+				let id_str: String = row.get(0)?;
+				Uuid::parse_str(&id_str).map_err(|_| rusqlite::Error::InvalidQuery)
+			})
+			.map_err(|e| StorageError::Database(e.to_string()))?;
+
+		let mut ids = HashSet::new();
+		for row in rows {
+			let id = row.map_err(|e| StorageError::Database(e.to_string()))?;
+			ids.insert(id);
+		}
+
+		Ok(ids)
+	}
+
+	fn intersect_candidate_ids(
+		candidate_ids: &mut Option<HashSet<Uuid>>,
+		ids: HashSet<Uuid>,
+		total_entries: usize,
+	) -> (usize, usize) {
+		let before = candidate_ids.as_ref().map_or(total_entries, HashSet::len);
+		let narrowed = match candidate_ids.take() {
+			Some(existing) => existing.intersection(&ids).copied().collect(),
+			None => ids,
+		};
+		let after = narrowed.len();
+		*candidate_ids = Some(narrowed);
+		(before, after)
+	}
+}
+
+impl StorageBackend for SqliteStorage {
+	fn insert(&mut self, entry: &Entry) -> StorageResult<()> {
+		if self.dedup_policy()?.enabled {
+			return self
+				.insert_batch_returning_ids(std::slice::from_ref(entry))
+				.map(|_| ());
+		}
+		self.validate_entry(entry)?;
+		self.validate_relation_targets(entry)?;
+		let established = self.check_batch_dimensions(std::slice::from_ref(entry))?;
+		let entry = &Entry {
+			version: 1,
+			..entry.clone()
+		};
 
 		let transaction = self
 			.conn
 			.transaction()
 			.map_err(|error| StorageError::Database(error.to_string()))?;
-		Self::write_embedding_profile(&transaction, profile)?;
+		Self::insert_entry_row(&transaction, entry)?;
+		Self::write_relations(&transaction, entry)?;
+		Self::record_revision(&transaction, entry, RevisionOperation::Insert)?;
+		Self::set_vector_dimensions(&transaction, &established)?;
+
 		transaction
 			.commit()
 			.map_err(|error| StorageError::Database(error.to_string()))
 	}
 
-	fn adopt_legacy_embedding_profile(&mut self, profile: &EmbeddingProfile) -> StorageResult<()> {
-		Self::validate_embedding_profile(profile)?;
-		if self.count()? == 0 {
-			return Err(StorageError::Database(
-				"There is no legacy embedding data to adopt".to_string(),
-			));
+	fn insert_batch(&mut self, entries: &[Entry]) -> StorageResult<()> {
+		self.insert_batch_returning_ids(entries).map(|_| ())
+	}
+
+	fn insert_batch_returning_ids(&mut self, entries: &[Entry]) -> StorageResult<Vec<Uuid>> {
+		if entries.is_empty() {
+			return Ok(Vec::new());
 		}
-		if self.embedding_profile()?.is_some() {
-			return Err(StorageError::Database(
-				"The database already has an embedding profile".to_string(),
-			));
+
+		let mut batch_ids = HashSet::with_capacity(entries.len());
+		for entry in entries {
+			self.validate_entry(entry)?;
+			if !batch_ids.insert(entry.id) {
+				return Err(StorageError::Database(format!(
+					"Duplicate entry ID in batch: {}",
+					entry.id
+				)));
+			}
 		}
-		if self.validate_existing_vectors()? != Some(profile.dimensions) {
-			return Err(StorageError::InvalidDimensions);
+		let established = self.check_batch_dimensions(entries)?;
+		let policy = self.dedup_policy()?;
+
+		// Take the write lock before looking for stored duplicates so none can
+		// appear between the lookup and the insert.
+		let transaction = self
+			.conn
+			.transaction_with_behavior(TransactionBehavior::Immediate)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let resolution = match policy.enabled {
+			true => Self::resolve_duplicates(&transaction, &policy, entries)?,
+			false => DedupResolution {
+				inserts: entries.to_vec(),
+				merged: Vec::new(),
+				ids: entries.iter().map(|entry| entry.id).collect(),
+			},
+		};
+		Self::check_relation_tenants(&transaction, &resolution.inserts)?;
+
+		let inserts: Vec<Entry> = resolution
+			.inserts
+			.into_iter()
+			.map(|entry| Entry {
+				version: 1,
+				..entry
+			})
+			.collect();
+		for entry in &inserts {
+			Self::insert_entry_row(&transaction, entry)?;
+		}
+		for entry in &inserts {
+			Self::write_relations(&transaction, entry)?;
+			Self::record_revision(&transaction, entry, RevisionOperation::Insert)?;
+		}
+		for entry in &resolution.merged {
+			Self::update_entry_row(&transaction, entry, entry.version - 1)?;
+			Self::record_revision(&transaction, entry, RevisionOperation::Update)?;
+		}
+		Self::set_vector_dimensions(&transaction, &established)?;
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(resolution.ids)
+	}
+
+	fn upsert_batch(&mut self, entries: &[Entry]) -> StorageResult<Vec<UpsertOutcome>> {
+		if entries.is_empty() {
+			return Ok(Vec::new());
+		}
+
+		let mut batch_ids = HashSet::with_capacity(entries.len());
+		for entry in entries {
+			self.validate_entry(entry)?;
+			if !batch_ids.insert(entry.id) {
+				return Err(StorageError::Database(format!(
+					"Duplicate entry ID in batch: {}",
+					entry.id
+				)));
+			}
 		}
+		let established = self.check_batch_dimensions(entries)?;
 
+		// Take the write lock before reading so no other writer can create or
+		// change these entries between the existence check and the write.
 		let transaction = self
 			.conn
-			.transaction()
+			.transaction_with_behavior(TransactionBehavior::Immediate)
 			.map_err(|error| StorageError::Database(error.to_string()))?;
-		Self::write_embedding_profile(&transaction, profile)?;
+		let outcomes = Self::upsert_rows(&transaction, entries)?;
+		Self::set_vector_dimensions(&transaction, &established)?;
 		transaction
 			.commit()
-			.map_err(|error| StorageError::Database(error.to_string()))
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(outcomes)
 	}
 
-	fn migrate_embeddings(
+	fn upsert_batch_by_external_id(
 		&mut self,
-		profile: &EmbeddingProfile,
-		replacements: &[(Uuid, Vec<f32>)],
-	) -> StorageResult<()> {
-		Self::validate_embedding_profile(profile)?;
-		let entries = self.get_all_entries()?;
-		if replacements.len() != entries.len() {
-			return Err(StorageError::Database(format!(
-				"Embedding migration requires {} replacement vectors, received {}",
-				entries.len(),
-				replacements.len()
-			)));
+		entries: &[Entry],
+	) -> StorageResult<Vec<(Uuid, UpsertOutcome)>> {
+		if entries.is_empty() {
+			return Ok(Vec::new());
 		}
 
-		let entry_ids: HashSet<Uuid> = entries.iter().map(|entry| entry.id).collect();
-		let mut replacement_vectors = HashMap::with_capacity(replacements.len());
-		for (id, vector) in replacements {
-			if !entry_ids.contains(id) {
-				return Err(StorageError::NotFound(*id));
+		let mut batch_keys = HashSet::with_capacity(entries.len());
+		for entry in entries {
+			self.validate_entry(entry)?;
+			let Some(external_id) = entry.external_id.as_deref() else {
+				return Err(StorageError::InvalidArgument(format!(
+					"Entry {} has no external ID to upsert by",
+					entry.id
+				)));
+			};
+			if !batch_keys.insert((entry.tenant.as_str(), external_id)) {
+				return Err(StorageError::InvalidArgument(format!(
+					"External ID '{external_id}' appears more than once in the batch"
+				)));
 			}
-			Self::validate_vector(vector)?;
-			if vector.len() != profile.dimensions {
-				return Err(StorageError::InvalidDimensions);
+		}
+		let established = self.check_batch_dimensions(entries)?;
+
+		// Resolve keys under the write lock so an entry another writer inserts
+		// with the same key meanwhile is updated rather than duplicated.
+		let transaction = self
+			.conn
+			.transaction_with_behavior(TransactionBehavior::Immediate)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let mut resolved = Vec::with_capacity(entries.len());
+		let mut batch_ids = HashSet::with_capacity(entries.len());
+		for entry in entries {
+			let mut entry = entry.clone();
+			let external_id = entry.external_id.as_deref().unwrap_or_default();
+			if let Some(id) =
+				Self::entry_with_external_id(&transaction, &entry.tenant, external_id)?
+			{
+				let stored = Self::read_entry(&transaction, id)?;
+				entry.id = stored.id;
+				entry.created_at = stored.created_at;
+				entry.updated_at = entry.updated_at.max(stored.updated_at);
 			}
-			if replacement_vectors.insert(*id, vector.clone()).is_some() {
+			if !batch_ids.insert(entry.id) {
 				return Err(StorageError::Database(format!(
-					"Duplicate replacement vector for entry {id}"
+					"Duplicate entry ID in batch: {}",
+					entry.id
 				)));
 			}
+			resolved.push(entry);
 		}
-		if let Some(missing) = entry_ids
+		let outcomes = Self::upsert_rows(&transaction, &resolved)?;
+		Self::set_vector_dimensions(&transaction, &established)?;
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(resolved
 			.iter()
-			.find(|id| !replacement_vectors.contains_key(id))
-		{
-			return Err(StorageError::Database(format!(
-				"Missing replacement vector for entry {missing}"
-			)));
+			.map(|entry| entry.id)
+			.zip(outcomes)
+			.collect())
+	}
+
+	fn get_by_external_id(&self, external_id: &str) -> StorageResult<Option<Entry>> {
+		let tenant = self.tenant.as_deref().unwrap_or(DEFAULT_TENANT);
+		let Some(id) = Self::entry_with_external_id(&self.conn, tenant, external_id)? else {
+			return Ok(None);
+		};
+		match self.get(id) {
+			Ok(entry) => Ok(Some(entry)),
+			Err(StorageError::NotFound(_)) => Ok(None),
+			Err(error) => Err(error),
 		}
+	}
 
-		let migration_time = Utc::now();
-		let updated_entries: Vec<Entry> = entries
-			.into_iter()
-			.map(|mut entry| {
-				entry.meaning = replacement_vectors
-					.remove(&entry.id)
-					.expect("replacement coverage was validated");
-				if entry.updated_at < migration_time {
-					entry.updated_at = migration_time;
-				}
-				entry
-			})
-			.collect();
+	fn get(&self, id: Uuid) -> StorageResult<Entry> {
+		let entry = self.read_scoped(id)?;
+		if entry.is_expired_at(Utc::now()) {
+			return Err(StorageError::NotFound(id));
+		}
+		Ok(entry)
+	}
+
+	fn query(&self, query: &Query) -> StorageResult<Vec<QueryResult>> {
+		self.execute(query).map(|execution| execution.results)
+	}
+
+	fn execute(&self, query: &Query) -> StorageResult<QueryExecution> {
+		self.validate_query(query)?;
+		if let Some(as_of) = query.as_of {
+			return self.execute_as_of(query, as_of);
+		}
+		let tenant_scope = self
+			.tenant
+			.as_ref()
+			.map(|tenant| format!("tenant = {}", Self::sql_string_literal(tenant)));
+		let trash_scope = self
+			.has_trashed_entries()?
+			.then(|| "deleted_at IS NULL".to_string());
+		let expiry_scope = match query.include_expired {
+			true => None,
+			false => self.expiry_predicate(Utc::now())?,
+		};
+		let collection_scope = Self::collection_predicate(&query.collections);
+		let scope = [
+			&trash_scope,
+			&tenant_scope,
+			&expiry_scope,
+			&collection_scope,
+		]
+		.into_iter()
+		.flatten()
+		.cloned()
+		.collect::<Vec<_>>();
+		let scope = (!scope.is_empty()).then(|| scope.join(" AND "));
+		let lexical_scores = match &query.expression {
+			Some(ExpressionFilter::FullText(value)) => {
+				self.full_text_scores(value, scope.as_deref())?
+			}
+			_ => HashMap::new(),
+		};
+		let total_entries = self.count()?;
+		let mut candidate_ids: Option<HashSet<Uuid>> = None;
+		let mut steps = Vec::new();
+		let mut candidate_filters = Vec::new();
+
+		if let Some(predicate) = &trash_scope {
+			let ids = self
+				.query_ids_with_params(&format!("SELECT id FROM entries WHERE {predicate}"), [])?;
+			candidate_filters.push("trash".to_string());
+			let (before, after) =
+				Self::intersect_candidate_ids(&mut candidate_ids, ids, total_entries);
+			steps.push(QueryPlanStep {
+				strategy: QueryPlanStrategy::SqlPredicate,
+				filter: Some(QueryFilterIdentity::Trash),
+				candidates_before: before,
+				candidates_after: after,
+			});
+		}
+
+		if let Some(predicate) = &tenant_scope {
+			let ids = self
+				.query_ids_with_params(&format!("SELECT id FROM entries WHERE {predicate}"), [])?;
+			candidate_filters.push("tenant".to_string());
+			let (before, after) =
+				Self::intersect_candidate_ids(&mut candidate_ids, ids, total_entries);
+			steps.push(QueryPlanStep {
+				strategy: QueryPlanStrategy::SqlPredicate,
+				filter: Some(QueryFilterIdentity::Tenant),
+				candidates_before: before,
+				candidates_after: after,
+			});
+		}
+
+		if let Some(predicate) = &expiry_scope {
+			let ids = self
+				.query_ids_with_params(&format!("SELECT id FROM entries WHERE {predicate}"), [])?;
+			candidate_filters.push("expiry".to_string());
+			let (before, after) =
+				Self::intersect_candidate_ids(&mut candidate_ids, ids, total_entries);
+			steps.push(QueryPlanStep {
+				strategy: QueryPlanStrategy::SqlPredicate,
+				filter: Some(QueryFilterIdentity::Expiry),
+				candidates_before: before,
+				candidates_after: after,
+			});
+		}
+
+		if let Some(predicate) = &collection_scope {
+			let ids = self
+				.query_ids_with_params(&format!("SELECT id FROM entries WHERE {predicate}"), [])?;
+			candidate_filters.push("collection".to_string());
+			let (before, after) =
+				Self::intersect_candidate_ids(&mut candidate_ids, ids, total_entries);
+			steps.push(QueryPlanStep {
+				strategy: QueryPlanStrategy::SqlPredicate,
+				filter: Some(QueryFilterIdentity::Collection),
+				candidates_before: before,
+				candidates_after: after,
+			});
+		}
+
+		if let Some(ref expr_filter) = query.expression {
+			let ids = self.query_expression_ids(expr_filter)?;
+			candidate_filters.push("expression".to_string());
+			if !matches!(expr_filter, ExpressionFilter::Matches(_)) {
+				let (before, after) =
+					Self::intersect_candidate_ids(&mut candidate_ids, ids, total_entries);
+				let (strategy, filter) = match expr_filter {
+					ExpressionFilter::Equals(_) => (
+						QueryPlanStrategy::SqlPredicate,
+						QueryFilterIdentity::ExpressionEquals,
+					),
+					ExpressionFilter::Contains(_) => (
+						QueryPlanStrategy::SqlPredicate,
+						QueryFilterIdentity::ExpressionContains,
+					),
+					ExpressionFilter::StartsWith(_) => (
+						QueryPlanStrategy::SqlPredicate,
+						QueryFilterIdentity::ExpressionStartsWith,
+					),
+					ExpressionFilter::FullText(_) => (
+						QueryPlanStrategy::Fts5,
+						QueryFilterIdentity::ExpressionFullText,
+					),
+					ExpressionFilter::Matches(_) => unreachable!(),
+				};
+				steps.push(QueryPlanStep {
+					strategy,
+					filter: Some(filter),
+					candidates_before: before,
+					candidates_after: after,
+				});
+			}
+		}
+
+		if let Some(ref context_filter) = query.context {
+			let scope = scope
+				.as_ref()
+				.map(|predicate| format!(" AND {predicate}"))
+				.unwrap_or_default();
+			let ids = self.query_context_ids(context_filter, &scope)?;
+			candidate_filters.push("context".to_string());
+			let (before, after) =
+				Self::intersect_candidate_ids(&mut candidate_ids, ids, total_entries);
+			steps.push(QueryPlanStep {
+				strategy: QueryPlanStrategy::JsonPredicate,
+				filter: Some(QueryFilterIdentity::Context),
+				candidates_before: before,
+				candidates_after: after,
+			});
+		}
+
+		if let Some(ref temporal_filter) = query.temporal {
+			let ids = self.query_temporal_ids(temporal_filter)?;
+			candidate_filters.push("temporal".to_string());
+			let (before, after) =
+				Self::intersect_candidate_ids(&mut candidate_ids, ids, total_entries);
+			steps.push(QueryPlanStep {
+				strategy: QueryPlanStrategy::SqlPredicate,
+				filter: Some(QueryFilterIdentity::Temporal),
+				candidates_before: before,
+				candidates_after: after,
+			});
+		}
+
+		if let Some(ref relation_filter) = query.relations {
+			let ids = self.query_relation_ids(relation_filter)?;
+			candidate_filters.push("relations".to_string());
+			let (before, after) =
+				Self::intersect_candidate_ids(&mut candidate_ids, ids, total_entries);
+			steps.push(QueryPlanStep {
+				strategy: QueryPlanStrategy::GraphTraversal,
+				filter: Some(QueryFilterIdentity::Relations),
+				candidates_before: before,
+				candidates_after: after,
+			});
+		}
+
+		// Start with filtered entries if possible
+		let results = match candidate_ids {
+			Some(ref ids) => self.get_entries_by_ids(ids)?,
+			None => self.get_all_entries()?,
+		};
+		let candidates_loaded = results.len();
+
+		let relation_index = if query.relations.is_some() {
+			Some(self.load_relation_index()?)
+		} else {
+			None
+		};
+
+		self.finish_query(
+			query,
+			results,
+			QueryCandidates {
+				candidates_loaded,
+				relation_index,
+				lexical_scores,
+				steps,
+				candidate_filters,
+			},
+		)
+	}
+
+	fn update(&mut self, entry: &Entry) -> StorageResult<()> {
+		self.update_versioned(entry, None)
+	}
+
+	fn update_if_version(&mut self, entry: &Entry, expected_version: u64) -> StorageResult<()> {
+		self.update_versioned(entry, Some(expected_version))
+	}
+
+	fn update_batch(&mut self, entries: &[Entry]) -> StorageResult<()> {
+		if entries.is_empty() {
+			return Ok(());
+		}
+		let mut ids = HashSet::with_capacity(entries.len());
+		for entry in entries {
+			self.validate_entry(entry)?;
+			self.validate_relation_targets(entry)?;
+			if !ids.insert(entry.id) {
+				return Err(StorageError::Database(format!(
+					"Duplicate entry ID in batch: {}",
+					entry.id
+				)));
+			}
+		}
+		// Point reads report the first missing entry without scanning every ID.
+		let mut stored = Vec::with_capacity(entries.len());
+		for entry in entries {
+			let existing = self.read_scoped(entry.id)?;
+			Self::check_same_tenant(entry, &existing)?;
+			if entry.created_at != existing.created_at || entry.updated_at < existing.updated_at {
+				return Err(StorageError::Database(
+					"Updates must preserve created_at and advance updated_at monotonically"
+						.to_string(),
+				));
+			}
+			stored.push((
+				Entry {
+					version: existing.version + 1,
+					..entry.clone()
+				},
+				existing.version,
+			));
+		}
+		let established = self.check_batch_dimensions(entries)?;
+
+		let transaction = self
+			.conn
+			.transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		for (entry, expected_version) in &stored {
+			Self::update_entry_row(&transaction, entry, *expected_version)?;
+		}
+		for (entry, _) in &stored {
+			Self::write_relations(&transaction, entry)?;
+			Self::record_revision(&transaction, entry, RevisionOperation::Update)?;
+		}
+		Self::set_vector_dimensions(&transaction, &established)?;
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn patch(&mut self, id: Uuid, patch: &EntryPatch) -> StorageResult<Entry> {
+		self.patch_versioned(id, patch, None)
+	}
+
+	fn patch_if_version(
+		&mut self,
+		id: Uuid,
+		patch: &EntryPatch,
+		expected_version: u64,
+	) -> StorageResult<Entry> {
+		self.patch_versioned(id, patch, Some(expected_version))
+	}
+
+	fn delete(&mut self, id: Uuid) -> StorageResult<()> {
+		self.delete_versioned(id, None)
+	}
+
+	fn delete_if_version(&mut self, id: Uuid, expected_version: u64) -> StorageResult<()> {
+		self.delete_versioned(id, Some(expected_version))
+	}
+
+	fn delete_batch(&mut self, ids: &[Uuid]) -> StorageResult<()> {
+		if ids.is_empty() {
+			return Ok(());
+		}
+		let unique_ids: HashSet<Uuid> = ids.iter().copied().collect();
+		if unique_ids.len() != ids.len() {
+			return Err(StorageError::Database(
+				"Duplicate entry ID in delete batch".to_string(),
+			));
+		}
+		let snapshots: Vec<Entry> = ids
+			.iter()
+			.map(|id| self.read_scoped(*id))
+			.collect::<StorageResult<_>>()?;
+		if self.trash_policy()?.soft_delete {
+			return self.move_to_trash(&snapshots);
+		}
+
+		let transaction = self
+			.conn
+			.transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		for (id, snapshot) in ids.iter().zip(&snapshots) {
+			transaction
+				.execute("DELETE FROM entries WHERE id = ?1", params![id.to_string()])
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+			Self::record_revision(&transaction, snapshot, RevisionOperation::Delete)?;
+		}
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn purge_batch(&mut self, ids: &[Uuid]) -> StorageResult<()> {
+		if ids.is_empty() {
+			return Ok(());
+		}
+		let purged: HashSet<Uuid> = ids.iter().copied().collect();
+		if purged.len() != ids.len() {
+			return Err(StorageError::InvalidArgument(
+				"Duplicate entry ID in purge batch".to_string(),
+			));
+		}
+		let live_ids = self.get_entry_ids()?;
+		let mut tenants = HashMap::new();
+		for id in ids {
+			// A scoped handle only knows the history of its own tenant's entries.
+			let known: bool = if self.tenant.is_some() {
+				!self.revisions(*id)?.is_empty()
+			} else {
+				self.conn
+					.query_row(
+						"SELECT EXISTS(SELECT 1 FROM entry_revisions WHERE entry_id = ?1)
+					 OR EXISTS(SELECT 1 FROM entry_tombstones WHERE entry_id = ?1)",
+						params![id.to_string()],
+						|row| row.get(0),
+					)
+					.map_err(|error| StorageError::Database(error.to_string()))?
+			};
+			if !known && !live_ids.contains(id) {
+				return Err(StorageError::NotFound(*id));
+			}
+			tenants.insert(*id, self.purged_tenant(*id)?);
+		}
+
+		// Surviving entries that point at purged ones lose those edges.
+		let index = self.load_relation_index()?;
+		let now = Utc::now();
+		let mut sources = Vec::new();
+		let mut source_ids: Vec<&Uuid> = index
+			.adjacency
+			.iter()
+			.filter(|(from, targets)| {
+				!purged.contains(from) && targets.iter().any(|to| purged.contains(to))
+			})
+			.map(|(from, _)| from)
+			.collect();
+		source_ids.sort();
+		for source_id in source_ids {
+			let mut source = self.read_scoped(*source_id)?;
+			source.relations.retain(|to| !purged.contains(to));
+			source.updated_at = source.updated_at.max(now);
+			source.version += 1;
+			sources.push(source);
+		}
+
+		let secure_delete: i64 = self
+			.conn
+			.query_row("PRAGMA secure_delete", [], |row| row.get(0))
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		self.conn
+			.pragma_update(None, "secure_delete", 1)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let result = (|| {
+			let transaction = self
+				.conn
+				.transaction()
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+			for id in ids {
+				let id_text = id.to_string();
+				for sql in [
+					"DELETE FROM relations WHERE from_id = ?1 OR to_id = ?1",
+					"DELETE FROM entries WHERE id = ?1",
+					"DELETE FROM entry_revisions WHERE entry_id = ?1",
+				] {
+					transaction
+						.execute(sql, params![id_text])
+						.map_err(|error| StorageError::Database(error.to_string()))?;
+				}
+				let tombstoned = transaction
+					.execute(
+						"INSERT OR IGNORE INTO entry_tombstones (entry_id, purged_at, tenant)
+						 VALUES (?1, ?2, ?3)",
+						params![id_text, now.to_rfc3339(), tenants[id]],
+					)
+					.map_err(|error| StorageError::Database(error.to_string()))?;
+				if tombstoned > 0 {
+					Self::record_change(
+						&transaction,
+						*id,
+						&tenants[id],
+						ChangeOperation::Purge,
+						None,
+						now,
+					)?;
+				}
+			}
+			for source in &sources {
+				Self::touch_entry_row(&transaction, source)?;
+				Self::record_revision(&transaction, source, RevisionOperation::Unrelate)?;
+			}
+			transaction
+				.commit()
+				.map_err(|error| StorageError::Database(error.to_string()))
+		})();
+		self.conn
+			.pragma_update(None, "secure_delete", secure_delete)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		result
+	}
+
+	fn changes_since(&self, seq: u64, limit: usize) -> StorageResult<Vec<Change>> {
+		if limit == 0 {
+			return Err(StorageError::InvalidArgument(
+				"Change limit must be greater than zero".to_string(),
+			));
+		}
+		let seq = i64::try_from(seq).map_err(|_| {
+			StorageError::InvalidArgument(format!("Change sequence {seq} is out of range"))
+		})?;
+		let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+		let mut statement = self
+			.conn
+			.prepare(
+				"SELECT seq, entry_id, operation, revision_id, recorded_at
+				 FROM change_log WHERE seq > ?1 AND (?3 IS NULL OR tenant = ?3)
+				 ORDER BY seq LIMIT ?2",
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let rows = statement
+			.query_map(params![seq, limit, self.tenant], |row| {
+				Ok((
+					row.get::<_, i64>(0)?,
+					row.get::<_, String>(1)?,
+					row.get::<_, String>(2)?,
+					row.get::<_, Option<String>>(3)?,
+					row.get::<_, String>(4)?,
+				))
+			})
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let mut changes = Vec::new();
+		for row in rows {
+			let (seq, entry_id, operation, revision_id, recorded_at) =
+				row.map_err(|error| StorageError::Database(error.to_string()))?;
+			changes.push(Change {
+				seq: seq as u64,
+				entry_id: Uuid::parse_str(&entry_id)
+					.map_err(|error| StorageError::Database(error.to_string()))?,
+				operation: Self::parse_change_operation(&operation)?,
+				revision_id: revision_id
+					.map(|id| Uuid::parse_str(&id))
+					.transpose()
+					.map_err(|error| StorageError::Database(error.to_string()))?,
+				recorded_at: DateTime::parse_from_rfc3339(&recorded_at)
+					.map_err(|error| StorageError::Database(error.to_string()))?
+					.with_timezone(&Utc),
+			});
+		}
+		Ok(changes)
+	}
+
+	fn tombstones(&self) -> StorageResult<Vec<Tombstone>> {
+		let mut statement = self
+			.conn
+			.prepare(
+				"SELECT entry_id, purged_at FROM entry_tombstones
+				 WHERE ?1 IS NULL OR tenant = ?1
+				 ORDER BY purged_at, entry_id",
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let rows = statement
+			.query_map(params![self.tenant], |row| {
+				Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+			})
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let mut tombstones = Vec::new();
+		for row in rows {
+			let (entry_id, purged_at) =
+				row.map_err(|error| StorageError::Database(error.to_string()))?;
+			tombstones.push(Tombstone {
+				entry_id: Uuid::parse_str(&entry_id)
+					.map_err(|error| StorageError::Database(error.to_string()))?,
+				purged_at: DateTime::parse_from_rfc3339(&purged_at)
+					.map_err(|error| StorageError::Database(error.to_string()))?
+					.with_timezone(&Utc),
+			});
+		}
+		Ok(tombstones)
+	}
+
+	fn vacuum(&mut self) -> StorageResult<()> {
+		// Merge FTS5 segments first so deleted postings are rewritten away.
+		self.conn
+			.execute(
+				"INSERT INTO entries_fts(entries_fts) VALUES ('optimize')",
+				[],
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		self.conn
+			.execute_batch("VACUUM")
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		self.conn
+			.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn expired_ids(&self, now: DateTime<Utc>, limit: usize) -> StorageResult<Vec<Uuid>> {
+		let mut statement = self
+			.conn
+			.prepare(
+				"SELECT id FROM entries
+				 WHERE expires_at <= ?1 AND deleted_at IS NULL AND (?2 IS NULL OR tenant = ?2)
+				 ORDER BY expires_at, id
+				 LIMIT ?3",
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let rows = statement
+			.query_map(
+				params![now.to_rfc3339(), self.tenant, limit as i64],
+				|row| {
+					let id: String = row.get(0)?;
+					Uuid::parse_str(&id).map_err(|_| rusqlite::Error::InvalidQuery)
+				},
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		rows.map(|row| row.map_err(|error| StorageError::Database(error.to_string())))
+			.collect()
+	}
+
+	fn count(&self) -> StorageResult<usize> {
+		let count: i64 = self
+			.conn
+			.query_row(
+				"SELECT COUNT(*) FROM entries
+				 WHERE deleted_at IS NULL AND (?1 IS NULL OR tenant = ?1)",
+				params![self.tenant],
+				|row| row.get(0),
+			)
+			.map_err(|e| StorageError::Database(e.to_string()))?;
+		Ok(count as usize)
+	}
+
+	fn integrity_check(&self) -> StorageResult<IntegrityReport> {
+		let mut report = IntegrityReport::default();
+		let quick_check: String = self
+			.conn
+			.query_row("PRAGMA quick_check", [], |row| row.get(0))
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		if quick_check != "ok" {
+			report.issues.push(IntegrityIssue {
+				area: "sqlite".to_string(),
+				message: quick_check,
+			});
+		}
+
+		let mut foreign_key_statement = self
+			.conn
+			.prepare("PRAGMA foreign_key_check")
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let foreign_key_rows = foreign_key_statement
+			.query_map([], |row| {
+				Ok((
+					row.get::<_, String>(0)?,
+					row.get::<_, i64>(1)?,
+					row.get::<_, String>(2)?,
+				))
+			})
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		for row in foreign_key_rows {
+			let (table, row_id, parent) =
+				row.map_err(|error| StorageError::Database(error.to_string()))?;
+			report.issues.push(IntegrityIssue {
+				area: "foreign_key".to_string(),
+				message: format!("{table} row {row_id} references missing {parent}"),
+			});
+		}
+		let cross_tenant_edges: usize = self
+			.conn
+			.query_row(
+				"SELECT COUNT(*)
+				 FROM relations AS relation
+				 JOIN entries AS source ON source.id = relation.from_id
+				 JOIN entries AS target ON target.id = relation.to_id
+				 WHERE source.tenant <> target.tenant",
+				[],
+				|row| row.get(0),
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		if cross_tenant_edges > 0 {
+			report.issues.push(IntegrityIssue {
+				area: "relations".to_string(),
+				message: format!(
+					"{cross_tenant_edges} relations connect entries of different tenants"
+				),
+			});
+		}
+
+		let actual_dimensions = match self.validate_existing_vectors() {
+			Ok(dimensions) => dimensions,
+			Err(error) => {
+				report.issues.push(IntegrityIssue {
+					area: "entries".to_string(),
+					message: error.to_string(),
+				});
+				HashMap::new()
+			}
+		};
+		if let Err(error) = self.validate_revisions() {
+			report.issues.push(IntegrityIssue {
+				area: "revisions".to_string(),
+				message: error.to_string(),
+			});
+		}
+		if let Err(error) = self.conn.execute(
+			"INSERT INTO entries_fts(entries_fts) VALUES ('integrity-check')",
+			[],
+		) {
+			report.issues.push(IntegrityIssue {
+				area: "full_text".to_string(),
+				message: format!("FTS5 integrity check failed: {error}"),
+			});
+		}
+		let entry_count: usize = self
+			.conn
+			.query_row("SELECT COUNT(*) FROM entries", [], |row| row.get(0))
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let search_count: usize = self
+			.conn
+			.query_row("SELECT COUNT(*) FROM entries_fts", [], |row| row.get(0))
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		if entry_count != search_count {
+			report.issues.push(IntegrityIssue {
+				area: "full_text".to_string(),
+				message: format!(
+					"Search index contains {search_count} rows for {entry_count} entries"
+				),
+			});
+		}
+		let search_mismatch_count: usize = self
+			.conn
+			.query_row(
+				"SELECT
+					(SELECT COUNT(*)
+					 FROM entries AS entry
+					 LEFT JOIN entries_fts AS search ON search.rowid = entry.rowid
+					 WHERE search.rowid IS NULL
+						OR search.id <> entry.id
+						OR search.expression <> entry.expression)
+					+
+					(SELECT COUNT(*)
+					 FROM entries_fts AS search
+					 LEFT JOIN entries AS entry ON entry.rowid = search.rowid
+					 WHERE entry.rowid IS NULL)",
+				[],
+				|row| row.get(0),
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		if search_mismatch_count > 0 {
+			report.issues.push(IntegrityIssue {
+				area: "full_text".to_string(),
+				message: format!(
+					"Search index contains {search_mismatch_count} stale or mismatched rows"
+				),
+			});
+		}
+		for collection in self.collection_names()? {
+			// Default-collection issues keep their original wording.
+			let scope = if collection == DEFAULT_COLLECTION {
+				String::new()
+			} else {
+				format!("Collection {collection}: ")
+			};
+			match self.inspect_embedding_metadata(&collection) {
+				Err(error) => report.issues.push(IntegrityIssue {
+					area: "metadata".to_string(),
+					message: format!("{scope}{error}"),
+				}),
+				Ok((_, metadata_dimension)) => {
+					let actual_dimension = actual_dimensions.get(&collection).copied();
+					if self.collection_count(&collection)? > 0 && metadata_dimension.is_none() {
+						report.issues.push(IntegrityIssue {
+							area: "metadata".to_string(),
+							message: format!(
+								"{scope}Stored entries are missing vector-dimension metadata"
+							),
+						});
+					}
+					if let (Some(metadata_dimension), Some(actual_dimension)) =
+						(metadata_dimension, actual_dimension)
+					{
+						if metadata_dimension != actual_dimension {
+							report.issues.push(IntegrityIssue {
+								area: "metadata".to_string(),
+								message: format!(
+									"{scope}Stored vector dimension {metadata_dimension} does not match data dimension {actual_dimension}"
+								),
+							});
+						}
+					}
+				}
+			}
+		}
+
+		Ok(report)
+	}
+
+	fn backup_to(&self, destination: &Path) -> StorageResult<()> {
+		if destination.exists() {
+			return Err(StorageError::Database(format!(
+				"Backup destination already exists: {}",
+				destination.display()
+			)));
+		}
+		let mut destination_connection = Connection::open(destination)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let backup = rusqlite::backup::Backup::new(&self.conn, &mut destination_connection)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		backup
+			.run_to_completion(64, Duration::from_millis(10), None)
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn embedding_profile(&self) -> StorageResult<Option<EmbeddingProfile>> {
+		self.collection_embedding_profile(DEFAULT_COLLECTION)
+	}
+
+	fn set_embedding_profile(&mut self, profile: &EmbeddingProfile) -> StorageResult<()> {
+		self.set_collection_embedding_profile(DEFAULT_COLLECTION, profile)
+	}
+
+	fn collections(&self) -> StorageResult<Vec<CollectionInfo>> {
+		let mut counts: HashMap<String, usize> = HashMap::new();
+		let mut statement = self
+			.conn
+			.prepare(
+				"SELECT collection, COUNT(*) FROM entries
+				 WHERE deleted_at IS NULL GROUP BY collection",
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let rows = statement
+			.query_map([], |row| {
+				Ok((row.get::<_, String>(0)?, row.get::<_, usize>(1)?))
+			})
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		for row in rows {
+			let (collection, count) =
+				row.map_err(|error| StorageError::Database(error.to_string()))?;
+			counts.insert(collection, count);
+		}
+		self.collection_names()?
+			.into_iter()
+			.map(|name| {
+				let (embedding_profile, _) = self.inspect_embedding_metadata(&name)?;
+				Ok(CollectionInfo {
+					entries: counts.get(&name).copied().unwrap_or(0),
+					vector_dimension: self.stored_vector_dimension(&name)?,
+					embedding_profile,
+					name,
+				})
+			})
+			.collect()
+	}
+
+	fn collection_embedding_profile(
+		&self,
+		collection: &str,
+	) -> StorageResult<Option<EmbeddingProfile>> {
+		Self::validate_name("collection", collection)?;
+		self.inspect_embedding_metadata(collection)
+			.map(|(profile, _)| profile)
+	}
+
+	fn set_collection_embedding_profile(
+		&mut self,
+		collection: &str,
+		profile: &EmbeddingProfile,
+	) -> StorageResult<()> {
+		Self::validate_name("collection", collection)?;
+		Self::validate_embedding_profile(profile)?;
+		if self
+			.stored_vector_dimension(collection)?
+			.is_some_and(|dimension| dimension != profile.dimensions)
+		{
+			return Err(StorageError::InvalidDimensions);
+		}
+		if self.collection_count(collection)? > 0 {
+			match self.collection_embedding_profile(collection)? {
+				Some(current) if current == *profile => return Ok(()),
+				Some(_) => {
+					return Err(StorageError::Database(
+						"Changing the embedding profile requires an explicit embedding migration"
+							.to_string(),
+					));
+				}
+				None => {
+					return Err(StorageError::Database(
+						"Populated legacy data must be explicitly adopted before assigning an embedding profile"
+							.to_string(),
+					));
+				}
+			}
+		}
+
+		let transaction = self
+			.conn
+			.transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Self::write_embedding_profile(&transaction, collection, profile)?;
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn adopt_legacy_embedding_profile(&mut self, profile: &EmbeddingProfile) -> StorageResult<()> {
+		Self::validate_embedding_profile(profile)?;
+		if self.collection_count(DEFAULT_COLLECTION)? == 0 {
+			return Err(StorageError::Database(
+				"There is no legacy embedding data to adopt".to_string(),
+			));
+		}
+		if self.embedding_profile()?.is_some() {
+			return Err(StorageError::Database(
+				"The database already has an embedding profile".to_string(),
+			));
+		}
+		if self
+			.validate_existing_vectors()?
+			.get(DEFAULT_COLLECTION)
+			.copied() != Some(profile.dimensions)
+		{
+			return Err(StorageError::InvalidDimensions);
+		}
+
+		let transaction = self
+			.conn
+			.transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Self::write_embedding_profile(&transaction, DEFAULT_COLLECTION, profile)?;
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn migrate_embeddings(
+		&mut self,
+		profile: &EmbeddingProfile,
+		replacements: &[(Uuid, Vec<f32>)],
+	) -> StorageResult<()> {
+		self.migrate_collection_embeddings(DEFAULT_COLLECTION, profile, replacements)
+	}
+
+	fn migrate_collection_embeddings(
+		&mut self,
+		collection: &str,
+		profile: &EmbeddingProfile,
+		replacements: &[(Uuid, Vec<f32>)],
+	) -> StorageResult<()> {
+		Self::validate_name("collection", collection)?;
+		Self::validate_embedding_profile(profile)?;
+		let mut entries = self.get_all_entries()?;
+		entries.retain(|entry| entry.collection == collection);
+		if replacements.len() != entries.len() {
+			return Err(StorageError::Database(format!(
+				"Embedding migration requires {} replacement vectors, received {}",
+				entries.len(),
+				replacements.len()
+			)));
+		}
+
+		let entry_ids: HashSet<Uuid> = entries.iter().map(|entry| entry.id).collect();
+		let mut replacement_vectors = HashMap::with_capacity(replacements.len());
+		for (id, vector) in replacements {
+			if !entry_ids.contains(id) {
+				return Err(StorageError::NotFound(*id));
+			}
+			Self::validate_vector(vector)?;
+			if vector.len() != profile.dimensions {
+				return Err(StorageError::InvalidDimensions);
+			}
+			if replacement_vectors.insert(*id, vector.clone()).is_some() {
+				return Err(StorageError::Database(format!(
+					"Duplicate replacement vector for entry {id}"
+				)));
+			}
+		}
+		if let Some(missing) = entry_ids
+			.iter()
+			.find(|id| !replacement_vectors.contains_key(id))
+		{
+			return Err(StorageError::Database(format!(
+				"Missing replacement vector for entry {missing}"
+			)));
+		}
+
+		let migration_time = Utc::now();
+		let updated_entries: Vec<Entry> = entries
+			.into_iter()
+			.map(|mut entry| {
+				entry.meaning = replacement_vectors
+					.remove(&entry.id)
+					.expect("replacement coverage was validated");
+				if entry.updated_at < migration_time {
+					entry.updated_at = migration_time;
+				}
+				entry.version += 1;
+				entry
+			})
+			.collect();
+
+		let transaction = self
+			.conn
+			.transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		for entry in &updated_entries {
+			let meaning = vector_codec::serialize(&entry.meaning).map_err(|error| {
+				StorageError::Database(format!("Failed to serialize vector: {error}"))
+			})?;
+			let updated = transaction
+				.execute(
+					"UPDATE entries SET meaning = ?1, updated_at = ?2, version = ?3
+					 WHERE id = ?4 AND version = ?5",
+					params![
+						meaning,
+						entry.updated_at.to_rfc3339(),
+						entry.version as i64,
+						entry.id.to_string(),
+						(entry.version - 1) as i64,
+					],
+				)
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+			Self::check_version_write(&transaction, entry.id, entry.version - 1, updated)?;
+			Self::record_revision(&transaction, entry, RevisionOperation::Update)?;
+		}
+		Self::write_embedding_profile(&transaction, collection, profile)?;
+		transaction
+			.execute(
+				"INSERT INTO contextdb_metadata (key, value) VALUES (?1, ?2)
+				 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+				params![
+					Self::metadata_key(collection, "embeddings_migrated_at"),
+					migration_time.to_rfc3339()
+				],
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn revisions(&self, id: Uuid) -> StorageResult<Vec<EntryRevision>> {
+		let mut revisions = self.load_revisions(Some(id))?;
+		// Entries never change tenant, so every revision shares the entry's scope.
+		revisions.retain(|stored| self.in_scope(&stored.revision.snapshot));
+		revisions.sort_by_key(StoredRevision::order_key);
+		Ok(revisions
+			.into_iter()
+			.map(|stored| stored.revision)
+			.collect())
+	}
+
+	fn retention_policy(&self) -> StorageResult<RetentionPolicy> {
+		match self.metadata_value("revision_retention")? {
+			Some(policy) => Ok(serde_json::from_str(&policy)?),
+			None => Ok(RetentionPolicy::default()),
+		}
+	}
+
+	fn set_retention_policy(&mut self, policy: &RetentionPolicy) -> StorageResult<()> {
+		Self::validate_retention_policy(policy)?;
+		self.conn
+			.execute(
+				"INSERT INTO contextdb_metadata (key, value) VALUES ('revision_retention', ?1)
+				 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+				params![serde_json::to_string(policy)?],
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(())
+	}
+
+	fn trash_policy(&self) -> StorageResult<TrashPolicy> {
+		match self.metadata_value("trash_policy")? {
+			Some(policy) => Ok(serde_json::from_str(&policy)?),
+			None => Ok(TrashPolicy::default()),
+		}
+	}
+
+	fn set_trash_policy(&mut self, policy: &TrashPolicy) -> StorageResult<()> {
+		self.conn
+			.execute(
+				"INSERT INTO contextdb_metadata (key, value) VALUES ('trash_policy', ?1)
+				 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+				params![serde_json::to_string(policy)?],
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(())
+	}
+
+	fn access_policy(&self) -> StorageResult<AccessPolicy> {
+		match self.metadata_value("access_policy")? {
+			Some(policy) => Ok(serde_json::from_str(&policy)?),
+			None => Ok(AccessPolicy::default()),
+		}
+	}
+
+	fn set_access_policy(&mut self, policy: &AccessPolicy) -> StorageResult<()> {
+		self.conn
+			.execute(
+				"INSERT INTO contextdb_metadata (key, value) VALUES ('access_policy', ?1)
+				 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+				params![serde_json::to_string(policy)?],
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(())
+	}
+
+	fn record_access(&self, ids: &[Uuid]) -> StorageResult<()> {
+		if ids.is_empty() || !self.access_policy()?.track {
+			return Ok(());
+		}
+		let accessed_at = Utc::now().to_rfc3339();
+		let transaction = self
+			.conn
+			.unchecked_transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		{
+			// Entries gone since the read, such as those returned by `as_of`, are skipped.
+			let mut statement = transaction
+				.prepare(
+					"INSERT INTO entry_access (entry_id, last_accessed_at, access_count)
+					 SELECT id, ?2, 1 FROM entries WHERE id = ?1
+					 ON CONFLICT(entry_id) DO UPDATE SET
+						last_accessed_at = excluded.last_accessed_at,
+						access_count = access_count + 1",
+				)
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+			for id in ids {
+				statement
+					.execute(params![id.to_string(), accessed_at])
+					.map_err(|error| StorageError::Database(error.to_string()))?;
+			}
+		}
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn access_stats(&self, id: Uuid) -> StorageResult<Option<AccessStats>> {
+		self.read_scoped(id)?;
+		let (last_accessed_at, access_count): (String, i64) = match self.conn.query_row(
+			"SELECT last_accessed_at, access_count FROM entry_access WHERE entry_id = ?1",
+			params![id.to_string()],
+			|row| Ok((row.get(0)?, row.get(1)?)),
+		) {
+			Ok(row) => row,
+			Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+			Err(error) => return Err(StorageError::Database(error.to_string())),
+		};
+		Ok(Some(AccessStats {
+			entry_id: id,
+			last_accessed_at: DateTime::parse_from_rfc3339(&last_accessed_at)
+				.map_err(|error| StorageError::Database(error.to_string()))?
+				.with_timezone(&Utc),
+			access_count: access_count as u64,
+		}))
+	}
+
+	fn never_accessed(&self) -> StorageResult<Vec<Entry>> {
+		let mut statement = self
+			.conn
+			.prepare(
+				"SELECT id FROM entries
+				 WHERE deleted_at IS NULL AND (?1 IS NULL OR tenant = ?1)
+					AND (expires_at IS NULL OR expires_at > ?2)
+					AND NOT EXISTS (SELECT 1 FROM entry_access WHERE entry_id = entries.id)
+				 ORDER BY created_at, id",
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let rows = statement
+			.query_map(params![self.tenant, Utc::now().to_rfc3339()], |row| {
+				row.get::<_, String>(0)
+			})
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let mut entries = Vec::new();
+		for row in rows {
+			let id = row.map_err(|error| StorageError::Database(error.to_string()))?;
+			let id =
+				Uuid::parse_str(&id).map_err(|error| StorageError::Database(error.to_string()))?;
+			entries.push(Self::read_entry(&self.conn, id)?);
+		}
+		Ok(entries)
+	}
+
+	fn record_feedback(
+		&mut self,
+		query_fingerprint: &str,
+		entry_id: Uuid,
+		signal: FeedbackSignal,
+	) -> StorageResult<Feedback> {
+		if query_fingerprint.trim().is_empty() {
+			return Err(StorageError::InvalidArgument(
+				"Query fingerprint cannot be empty".to_string(),
+			));
+		}
+		self.get(entry_id)?;
+		let feedback = Feedback {
+			query_fingerprint: query_fingerprint.to_string(),
+			entry_id,
+			signal,
+			recorded_at: Utc::now(),
+		};
+		self.conn
+			.execute(
+				"INSERT INTO entry_feedback (query_fingerprint, entry_id, signal, recorded_at)
+				 VALUES (?1, ?2, ?3, ?4)",
+				params![
+					feedback.query_fingerprint,
+					entry_id.to_string(),
+					Self::feedback_signal_name(signal),
+					feedback.recorded_at.to_rfc3339(),
+				],
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(feedback)
+	}
+
+	fn feedback(&self, entry_id: Uuid) -> StorageResult<Vec<Feedback>> {
+		self.read_scoped(entry_id)?;
+		let mut statement = self
+			.conn
+			.prepare(
+				"SELECT query_fingerprint, signal, recorded_at FROM entry_feedback
+				 WHERE entry_id = ?1 ORDER BY id",
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let rows = statement
+			.query_map(params![entry_id.to_string()], |row| {
+				Ok((
+					row.get::<_, String>(0)?,
+					row.get::<_, String>(1)?,
+					row.get::<_, String>(2)?,
+				))
+			})
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let mut feedback = Vec::new();
+		for row in rows {
+			let (query_fingerprint, signal, recorded_at) =
+				row.map_err(|error| StorageError::Database(error.to_string()))?;
+			let signal = match signal.as_str() {
+				"positive" => FeedbackSignal::Positive,
+				"negative" => FeedbackSignal::Negative,
+				other => {
+					return Err(StorageError::Database(format!(
+						"Unknown feedback signal: {other}"
+					)))
+				}
+			};
+			feedback.push(Feedback {
+				query_fingerprint,
+				entry_id,
+				signal,
+				recorded_at: DateTime::parse_from_rfc3339(&recorded_at)
+					.map_err(|error| StorageError::Database(error.to_string()))?
+					.with_timezone(&Utc),
+			});
+		}
+		Ok(feedback)
+	}
+
+	fn dedup_policy(&self) -> StorageResult<DedupPolicy> {
+		Self::dedup_policy_in(&self.conn)
+	}
+
+	fn set_dedup_policy(&mut self, policy: &DedupPolicy) -> StorageResult<()> {
+		let transaction = self
+			.conn
+			.transaction_with_behavior(TransactionBehavior::Immediate)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		transaction
+			.execute(
+				"INSERT INTO contextdb_metadata (key, value) VALUES ('dedup_policy', ?1)
+				 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+				params![serde_json::to_string(policy)?],
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		// Clear every hash first so rehashing never trips over a stale one.
+		transaction
+			.execute("UPDATE entries SET content_hash = NULL", [])
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		if policy.enabled {
+			let entries = {
+				let mut statement = transaction
+					.prepare(&format!(
+						"SELECT {ENTRY_COLUMNS} FROM entries ORDER BY created_at, id"
+					))
+					.map_err(|error| StorageError::Database(error.to_string()))?;
+				let rows = statement
+					.query_map([], Self::read_entry_row)
+					.map_err(|error| StorageError::Database(error.to_string()))?;
+				let mut entries = Vec::new();
+				for row in rows {
+					let row = row.map_err(|error| StorageError::Database(error.to_string()))?;
+					entries.push(Self::entry_from_row(row)?);
+				}
+				entries
+			};
+			// Expired entries keep no hash, as if written after they expired.
+			let now = Utc::now();
+			for entry in entries.iter().filter(|entry| !entry.is_expired_at(now)) {
+				Self::write_content_hash(&transaction, entry)?;
+			}
+		}
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn find_near_duplicates(&self, threshold: f32) -> StorageResult<Vec<NearDuplicateCluster>> {
+		if !threshold.is_finite() || !(0.0..=1.0).contains(&threshold) {
+			return Err(StorageError::InvalidArgument(
+				"Similarity threshold must be finite and between 0 and 1".to_string(),
+			));
+		}
+		let entries: Vec<Entry> = self
+			.query(&Query::new())?
+			.into_iter()
+			.map(|result| result.entry)
+			.collect();
+		Ok(cluster_near_duplicates(&entries, threshold))
+	}
+
+	fn merge(&mut self, ids: &[Uuid], strategy: MergeStrategy) -> StorageResult<Entry> {
+		let merged_ids: HashSet<Uuid> = ids.iter().copied().collect();
+		if ids.len() < 2 || merged_ids.len() != ids.len() {
+			return Err(StorageError::InvalidArgument(
+				"A merge needs at least two distinct entries".to_string(),
+			));
+		}
+		let entries: Vec<Entry> = ids
+			.iter()
+			.map(|id| self.get(*id))
+			.collect::<StorageResult<_>>()?;
+		if entries.iter().any(|entry| {
+			entry.tenant != entries[0].tenant || entry.collection != entries[0].collection
+		}) {
+			return Err(StorageError::InvalidArgument(
+				"Merged entries must share a tenant and collection".to_string(),
+			));
+		}
+		let survivor_index = strategy.survivor(&entries);
+		let removed: Vec<&Entry> = entries
+			.iter()
+			.enumerate()
+			.filter(|(index, _)| *index != survivor_index)
+			.map(|(_, entry)| entry)
+			.collect();
+
+		// Removed entries are applied in order and the survivor last, so its
+		// own values win where contexts disagree. A null context adds nothing.
+		let mut survivor = entries[survivor_index].clone();
+		let mut context = serde_json::Value::Null;
+		for entry in removed.iter().copied().chain([&survivor]) {
+			if !entry.context.is_null() {
+				merge_patch(&mut context, &entry.context);
+			}
+		}
+		let mut relations = Vec::new();
+		for target in survivor
+			.relations
+			.iter()
+			.chain(removed.iter().flat_map(|entry| &entry.relations))
+		{
+			if !merged_ids.contains(target) && !relations.contains(target) {
+				relations.push(*target);
+			}
+		}
+		survivor.context = context;
+		survivor.relations = relations;
+		survivor.version += 1;
+		survivor.updated_at = survivor.updated_at.max(Utc::now());
+		let expected_version = survivor.version - 1;
+
+		let removed_ids: Vec<String> = removed.iter().map(|entry| entry.id.to_string()).collect();
+		let placeholders = vec!["?"; removed_ids.len()].join(", ");
+		let mut sources = Vec::new();
+		{
+			let mut statement = self
+				.conn
+				.prepare(&format!(
+					"SELECT DISTINCT relation.from_id FROM relations AS relation
+					 JOIN entries AS source ON source.id = relation.from_id
+					 WHERE relation.to_id IN ({placeholders}) AND source.deleted_at IS NULL
+					 ORDER BY relation.from_id"
+				))
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+			let rows = statement
+				.query_map(rusqlite::params_from_iter(&removed_ids), |row| {
+					row.get::<_, String>(0)
+				})
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+			for row in rows {
+				let id = row.map_err(|error| StorageError::Database(error.to_string()))?;
+				let id = Uuid::parse_str(&id)
+					.map_err(|error| StorageError::Database(error.to_string()))?;
+				if !merged_ids.contains(&id) {
+					sources.push(Self::read_entry(&self.conn, id)?);
+				}
+			}
+		}
+		let soft_delete = self.trash_policy()?.soft_delete;
+		let now = Utc::now();
+
+		let transaction = self
+			.conn
+			.transaction_with_behavior(TransactionBehavior::Immediate)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		for source in &mut sources {
+			let mut redirected = Vec::with_capacity(source.relations.len());
+			for target in &source.relations {
+				let target = match merged_ids.contains(target) {
+					true => survivor.id,
+					false => *target,
+				};
+				if !redirected.contains(&target) {
+					redirected.push(target);
+				}
+			}
+			source.relations = redirected;
+			source.updated_at = source.updated_at.max(now);
+			source.version += 1;
+			Self::touch_entry_row(&transaction, source)?;
+			Self::write_relations(&transaction, source)?;
+			Self::record_revision(&transaction, source, RevisionOperation::Relate)?;
+		}
+		let removed: Vec<Entry> = removed.into_iter().cloned().collect();
+		if soft_delete {
+			Self::trash_rows(&transaction, &removed)?;
+		} else {
+			for entry in &removed {
+				let rows_affected = transaction
+					.execute(
+						"DELETE FROM entries WHERE id = ?1 AND version = ?2",
+						params![entry.id.to_string(), entry.version as i64],
+					)
+					.map_err(|error| StorageError::Database(error.to_string()))?;
+				Self::check_version_write(&transaction, entry.id, entry.version, rows_affected)?;
+				Self::record_revision(&transaction, entry, RevisionOperation::Delete)?;
+			}
+		}
+		// Edges to trashed entries survive `write_relations`, so drop the
+		// survivor's edges to the removed entries explicitly.
+		transaction
+			.execute(
+				&format!("DELETE FROM relations WHERE from_id = ? AND to_id IN ({placeholders})"),
+				rusqlite::params_from_iter(
+					std::iter::once(survivor.id.to_string()).chain(removed_ids.iter().cloned()),
+				),
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Self::update_entry_row(&transaction, &survivor, expected_version)?;
+		Self::write_relations(&transaction, &survivor)?;
+		Self::record_revision(&transaction, &survivor, RevisionOperation::Update)?;
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(survivor)
+	}
+
+	fn list_deleted(&self) -> StorageResult<Vec<DeletedEntry>> {
+		let mut statement = self
+			.conn
+			.prepare(
+				"SELECT id, deleted_at FROM entries
+				 WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR tenant = ?1)
+				 ORDER BY deleted_at, id",
+			)
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let rows = statement
+			.query_map(params![self.tenant], |row| {
+				Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+			})
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		let mut deleted = Vec::new();
+		for row in rows {
+			let (id, deleted_at) =
+				row.map_err(|error| StorageError::Database(error.to_string()))?;
+			let id =
+				Uuid::parse_str(&id).map_err(|error| StorageError::Database(error.to_string()))?;
+			deleted.push(DeletedEntry {
+				entry: Self::read_trashed_entry(&self.conn, id)?,
+				deleted_at: DateTime::parse_from_rfc3339(&deleted_at)
+					.map_err(|error| StorageError::Database(error.to_string()))?
+					.with_timezone(&Utc),
+			});
+		}
+		Ok(deleted)
+	}
+
+	fn restore_deleted(&mut self, id: Uuid) -> StorageResult<Entry> {
+		let trashed = Self::read_trashed_entry(&self.conn, id)?;
+		if !self.in_scope(&trashed) {
+			return Err(StorageError::NotFound(id));
+		}
+		let updated_at = trashed.updated_at.max(Utc::now());
+		let transaction = self
+			.conn
+			.transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		// A live entry may have taken this content while the entry was trashed.
+		let content_hash = Self::content_hash_in(&transaction, &trashed)?;
+		Self::release_expired_content_hash(&transaction, &trashed, content_hash.as_deref())?;
+		Self::release_expired_external_id(&transaction, &trashed)?;
+		transaction
+			.execute(
+				"UPDATE entries SET deleted_at = NULL, version = version + 1, updated_at = ?1
+				 WHERE id = ?2",
+				params![updated_at.to_rfc3339(), id.to_string()],
+			)
+			.map_err(|error| {
+				Self::unique_write_error(&transaction, &trashed, content_hash.as_deref(), error)
+			})?;
+		// Reading after the update picks up edges in both directions again.
+		let entry = Self::read_entry(&transaction, id)?;
+		Self::record_revision(&transaction, &entry, RevisionOperation::Revert)?;
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(entry)
+	}
+
+	fn remove_from_trash(&mut self, ids: &[Uuid]) -> StorageResult<()> {
+		let unique_ids: HashSet<Uuid> = ids.iter().copied().collect();
+		if unique_ids.len() != ids.len() {
+			return Err(StorageError::InvalidArgument(
+				"Duplicate entry ID in trash batch".to_string(),
+			));
+		}
+		for id in ids {
+			let trashed = Self::read_trashed_entry(&self.conn, *id)?;
+			if !self.in_scope(&trashed) {
+				return Err(StorageError::NotFound(*id));
+			}
+		}
+		let transaction = self
+			.conn
+			.transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		for id in ids {
+			let id = id.to_string();
+			transaction
+				.execute(
+					"DELETE FROM relations WHERE from_id = ?1 OR to_id = ?1",
+					params![id],
+				)
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+			transaction
+				.execute(
+					"DELETE FROM entries WHERE id = ?1 AND deleted_at IS NOT NULL",
+					params![id],
+				)
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+		}
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))
+	}
+
+	fn compact_revisions_with(
+		&mut self,
+		policy: &RetentionPolicy,
+	) -> StorageResult<CompactionReport> {
+		Self::validate_retention_policy(policy)?;
+		let revisions = self.load_revisions(None)?;
+		let cutoff = policy
+			.keep_for_days
+			.map(|days| Utc::now() - chrono::Duration::days(i64::from(days)));
+		let mut report = CompactionReport {
+			revisions_examined: revisions.len(),
+			bytes_before: revisions.iter().map(|stored| stored.stored_bytes).sum(),
+			..CompactionReport::default()
+		};
+
+		let mut removed = Vec::new();
+		let mut rewritten = Vec::new();
+		let mut horizons: HashMap<String, DateTime<Utc>> = HashMap::new();
+		let mut history: Vec<StoredRevision> = Vec::new();
+		let mut revisions = revisions.into_iter().peekable();
+		while let Some(stored) = revisions.next() {
+			let entry_id = stored.revision.entry_id;
+			history.push(stored);
+			if revisions
+				.peek()
+				.is_some_and(|next| next.revision.entry_id == entry_id)
+			{
+				continue;
+			}
+
+			// Retention ranks revisions chronologically; deltas follow rowid order.
+			let count = history.len();
+			let mut chronological: Vec<usize> = (0..count).collect();
+			chronological.sort_by_key(|index| history[*index].order_key());
+			let mut ranks = vec![0; count];
+			for (position, index) in chronological.iter().enumerate() {
+				ranks[*index] = position;
+			}
+			let mut kept = vec![false; count];
+			let mut previous: Option<&StoredRevision> = None;
+			let mut previous_row_kept = false;
+			let mut chain = 0;
+			for (index, (stored, position)) in history.iter().zip(ranks).enumerate() {
+				let unbounded = policy.keep_last.is_none() && cutoff.is_none();
+				let keep = position + 1 == count
+					|| unbounded || policy
+					.keep_last
+					.is_some_and(|last| position + last >= count)
+					|| cutoff.is_some_and(|cutoff| stored.revision.recorded_at >= cutoff)
+					|| (policy.keep_deletes
+						&& stored.revision.operation == RevisionOperation::Delete);
+				if !keep {
+					removed.push(stored.rowid);
+					previous_row_kept = false;
+					continue;
+				}
+				kept[index] = true;
+				let base_kept = previous_row_kept;
+				previous_row_kept = true;
+				// An edge edit whose base survives is already as small as it gets.
+				if stored.relations_only && base_kept && chain < MAX_DELTA_CHAIN {
+					chain += 1;
+					report.bytes_after += stored.stored_bytes;
+					previous = Some(stored);
+					continue;
+				}
+
+				let full = serde_json::to_string(&stored.document)?;
+				let delta = match previous {
+					Some(base) if policy.delta_encoding && chain < MAX_DELTA_CHAIN => Some(
+						serde_json::to_string(&json_patch(&base.document, &stored.document))?,
+					)
+					.filter(|delta| delta.len() < full.len()),
+					_ => None,
+				};
+				chain = if delta.is_some() { chain + 1 } else { 0 };
+				let (encoding, snapshot) = match delta {
+					Some(delta) => ("delta", delta),
+					None => ("full", full),
+				};
+				report.bytes_after += snapshot.len();
+				report.delta_revisions += usize::from(encoding == "delta");
+				if stored.delta || encoding == "delta" {
+					rewritten.push((stored.rowid, encoding, snapshot));
+				}
+				previous = Some(stored);
+			}
+			// State between a pruned revision and the next kept one is lost, so
+			// `as_of` can only replay from that kept revision on.
+			let mut pruned = false;
+			for index in chronological {
+				if !kept[index] {
+					pruned = true;
+				} else if pruned {
+					let revision = &history[index].revision;
+					let horizon = horizons
+						.entry(revision.snapshot.tenant.clone())
+						.or_insert(revision.recorded_at);
+					*horizon = (*horizon).max(revision.recorded_at);
+					pruned = false;
+				}
+			}
+			history.clear();
+		}
+		report.revisions_removed = removed.len();
+
+		let transaction = self
+			.conn
+			.transaction()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		for rowid in removed {
+			transaction
+				.execute(
+					"DELETE FROM entry_revisions WHERE rowid = ?1",
+					params![rowid],
+				)
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+		}
+		for (rowid, encoding, snapshot) in rewritten {
+			transaction
+				.execute(
+					"UPDATE entry_revisions SET encoding = ?1, snapshot = ?2 WHERE rowid = ?3",
+					params![encoding, snapshot, rowid],
+				)
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+		}
+		for (tenant, horizon) in horizons {
+			transaction
+				.execute(
+					"INSERT INTO contextdb_metadata (key, value) VALUES (?1, ?2)
+					 ON CONFLICT(key) DO UPDATE SET value = MAX(value, excluded.value)",
+					params![format!("revision_horizon/{tenant}"), horizon.to_rfc3339()],
+				)
+				.map_err(|error| StorageError::Database(error.to_string()))?;
+		}
+		transaction
+			.commit()
+			.map_err(|error| StorageError::Database(error.to_string()))?;
+		Ok(report)
+	}
+
+	fn revert(&mut self, entry_id: Uuid, revision_id: Uuid) -> StorageResult<Entry> {
+		let revision = self
+			.revisions(entry_id)?
+			.into_iter()
+			.find(|revision| revision.revision_id == revision_id)
+			.ok_or(StorageError::NotFound(revision_id))?;
+		self.restore_revision(revision)
+	}
+
+	fn undelete(&mut self, entry_id: Uuid) -> StorageResult<Entry> {
+		if Self::is_trashed(&self.conn, entry_id)? {
+			return self.restore_deleted(entry_id);
+		}
+		if self.get_entry_ids()?.contains(&entry_id) {
+			return Err(StorageError::InvalidArgument(format!(
+				"Entry {entry_id} is not deleted"
+			)));
+		}
+		let revision = self
+			.revisions(entry_id)?
+			.pop()
+			.filter(|revision| revision.operation == RevisionOperation::Delete)
+			.ok_or(StorageError::NotFound(entry_id))?;
+		self.restore_revision(revision)
+	}
+
+	fn scope_to_tenant(&mut self, tenant: &str) -> StorageResult<()> {
+		Self::validate_name("tenant", tenant)?;
+		match &self.tenant {
+			Some(current) if current != tenant => Err(StorageError::InvalidArgument(format!(
+				"Storage is already scoped to tenant {current}"
+			))),
+			_ => {
+				self.tenant = Some(tenant.to_string());
+				Ok(())
+			}
+		}
+	}
+
+	fn tenant_scope(&self) -> Option<&str> {
+		self.tenant.as_deref()
+	}
+
+	fn create_context_index(&mut self, path: &str) -> StorageResult<String> {
+		self.create_context_index_in(None, path)
+	}
+
+	fn create_collection_context_index(
+		&mut self,
+		collection: &str,
+		path: &str,
+	) -> StorageResult<String> {
+		Self::validate_name("collection", collection)?;
+		self.create_context_index_in(Some(collection), path)
+	}
+
+	fn relate_batch(&mut self, edges: &[(Uuid, Uuid)]) -> StorageResult<()> {
+		self.change_relations(edges, RevisionOperation::Relate)
+	}
+
+	fn unrelate_batch(&mut self, edges: &[(Uuid, Uuid)]) -> StorageResult<()> {
+		self.change_relations(edges, RevisionOperation::Unrelate)
+	}
+
+	fn graph_stats(&self, top: usize) -> StorageResult<GraphStats> {
+		let ids = self.get_entry_ids()?;
+		// Unlike traversal, keep edges into the trash so they show up as
+		// dangling; scoped handles still never see another tenant's targets.
+		let tenant = match &self.tenant {
+			Some(tenant) => format!(
+				" AND f.tenant = {tenant} AND t.tenant = {tenant}",
+				tenant = Self::sql_string_literal(tenant)
+			),
+			None => String::new(),
+		};
+		let sql = format!(
+			"SELECT r.from_id, r.to_id FROM relations r
+			 JOIN entries f ON f.id = r.from_id
+			 JOIN entries t ON t.id = r.to_id
+			 WHERE f.deleted_at IS NULL{tenant}"
+		);
+		let mut stmt = self
+			.conn
+			.prepare(&sql)
+			.map_err(|e| StorageError::Database(e.to_string()))?;
+		let edges = stmt
+			.query_map([], |row| {
+				let from_id_str: String = row.get(0)?;
+				let to_id_str: String = row.get(1)?;
+				let from_id =
+					Uuid::parse_str(&from_id_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
+				let to_id =
+					Uuid::parse_str(&to_id_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
+				Ok((from_id, to_id))
+			})
+			.map_err(|e| StorageError::Database(e.to_string()))?
+			.collect::<Result<Vec<(Uuid, Uuid)>, _>>()
+			.map_err(|e| StorageError::Database(e.to_string()))?;
+		Ok(GraphStats::compute(&ids, &edges, top))
+	}
+
+	fn backend_name(&self) -> &str {
+		"SQLite"
+	}
+}
+
+struct RelationIndex {
+	adjacency: HashMap<Uuid, Vec<Uuid>>,
+	related_ids: HashSet<Uuid>,
+}
+
+impl RelationIndex {
+	fn from_entries(entries: &[Entry]) -> Self {
+		let mut adjacency = HashMap::new();
+		let mut related_ids = HashSet::new();
+		for entry in entries.iter().filter(|entry| !entry.relations.is_empty()) {
+			adjacency.insert(entry.id, entry.relations.clone());
+			related_ids.insert(entry.id);
+		}
+		Self {
+			adjacency,
+			related_ids,
+		}
+	}
+}
+
+/// A decoded revision row
+struct StoredRevision {
+	rowid: i64,
+	/// Stored relative to the previous row, as a delta or relations payload
+	delta: bool,
+	/// Stored as the relations payload of an edge edit
+	relations_only: bool,
+	stored_bytes: usize,
+	/// Decoded snapshot JSON, the base for the next delta of the same entry
+	document: serde_json::Value,
+	revision: EntryRevision,
+}
+
+impl StoredRevision {
+	/// Chronological order, with insertion order breaking timestamp ties
+	fn order_key(&self) -> (DateTime<Utc>, i64) {
+		(self.revision.recorded_at, self.rowid)
+	}
+}
+
+/// Candidates and plan state gathered before ranking and pagination
+struct QueryCandidates {
+	candidates_loaded: usize,
+	relation_index: Option<RelationIndex>,
+	lexical_scores: HashMap<Uuid, f32>,
+	steps: Vec<QueryPlanStep>,
+	candidate_filters: Vec<String>,
+}
+
+// Vectors are JSON-encoded inside the BLOB column for portable decoding.
+mod vector_codec {
+	use serde::{Deserialize, Serialize};
+
+	pub fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+		serde_json::to_vec(value).map_err(|e| e.to_string())
+	}
+
+	pub fn deserialize<T: for<'de> Deserialize<'de>>(bytes: &[u8]) -> Result<T, String> {
+		serde_json::from_slice(bytes).map_err(|e| e.to_string())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::query::{
+		ContextFilter, DecayRanking, MeaningFilter, QueryPrimaryOrder, RelationFilter,
+		TemporalFilter,
+	};
+	use chrono::TimeZone;
+	use std::collections::HashSet;
+
+	fn create_test_storage() -> SqliteStorage {
+		SqliteStorage::in_memory().unwrap()
+	}
+
+	fn create_test_entry(meaning: Vec<f32>, expression: &str) -> Entry {
+		Entry::new(meaning, expression.to_string())
+	}
+
+	// ==================== Storage Initialization Tests ====================
+
+	#[test]
+	fn test_storage_in_memory_creation() {
+		let storage = SqliteStorage::in_memory();
+		assert!(storage.is_ok());
+	}
+
+	#[test]
+	fn test_storage_backend_name() {
+		let storage = create_test_storage();
+		assert_eq!(storage.backend_name(), "SQLite");
+	}
+
+	#[test]
+	fn test_storage_initial_count_is_zero() {
+		let storage = create_test_storage();
+		assert_eq!(storage.count().unwrap(), 0);
+	}
+
+	#[test]
+	fn test_storage_initializes_schema_and_foreign_keys() {
+		let storage = create_test_storage();
+		let version: i64 = storage
+			.conn
+			.query_row("PRAGMA user_version", [], |row| row.get(0))
+			.unwrap();
+		let foreign_keys: i64 = storage
+			.conn
+			.query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+			.unwrap();
+
+		assert_eq!(version, SCHEMA_VERSION);
+		assert_eq!(foreign_keys, 1);
+	}
+
+	#[test]
+	fn test_file_storage_configures_durability_and_contention() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let storage = SqliteStorage::new(directory.path().join("configured.db")).unwrap();
+		let journal_mode: String = storage
+			.conn
+			.query_row("PRAGMA journal_mode", [], |row| row.get(0))
+			.unwrap();
+		let synchronous: i64 = storage
+			.conn
+			.query_row("PRAGMA synchronous", [], |row| row.get(0))
+			.unwrap();
+		let busy_timeout: i64 = storage
+			.conn
+			.query_row("PRAGMA busy_timeout", [], |row| row.get(0))
+			.unwrap();
+
+		assert_eq!(journal_mode, "wal");
+		assert_eq!(synchronous, 1);
+		assert_eq!(busy_timeout, 5_000);
+	}
+
+	#[test]
+	fn test_legacy_database_migrates_with_snapshot_history() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("legacy.db");
+		let entry = create_test_entry(vec![0.1, 0.2], "Legacy");
+		let connection = Connection::open(&path).unwrap();
+		connection
+			.execute_batch(
+				"CREATE TABLE entries (
+					id TEXT PRIMARY KEY, meaning BLOB NOT NULL, expression TEXT NOT NULL,
+					context TEXT NOT NULL, created_at TEXT NOT NULL, updated_at TEXT NOT NULL
+				);
+				CREATE TABLE relations (
+					from_id TEXT NOT NULL, to_id TEXT NOT NULL,
+					PRIMARY KEY (from_id, to_id)
+				);",
+			)
+			.unwrap();
+		connection
+			.execute(
+				"INSERT INTO entries VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+				params![
+					entry.id.to_string(),
+					vector_codec::serialize(&entry.meaning).unwrap(),
+					entry.expression,
+					serde_json::to_string(&entry.context).unwrap(),
+					entry.created_at.to_rfc3339(),
+					entry.updated_at.to_rfc3339(),
+				],
+			)
+			.unwrap();
+		drop(connection);
+
+		let storage = SqliteStorage::new(&path).unwrap();
+
+		assert_eq!(storage.get(entry.id).unwrap().expression, "Legacy");
+		assert_eq!(
+			storage.revisions(entry.id).unwrap()[0].operation,
+			RevisionOperation::Snapshot
+		);
+	}
+
+	#[test]
+	fn test_version_two_database_upgrades_revision_encoding() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("v2.db");
+		let entry = create_test_entry(vec![0.1], "Existing");
+		{
+			let mut storage = SqliteStorage::new(&path).unwrap();
+			storage.insert(&entry).unwrap();
+			storage
+				.conn
+				.execute_batch(
+					"ALTER TABLE entry_revisions DROP COLUMN encoding;
+					 PRAGMA user_version = 2;",
+				)
+				.unwrap();
+		}
+
+		let storage = SqliteStorage::new(&path).unwrap();
+
+		let version: i64 = storage
+			.conn
+			.query_row("PRAGMA user_version", [], |row| row.get(0))
+			.unwrap();
+		assert_eq!(version, SCHEMA_VERSION);
+		let revisions = storage.revisions(entry.id).unwrap();
+		assert_eq!(revisions.len(), 1);
+		assert_eq!(revisions[0].operation, RevisionOperation::Insert);
+	}
+
+	#[test]
+	fn test_version_three_database_upgrades_entry_versions() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("v3.db");
+		let mut entry = create_test_entry(vec![0.1], "Existing");
+		{
+			let mut storage = SqliteStorage::new(&path).unwrap();
+			storage.insert(&entry).unwrap();
+			storage
+				.conn
+				.execute_batch(
+					"ALTER TABLE entries DROP COLUMN version;
+					 PRAGMA user_version = 3;",
+				)
+				.unwrap();
+		}
+
+		let mut storage = SqliteStorage::new(&path).unwrap();
+
+		assert_eq!(storage.get(entry.id).unwrap().version, 1);
+		entry.expression = "Existing, revised".to_string();
+		entry.updated_at = Utc::now();
+		storage.update_if_version(&entry, 1).unwrap();
+		assert_eq!(storage.get(entry.id).unwrap().version, 2);
+	}
+
+	#[test]
+	fn test_version_four_database_upgrades_to_default_collection() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("v4.db");
+		let entry = create_test_entry(vec![0.1], "Existing");
+		{
+			let mut storage = SqliteStorage::new(&path).unwrap();
+			storage.insert(&entry).unwrap();
+			storage
+				.conn
+				.execute_batch(
+					"DROP INDEX idx_entries_external_id;
+					 ALTER TABLE entries DROP COLUMN external_id;
+					 DROP INDEX idx_entries_content_hash;
+					 ALTER TABLE entries DROP COLUMN content_hash;
+					 DROP INDEX idx_entries_collection;
+					 ALTER TABLE entries DROP COLUMN collection;
+					 PRAGMA user_version = 4;",
+				)
+				.unwrap();
+		}
+
+		let storage = SqliteStorage::new(&path).unwrap();
+
+		assert_eq!(
+			storage.get(entry.id).unwrap().collection,
+			DEFAULT_COLLECTION
+		);
+		assert_eq!(storage.collections().unwrap()[0].vector_dimension, Some(1));
+		assert!(storage.integrity_check().unwrap().is_healthy());
+	}
+
+	#[test]
+	fn test_version_five_database_upgrades_to_default_tenant() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("v5.db");
+		let entry = create_test_entry(vec![0.1], "Existing");
+		{
+			let mut storage = SqliteStorage::new(&path).unwrap();
+			storage.insert(&entry).unwrap();
+			storage
+				.conn
+				.execute_batch(
+					"DROP INDEX idx_entries_external_id;
+					 ALTER TABLE entries DROP COLUMN external_id;
+					 DROP INDEX idx_entries_content_hash;
+					 ALTER TABLE entries DROP COLUMN content_hash;
+					 DROP INDEX idx_entries_tenant;
+					 ALTER TABLE entries DROP COLUMN tenant;
+					 PRAGMA user_version = 5;",
+				)
+				.unwrap();
+		}
+
+		let mut storage = SqliteStorage::new(&path).unwrap();
+
+		assert_eq!(
+			storage.get(entry.id).unwrap().tenant,
+			crate::types::DEFAULT_TENANT
+		);
+		storage.scope_to_tenant("default").unwrap();
+		assert_eq!(storage.revisions(entry.id).unwrap().len(), 1);
+		assert!(storage.integrity_check().unwrap().is_healthy());
+	}
+
+	#[test]
+	fn test_version_six_database_upgrades_without_expiry() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("v6.db");
+		let entry = create_test_entry(vec![0.1], "Existing");
+		{
+			let mut storage = SqliteStorage::new(&path).unwrap();
+			storage.insert(&entry).unwrap();
+			storage
+				.conn
+				.execute_batch(
+					"DROP INDEX idx_entries_expires_at;
+					 ALTER TABLE entries DROP COLUMN expires_at;
+					 PRAGMA user_version = 6;",
+				)
+				.unwrap();
+		}
+
+		let storage = SqliteStorage::new(&path).unwrap();
+
+		assert_eq!(storage.get(entry.id).unwrap().expires_at, None);
+		assert!(storage.integrity_check().unwrap().is_healthy());
+	}
+
+	#[test]
+	fn test_version_seven_database_upgrades_without_trash() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("v7.db");
+		let entry = create_test_entry(vec![0.1], "Existing");
+		{
+			let mut storage = SqliteStorage::new(&path).unwrap();
+			storage.insert(&entry).unwrap();
+			storage
+				.conn
+				.execute_batch(
+					"DROP INDEX idx_entries_external_id;
+					 ALTER TABLE entries DROP COLUMN external_id;
+					 DROP INDEX idx_entries_content_hash;
+					 ALTER TABLE entries DROP COLUMN content_hash;
+					 DROP INDEX idx_entries_deleted_at;
+					 ALTER TABLE entries DROP COLUMN deleted_at;
+					 PRAGMA user_version = 7;",
+				)
+				.unwrap();
+		}
+
+		let storage = SqliteStorage::new(&path).unwrap();
+
+		assert_eq!(storage.count().unwrap(), 1);
+		assert!(storage.list_deleted().unwrap().is_empty());
+		assert!(storage.integrity_check().unwrap().is_healthy());
+	}
+
+	#[test]
+	fn test_soft_delete_hides_entry_and_restores_its_relations() {
+		let mut storage = create_test_storage();
+		storage
+			.set_trash_policy(&TrashPolicy {
+				soft_delete: true,
+				..TrashPolicy::default()
+			})
+			.unwrap();
+		let leaf = create_test_entry(vec![1.0], "Leaf note");
+		let middle = create_test_entry(vec![1.0], "Middle note").add_relation(leaf.id);
+		let source = create_test_entry(vec![1.0], "Source note").add_relation(middle.id);
+		storage
+			.insert_batch(&[leaf.clone(), middle.clone(), source.clone()])
+			.unwrap();
+
+		storage.delete(middle.id).unwrap();
+
+		assert!(matches!(
+			storage.get(middle.id),
+			Err(StorageError::NotFound(_))
+		));
+		assert_eq!(storage.count().unwrap(), 2);
+		assert!(storage.get(source.id).unwrap().relations.is_empty());
+		let execution = storage
+			.execute(&Query::new().with_expression(ExpressionFilter::FullText("note".into())))
+			.unwrap();
+		assert_eq!(execution.results.len(), 2);
+		assert_eq!(
+			execution.plan.steps[0].filter,
+			Some(QueryFilterIdentity::Trash)
+		);
+		assert!(matches!(
+			storage.relate(leaf.id, middle.id),
+			Err(StorageError::NotFound(id)) if id == middle.id
+		));
+		let deleted = storage.list_deleted().unwrap();
+		assert_eq!(deleted.len(), 1);
+		assert_eq!(deleted[0].entry.relations, vec![leaf.id]);
+		assert_eq!(
+			storage
+				.revisions(middle.id)
+				.unwrap()
+				.last()
+				.unwrap()
+				.operation,
+			RevisionOperation::Delete
+		);
+
+		// Updating the source keeps its hidden edge to the trashed entry.
+		let mut edited = storage.get(source.id).unwrap();
+		edited.expression = "Edited source note".to_string();
+		edited.updated_at = Utc::now();
+		storage.update(&edited).unwrap();
+
+		let restored = storage.restore_deleted(middle.id).unwrap();
+
+		assert_eq!(restored.relations, vec![leaf.id]);
+		assert_eq!(storage.get(source.id).unwrap().relations, vec![middle.id]);
+		assert_eq!(
+			storage
+				.revisions(middle.id)
+				.unwrap()
+				.last()
+				.unwrap()
+				.operation,
+			RevisionOperation::Revert
+		);
+		assert!(storage.list_deleted().unwrap().is_empty());
+		assert!(storage.integrity_check().unwrap().is_healthy());
+	}
+
+	#[test]
+	fn test_remove_from_trash_deletes_rows_but_keeps_history() {
+		let mut storage = create_test_storage();
+		storage
+			.set_trash_policy(&TrashPolicy {
+				soft_delete: true,
+				..TrashPolicy::default()
+			})
+			.unwrap();
+		let kept = create_test_entry(vec![1.0], "Kept");
+		let trashed = create_test_entry(vec![1.0], "Trashed").add_relation(kept.id);
+		let undeleted = create_test_entry(vec![1.0], "Undeleted");
+		storage
+			.insert_batch(&[kept.clone(), trashed.clone(), undeleted.clone()])
+			.unwrap();
+		storage.delete_batch(&[trashed.id, undeleted.id]).unwrap();
+
+		assert!(matches!(
+			storage.remove_from_trash(&[kept.id]),
+			Err(StorageError::NotFound(id)) if id == kept.id
+		));
+		assert_eq!(storage.undelete(undeleted.id).unwrap().id, undeleted.id);
+		storage.remove_from_trash(&[trashed.id]).unwrap();
+
+		assert!(storage.list_deleted().unwrap().is_empty());
+		assert_eq!(storage.count().unwrap(), 2);
+		assert_eq!(storage.revisions(trashed.id).unwrap().len(), 2);
+		let relations: i64 = storage
+			.conn
+			.query_row("SELECT COUNT(*) FROM relations", [], |row| row.get(0))
+			.unwrap();
+		assert_eq!(relations, 0);
+	}
+
+	#[test]
+	fn test_version_eight_database_upgrades_without_content_hashes() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("v8.db");
+		let entry = create_test_entry(vec![0.1], "Existing");
+		{
+			let mut storage = SqliteStorage::new(&path).unwrap();
+			storage.insert(&entry).unwrap();
+			storage
+				.conn
+				.execute_batch(
+					"DROP INDEX idx_entries_external_id;
+					 ALTER TABLE entries DROP COLUMN external_id;
+					 DROP INDEX idx_entries_content_hash;
+					 ALTER TABLE entries DROP COLUMN content_hash;
+					 PRAGMA user_version = 8;",
+				)
+				.unwrap();
+		}
+
+		let mut storage = SqliteStorage::new(&path).unwrap();
+
+		assert_eq!(storage.dedup_policy().unwrap(), DedupPolicy::default());
+		storage
+			.set_dedup_policy(&DedupPolicy {
+				enabled: true,
+				..DedupPolicy::default()
+			})
+			.unwrap();
+		let duplicate = create_test_entry(vec![0.2], "existing");
+		assert_eq!(
+			storage.insert_batch_returning_ids(&[duplicate]).unwrap(),
+			vec![entry.id]
+		);
+		assert!(storage.integrity_check().unwrap().is_healthy());
+	}
+
+	#[test]
+	fn test_version_nine_database_upgrades_without_external_ids() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("v9.db");
+		let entry = create_test_entry(vec![0.1], "Existing");
+		{
+			let mut storage = SqliteStorage::new(&path).unwrap();
+			storage.insert(&entry).unwrap();
+			storage
+				.conn
+				.execute_batch(
+					"DROP INDEX idx_entries_external_id;
+					 ALTER TABLE entries DROP COLUMN external_id;
+					 PRAGMA user_version = 9;",
+				)
+				.unwrap();
+		}
+
+		let storage = SqliteStorage::new(&path).unwrap();
+
+		assert_eq!(storage.get(entry.id).unwrap().external_id, None);
+		assert!(storage.get_by_external_id("TICKET-1").unwrap().is_none());
+		assert!(storage.integrity_check().unwrap().is_healthy());
+	}
+
+	#[test]
+	fn test_version_ten_database_upgrades_without_importance() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("v10.db");
+		let entry = create_test_entry(vec![0.1], "Existing");
+		{
+			let mut storage = SqliteStorage::new(&path).unwrap();
+			storage.insert(&entry).unwrap();
+			storage
+				.conn
+				.execute_batch(
+					"ALTER TABLE entries DROP COLUMN importance;
+					 PRAGMA user_version = 10;",
+				)
+				.unwrap();
+		}
+
+		let mut storage = SqliteStorage::new(&path).unwrap();
+		let rated = storage.get(entry.id).unwrap().with_importance(0.75);
+		storage.update(&rated).unwrap();
+
+		assert_eq!(storage.get(entry.id).unwrap().importance, Some(0.75));
+		assert!(storage.integrity_check().unwrap().is_healthy());
+	}
+
+	#[test]
+	fn test_external_ids_are_unique_per_tenant_and_drive_upserts() {
+		let mut storage = create_test_storage();
+		let ticket = create_test_entry(vec![1.0], "Login fails").with_external_id("TICKET-1");
+		storage.insert(&ticket).unwrap();
+		let other_tenant = create_test_entry(vec![1.0], "Other login")
+			.for_tenant("acme")
+			.with_external_id("TICKET-1");
+		storage.insert(&other_tenant).unwrap();
+
+		assert_eq!(
+			storage.get_by_external_id("TICKET-1").unwrap().unwrap().id,
+			ticket.id
+		);
+		assert!(storage.get_by_external_id("TICKET-2").unwrap().is_none());
+		let clash = create_test_entry(vec![1.0], "Clash").with_external_id("TICKET-1");
+		assert!(matches!(
+			storage.insert(&clash),
+			Err(StorageError::Duplicate { id, existing })
+				if id == clash.id && existing == ticket.id
+		));
+		assert!(matches!(
+			storage.insert(&create_test_entry(vec![1.0], "Blank").with_external_id(" ")),
+			Err(StorageError::InvalidArgument(_))
+		));
+
+		let mut revised =
+			create_test_entry(vec![1.0], "Login fails on Safari").with_external_id("TICKET-1");
+		revised.updated_at = Utc::now();
+		let fresh = create_test_entry(vec![1.0], "Export is slow").with_external_id("TICKET-2");
+		let outcomes = storage
+			.upsert_batch_by_external_id(&[revised.clone(), fresh.clone()])
+			.unwrap();
+
+		assert_eq!(
+			outcomes,
+			vec![
+				(ticket.id, UpsertOutcome::Updated),
+				(fresh.id, UpsertOutcome::Inserted)
+			]
+		);
+		let stored = storage.get(ticket.id).unwrap();
+		assert_eq!(stored.expression, "Login fails on Safari");
+		assert_eq!(stored.created_at, ticket.created_at);
+		assert!(matches!(
+			storage.get(revised.id),
+			Err(StorageError::NotFound(_))
+		));
+		assert!(matches!(
+			storage.upsert_by_external_id(&create_test_entry(vec![1.0], "Unkeyed")),
+			Err(StorageError::InvalidArgument(_))
+		));
+
+		storage.scope_to_tenant("acme").unwrap();
+		assert_eq!(
+			storage.get_by_external_id("TICKET-1").unwrap().unwrap().id,
+			other_tenant.id
+		);
+	}
+
+	#[test]
+	fn test_expired_entries_give_up_their_external_ids() {
+		let mut storage = create_test_storage();
+		let lapsed = create_test_entry(vec![1.0], "Old ticket")
+			.with_external_id("TICKET-3")
+			.with_expiry(Utc::now() + chrono::Duration::milliseconds(20));
+		storage.insert(&lapsed).unwrap();
+		std::thread::sleep(std::time::Duration::from_millis(40));
+
+		assert!(storage.get_by_external_id("TICKET-3").unwrap().is_none());
+		let reopened = create_test_entry(vec![1.0], "Reopened ticket").with_external_id("TICKET-3");
+		storage.insert(&reopened).unwrap();
+		let mut revised =
+			create_test_entry(vec![1.0], "Reopened again").with_external_id("TICKET-3");
+		revised.updated_at = Utc::now();
+		assert_eq!(
+			storage.upsert_by_external_id(&revised).unwrap(),
+			(reopened.id, UpsertOutcome::Updated)
+		);
+		assert_eq!(
+			storage
+				.get_by_external_id("TICKET-3")
+				.unwrap()
+				.unwrap()
+				.expression,
+			"Reopened again"
+		);
+		let expired = storage
+			.query(&Query::new().including_expired())
+			.unwrap()
+			.into_iter()
+			.find(|result| result.entry.id == lapsed.id)
+			.unwrap()
+			.entry;
+		assert_eq!(expired.external_id, None);
+	}
+
+	#[test]
+	fn test_upsert_by_external_id_rejects_a_key_repeated_in_one_batch() {
+		let mut storage = create_test_storage();
+		let first = create_test_entry(vec![1.0], "First draft").with_external_id("TICKET-7");
+		let second = create_test_entry(vec![1.0], "Second draft").with_external_id("TICKET-7");
+		let other = create_test_entry(vec![1.0], "Unrelated").with_external_id("TICKET-8");
+
+		assert!(matches!(
+			storage.upsert_batch_by_external_id(&[first, other, second]),
+			Err(StorageError::InvalidArgument(message)) if message.contains("TICKET-7")
+		));
+		assert_eq!(storage.count().unwrap(), 0);
+	}
+
+	#[test]
+	fn test_dedup_skip_returns_existing_ids_and_redirects_relations() {
+		let mut storage = create_test_storage();
+		storage
+			.set_dedup_policy(&DedupPolicy {
+				enabled: true,
+				..DedupPolicy::default()
+			})
+			.unwrap();
+		let stored = create_test_entry(vec![1.0], "User prefers cold brew");
+		storage.insert(&stored).unwrap();
+		let restated = create_test_entry(vec![0.5], "  user prefers COLD brew");
+		let first = create_test_entry(vec![1.0], "Meeting on Friday");
+		let repeated = create_test_entry(vec![1.0], "meeting on friday");
+		let linked = create_test_entry(vec![1.0], "Agenda")
+			.add_relation(restated.id)
+			.add_relation(repeated.id);
+		let other_collection = Entry {
+			collection: "archive".to_string(),
+			..create_test_entry(vec![1.0], "Meeting on Friday")
+		};
+
+		let ids = storage
+			.insert_batch_returning_ids(&[
+				restated.clone(),
+				first.clone(),
+				repeated.clone(),
+				linked.clone(),
+				other_collection.clone(),
+			])
+			.unwrap();
+
+		assert_eq!(
+			ids,
+			vec![
+				stored.id,
+				first.id,
+				first.id,
+				linked.id,
+				other_collection.id
+			]
+		);
+		assert_eq!(storage.count().unwrap(), 4);
+		assert!(matches!(
+			storage.get(repeated.id),
+			Err(StorageError::NotFound(_))
+		));
+		let mut relations = storage.get(linked.id).unwrap().relations;
+		relations.sort();
+		let mut expected = vec![stored.id, first.id];
+		expected.sort();
+		assert_eq!(relations, expected);
+		assert_eq!(storage.revisions(stored.id).unwrap().len(), 1);
+	}
+
+	#[test]
+	fn test_dedup_merge_context_updates_the_existing_entry() {
+		let mut storage = create_test_storage();
+		storage
+			.set_dedup_policy(&DedupPolicy {
+				enabled: true,
+				on_duplicate: DuplicateAction::MergeContext,
+				..DedupPolicy::default()
+			})
+			.unwrap();
+		let stored = create_test_entry(vec![1.0], "Shared fact")
+			.with_context(serde_json::json!({"source": "chat", "tags": ["a"]}));
+		storage.insert(&stored).unwrap();
+		let first = create_test_entry(vec![1.0], "Shared fact")
+			.with_context(serde_json::json!({"confidence": 0.9}));
+		let second = create_test_entry(vec![1.0], "shared fact")
+			.with_context(serde_json::json!({"source": null, "tags": ["b"]}));
+
+		let ids = storage
+			.insert_batch_returning_ids(&[first.clone(), second.clone()])
+			.unwrap();
+
+		assert_eq!(ids, vec![stored.id, stored.id]);
+		let merged = storage.get(stored.id).unwrap();
+		assert_eq!(
+			merged.context,
+			serde_json::json!({"confidence": 0.9, "tags": ["b"]})
+		);
+		assert_eq!(merged.version, 2);
+		assert_eq!(storage.count().unwrap(), 1);
+		let revisions = storage.revisions(stored.id).unwrap();
+		assert_eq!(revisions.len(), 2);
+		assert_eq!(revisions[1].operation, RevisionOperation::Update);
+	}
+
+	#[test]
+	fn test_dedup_reject_and_conflicting_writes_report_the_existing_entry() {
+		let mut storage = create_test_storage();
+		let stored = create_test_entry(vec![1.0], "Original");
+		let copy = create_test_entry(vec![1.0], "original");
+		storage
+			.insert_batch(&[stored.clone(), copy.clone()])
+			.unwrap();
+		let policy = DedupPolicy {
+			enabled: true,
+			on_duplicate: DuplicateAction::Reject,
+			..DedupPolicy::default()
+		};
+
+		assert!(matches!(
+			storage.set_dedup_policy(&policy),
+			Err(StorageError::Duplicate { .. })
+		));
+		assert_eq!(storage.dedup_policy().unwrap(), DedupPolicy::default());
+		storage.delete(copy.id).unwrap();
+		storage.set_dedup_policy(&policy).unwrap();
+
+		let rejected = create_test_entry(vec![1.0], "ORIGINAL");
+		assert!(matches!(
+			storage.insert(&rejected),
+			Err(StorageError::Duplicate { id, existing })
+				if id == rejected.id && existing == stored.id
+		));
+		let other = create_test_entry(vec![1.0], "Other");
+		storage.insert(&other).unwrap();
+		let mut edited = other.clone();
+		edited.expression = "Original".to_string();
+		edited.updated_at = Utc::now();
+		assert!(matches!(
+			storage.update(&edited),
+			Err(StorageError::Duplicate { id, existing })
+				if id == other.id && existing == stored.id
+		));
+		assert_eq!(storage.count().unwrap(), 2);
+	}
+
+	#[test]
+	fn test_dedup_ignores_expired_entries_until_they_are_swept() {
+		let mut storage = create_test_storage();
+		storage
+			.set_dedup_policy(&DedupPolicy {
+				enabled: true,
+				..DedupPolicy::default()
+			})
+			.unwrap();
+		let fleeting = create_test_entry(vec![1.0], "Standup at ten")
+			.with_expiry(Utc::now() + chrono::Duration::milliseconds(20));
+		storage.insert(&fleeting).unwrap();
+		std::thread::sleep(std::time::Duration::from_millis(40));
+
+		let again = create_test_entry(vec![1.0], "standup at TEN");
+		assert_eq!(
+			storage
+				.insert_batch_returning_ids(std::slice::from_ref(&again))
+				.unwrap(),
+			vec![again.id]
+		);
+		assert_eq!(storage.get(again.id).unwrap().expression, "standup at TEN");
+
+		let mut revived = storage
+			.query(&Query::new().including_expired())
+			.unwrap()
+			.into_iter()
+			.find(|result| result.entry.id == fleeting.id)
+			.unwrap()
+			.entry;
+		revived.expires_at = None;
+		revived.updated_at = Utc::now();
+		assert!(matches!(
+			storage.update(&revived),
+			Err(StorageError::Duplicate { id, existing })
+				if id == fleeting.id && existing == again.id
+		));
+		assert_eq!(storage.sweep_expired(10).unwrap(), vec![fleeting.id]);
+		assert!(storage.integrity_check().unwrap().is_healthy());
+	}
+
+	#[test]
+	fn test_merge_unions_relations_and_redirects_incoming_edges() {
+		let mut storage = create_test_storage();
+		let target = create_test_entry(vec![0.0, 1.0], "Target");
+		let other_target = create_test_entry(vec![0.0, 1.0], "Other target");
+		let survivor = create_test_entry(vec![1.0, 0.0], "Dark mode")
+			.with_context(serde_json::json!({"source": "profile", "tags": ["ui"]}))
+			.add_relation(target.id);
+		let duplicate = create_test_entry(vec![0.99, 0.01], "Prefers dark mode")
+			.with_context(serde_json::json!({"source": "chat", "confidence": 0.8}))
+			.add_relation(other_target.id)
+			.add_relation(survivor.id);
+		let pointer = create_test_entry(vec![0.0, 1.0], "Pointer")
+			.add_relation(duplicate.id)
+			.add_relation(survivor.id);
+		storage
+			.insert_batch(&[
+				target.clone(),
+				other_target.clone(),
+				survivor.clone(),
+				duplicate.clone(),
+				pointer.clone(),
+			])
+			.unwrap();
+
+		let clusters = storage.find_near_duplicates(0.99).unwrap();
+		assert_eq!(clusters.len(), 2);
+		assert!(clusters.iter().any(
+			|cluster| cluster.ids.contains(&survivor.id) && cluster.ids.contains(&duplicate.id)
+		));
+		assert!(matches!(
+			storage.find_near_duplicates(1.5),
+			Err(StorageError::InvalidArgument(_))
+		));
+		assert!(matches!(
+			storage.merge(&[survivor.id], MergeStrategy::KeepFirst),
+			Err(StorageError::InvalidArgument(_))
+		));
+
+		let merged = storage
+			.merge(&[survivor.id, duplicate.id], MergeStrategy::KeepFirst)
+			.unwrap();
+
+		assert_eq!(merged.id, survivor.id);
+		assert_eq!(merged.version, 2);
+		assert_eq!(
+			merged.context,
+			serde_json::json!({"source": "profile", "tags": ["ui"], "confidence": 0.8})
+		);
+		assert_eq!(storage.get(survivor.id).unwrap().context, merged.context);
+		assert_eq!(merged.relations, vec![target.id, other_target.id]);
+		assert!(matches!(
+			storage.get(duplicate.id),
+			Err(StorageError::NotFound(_))
+		));
+		let pointer = storage.get(pointer.id).unwrap();
+		assert_eq!(pointer.relations, vec![survivor.id]);
+		assert_eq!(
+			storage
+				.revisions(pointer.id)
+				.unwrap()
+				.last()
+				.unwrap()
+				.operation,
+			RevisionOperation::Relate
+		);
+		assert_eq!(
+			storage
+				.revisions(duplicate.id)
+				.unwrap()
+				.last()
+				.unwrap()
+				.operation,
+			RevisionOperation::Delete
+		);
+		assert!(storage.integrity_check().unwrap().is_healthy());
+	}
+
+	#[test]
+	fn test_merge_moves_removed_entries_to_the_trash_under_soft_delete() {
+		let mut storage = create_test_storage();
+		storage
+			.set_trash_policy(&TrashPolicy {
+				soft_delete: true,
+				..TrashPolicy::default()
+			})
+			.unwrap();
+		let older = create_test_entry(vec![1.0], "Older");
+		let mut newer = create_test_entry(vec![1.0], "Newer").add_relation(older.id);
+		newer.created_at = older.created_at + chrono::Duration::seconds(1);
+		newer.updated_at = newer.created_at;
+		let archived = Entry {
+			collection: "archive".to_string(),
+			..create_test_entry(vec![1.0], "Archived")
+		};
+		storage
+			.insert_batch(&[older.clone(), newer.clone(), archived.clone()])
+			.unwrap();
+		assert!(matches!(
+			storage.merge(&[older.id, archived.id], MergeStrategy::KeepFirst),
+			Err(StorageError::InvalidArgument(_))
+		));
+
+		let merged = storage
+			.merge(&[older.id, newer.id], MergeStrategy::KeepNewest)
+			.unwrap();
+
+		assert_eq!(merged.id, newer.id);
+		assert!(merged.relations.is_empty());
+		let deleted = storage.list_deleted().unwrap();
+		assert_eq!(deleted.len(), 1);
+		assert_eq!(deleted[0].entry.id, older.id);
+		assert!(storage.integrity_check().unwrap().is_healthy());
+	}
+
+	#[test]
+	fn test_expired_entries_are_hidden_until_swept() {
+		let mut storage = create_test_storage();
+		let now = Utc::now();
+		let live = create_test_entry(vec![1.0], "Live note");
+		let expired = create_test_entry(vec![1.0], "Expired note")
+			.with_expiry(now - chrono::Duration::hours(1));
+		let expiring = create_test_entry(vec![1.0], "Expiring note")
+			.with_expiry(now + chrono::Duration::hours(1));
+		storage
+			.insert_batch(&[live.clone(), expired.clone(), expiring.clone()])
+			.unwrap();
+
+		assert!(matches!(
+			storage.get(expired.id),
+			Err(StorageError::NotFound(id)) if id == expired.id
+		));
+		assert_eq!(
+			storage.get(expiring.id).unwrap().expires_at,
+			expiring.expires_at
+		);
+		let execution = storage
+			.execute(&Query::new().with_expression(ExpressionFilter::FullText("note".into())))
+			.unwrap();
+		assert_eq!(execution.results.len(), 2);
+		assert_eq!(
+			execution.plan.steps[0].filter,
+			Some(QueryFilterIdentity::Expiry)
+		);
+		assert_eq!(
+			storage
+				.query(&Query::new().including_expired())
+				.unwrap()
+				.len(),
+			3
+		);
+		assert_eq!(
+			storage
+				.query(&Query::new().as_of(Utc::now()))
+				.unwrap()
+				.len(),
+			2
+		);
+
+		let swept = storage.sweep_expired(1).unwrap();
+
+		assert_eq!(swept, vec![expired.id]);
+		assert_eq!(storage.count().unwrap(), 2);
+		assert_eq!(
+			storage
+				.revisions(expired.id)
+				.unwrap()
+				.last()
+				.unwrap()
+				.operation,
+			RevisionOperation::Delete
+		);
+		assert!(storage.sweep_expired(1).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_tenant_scope_hides_other_tenants() {
+		let mut storage = create_test_storage();
+		let acme = create_test_entry(vec![1.0, 0.0], "Acme roadmap").for_tenant("acme");
+		let globex = create_test_entry(vec![1.0, 0.0], "Globex roadmap").for_tenant("globex");
+		storage
+			.insert_batch(&[acme.clone(), globex.clone()])
+			.unwrap();
+
+		storage.scope_to_tenant("acme").unwrap();
+
+		assert_eq!(storage.tenant_scope(), Some("acme"));
+		assert!(matches!(
+			storage.get(globex.id),
+			Err(StorageError::NotFound(id)) if id == globex.id
+		));
+		assert!(storage.revisions(globex.id).unwrap().is_empty());
+		assert_eq!(storage.count().unwrap(), 1);
+		let results = storage
+			.query(&Query::new().with_expression(ExpressionFilter::FullText("roadmap".into())))
+			.unwrap();
+		assert_eq!(
+			results
+				.iter()
+				.map(|result| result.entry.id)
+				.collect::<Vec<_>>(),
+			vec![acme.id]
+		);
+		let past = storage.query(&Query::new().as_of(Utc::now())).unwrap();
+		assert_eq!(past.len(), 1);
+		assert!(matches!(
+			storage.delete(globex.id),
+			Err(StorageError::NotFound(_))
+		));
+		assert!(matches!(
+			storage.insert(&create_test_entry(vec![0.0, 1.0], "Leak").for_tenant("globex")),
+			Err(StorageError::InvalidArgument(_))
+		));
+		assert!(matches!(
+			storage.scope_to_tenant("globex"),
+			Err(StorageError::InvalidArgument(_))
+		));
+	}
+
+	#[test]
+	fn test_tenant_scope_hides_other_tenants_changes_and_tombstones() {
+		let mut storage = create_test_storage();
+		let acme = create_test_entry(vec![1.0], "Acme note").for_tenant("acme");
+		let globex = create_test_entry(vec![1.0], "Globex note").for_tenant("globex");
+		let globex_gone = create_test_entry(vec![1.0], "Globex secret").for_tenant("globex");
+		storage
+			.insert_batch(&[acme.clone(), globex.clone(), globex_gone.clone()])
+			.unwrap();
+		storage.purge(globex_gone.id).unwrap();
+		let acme_gone = create_test_entry(vec![1.0], "Acme draft").for_tenant("acme");
+		storage.insert(&acme_gone).unwrap();
+		storage.purge(acme_gone.id).unwrap();
+		assert_eq!(storage.tombstones().unwrap().len(), 2);
+
+		storage.scope_to_tenant("acme").unwrap();
+
+		let changed: HashSet<Uuid> = storage
+			.changes_since(0, 100)
+			.unwrap()
+			.into_iter()
+			.map(|change| change.entry_id)
+			.collect();
+		assert_eq!(changed, HashSet::from([acme.id, acme_gone.id]));
+		let tombstones = storage.tombstones().unwrap();
+		assert_eq!(tombstones.len(), 1);
+		assert_eq!(tombstones[0].entry_id, acme_gone.id);
+	}
+
+	#[test]
+	fn test_version_eleven_history_is_attributed_to_tenants() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("v11.db");
+		let acme = create_test_entry(vec![0.1], "Acme note").for_tenant("acme");
+		let purged = create_test_entry(vec![0.1], "Acme draft").for_tenant("acme");
+		{
+			let mut storage = SqliteStorage::new(&path).unwrap();
+			storage
+				.insert_batch(&[acme.clone(), purged.clone()])
+				.unwrap();
+			storage.purge(purged.id).unwrap();
+			storage
+				.conn
+				.execute_batch(
+					"DROP INDEX idx_change_log_tenant;
+					 ALTER TABLE change_log DROP COLUMN tenant;
+					 ALTER TABLE entry_tombstones DROP COLUMN tenant;
+					 PRAGMA user_version = 11;",
+				)
+				.unwrap();
+		}
+
+		let unscoped = SqliteStorage::new(&path).unwrap();
+		let mut storage = SqliteStorage::new(&path).unwrap();
+		storage.scope_to_tenant("acme").unwrap();
+
+		// The purged entry's revisions are gone, so its history stays unscoped.
+		let changes = storage.changes_since(0, 100).unwrap();
+		assert_eq!(changes.len(), 1);
+		assert_eq!(changes[0].entry_id, acme.id);
+		assert!(storage.tombstones().unwrap().is_empty());
+		assert_eq!(unscoped.changes_since(0, 100).unwrap().len(), 3);
+		assert_eq!(unscoped.tombstones().unwrap()[0].entry_id, purged.id);
+	}
+
+	#[test]
+	fn test_relations_cannot_cross_tenants() {
+		let mut storage = create_test_storage();
+		let acme = create_test_entry(vec![1.0], "Acme").for_tenant("acme");
+		let globex = create_test_entry(vec![1.0], "Globex").for_tenant("globex");
+		storage
+			.insert_batch(&[acme.clone(), globex.clone()])
+			.unwrap();
+
+		assert!(matches!(
+			storage.insert(
+				&create_test_entry(vec![1.0], "Linked")
+					.for_tenant("acme")
+					.add_relation(globex.id)
+			),
+			Err(StorageError::NotFound(id)) if id == globex.id
+		));
+		assert!(matches!(
+			storage.relate(acme.id, globex.id),
+			Err(StorageError::NotFound(id)) if id == globex.id
+		));
+		let mut moved = acme.clone();
+		moved.tenant = "globex".to_string();
+		assert!(matches!(
+			storage.update(&moved),
+			Err(StorageError::InvalidArgument(_))
+		));
+
+		// An edge written around the API is reported and never traversed by scoped handles.
+		storage
+			.conn
+			.execute(
+				"INSERT INTO relations (from_id, to_id) VALUES (?1, ?2)",
+				params![acme.id.to_string(), globex.id.to_string()],
+			)
+			.unwrap();
+		let report = storage.integrity_check().unwrap();
+		assert!(report
+			.issues
+			.iter()
+			.any(|issue| issue.area == "relations" && issue.message.contains("different tenants")));
+		storage.scope_to_tenant("acme").unwrap();
+		assert!(!storage
+			.load_relation_index()
+			.unwrap()
+			.adjacency
+			.contains_key(&acme.id));
+	}
+
+	#[test]
+	fn test_legacy_database_with_orphan_relation_is_rejected() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("orphan.db");
+		let connection = Connection::open(&path).unwrap();
+		connection
+			.execute_batch(
+				"CREATE TABLE entries (
+					id TEXT PRIMARY KEY, meaning BLOB NOT NULL, expression TEXT NOT NULL,
+					context TEXT NOT NULL, created_at TEXT NOT NULL, updated_at TEXT NOT NULL
+				);
+				CREATE TABLE relations (
+					from_id TEXT NOT NULL, to_id TEXT NOT NULL,
+					PRIMARY KEY (from_id, to_id)
+				);",
+			)
+			.unwrap();
+		connection
+			.execute(
+				"INSERT INTO relations VALUES (?1, ?2)",
+				params![Uuid::new_v4().to_string(), Uuid::new_v4().to_string()],
+			)
+			.unwrap();
+		drop(connection);
+
+		let result = SqliteStorage::new(&path);
+
+		assert!(matches!(
+			result,
+			Err(StorageError::Database(message)) if message.contains("invalid relation")
+		));
+	}
+
+	// ==================== Insert Tests ====================
+
+	#[test]
+	fn test_insert_single_entry() {
+		let mut storage = create_test_storage();
+		let entry = create_test_entry(vec![0.1, 0.2, 0.3], "Test entry");
+
+		let result = storage.insert(&entry);
+		assert!(result.is_ok());
+		assert_eq!(storage.count().unwrap(), 1);
+	}
+
+	#[test]
+	fn test_insert_multiple_entries() {
+		let mut storage = create_test_storage();
+
+		for i in 0..10 {
+			let entry = create_test_entry(vec![i as f32], &format!("Entry {}", i));
+			storage.insert(&entry).unwrap();
+		}
+
+		assert_eq!(storage.count().unwrap(), 10);
+	}
+
+	#[test]
+	fn test_insert_batch_supports_internal_relations() {
+		let mut storage = create_test_storage();
+		let target = create_test_entry(vec![0.1], "Target");
+		let source = create_test_entry(vec![0.2], "Source").add_relation(target.id);
+
+		storage.insert_batch(&[source.clone(), target]).unwrap();
+
+		assert_eq!(storage.count().unwrap(), 2);
+		assert_eq!(storage.get(source.id).unwrap().relations.len(), 1);
+	}
+
+	#[test]
+	fn test_insert_batch_rolls_back_on_duplicate() {
+		let mut storage = create_test_storage();
+		let entry = create_test_entry(vec![0.1], "Duplicate");
+
+		assert!(storage.insert_batch(&[entry.clone(), entry]).is_err());
+		assert_eq!(storage.count().unwrap(), 0);
+	}
+
+	#[test]
+	fn test_insert_batch_rejects_invalid_timestamps_atomically() {
+		let mut storage = create_test_storage();
+		let valid = create_test_entry(vec![0.1], "Valid");
+		let mut invalid = create_test_entry(vec![0.2], "Invalid");
+		invalid.updated_at = invalid.created_at - chrono::Duration::seconds(1);
+
+		assert!(storage.insert_batch(&[valid, invalid]).is_err());
+		assert_eq!(storage.count().unwrap(), 0);
+	}
+
+	#[test]
+	fn test_insert_entry_with_context() {
+		let mut storage = create_test_storage();
+		let context = serde_json::json!({
+			"source": "test",
+			"priority": 1,
+			"tags": ["a", "b", "c"]
+		});
+		let entry = create_test_entry(vec![0.1], "With context").with_context(context);
+
+		storage.insert(&entry).unwrap();
+
+		let retrieved = storage.get(entry.id).unwrap();
+		assert_eq!(retrieved.context["source"], "test");
+		assert_eq!(retrieved.context["priority"], 1);
+	}
+
+	#[test]
+	fn test_insert_entry_with_relations() {
+		let mut storage = create_test_storage();
+
+		// Insert two entries
+		let entry1 = create_test_entry(vec![0.1], "Entry 1");
+		let entry2 = create_test_entry(vec![0.2], "Entry 2");
+		storage.insert(&entry1).unwrap();
+		storage.insert(&entry2).unwrap();
+
+		// Insert entry with relation
+		let entry3 = create_test_entry(vec![0.3], "Entry 3")
+			.add_relation(entry1.id)
+			.add_relation(entry2.id);
+		storage.insert(&entry3).unwrap();
+
+		let retrieved = storage.get(entry3.id).unwrap();
+		assert_eq!(retrieved.relations.len(), 2);
+		assert!(retrieved.relations.contains(&entry1.id));
+		assert!(retrieved.relations.contains(&entry2.id));
+	}
+
+	#[test]
+	fn test_insert_entry_with_empty_meaning() {
+		let mut storage = create_test_storage();
+		let entry = create_test_entry(vec![], "No embedding");
+
+		let result = storage.insert(&entry);
+		assert!(matches!(result, Err(StorageError::InvalidDimensions)));
+	}
+
+	#[test]
+	fn test_insert_rejects_non_finite_meaning() {
+		let mut storage = create_test_storage();
+		let entry = create_test_entry(vec![f32::NAN], "Invalid embedding");
+
+		assert!(matches!(
+			storage.insert(&entry),
+			Err(StorageError::InvalidDimensions)
+		));
+	}
+
+	#[test]
+	fn test_insert_rejects_mismatched_dimensions() {
+		let mut storage = create_test_storage();
+		storage
+			.insert(&create_test_entry(vec![0.1, 0.2], "First"))
+			.unwrap();
+
+		assert!(matches!(
+			storage.insert(&create_test_entry(vec![0.1], "Second")),
+			Err(StorageError::InvalidDimensions)
+		));
+	}
+
+	#[test]
+	fn test_insert_entry_with_large_vector() {
+		let mut storage = create_test_storage();
+		let large_vector: Vec<f32> = (0..1536).map(|i| i as f32 / 1536.0).collect();
+		let entry = create_test_entry(large_vector.clone(), "Large vector");
+
+		storage.insert(&entry).unwrap();
+
+		let retrieved = storage.get(entry.id).unwrap();
+		assert_eq!(retrieved.meaning.len(), 1536);
+		assert!((retrieved.meaning[0] - large_vector[0]).abs() < 0.0001);
+	}
+
+	// ==================== Get Tests ====================
+
+	#[test]
+	fn test_get_existing_entry() {
+		let mut storage = create_test_storage();
+		let entry = create_test_entry(vec![0.1, 0.2, 0.3], "Test entry");
+		storage.insert(&entry).unwrap();
+
+		let retrieved = storage.get(entry.id).unwrap();
+
+		assert_eq!(retrieved.id, entry.id);
+		assert_eq!(retrieved.expression, entry.expression);
+		assert_eq!(retrieved.meaning, entry.meaning);
+	}
+
+	#[test]
+	fn test_get_nonexistent_entry() {
+		let storage = create_test_storage();
+		let fake_id = Uuid::new_v4();
+
+		let result = storage.get(fake_id);
+		assert!(result.is_err());
+
+		match result {
+			Err(StorageError::NotFound(id)) => assert_eq!(id, fake_id),
+			_ => panic!("Expected NotFound error"),
+		}
+	}
+
+	#[test]
+	fn test_get_preserves_timestamps() {
+		let mut storage = create_test_storage();
+		let entry = create_test_entry(vec![0.1], "Test");
+		let original_created = entry.created_at;
+		let original_updated = entry.updated_at;
+		storage.insert(&entry).unwrap();
+
+		let retrieved = storage.get(entry.id).unwrap();
+
+		// Timestamps should be close (within 1 second due to serialization)
+		assert!(
+			(retrieved.created_at - original_created)
+				.num_seconds()
+				.abs() < 1
+		);
+		assert!(
+			(retrieved.updated_at - original_updated)
+				.num_seconds()
+				.abs() < 1
+		);
+	}
+
+	// ==================== Update Tests ====================
+
+	#[test]
+	fn test_update_entry_expression() {
+		let mut storage = create_test_storage();
+		let mut entry = create_test_entry(vec![0.1], "Original");
+		storage.insert(&entry).unwrap();
+
+		entry.expression = "Updated".to_string();
+		entry.updated_at = Utc::now();
+		storage.update(&entry).unwrap();
+
+		let retrieved = storage.get(entry.id).unwrap();
+		assert_eq!(retrieved.expression, "Updated");
+	}
+
+	#[test]
+	fn test_update_entry_meaning() {
+		let mut storage = create_test_storage();
+		let mut entry = create_test_entry(vec![0.1, 0.2], "Test");
+		storage.insert(&entry).unwrap();
+
+		entry.meaning = vec![0.9, 0.8];
+		storage.update(&entry).unwrap();
+
+		let retrieved = storage.get(entry.id).unwrap();
+		assert_eq!(retrieved.meaning, vec![0.9, 0.8]);
+	}
+
+	#[test]
+	fn test_update_entry_context() {
+		let mut storage = create_test_storage();
+		let mut entry =
+			create_test_entry(vec![0.1], "Test").with_context(serde_json::json!({"version": 1}));
+		storage.insert(&entry).unwrap();
+
+		entry.context = serde_json::json!({"version": 2, "new_field": "added"});
+		storage.update(&entry).unwrap();
+
+		let retrieved = storage.get(entry.id).unwrap();
+		assert_eq!(retrieved.context["version"], 2);
+		assert_eq!(retrieved.context["new_field"], "added");
+	}
+
+	#[test]
+	fn test_update_entry_relations() {
+		let mut storage = create_test_storage();
+
+		let target1 = create_test_entry(vec![0.1], "Target 1");
+		let target2 = create_test_entry(vec![0.2], "Target 2");
+		storage.insert(&target1).unwrap();
+		storage.insert(&target2).unwrap();
 
-		let transaction = self
-			.conn
-			.transaction()
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		for entry in &updated_entries {
-			let meaning = vector_codec::serialize(&entry.meaning).map_err(|error| {
-				StorageError::Database(format!("Failed to serialize vector: {error}"))
-			})?;
-			let updated = transaction
-				.execute(
-					"UPDATE entries SET meaning = ?1, updated_at = ?2 WHERE id = ?3",
-					params![meaning, entry.updated_at.to_rfc3339(), entry.id.to_string()],
-				)
-				.map_err(|error| StorageError::Database(error.to_string()))?;
-			if updated != 1 {
-				return Err(StorageError::NotFound(entry.id));
-			}
-			Self::record_revision(&transaction, entry, RevisionOperation::Update)?;
-		}
-		Self::write_embedding_profile(&transaction, profile)?;
-		transaction
-			.commit()
-			.map_err(|error| StorageError::Database(error.to_string()))
+		let mut entry = create_test_entry(vec![0.3], "Entry").add_relation(target1.id);
+		storage.insert(&entry).unwrap();
+
+		// Update relations
+		entry.relations = vec![target2.id];
+		storage.update(&entry).unwrap();
+
+		let retrieved = storage.get(entry.id).unwrap();
+		assert_eq!(retrieved.relations.len(), 1);
+		assert!(retrieved.relations.contains(&target2.id));
+		assert!(!retrieved.relations.contains(&target1.id));
 	}
 
-	fn revisions(&self, id: Uuid) -> StorageResult<Vec<EntryRevision>> {
-		let mut statement = self
-			.conn
-			.prepare(
-				"SELECT revision_id, operation, snapshot, recorded_at
-				 FROM entry_revisions
-				 WHERE entry_id = ?1
-				 ORDER BY recorded_at, revision_id",
-			)
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		let rows = statement
-			.query_map(params![id.to_string()], |row| {
-				Ok((
-					row.get::<_, String>(0)?,
-					row.get::<_, String>(1)?,
-					row.get::<_, String>(2)?,
-					row.get::<_, String>(3)?,
-				))
-			})
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		let mut revisions = Vec::new();
-		for row in rows {
-			let (revision_id, operation, snapshot, recorded_at) =
-				row.map_err(|error| StorageError::Database(error.to_string()))?;
-			let operation = match operation.as_str() {
-				"snapshot" => RevisionOperation::Snapshot,
-				"insert" => RevisionOperation::Insert,
-				"update" => RevisionOperation::Update,
-				"delete" => RevisionOperation::Delete,
-				other => {
-					return Err(StorageError::Database(format!(
-						"Unknown revision operation: {other}"
-					)))
-				}
-			};
-			revisions.push(EntryRevision {
-				revision_id: Uuid::parse_str(&revision_id)
-					.map_err(|error| StorageError::Database(error.to_string()))?,
-				entry_id: id,
-				operation,
-				snapshot: serde_json::from_str(&snapshot)?,
-				recorded_at: DateTime::parse_from_rfc3339(&recorded_at)
-					.map_err(|error| StorageError::Database(error.to_string()))?
-					.with_timezone(&Utc),
-			});
-		}
-		Ok(revisions)
+	#[test]
+	fn test_update_nonexistent_entry_returns_not_found() {
+		let mut storage = create_test_storage();
+		let entry = create_test_entry(vec![0.1], "Missing");
+
+		assert!(matches!(
+			storage.update(&entry),
+			Err(StorageError::NotFound(id)) if id == entry.id
+		));
 	}
 
-	fn create_context_index(&mut self, path: &str) -> StorageResult<String> {
-		let sqlite_path = Self::json_pointer_to_sqlite_path(path)?;
-		let mut hash = 0xcbf29ce484222325_u64;
-		for byte in path.as_bytes() {
-			hash ^= u64::from(*byte);
-			hash = hash.wrapping_mul(0x100000001b3);
-		}
-		let name = format!("idx_entries_context_{hash:016x}");
-		let path_literal = Self::sql_string_literal(&sqlite_path);
-		self.conn
-			.execute(
-				&format!("CREATE INDEX IF NOT EXISTS {name} ON entries(json_extract(context, {path_literal}))"),
-				[],
-			)
-			.map_err(|error| StorageError::Database(error.to_string()))?;
-		Ok(name)
+	#[test]
+	fn test_update_batch_is_atomic() {
+		let mut storage = create_test_storage();
+		let mut first = create_test_entry(vec![0.1], "First");
+		let second = create_test_entry(vec![0.2], "Second");
+		storage.insert(&first).unwrap();
+		first.expression = "Changed".to_string();
+
+		assert!(storage.update_batch(&[first.clone(), second]).is_err());
+		assert_eq!(storage.get(first.id).unwrap().expression, "First");
 	}
 
-	fn backend_name(&self) -> &str {
-		"SQLite"
+	// ==================== Delete Tests ====================
+
+	#[test]
+	fn test_delete_entry() {
+		let mut storage = create_test_storage();
+		let entry = create_test_entry(vec![0.1], "Test");
+		storage.insert(&entry).unwrap();
+
+		let result = storage.delete(entry.id);
+		assert!(result.is_ok());
+		assert_eq!(storage.count().unwrap(), 0);
 	}
-}
 
-struct RelationIndex {
-	adjacency: HashMap<Uuid, Vec<Uuid>>,
-	related_ids: HashSet<Uuid>,
-}
+	#[test]
+	fn test_delete_nonexistent_entry() {
+		let mut storage = create_test_storage();
+		let fake_id = Uuid::new_v4();
 
-// Vectors are JSON-encoded inside the BLOB column for portable decoding.
-mod vector_codec {
-	use serde::{Deserialize, Serialize};
+		let result = storage.delete(fake_id);
+		assert!(result.is_err());
 
-	pub fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
-		serde_json::to_vec(value).map_err(|e| e.to_string())
+		match result {
+			Err(StorageError::NotFound(id)) => assert_eq!(id, fake_id),
+			_ => panic!("Expected NotFound error"),
+		}
 	}
 
-	pub fn deserialize<T: for<'de> Deserialize<'de>>(bytes: &[u8]) -> Result<T, String> {
-		serde_json::from_slice(bytes).map_err(|e| e.to_string())
+	#[test]
+	fn test_delete_batch_is_atomic() {
+		let mut storage = create_test_storage();
+		let entry = create_test_entry(vec![0.1], "Existing");
+		storage.insert(&entry).unwrap();
+
+		assert!(storage.delete_batch(&[entry.id, Uuid::new_v4()]).is_err());
+		assert_eq!(storage.count().unwrap(), 1);
 	}
-}
 
-#[cfg(test)]
-mod tests {
-	use super::*;
-	use crate::query::{ContextFilter, MeaningFilter, RelationFilter, TemporalFilter};
-	use chrono::TimeZone;
-	use std::collections::HashSet;
+	#[test]
+	fn test_integrity_check_reports_healthy_database() {
+		let mut storage = create_test_storage();
+		storage
+			.insert(&create_test_entry(vec![0.1], "Healthy"))
+			.unwrap();
 
-	fn create_test_storage() -> SqliteStorage {
-		SqliteStorage::in_memory().unwrap()
+		assert!(storage.integrity_check().unwrap().is_healthy());
 	}
 
-	fn create_test_entry(meaning: Vec<f32>, expression: &str) -> Entry {
-		Entry::new(meaning, expression.to_string())
+	#[test]
+	fn test_integrity_check_detects_equal_count_full_text_drift() {
+		let mut storage = create_test_storage();
+		let entry = create_test_entry(vec![0.1], "Canonical expression");
+		storage.insert(&entry).unwrap();
+		storage
+			.conn
+			.execute(
+				"UPDATE entries_fts SET expression = 'stale expression' WHERE id = ?1",
+				params![entry.id.to_string()],
+			)
+			.unwrap();
+
+		let report = storage.integrity_check().unwrap();
+
+		assert!(report.issues.iter().any(|issue| {
+			issue.area == "full_text" && issue.message.contains("stale or mismatched")
+		}));
 	}
 
-	// ==================== Storage Initialization Tests ====================
+	#[test]
+	fn test_embedding_profile_is_persisted_and_locked_for_existing_data() {
+		let mut storage = create_test_storage();
+		let profile = EmbeddingProfile {
+			model: "text-embedding-model".to_string(),
+			version: Some("2026-07".to_string()),
+			dimensions: 2,
+		};
+		storage.set_embedding_profile(&profile).unwrap();
+		storage
+			.insert(&create_test_entry(vec![0.1, 0.2], "Profiled"))
+			.unwrap();
+
+		assert_eq!(storage.embedding_profile().unwrap(), Some(profile));
+		assert!(storage
+			.set_embedding_profile(&EmbeddingProfile {
+				model: "different-model".to_string(),
+				version: None,
+				dimensions: 2,
+			})
+			.is_err());
+	}
 
 	#[test]
-	fn test_storage_in_memory_creation() {
-		let storage = SqliteStorage::in_memory();
-		assert!(storage.is_ok());
+	fn test_populated_legacy_profile_requires_explicit_adoption() {
+		let mut storage = create_test_storage();
+		storage
+			.insert(&create_test_entry(vec![0.1, 0.2], "Legacy"))
+			.unwrap();
+		let profile = EmbeddingProfile {
+			model: "legacy-model".to_string(),
+			version: Some("v1".to_string()),
+			dimensions: 2,
+		};
+
+		assert!(storage.set_embedding_profile(&profile).is_err());
+		assert_eq!(storage.embedding_profile().unwrap(), None);
+		storage.adopt_legacy_embedding_profile(&profile).unwrap();
+		assert_eq!(storage.embedding_profile().unwrap(), Some(profile));
 	}
 
 	#[test]
-	fn test_storage_backend_name() {
-		let storage = create_test_storage();
-		assert_eq!(storage.backend_name(), "SQLite");
+	fn test_legacy_profile_adoption_rejects_wrong_dimensions() {
+		let mut storage = create_test_storage();
+		storage
+			.insert(&create_test_entry(vec![0.1, 0.2], "Legacy"))
+			.unwrap();
+		let profile = EmbeddingProfile {
+			model: "wrong-model".to_string(),
+			version: None,
+			dimensions: 3,
+		};
+
+		assert!(matches!(
+			storage.adopt_legacy_embedding_profile(&profile),
+			Err(StorageError::InvalidDimensions)
+		));
+		assert_eq!(storage.embedding_profile().unwrap(), None);
 	}
 
-	#[test]
-	fn test_storage_initial_count_is_zero() {
-		let storage = create_test_storage();
-		assert_eq!(storage.count().unwrap(), 0);
+	#[test]
+	fn test_migrate_embeddings_replaces_every_vector_and_records_revisions() {
+		let mut storage = create_test_storage();
+		let old_profile = EmbeddingProfile {
+			model: "old-model".to_string(),
+			version: Some("v1".to_string()),
+			dimensions: 2,
+		};
+		storage.set_embedding_profile(&old_profile).unwrap();
+		let first = create_test_entry(vec![0.1, 0.2], "First");
+		let second = create_test_entry(vec![0.3, 0.4], "Second");
+		storage
+			.insert_batch(&[first.clone(), second.clone()])
+			.unwrap();
+		let new_profile = EmbeddingProfile {
+			model: "new-model".to_string(),
+			version: Some("v2".to_string()),
+			dimensions: 3,
+		};
+
+		storage
+			.migrate_embeddings(
+				&new_profile,
+				&[
+					(first.id, vec![1.0, 0.0, 0.0]),
+					(second.id, vec![0.0, 1.0, 0.0]),
+				],
+			)
+			.unwrap();
+
+		assert_eq!(storage.embedding_profile().unwrap(), Some(new_profile));
+		assert_eq!(storage.get(first.id).unwrap().meaning, vec![1.0, 0.0, 0.0]);
+		assert_eq!(storage.get(second.id).unwrap().meaning, vec![0.0, 1.0, 0.0]);
+		let first_revisions = storage.revisions(first.id).unwrap();
+		assert_eq!(first_revisions.len(), 2);
+		assert_eq!(first_revisions[1].operation, RevisionOperation::Update);
+		assert_eq!(first_revisions[1].snapshot.meaning, vec![1.0, 0.0, 0.0]);
 	}
 
 	#[test]
-	fn test_storage_initializes_schema_and_foreign_keys() {
-		let storage = create_test_storage();
-		let version: i64 = storage
-			.conn
-			.query_row("PRAGMA user_version", [], |row| row.get(0))
+	fn test_migrate_embeddings_rolls_back_vectors_profile_and_revisions() {
+		let mut storage = create_test_storage();
+		let old_profile = EmbeddingProfile {
+			model: "old-model".to_string(),
+			version: None,
+			dimensions: 2,
+		};
+		storage.set_embedding_profile(&old_profile).unwrap();
+		let first = create_test_entry(vec![0.1, 0.2], "First");
+		let second = create_test_entry(vec![0.3, 0.4], "Second");
+		storage
+			.insert_batch(&[first.clone(), second.clone()])
 			.unwrap();
-		let foreign_keys: i64 = storage
+		let failing_id = [first.id, second.id].into_iter().max().unwrap();
+		storage
 			.conn
-			.query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+			.execute_batch(&format!(
+				"CREATE TRIGGER fail_embedding_migration
+				 BEFORE UPDATE OF meaning ON entries
+				 WHEN old.id = '{failing_id}'
+				 BEGIN SELECT RAISE(ABORT, 'forced migration failure'); END;"
+			))
 			.unwrap();
+		let new_profile = EmbeddingProfile {
+			model: "new-model".to_string(),
+			version: None,
+			dimensions: 3,
+		};
 
-		assert_eq!(version, SCHEMA_VERSION);
-		assert_eq!(foreign_keys, 1);
+		assert!(storage
+			.migrate_embeddings(
+				&new_profile,
+				&[
+					(first.id, vec![1.0, 0.0, 0.0]),
+					(second.id, vec![0.0, 1.0, 0.0])
+				],
+			)
+			.is_err());
+
+		assert_eq!(storage.embedding_profile().unwrap(), Some(old_profile));
+		assert_eq!(storage.get(first.id).unwrap().meaning, first.meaning);
+		assert_eq!(storage.get(second.id).unwrap().meaning, second.meaning);
+		assert_eq!(storage.revisions(first.id).unwrap().len(), 1);
+		assert_eq!(storage.revisions(second.id).unwrap().len(), 1);
 	}
 
 	#[test]
-	fn test_file_storage_configures_durability_and_contention() {
-		let directory = tempfile::TempDir::new().unwrap();
-		let storage = SqliteStorage::new(directory.path().join("configured.db")).unwrap();
-		let journal_mode: String = storage
-			.conn
-			.query_row("PRAGMA journal_mode", [], |row| row.get(0))
-			.unwrap();
-		let synchronous: i64 = storage
-			.conn
-			.query_row("PRAGMA synchronous", [], |row| row.get(0))
-			.unwrap();
-		let busy_timeout: i64 = storage
-			.conn
-			.query_row("PRAGMA busy_timeout", [], |row| row.get(0))
+	fn test_migrate_embeddings_requires_exact_valid_replacements() {
+		let mut storage = create_test_storage();
+		let first = create_test_entry(vec![0.1], "First");
+		let second = create_test_entry(vec![0.2], "Second");
+		storage
+			.insert_batch(&[first.clone(), second.clone()])
 			.unwrap();
+		let profile = EmbeddingProfile {
+			model: "new-model".to_string(),
+			version: None,
+			dimensions: 2,
+		};
 
-		assert_eq!(journal_mode, "wal");
-		assert_eq!(synchronous, 1);
-		assert_eq!(busy_timeout, 5_000);
+		assert!(storage
+			.migrate_embeddings(&profile, &[(first.id, vec![1.0, 0.0])])
+			.is_err());
+		assert!(storage
+			.migrate_embeddings(
+				&profile,
+				&[(first.id, vec![1.0, 0.0]), (first.id, vec![0.0, 1.0])],
+			)
+			.is_err());
+		assert!(storage
+			.migrate_embeddings(
+				&profile,
+				&[(first.id, vec![f32::NAN, 0.0]), (second.id, vec![0.0, 1.0])],
+			)
+			.is_err());
+		assert_eq!(storage.get(first.id).unwrap().meaning, first.meaning);
+		assert_eq!(storage.get(second.id).unwrap().meaning, second.meaning);
 	}
 
 	#[test]
-	fn test_legacy_database_migrates_with_snapshot_history() {
-		let directory = tempfile::TempDir::new().unwrap();
-		let path = directory.path().join("legacy.db");
-		let entry = create_test_entry(vec![0.1, 0.2], "Legacy");
-		let connection = Connection::open(&path).unwrap();
-		connection
-			.execute_batch(
-				"CREATE TABLE entries (
-					id TEXT PRIMARY KEY, meaning BLOB NOT NULL, expression TEXT NOT NULL,
-					context TEXT NOT NULL, created_at TEXT NOT NULL, updated_at TEXT NOT NULL
-				);
-				CREATE TABLE relations (
-					from_id TEXT NOT NULL, to_id TEXT NOT NULL,
-					PRIMARY KEY (from_id, to_id)
-				);",
-			)
+	fn test_integrity_check_reports_partial_and_malformed_profile_metadata() {
+		let mut storage = create_test_storage();
+		storage
+			.insert(&create_test_entry(vec![0.1, 0.2], "Entry"))
 			.unwrap();
-		connection
+		storage
+			.conn
 			.execute(
-				"INSERT INTO entries VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-				params![
-					entry.id.to_string(),
-					vector_codec::serialize(&entry.meaning).unwrap(),
-					entry.expression,
-					serde_json::to_string(&entry.context).unwrap(),
-					entry.created_at.to_rfc3339(),
-					entry.updated_at.to_rfc3339(),
-				],
+				"INSERT INTO contextdb_metadata (key, value) VALUES ('embedding_model_version', 'v1')",
+				[],
 			)
 			.unwrap();
-		drop(connection);
 
-		let storage = SqliteStorage::new(&path).unwrap();
+		let partial = storage.integrity_check().unwrap();
+		assert!(partial.issues.iter().any(|issue| issue.area == "metadata"));
 
-		assert_eq!(storage.get(entry.id).unwrap().expression, "Legacy");
-		assert_eq!(
-			storage.revisions(entry.id).unwrap()[0].operation,
-			RevisionOperation::Snapshot
-		);
+		storage
+			.conn
+			.execute(
+				"UPDATE contextdb_metadata SET value = 'invalid' WHERE key = 'vector_dimension'",
+				[],
+			)
+			.unwrap();
+		let malformed = storage.integrity_check().unwrap();
+		assert!(malformed
+			.issues
+			.iter()
+			.any(|issue| issue.area == "metadata" && issue.message.contains("vector dimension")));
 	}
 
 	#[test]
-	fn test_legacy_database_with_orphan_relation_is_rejected() {
+	fn test_backup_and_restore_preserve_data() {
 		let directory = tempfile::TempDir::new().unwrap();
-		let path = directory.path().join("orphan.db");
-		let connection = Connection::open(&path).unwrap();
-		connection
-			.execute_batch(
-				"CREATE TABLE entries (
-					id TEXT PRIMARY KEY, meaning BLOB NOT NULL, expression TEXT NOT NULL,
-					context TEXT NOT NULL, created_at TEXT NOT NULL, updated_at TEXT NOT NULL
-				);
-				CREATE TABLE relations (
-					from_id TEXT NOT NULL, to_id TEXT NOT NULL,
-					PRIMARY KEY (from_id, to_id)
-				);",
-			)
-			.unwrap();
-		connection
-			.execute(
-				"INSERT INTO relations VALUES (?1, ?2)",
-				params![Uuid::new_v4().to_string(), Uuid::new_v4().to_string()],
-			)
-			.unwrap();
-		drop(connection);
+		let source_path = directory.path().join("source.db");
+		let backup_path = directory.path().join("backup.db");
+		let restored_path = directory.path().join("restored.db");
+		let mut source = SqliteStorage::new(&source_path).unwrap();
+		let entry = create_test_entry(vec![0.1], "Backed up");
+		source.insert(&entry).unwrap();
+		source.backup_to(&backup_path).unwrap();
 
-		let result = SqliteStorage::new(&path);
+		SqliteStorage::restore_from(&backup_path, &restored_path).unwrap();
+		let restored = SqliteStorage::new(&restored_path).unwrap();
 
-		assert!(matches!(
-			result,
-			Err(StorageError::Database(message)) if message.contains("invalid relation")
-		));
+		assert_eq!(restored.get(entry.id).unwrap().expression, "Backed up");
+		assert!(restored.integrity_check().unwrap().is_healthy());
 	}
 
-	// ==================== Insert Tests ====================
-
 	#[test]
-	fn test_insert_single_entry() {
+	fn test_revision_history_survives_deletion() {
 		let mut storage = create_test_storage();
-		let entry = create_test_entry(vec![0.1, 0.2, 0.3], "Test entry");
+		let mut entry = create_test_entry(vec![0.1], "Original");
+		storage.insert(&entry).unwrap();
+		entry.expression = "Updated".to_string();
+		entry.updated_at = Utc::now();
+		storage.update(&entry).unwrap();
+		storage.delete(entry.id).unwrap();
 
-		let result = storage.insert(&entry);
-		assert!(result.is_ok());
-		assert_eq!(storage.count().unwrap(), 1);
+		let revisions = storage.revisions(entry.id).unwrap();
+		assert_eq!(revisions.len(), 3);
+		assert_eq!(revisions[0].operation, RevisionOperation::Insert);
+		assert_eq!(revisions[0].snapshot.expression, "Original");
+		assert_eq!(revisions[1].operation, RevisionOperation::Update);
+		assert_eq!(revisions[1].snapshot.expression, "Updated");
+		assert_eq!(revisions[2].operation, RevisionOperation::Delete);
+		assert_eq!(revisions[2].snapshot.expression, "Updated");
 	}
 
 	#[test]
-	fn test_insert_multiple_entries() {
+	fn test_revert_restores_snapshot_as_new_revision() {
 		let mut storage = create_test_storage();
+		let mut entry = create_test_entry(vec![0.1], "Original");
+		storage.insert(&entry).unwrap();
+		entry.expression = "Updated".to_string();
+		entry.updated_at = Utc::now();
+		storage.update(&entry).unwrap();
+		let original = storage.revisions(entry.id).unwrap()[0].clone();
 
-		for i in 0..10 {
-			let entry = create_test_entry(vec![i as f32], &format!("Entry {}", i));
-			storage.insert(&entry).unwrap();
-		}
+		let restored = storage.revert(entry.id, original.revision_id).unwrap();
 
-		assert_eq!(storage.count().unwrap(), 10);
+		assert_eq!(restored.expression, "Original");
+		assert!(restored.updated_at >= entry.updated_at);
+		assert_eq!(storage.get(entry.id).unwrap().expression, "Original");
+		let revisions = storage.revisions(entry.id).unwrap();
+		assert_eq!(revisions.len(), 3);
+		assert_eq!(revisions[2].operation, RevisionOperation::Revert);
+		assert!(matches!(
+			storage.revert(entry.id, Uuid::new_v4()),
+			Err(StorageError::NotFound(_))
+		));
+		assert!(storage.integrity_check().unwrap().is_healthy());
 	}
 
 	#[test]
-	fn test_insert_batch_supports_internal_relations() {
+	fn test_undelete_revalidates_relations() {
 		let mut storage = create_test_storage();
 		let target = create_test_entry(vec![0.1], "Target");
-		let source = create_test_entry(vec![0.2], "Source").add_relation(target.id);
+		let entry = create_test_entry(vec![0.2], "Entry").add_relation(target.id);
+		storage
+			.insert_batch(&[target.clone(), entry.clone()])
+			.unwrap();
+		storage.delete(entry.id).unwrap();
+		storage.delete(target.id).unwrap();
 
-		storage.insert_batch(&[source.clone(), target]).unwrap();
+		assert!(matches!(
+			storage.undelete(entry.id),
+			Err(StorageError::NotFound(id)) if id == target.id
+		));
+		storage.undelete(target.id).unwrap();
+		let restored = storage.undelete(entry.id).unwrap();
 
-		assert_eq!(storage.count().unwrap(), 2);
-		assert_eq!(storage.get(source.id).unwrap().relations.len(), 1);
+		assert_eq!(restored.relations, vec![target.id]);
+		assert_eq!(storage.get(entry.id).unwrap().relations, vec![target.id]);
+		assert!(matches!(
+			storage.undelete(entry.id),
+			Err(StorageError::InvalidArgument(_))
+		));
+		assert!(matches!(
+			storage.undelete(Uuid::new_v4()),
+			Err(StorageError::NotFound(_))
+		));
 	}
 
 	#[test]
-	fn test_insert_batch_rolls_back_on_duplicate() {
+	fn test_revert_rejects_snapshots_from_previous_embeddings() {
 		let mut storage = create_test_storage();
-		let entry = create_test_entry(vec![0.1], "Duplicate");
+		let entry = create_test_entry(vec![0.1, 0.2], "Entry");
+		storage.insert(&entry).unwrap();
+		let profile = EmbeddingProfile {
+			model: "new-model".to_string(),
+			version: None,
+			dimensions: 2,
+		};
+		storage
+			.migrate_embeddings(&profile, &[(entry.id, vec![1.0, 0.0])])
+			.unwrap();
+		let revisions = storage.revisions(entry.id).unwrap();
 
-		assert!(storage.insert_batch(&[entry.clone(), entry]).is_err());
-		assert_eq!(storage.count().unwrap(), 0);
+		assert!(matches!(
+			storage.revert(entry.id, revisions[0].revision_id),
+			Err(StorageError::InvalidArgument(_))
+		));
+		storage.revert(entry.id, revisions[1].revision_id).unwrap();
+		assert_eq!(storage.get(entry.id).unwrap().meaning, vec![1.0, 0.0]);
+	}
+
+	fn update_repeatedly(storage: &mut SqliteStorage, entry: &mut Entry, times: usize) {
+		for revision in 0..times {
+			entry.expression = format!("Revision {revision}");
+			entry.updated_at = Utc::now();
+			storage.update(entry).unwrap();
+		}
 	}
 
 	#[test]
-	fn test_insert_batch_rejects_invalid_timestamps_atomically() {
+	fn test_compaction_keeps_last_revisions_and_deletes() {
 		let mut storage = create_test_storage();
-		let valid = create_test_entry(vec![0.1], "Valid");
-		let mut invalid = create_test_entry(vec![0.2], "Invalid");
-		invalid.updated_at = invalid.created_at - chrono::Duration::seconds(1);
+		let mut kept = create_test_entry(vec![0.1], "Kept");
+		let mut deleted = create_test_entry(vec![0.2], "Deleted");
+		storage
+			.insert_batch(&[kept.clone(), deleted.clone()])
+			.unwrap();
+		update_repeatedly(&mut storage, &mut kept, 4);
+		update_repeatedly(&mut storage, &mut deleted, 2);
+		storage.delete(deleted.id).unwrap();
+
+		let report = storage
+			.compact_revisions_with(&RetentionPolicy {
+				keep_last: Some(2),
+				..RetentionPolicy::default()
+			})
+			.unwrap();
 
-		assert!(storage.insert_batch(&[valid, invalid]).is_err());
-		assert_eq!(storage.count().unwrap(), 0);
+		assert_eq!(report.revisions_examined, 9);
+		assert_eq!(report.revisions_removed, 5);
+		let history = storage.revisions(kept.id).unwrap();
+		assert_eq!(history.len(), 2);
+		assert_eq!(history[1].snapshot.expression, "Revision 3");
+		let deleted_history = storage.revisions(deleted.id).unwrap();
+		assert_eq!(deleted_history.len(), 2);
+		assert_eq!(deleted_history[1].operation, RevisionOperation::Delete);
+		storage.undelete(deleted.id).unwrap();
+		assert!(storage.integrity_check().unwrap().is_healthy());
 	}
 
 	#[test]
-	fn test_insert_entry_with_context() {
+	fn test_compaction_time_window_and_latest_revision_are_kept() {
 		let mut storage = create_test_storage();
-		let context = serde_json::json!({
-			"source": "test",
-			"priority": 1,
-			"tags": ["a", "b", "c"]
-		});
-		let entry = create_test_entry(vec![0.1], "With context").with_context(context);
-
+		let mut entry = create_test_entry(vec![0.1], "Entry");
 		storage.insert(&entry).unwrap();
+		update_repeatedly(&mut storage, &mut entry, 2);
+		storage.delete(entry.id).unwrap();
 
-		let retrieved = storage.get(entry.id).unwrap();
-		assert_eq!(retrieved.context["source"], "test");
-		assert_eq!(retrieved.context["priority"], 1);
+		let recent = storage
+			.compact_revisions_with(&RetentionPolicy {
+				keep_last: Some(1),
+				keep_for_days: Some(1),
+				..RetentionPolicy::default()
+			})
+			.unwrap();
+		assert_eq!(recent.revisions_removed, 0);
+
+		let report = storage
+			.compact_revisions_with(&RetentionPolicy {
+				keep_last: Some(1),
+				keep_deletes: false,
+				..RetentionPolicy::default()
+			})
+			.unwrap();
+		assert_eq!(report.revisions_removed, 3);
+		let history = storage.revisions(entry.id).unwrap();
+		assert_eq!(history.len(), 1);
+		assert_eq!(history[0].operation, RevisionOperation::Delete);
 	}
 
 	#[test]
-	fn test_insert_entry_with_relations() {
+	fn test_delta_encoded_history_decodes_identically() {
 		let mut storage = create_test_storage();
+		let mut entry = create_test_entry(vec![0.25; 64], "Entry")
+			.with_context(serde_json::json!({"tags": ["a"]}));
+		storage.insert(&entry).unwrap();
+		update_repeatedly(&mut storage, &mut entry, 20);
+		let checkpoint = pause();
+		entry.context = serde_json::json!({"tags": ["a", "b"]});
+		entry.updated_at = Utc::now();
+		storage.update(&entry).unwrap();
+		let before = storage.revisions(entry.id).unwrap();
 
-		// Insert two entries
-		let entry1 = create_test_entry(vec![0.1], "Entry 1");
-		let entry2 = create_test_entry(vec![0.2], "Entry 2");
-		storage.insert(&entry1).unwrap();
-		storage.insert(&entry2).unwrap();
-
-		// Insert entry with relation
-		let entry3 = create_test_entry(vec![0.3], "Entry 3")
-			.add_relation(entry1.id)
-			.add_relation(entry2.id);
-		storage.insert(&entry3).unwrap();
+		let report = storage
+			.compact_revisions_with(&RetentionPolicy {
+				delta_encoding: true,
+				..RetentionPolicy::default()
+			})
+			.unwrap();
 
-		let retrieved = storage.get(entry3.id).unwrap();
-		assert_eq!(retrieved.relations.len(), 2);
-		assert!(retrieved.relations.contains(&entry1.id));
-		assert!(retrieved.relations.contains(&entry2.id));
-	}
+		assert_eq!(report.revisions_removed, 0);
+		assert_eq!(report.delta_revisions, 20);
+		assert!(report.bytes_after < report.bytes_before);
+		let after = storage.revisions(entry.id).unwrap();
+		assert_eq!(
+			serde_json::to_value(&before).unwrap(),
+			serde_json::to_value(&after).unwrap()
+		);
+		assert!(storage.integrity_check().unwrap().is_healthy());
 
-	#[test]
-	fn test_insert_entry_with_empty_meaning() {
-		let mut storage = create_test_storage();
-		let entry = create_test_entry(vec![], "No embedding");
+		let past = storage.query(&Query::new().as_of(checkpoint)).unwrap();
+		assert_eq!(past[0].entry.expression, "Revision 19");
+		storage.revert(entry.id, after[5].revision_id).unwrap();
+		assert_eq!(storage.get(entry.id).unwrap().expression, "Revision 4");
 
-		let result = storage.insert(&entry);
-		assert!(matches!(result, Err(StorageError::InvalidDimensions)));
+		let pruned = storage
+			.compact_revisions_with(&RetentionPolicy {
+				keep_last: Some(3),
+				delta_encoding: true,
+				..RetentionPolicy::default()
+			})
+			.unwrap();
+		assert_eq!(pruned.revisions_removed, 20);
+		assert_eq!(storage.revisions(entry.id).unwrap().len(), 3);
+		storage
+			.compact_revisions_with(&RetentionPolicy::default())
+			.unwrap();
+		assert!(storage.integrity_check().unwrap().is_healthy());
 	}
 
 	#[test]
-	fn test_insert_rejects_non_finite_meaning() {
+	fn test_retention_policy_is_stored_and_validated() {
 		let mut storage = create_test_storage();
-		let entry = create_test_entry(vec![f32::NAN], "Invalid embedding");
+		assert_eq!(
+			storage.retention_policy().unwrap(),
+			RetentionPolicy::default()
+		);
+		let policy = RetentionPolicy {
+			keep_last: Some(5),
+			keep_for_days: Some(30),
+			keep_deletes: true,
+			delta_encoding: true,
+		};
+
+		storage.set_retention_policy(&policy).unwrap();
 
+		assert_eq!(storage.retention_policy().unwrap(), policy);
+		assert_eq!(storage.compact_revisions().unwrap().revisions_examined, 0);
 		assert!(matches!(
-			storage.insert(&entry),
-			Err(StorageError::InvalidDimensions)
+			storage.set_retention_policy(&RetentionPolicy {
+				keep_last: Some(0),
+				..RetentionPolicy::default()
+			}),
+			Err(StorageError::InvalidArgument(_))
 		));
 	}
 
 	#[test]
-	fn test_insert_rejects_mismatched_dimensions() {
+	fn test_purge_erases_entry_history_and_edges() {
 		let mut storage = create_test_storage();
+		let target = create_test_entry(vec![0.1], "Personal detail");
+		let source = create_test_entry(vec![0.2], "Source").add_relation(target.id);
+		let outgoing = create_test_entry(vec![0.3], "Outgoing");
+		let mut purged = target.clone();
 		storage
-			.insert(&create_test_entry(vec![0.1, 0.2], "First"))
+			.insert_batch(&[target.clone(), source.clone(), outgoing.clone()])
 			.unwrap();
+		purged.relations = vec![outgoing.id];
+		purged.updated_at = Utc::now();
+		storage.update(&purged).unwrap();
+
+		storage.purge(target.id).unwrap();
 
 		assert!(matches!(
-			storage.insert(&create_test_entry(vec![0.1], "Second")),
-			Err(StorageError::InvalidDimensions)
+			storage.get(target.id),
+			Err(StorageError::NotFound(_))
 		));
+		assert!(storage.revisions(target.id).unwrap().is_empty());
+		assert!(storage.get(source.id).unwrap().relations.is_empty());
+		assert_eq!(
+			storage
+				.revisions(source.id)
+				.unwrap()
+				.last()
+				.unwrap()
+				.operation,
+			RevisionOperation::Unrelate
+		);
+		let search_rows: i64 = storage
+			.conn
+			.query_row(
+				"SELECT COUNT(*) FROM entries_fts WHERE entries_fts MATCH 'personal'",
+				[],
+				|row| row.get(0),
+			)
+			.unwrap();
+		assert_eq!(search_rows, 0);
+		let tombstones = storage.tombstones().unwrap();
+		assert_eq!(tombstones.len(), 1);
+		assert_eq!(tombstones[0].entry_id, target.id);
+		assert!(storage.integrity_check().unwrap().is_healthy());
 	}
 
 	#[test]
-	fn test_insert_entry_with_large_vector() {
+	fn test_purge_covers_deleted_entries_and_is_idempotent() {
 		let mut storage = create_test_storage();
-		let large_vector: Vec<f32> = (0..1536).map(|i| i as f32 / 1536.0).collect();
-		let entry = create_test_entry(large_vector.clone(), "Large vector");
-
+		let entry = create_test_entry(vec![0.1], "Deleted earlier");
 		storage.insert(&entry).unwrap();
+		storage.delete(entry.id).unwrap();
 
-		let retrieved = storage.get(entry.id).unwrap();
-		assert_eq!(retrieved.meaning.len(), 1536);
-		assert!((retrieved.meaning[0] - large_vector[0]).abs() < 0.0001);
-	}
+		storage.purge(entry.id).unwrap();
+		storage.purge(entry.id).unwrap();
 
-	// ==================== Get Tests ====================
+		assert!(storage.revisions(entry.id).unwrap().is_empty());
+		assert_eq!(storage.tombstones().unwrap().len(), 1);
+		assert!(matches!(
+			storage.purge(Uuid::new_v4()),
+			Err(StorageError::NotFound(_))
+		));
+	}
 
 	#[test]
-	fn test_get_existing_entry() {
+	fn test_purge_matching_erases_query_results() {
 		let mut storage = create_test_storage();
-		let entry = create_test_entry(vec![0.1, 0.2, 0.3], "Test entry");
-		storage.insert(&entry).unwrap();
+		let subject = create_test_entry(vec![0.1], "Subject")
+			.with_context(serde_json::json!({"user": "alice"}));
+		let other =
+			create_test_entry(vec![0.2], "Other").with_context(serde_json::json!({"user": "bob"}));
+		storage
+			.insert_batch(&[subject.clone(), other.clone()])
+			.unwrap();
 
-		let retrieved = storage.get(entry.id).unwrap();
+		let purged = storage
+			.purge_matching(&Query::new().with_context(ContextFilter::PathEquals(
+				"/user".to_string(),
+				serde_json::json!("alice"),
+			)))
+			.unwrap();
 
-		assert_eq!(retrieved.id, entry.id);
-		assert_eq!(retrieved.expression, entry.expression);
-		assert_eq!(retrieved.meaning, entry.meaning);
+		assert_eq!(purged, vec![subject.id]);
+		assert_eq!(storage.count().unwrap(), 1);
+		assert!(storage.get(other.id).is_ok());
 	}
 
 	#[test]
-	fn test_get_nonexistent_entry() {
-		let storage = create_test_storage();
-		let fake_id = Uuid::new_v4();
+	fn test_purge_and_vacuum_remove_bytes_from_database_files() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("erasure.db");
+		let mut storage = SqliteStorage::new(&path).unwrap();
+		let mut entry = create_test_entry(vec![0.1], "zqxmarker confidential");
+		storage.insert(&entry).unwrap();
+		entry.expression = "zqxmarker revised".to_string();
+		entry.updated_at = Utc::now();
+		storage.update(&entry).unwrap();
 
-		let result = storage.get(fake_id);
-		assert!(result.is_err());
+		storage.purge(entry.id).unwrap();
+		storage.vacuum().unwrap();
 
-		match result {
-			Err(StorageError::NotFound(id)) => assert_eq!(id, fake_id),
-			_ => panic!("Expected NotFound error"),
+		for file in std::fs::read_dir(directory.path()).unwrap() {
+			let bytes = std::fs::read(file.unwrap().path()).unwrap();
+			assert!(!bytes.windows(9).any(|window| window == b"zqxmarker"));
 		}
+		assert!(storage.integrity_check().unwrap().is_healthy());
 	}
 
 	#[test]
-	fn test_get_preserves_timestamps() {
+	fn test_change_feed_records_committed_mutations_in_order() {
 		let mut storage = create_test_storage();
-		let entry = create_test_entry(vec![0.1], "Test");
-		let original_created = entry.created_at;
-		let original_updated = entry.updated_at;
-		storage.insert(&entry).unwrap();
-
-		let retrieved = storage.get(entry.id).unwrap();
+		let target = create_test_entry(vec![0.1], "Target");
+		let mut source = create_test_entry(vec![0.2], "Source");
+		storage
+			.insert_batch(&[target.clone(), source.clone()])
+			.unwrap();
+		storage.relate(source.id, target.id).unwrap();
+		source = storage.get(source.id).unwrap();
+		source.expression = "Source revised".to_string();
+		source.updated_at = Utc::now();
+		storage.update(&source).unwrap();
+		storage.delete(source.id).unwrap();
+		storage.purge(source.id).unwrap();
+		storage.purge(source.id).unwrap();
+
+		// A failed batch leaves no trace in the feed.
+		let duplicate = create_test_entry(vec![0.3], "Duplicate");
+		assert!(storage
+			.insert_batch(&[duplicate.clone(), duplicate])
+			.is_err());
 
-		// Timestamps should be close (within 1 second due to serialization)
-		assert!(
-			(retrieved.created_at - original_created)
-				.num_seconds()
-				.abs() < 1
-		);
-		assert!(
-			(retrieved.updated_at - original_updated)
-				.num_seconds()
-				.abs() < 1
+		let changes = storage.changes_since(0, 100).unwrap();
+		let operations: Vec<_> = changes
+			.iter()
+			.map(|change| (change.entry_id, change.operation))
+			.collect();
+		assert_eq!(
+			operations,
+			vec![
+				(target.id, ChangeOperation::Insert),
+				(source.id, ChangeOperation::Insert),
+				(source.id, ChangeOperation::Relate),
+				(source.id, ChangeOperation::Update),
+				(source.id, ChangeOperation::Delete),
+				(source.id, ChangeOperation::Purge),
+			]
 		);
-	}
+		assert!(changes.windows(2).all(|pair| pair[0].seq < pair[1].seq));
+		assert!(changes[5].revision_id.is_none());
+		let target_history = storage.revisions(target.id).unwrap();
+		assert_eq!(changes[0].revision_id, Some(target_history[0].revision_id));
 
-	// ==================== Update Tests ====================
+		let page = storage.changes_since(changes[1].seq, 2).unwrap();
+		assert_eq!(page, changes[2..4].to_vec());
+		assert!(storage
+			.changes_since(changes[5].seq, 10)
+			.unwrap()
+			.is_empty());
+		assert!(matches!(
+			storage.changes_since(0, 0),
+			Err(StorageError::InvalidArgument(_))
+		));
+	}
 
 	#[test]
-	fn test_update_entry_expression() {
+	fn test_change_sequence_is_not_reused_after_rows_are_removed() {
 		let mut storage = create_test_storage();
-		let mut entry = create_test_entry(vec![0.1], "Original");
-		storage.insert(&entry).unwrap();
-
-		entry.expression = "Updated".to_string();
-		entry.updated_at = Utc::now();
-		storage.update(&entry).unwrap();
+		let first = create_test_entry(vec![0.1], "First");
+		storage.insert(&first).unwrap();
+		let last_seq = storage.changes_since(0, 10).unwrap()[0].seq;
+		storage.conn.execute("DELETE FROM change_log", []).unwrap();
 
-		let retrieved = storage.get(entry.id).unwrap();
-		assert_eq!(retrieved.expression, "Updated");
+		let second = create_test_entry(vec![0.2], "Second");
+		storage.insert(&second).unwrap();
+		let changes = storage.changes_since(0, 10).unwrap();
+		assert_eq!(changes.len(), 1);
+		assert!(changes[0].seq > last_seq);
 	}
 
 	#[test]
-	fn test_update_entry_meaning() {
+	fn test_upsert_inserts_updates_and_skips_identical_writes() {
 		let mut storage = create_test_storage();
-		let mut entry = create_test_entry(vec![0.1, 0.2], "Test");
-		storage.insert(&entry).unwrap();
+		let mut entry = create_test_entry(vec![0.1, 0.2], "Ingested");
 
-		entry.meaning = vec![0.9, 0.8];
-		storage.update(&entry).unwrap();
+		assert_eq!(storage.upsert(&entry).unwrap(), UpsertOutcome::Inserted);
+		assert_eq!(storage.upsert(&entry).unwrap(), UpsertOutcome::Unchanged);
+		entry.expression = "Ingested again".to_string();
+		entry.updated_at = Utc::now();
+		assert_eq!(storage.upsert(&entry).unwrap(), UpsertOutcome::Updated);
 
-		let retrieved = storage.get(entry.id).unwrap();
-		assert_eq!(retrieved.meaning, vec![0.9, 0.8]);
+		assert_eq!(storage.get(entry.id).unwrap().expression, "Ingested again");
+		let operations: Vec<_> = storage
+			.revisions(entry.id)
+			.unwrap()
+			.into_iter()
+			.map(|revision| revision.operation)
+			.collect();
+		assert_eq!(
+			operations,
+			vec![RevisionOperation::Insert, RevisionOperation::Update]
+		);
+
+		let mut recreated = entry.clone();
+		recreated.created_at = Utc::now();
+		recreated.updated_at = recreated.created_at;
+		assert!(matches!(
+			storage.upsert(&recreated),
+			Err(StorageError::Database(_))
+		));
 	}
 
 	#[test]
-	fn test_update_entry_context() {
+	fn test_upsert_batch_is_atomic_and_resolves_batch_relations() {
 		let mut storage = create_test_storage();
-		let mut entry =
-			create_test_entry(vec![0.1], "Test").with_context(serde_json::json!({"version": 1}));
-		storage.insert(&entry).unwrap();
-
-		entry.context = serde_json::json!({"version": 2, "new_field": "added"});
-		storage.update(&entry).unwrap();
+		let mut existing = create_test_entry(vec![0.1], "Existing");
+		storage.insert(&existing).unwrap();
+		let new_target = create_test_entry(vec![0.2], "New target");
+		existing.relations = vec![new_target.id];
+		existing.updated_at = Utc::now();
+
+		let outcomes = storage
+			.upsert_batch(&[existing.clone(), new_target.clone()])
+			.unwrap();
+		assert_eq!(
+			outcomes,
+			vec![UpsertOutcome::Updated, UpsertOutcome::Inserted]
+		);
+		assert_eq!(
+			storage.get(existing.id).unwrap().relations,
+			vec![new_target.id]
+		);
 
-		let retrieved = storage.get(entry.id).unwrap();
-		assert_eq!(retrieved.context["version"], 2);
-		assert_eq!(retrieved.context["new_field"], "added");
+		let fresh = create_test_entry(vec![0.3], "Fresh");
+		let dangling = create_test_entry(vec![0.4], "Dangling").add_relation(Uuid::new_v4());
+		assert!(matches!(
+			storage.upsert_batch(&[fresh.clone(), dangling]),
+			Err(StorageError::NotFound(_))
+		));
+		assert!(matches!(
+			storage.get(fresh.id),
+			Err(StorageError::NotFound(_))
+		));
+		assert_eq!(storage.count().unwrap(), 2);
 	}
 
 	#[test]
-	fn test_update_entry_relations() {
+	fn test_patch_writes_only_changed_fields() {
 		let mut storage = create_test_storage();
-
-		let target1 = create_test_entry(vec![0.1], "Target 1");
-		let target2 = create_test_entry(vec![0.2], "Target 2");
-		storage.insert(&target1).unwrap();
-		storage.insert(&target2).unwrap();
-
-		let mut entry = create_test_entry(vec![0.3], "Entry").add_relation(target1.id);
+		let entry = create_test_entry(vec![0.1, 0.2], "Quarterly plan")
+			.with_context(serde_json::json!({"status": "draft", "owner": "ana"}));
 		storage.insert(&entry).unwrap();
+		storage
+			.conn
+			.execute_batch(
+				"CREATE TEMP TABLE expression_writes (id TEXT);
+				 CREATE TEMP TRIGGER count_expression_writes AFTER UPDATE OF expression ON entries
+				 BEGIN INSERT INTO expression_writes VALUES (new.id); END;",
+			)
+			.unwrap();
+		let expression_writes = |storage: &SqliteStorage| -> i64 {
+			storage
+				.conn
+				.query_row("SELECT COUNT(*) FROM expression_writes", [], |row| {
+					row.get(0)
+				})
+				.unwrap()
+		};
 
-		// Update relations
-		entry.relations = vec![target2.id];
-		storage.update(&entry).unwrap();
+		let patched = storage
+			.patch(
+				entry.id,
+				&EntryPatch::new()
+					.with_context_patch(serde_json::json!({"status": "final", "owner": null})),
+			)
+			.unwrap();
+		assert_eq!(patched.context, serde_json::json!({"status": "final"}));
+		assert!(patched.updated_at >= entry.updated_at);
+		assert_eq!(expression_writes(&storage), 0);
 
-		let retrieved = storage.get(entry.id).unwrap();
-		assert_eq!(retrieved.relations.len(), 1);
-		assert!(retrieved.relations.contains(&target2.id));
-		assert!(!retrieved.relations.contains(&target1.id));
-	}
+		storage
+			.patch(entry.id, &EntryPatch::new().with_expression("Annual plan"))
+			.unwrap();
+		assert_eq!(expression_writes(&storage), 1);
+		let found = storage
+			.query(&Query::new().with_expression(ExpressionFilter::FullText("annual".into())))
+			.unwrap();
+		assert_eq!(found.len(), 1);
 
-	#[test]
-	fn test_update_nonexistent_entry_returns_not_found() {
-		let mut storage = create_test_storage();
-		let entry = create_test_entry(vec![0.1], "Missing");
+		let stored = storage.get(entry.id).unwrap();
+		assert_eq!(stored.meaning, entry.meaning);
+		assert_eq!(stored.context, serde_json::json!({"status": "final"}));
+		assert_eq!(storage.revisions(entry.id).unwrap().len(), 3);
+
+		let unchanged = storage
+			.patch(entry.id, &EntryPatch::new().with_expression("Annual plan"))
+			.unwrap();
+		assert_eq!(unchanged.updated_at, stored.updated_at);
+		assert_eq!(storage.revisions(entry.id).unwrap().len(), 3);
 
 		assert!(matches!(
-			storage.update(&entry),
-			Err(StorageError::NotFound(id)) if id == entry.id
+			storage.patch(entry.id, &EntryPatch::new().with_meaning(vec![0.1])),
+			Err(StorageError::InvalidDimensions)
+		));
+		assert!(matches!(
+			storage.patch(Uuid::new_v4(), &EntryPatch::new().with_expression("x")),
+			Err(StorageError::NotFound(_))
 		));
 	}
 
 	#[test]
-	fn test_update_batch_is_atomic() {
+	fn test_every_write_advances_the_entry_version() {
 		let mut storage = create_test_storage();
-		let mut first = create_test_entry(vec![0.1], "First");
-		let second = create_test_entry(vec![0.2], "Second");
-		storage.insert(&first).unwrap();
-		first.expression = "Changed".to_string();
-
-		assert!(storage.update_batch(&[first.clone(), second]).is_err());
-		assert_eq!(storage.get(first.id).unwrap().expression, "First");
-	}
+		let target = create_test_entry(vec![0.1], "Target");
+		let mut entry = create_test_entry(vec![0.2], "Versioned");
+		assert_eq!(entry.version, 0);
+		storage
+			.insert_batch(&[target.clone(), entry.clone()])
+			.unwrap();
+		assert_eq!(storage.get(entry.id).unwrap().version, 1);
 
-	// ==================== Delete Tests ====================
+		entry.expression = "Versioned, revised".to_string();
+		entry.updated_at = Utc::now();
+		storage.update(&entry).unwrap();
+		storage.relate(entry.id, target.id).unwrap();
+		storage
+			.patch(entry.id, &EntryPatch::new().with_expression("Patched"))
+			.unwrap();
+		storage.purge(target.id).unwrap();
+		assert_eq!(storage.get(entry.id).unwrap().version, 5);
 
-	#[test]
-	fn test_delete_entry() {
-		let mut storage = create_test_storage();
-		let entry = create_test_entry(vec![0.1], "Test");
-		storage.insert(&entry).unwrap();
+		let versions: Vec<u64> = storage
+			.revisions(entry.id)
+			.unwrap()
+			.iter()
+			.map(|revision| revision.snapshot.version)
+			.collect();
+		assert_eq!(versions, vec![1, 2, 3, 4, 5]);
 
-		let result = storage.delete(entry.id);
-		assert!(result.is_ok());
-		assert_eq!(storage.count().unwrap(), 0);
+		storage.delete(entry.id).unwrap();
+		assert_eq!(storage.undelete(entry.id).unwrap().version, 6);
+		assert_eq!(storage.get(entry.id).unwrap().version, 6);
 	}
 
 	#[test]
-	fn test_delete_nonexistent_entry() {
+	fn test_conditional_writes_reject_stale_versions() {
 		let mut storage = create_test_storage();
-		let fake_id = Uuid::new_v4();
+		let entry = create_test_entry(vec![0.1], "Shared");
+		storage.insert(&entry).unwrap();
+		let mut first = storage.get(entry.id).unwrap();
+		let mut second = first.clone();
 
-		let result = storage.delete(fake_id);
-		assert!(result.is_err());
+		first.expression = "First worker".to_string();
+		first.updated_at = Utc::now();
+		storage.update_if_version(&first, first.version).unwrap();
 
-		match result {
-			Err(StorageError::NotFound(id)) => assert_eq!(id, fake_id),
-			_ => panic!("Expected NotFound error"),
+		second.expression = "Second worker".to_string();
+		second.updated_at = Utc::now();
+		match storage.update_if_version(&second, second.version) {
+			Err(StorageError::Conflict {
+				id,
+				expected,
+				actual,
+			}) => assert_eq!((id, expected, actual), (entry.id, 1, 2)),
+			other => panic!("expected a version conflict, got {other:?}"),
 		}
+		assert!(matches!(
+			storage.patch_if_version(entry.id, &EntryPatch::new().with_expression("Late"), 1),
+			Err(StorageError::Conflict { .. })
+		));
+		assert!(matches!(
+			storage.delete_if_version(entry.id, 1),
+			Err(StorageError::Conflict { .. })
+		));
+		assert_eq!(storage.get(entry.id).unwrap().expression, "First worker");
+		assert_eq!(storage.revisions(entry.id).unwrap().len(), 2);
+
+		let patched = storage
+			.patch_if_version(entry.id, &EntryPatch::new().with_expression("Patched"), 2)
+			.unwrap();
+		assert_eq!(patched.version, 3);
+		storage.delete_if_version(entry.id, 3).unwrap();
+		assert!(matches!(
+			storage.delete_if_version(entry.id, 3),
+			Err(StorageError::NotFound(_))
+		));
 	}
 
 	#[test]
-	fn test_delete_batch_is_atomic() {
-		let mut storage = create_test_storage();
-		let entry = create_test_entry(vec![0.1], "Existing");
+	fn test_version_guard_detects_concurrent_writers() {
+		let directory = tempfile::TempDir::new().unwrap();
+		let path = directory.path().join("shared.db");
+		let mut storage = SqliteStorage::new(&path).unwrap();
+		let entry = create_test_entry(vec![0.1], "Shared");
 		storage.insert(&entry).unwrap();
+		let stored = storage.get(entry.id).unwrap();
 
-		assert!(storage.delete_batch(&[entry.id, Uuid::new_v4()]).is_err());
-		assert_eq!(storage.count().unwrap(), 1);
+		// Another process bumps the version after this writer read the entry.
+		let other = Connection::open(&path).unwrap();
+		other
+			.execute(
+				"UPDATE entries SET version = version + 1 WHERE id = ?1",
+				params![entry.id.to_string()],
+			)
+			.unwrap();
+		let transaction = storage.conn.transaction().unwrap();
+		assert!(matches!(
+			SqliteStorage::update_entry_row(&transaction, &stored, stored.version),
+			Err(StorageError::Conflict {
+				expected: 1,
+				actual: 2,
+				..
+			})
+		));
 	}
 
 	#[test]
-	fn test_integrity_check_reports_healthy_database() {
+	fn test_collections_keep_separate_embedding_profiles() {
 		let mut storage = create_test_storage();
+		let small = EmbeddingProfile {
+			model: "small".to_string(),
+			version: None,
+			dimensions: 3,
+		};
 		storage
-			.insert(&create_test_entry(vec![0.1], "Healthy"))
+			.set_collection_embedding_profile("agent-7", &small)
+			.unwrap();
+		let user = create_test_entry(vec![1.0, 0.0], "User memory");
+		let agent = create_test_entry(vec![1.0, 0.0, 0.0], "Agent memory").in_collection("agent-7");
+		storage.insert(&user).unwrap();
+		storage.insert(&agent).unwrap();
+
+		assert!(matches!(
+			storage.insert(&create_test_entry(vec![1.0, 0.0], "Wrong").in_collection("agent-7")),
+			Err(StorageError::InvalidDimensions)
+		));
+		assert!(matches!(
+			storage.insert(&create_test_entry(vec![1.0], "Bad name").in_collection("no spaces")),
+			Err(StorageError::InvalidArgument(_))
+		));
+		assert_eq!(storage.embedding_profile().unwrap(), None);
+		assert_eq!(
+			storage.collection_embedding_profile("agent-7").unwrap(),
+			Some(small)
+		);
+		let collections = storage.collections().unwrap();
+		assert_eq!(
+			collections
+				.iter()
+				.map(|info| (info.name.as_str(), info.entries, info.vector_dimension))
+				.collect::<Vec<_>>(),
+			vec![("agent-7", 1, Some(3)), ("default", 1, Some(2))]
+		);
+
+		// Semantic queries must target collections sharing the query's dimensions.
+		assert!(matches!(
+			storage.query(&Query::new().with_meaning(vec![1.0, 0.0], None)),
+			Err(StorageError::InvalidDimensions)
+		));
+		let results = storage
+			.query(
+				&Query::new()
+					.with_meaning(vec![1.0, 0.0], None)
+					.in_collection(DEFAULT_COLLECTION),
+			)
 			.unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].entry.id, user.id);
 
+		let large = EmbeddingProfile {
+			model: "large".to_string(),
+			version: None,
+			dimensions: 4,
+		};
+		storage
+			.migrate_collection_embeddings(
+				"agent-7",
+				&large,
+				&[(agent.id, vec![0.0, 1.0, 0.0, 0.0])],
+			)
+			.unwrap();
+		assert_eq!(storage.get(agent.id).unwrap().meaning.len(), 4);
+		assert_eq!(storage.get(user.id).unwrap().meaning.len(), 2);
 		assert!(storage.integrity_check().unwrap().is_healthy());
 	}
 
 	#[test]
-	fn test_integrity_check_detects_equal_count_full_text_drift() {
+	fn test_query_scopes_filters_full_text_and_indexes_to_collections() {
 		let mut storage = create_test_storage();
-		let entry = create_test_entry(vec![0.1], "Canonical expression");
-		storage.insert(&entry).unwrap();
-		storage
-			.conn
+		let index = storage
+			.create_collection_context_index("notes", "/type")
+			.unwrap();
+		for (collection, expression) in [
+			("notes", "rust ownership notes"),
+			("notes", "gardening notes"),
+			("tasks", "rust upgrade task"),
+			(DEFAULT_COLLECTION, "rust default"),
+		] {
+			storage
+				.insert(
+					&create_test_entry(vec![1.0], expression)
+						.with_context(serde_json::json!({"type": "memory"}))
+						.in_collection(collection),
+				)
+				.unwrap();
+		}
+
+		let full_text = storage
 			.execute(
-				"UPDATE entries_fts SET expression = 'stale expression' WHERE id = ?1",
-				params![entry.id.to_string()],
+				&Query::new()
+					.with_expression(ExpressionFilter::FullText("rust".to_string()))
+					.in_collection("notes")
+					.with_explanation(),
 			)
 			.unwrap();
+		assert_eq!(full_text.results.len(), 1);
+		assert_eq!(
+			full_text.results[0].entry.expression,
+			"rust ownership notes"
+		);
+		assert_eq!(
+			full_text.plan.steps[0].filter,
+			Some(QueryFilterIdentity::Collection)
+		);
 
-		let report = storage.integrity_check().unwrap();
+		let several = storage
+			.query(
+				&Query::new()
+					.with_context(ContextFilter::PathEquals(
+						"/type".to_string(),
+						serde_json::json!("memory"),
+					))
+					.in_collections(["notes", "tasks"]),
+			)
+			.unwrap();
+		assert_eq!(several.len(), 3);
+		assert!(several
+			.iter()
+			.all(|result| result.entry.collection != DEFAULT_COLLECTION));
 
-		assert!(report.issues.iter().any(|issue| {
-			issue.area == "full_text" && issue.message.contains("stale or mismatched")
-		}));
+		let plan: String = storage
+			.conn
+			.query_row(
+				"EXPLAIN QUERY PLAN SELECT id FROM entries
+				 WHERE json_extract(context, '$.\"type\"') = json_extract(?1, '$')
+				 AND collection = 'notes'",
+				params!["\"memory\""],
+				|row| row.get(3),
+			)
+			.unwrap();
+		assert!(plan.contains(&index), "{plan}");
 	}
 
 	#[test]
-	fn test_embedding_profile_is_persisted_and_locked_for_existing_data() {
+	fn test_delete_entry_with_relations() {
 		let mut storage = create_test_storage();
-		let profile = EmbeddingProfile {
-			model: "text-embedding-model".to_string(),
-			version: Some("2026-07".to_string()),
-			dimensions: 2,
-		};
-		storage.set_embedding_profile(&profile).unwrap();
-		storage
-			.insert(&create_test_entry(vec![0.1, 0.2], "Profiled"))
-			.unwrap();
 
-		assert_eq!(storage.embedding_profile().unwrap(), Some(profile));
-		assert!(storage
-			.set_embedding_profile(&EmbeddingProfile {
-				model: "different-model".to_string(),
-				version: None,
-				dimensions: 2,
-			})
-			.is_err());
-	}
+		let target = create_test_entry(vec![0.1], "Target");
+		storage.insert(&target).unwrap();
 
-	#[test]
-	fn test_populated_legacy_profile_requires_explicit_adoption() {
-		let mut storage = create_test_storage();
-		storage
-			.insert(&create_test_entry(vec![0.1, 0.2], "Legacy"))
-			.unwrap();
-		let profile = EmbeddingProfile {
-			model: "legacy-model".to_string(),
-			version: Some("v1".to_string()),
-			dimensions: 2,
-		};
+		let entry = create_test_entry(vec![0.2], "Entry").add_relation(target.id);
+		storage.insert(&entry).unwrap();
 
-		assert!(storage.set_embedding_profile(&profile).is_err());
-		assert_eq!(storage.embedding_profile().unwrap(), None);
-		storage.adopt_legacy_embedding_profile(&profile).unwrap();
-		assert_eq!(storage.embedding_profile().unwrap(), Some(profile));
+		// Delete entry with relations
+		storage.delete(entry.id).unwrap();
+		assert_eq!(storage.count().unwrap(), 1);
+
+		// Target should still exist
+		assert!(storage.get(target.id).is_ok());
 	}
 
 	#[test]
-	fn test_legacy_profile_adoption_rejects_wrong_dimensions() {
+	fn test_delete_target_of_relation() {
 		let mut storage = create_test_storage();
-		storage
-			.insert(&create_test_entry(vec![0.1, 0.2], "Legacy"))
-			.unwrap();
-		let profile = EmbeddingProfile {
-			model: "wrong-model".to_string(),
-			version: None,
-			dimensions: 3,
-		};
 
-		assert!(matches!(
-			storage.adopt_legacy_embedding_profile(&profile),
-			Err(StorageError::InvalidDimensions)
-		));
-		assert_eq!(storage.embedding_profile().unwrap(), None);
+		let target = create_test_entry(vec![0.1], "Target");
+		storage.insert(&target).unwrap();
+
+		let entry = create_test_entry(vec![0.2], "Entry").add_relation(target.id);
+		storage.insert(&entry).unwrap();
+
+		// Delete target (should clean up relation)
+		storage.delete(target.id).unwrap();
+
+		// Source entry should still exist but relation should be gone
+		// Note: This tests the DELETE cascade on relations
+		assert_eq!(storage.count().unwrap(), 1);
 	}
 
 	#[test]
-	fn test_migrate_embeddings_replaces_every_vector_and_records_revisions() {
+	fn test_relate_and_unrelate_touch_only_relations_and_history() {
 		let mut storage = create_test_storage();
-		let old_profile = EmbeddingProfile {
-			model: "old-model".to_string(),
-			version: Some("v1".to_string()),
-			dimensions: 2,
-		};
-		storage.set_embedding_profile(&old_profile).unwrap();
-		let first = create_test_entry(vec![0.1, 0.2], "First");
-		let second = create_test_entry(vec![0.3, 0.4], "Second");
+		let target = create_test_entry(vec![0.1], "Target");
+		let source = create_test_entry(vec![0.2], "Source");
 		storage
-			.insert_batch(&[first.clone(), second.clone()])
+			.insert_batch(&[target.clone(), source.clone()])
 			.unwrap();
-		let new_profile = EmbeddingProfile {
-			model: "new-model".to_string(),
-			version: Some("v2".to_string()),
-			dimensions: 3,
-		};
 
-		storage
-			.migrate_embeddings(
-				&new_profile,
-				&[
-					(first.id, vec![1.0, 0.0, 0.0]),
-					(second.id, vec![0.0, 1.0, 0.0]),
-				],
-			)
-			.unwrap();
+		storage.relate(source.id, target.id).unwrap();
+		storage.relate(source.id, target.id).unwrap();
+		let related = storage.get(source.id).unwrap();
+		assert_eq!(related.relations, vec![target.id]);
+		assert_eq!(related.expression, "Source");
+		assert!(related.updated_at >= source.updated_at);
 
-		assert_eq!(storage.embedding_profile().unwrap(), Some(new_profile));
-		assert_eq!(storage.get(first.id).unwrap().meaning, vec![1.0, 0.0, 0.0]);
-		assert_eq!(storage.get(second.id).unwrap().meaning, vec![0.0, 1.0, 0.0]);
-		let first_revisions = storage.revisions(first.id).unwrap();
-		assert_eq!(first_revisions.len(), 2);
-		assert_eq!(first_revisions[1].operation, RevisionOperation::Update);
-		assert_eq!(first_revisions[1].snapshot.meaning, vec![1.0, 0.0, 0.0]);
-	}
+		storage.unrelate(source.id, target.id).unwrap();
+		storage.unrelate(source.id, target.id).unwrap();
+		assert!(storage.get(source.id).unwrap().relations.is_empty());
 
-	#[test]
-	fn test_migrate_embeddings_rolls_back_vectors_profile_and_revisions() {
-		let mut storage = create_test_storage();
-		let old_profile = EmbeddingProfile {
-			model: "old-model".to_string(),
-			version: None,
-			dimensions: 2,
-		};
-		storage.set_embedding_profile(&old_profile).unwrap();
-		let first = create_test_entry(vec![0.1, 0.2], "First");
-		let second = create_test_entry(vec![0.3, 0.4], "Second");
+		let operations: Vec<_> = storage
+			.revisions(source.id)
+			.unwrap()
+			.into_iter()
+			.map(|revision| revision.operation)
+			.collect();
+		assert_eq!(
+			operations,
+			vec![
+				RevisionOperation::Insert,
+				RevisionOperation::Relate,
+				RevisionOperation::Unrelate
+			]
+		);
+		assert!(storage.integrity_check().unwrap().is_healthy());
+	}
+
+	#[test]
+	fn test_edge_edits_store_relations_only_revisions() {
+		let mut storage = create_test_storage();
+		let target = create_test_entry(vec![0.1; 64], "Target");
+		let source = create_test_entry(vec![0.2; 64], "Source with a long vector");
 		storage
-			.insert_batch(&[first.clone(), second.clone()])
+			.insert_batch(&[target.clone(), source.clone()])
 			.unwrap();
-		let failing_id = [first.id, second.id].into_iter().max().unwrap();
-		storage
+		storage.relate(source.id, target.id).unwrap();
+		storage.unrelate(source.id, target.id).unwrap();
+
+		let rows: Vec<(String, String)> = storage
 			.conn
-			.execute_batch(&format!(
-				"CREATE TRIGGER fail_embedding_migration
-				 BEFORE UPDATE OF meaning ON entries
-				 WHEN old.id = '{failing_id}'
-				 BEGIN SELECT RAISE(ABORT, 'forced migration failure'); END;"
-			))
+			.prepare(
+				"SELECT encoding, snapshot FROM entry_revisions WHERE entry_id = ?1 ORDER BY rowid",
+			)
+			.unwrap()
+			.query_map(params![source.id.to_string()], |row| {
+				Ok((row.get(0)?, row.get(1)?))
+			})
+			.unwrap()
+			.collect::<Result<_, _>>()
 			.unwrap();
-		let new_profile = EmbeddingProfile {
-			model: "new-model".to_string(),
-			version: None,
-			dimensions: 3,
-		};
-
-		assert!(storage
-			.migrate_embeddings(
-				&new_profile,
-				&[
-					(first.id, vec![1.0, 0.0, 0.0]),
-					(second.id, vec![0.0, 1.0, 0.0])
-				],
+		let encodings: Vec<&str> = rows.iter().map(|(encoding, _)| encoding.as_str()).collect();
+		assert_eq!(encodings, vec!["full", "relations", "relations"]);
+		assert!(!rows[1].1.contains("meaning"));
+		assert!(rows[1].1.len() * 4 < rows[0].1.len());
+
+		let revisions = storage.revisions(source.id).unwrap();
+		assert_eq!(revisions[1].snapshot.relations, vec![target.id]);
+		assert_eq!(revisions[1].snapshot.meaning, source.meaning);
+		assert_eq!(revisions[1].snapshot.version, 2);
+		assert!(revisions[2].snapshot.relations.is_empty());
+		let restored = storage.revert(source.id, revisions[1].revision_id).unwrap();
+		assert_eq!(restored.relations, vec![target.id]);
+		assert_eq!(restored.expression, source.expression);
+
+		let report = storage
+			.compact_revisions_with(&RetentionPolicy::default())
+			.unwrap();
+		assert_eq!(report.revisions_removed, 0);
+		let kept: i64 = storage
+			.conn
+			.query_row(
+				"SELECT COUNT(*) FROM entry_revisions WHERE encoding = 'relations'",
+				[],
+				|row| row.get(0),
 			)
-			.is_err());
-
-		assert_eq!(storage.embedding_profile().unwrap(), Some(old_profile));
-		assert_eq!(storage.get(first.id).unwrap().meaning, first.meaning);
-		assert_eq!(storage.get(second.id).unwrap().meaning, second.meaning);
-		assert_eq!(storage.revisions(first.id).unwrap().len(), 1);
-		assert_eq!(storage.revisions(second.id).unwrap().len(), 1);
+			.unwrap();
+		assert_eq!(kept, 2);
+		storage
+			.compact_revisions_with(&RetentionPolicy {
+				keep_last: Some(1),
+				..RetentionPolicy::default()
+			})
+			.unwrap();
+		assert_eq!(
+			storage.revisions(source.id).unwrap()[0].snapshot.relations,
+			vec![target.id]
+		);
+		assert!(storage.integrity_check().unwrap().is_healthy());
 	}
 
 	#[test]
-	fn test_migrate_embeddings_requires_exact_valid_replacements() {
+	fn test_relate_batch_is_atomic() {
 		let mut storage = create_test_storage();
 		let first = create_test_entry(vec![0.1], "First");
 		let second = create_test_entry(vec![0.2], "Second");
 		storage
 			.insert_batch(&[first.clone(), second.clone()])
 			.unwrap();
-		let profile = EmbeddingProfile {
-			model: "new-model".to_string(),
-			version: None,
-			dimensions: 2,
-		};
 
+		assert!(matches!(
+			storage.relate_batch(&[(first.id, second.id), (second.id, Uuid::new_v4())]),
+			Err(StorageError::NotFound(_))
+		));
 		assert!(storage
-			.migrate_embeddings(&profile, &[(first.id, vec![1.0, 0.0])])
-			.is_err());
-		assert!(storage
-			.migrate_embeddings(
-				&profile,
-				&[(first.id, vec![1.0, 0.0]), (first.id, vec![0.0, 1.0])],
-			)
-			.is_err());
-		assert!(storage
-			.migrate_embeddings(
-				&profile,
-				&[(first.id, vec![f32::NAN, 0.0]), (second.id, vec![0.0, 1.0])],
-			)
+			.relate_batch(&[(first.id, second.id), (second.id, second.id)])
 			.is_err());
-		assert_eq!(storage.get(first.id).unwrap().meaning, first.meaning);
-		assert_eq!(storage.get(second.id).unwrap().meaning, second.meaning);
+		assert!(storage.get(first.id).unwrap().relations.is_empty());
+		assert_eq!(storage.revisions(first.id).unwrap().len(), 1);
+
+		storage
+			.relate_batch(&[(first.id, second.id), (second.id, first.id)])
+			.unwrap();
+		assert_eq!(storage.get(second.id).unwrap().relations, vec![first.id]);
 	}
 
 	#[test]
-	fn test_integrity_check_reports_partial_and_malformed_profile_metadata() {
+	fn test_graph_stats_reflect_stored_relations() {
 		let mut storage = create_test_storage();
+		let target = create_test_entry(vec![0.1], "Target");
+		let source = create_test_entry(vec![0.2], "Source").add_relation(target.id);
+		let isolated = create_test_entry(vec![0.3], "Isolated");
 		storage
-			.insert(&create_test_entry(vec![0.1, 0.2], "Entry"))
-			.unwrap();
-		storage
-			.conn
-			.execute(
-				"INSERT INTO contextdb_metadata (key, value) VALUES ('embedding_model_version', 'v1')",
-				[],
-			)
+			.insert_batch(&[target.clone(), source.clone(), isolated.clone()])
 			.unwrap();
 
-		let partial = storage.integrity_check().unwrap();
-		assert!(partial.issues.iter().any(|issue| issue.area == "metadata"));
+		let stats = storage.graph_stats(5).unwrap();
+
+		assert_eq!(stats.entry_count, 3);
+		assert_eq!(stats.relation_count, 1);
+		assert_eq!(stats.component_count, 2);
+		assert_eq!(stats.isolated_entries, vec![isolated.id]);
+		assert_eq!(stats.sink_entries, vec![target.id]);
+		assert!(stats.dangling_relations.is_empty());
+		assert!(stats.is_acyclic());
 
 		storage
-			.conn
-			.execute(
-				"UPDATE contextdb_metadata SET value = 'invalid' WHERE key = 'vector_dimension'",
-				[],
-			)
+			.set_trash_policy(&TrashPolicy {
+				soft_delete: true,
+				..TrashPolicy::default()
+			})
 			.unwrap();
-		let malformed = storage.integrity_check().unwrap();
-		assert!(malformed
-			.issues
-			.iter()
-			.any(|issue| issue.area == "metadata" && issue.message.contains("vector dimension")));
-	}
+		storage.delete(target.id).unwrap();
+		let stats = storage.graph_stats(5).unwrap();
 
-	#[test]
-	fn test_backup_and_restore_preserve_data() {
-		let directory = tempfile::TempDir::new().unwrap();
-		let source_path = directory.path().join("source.db");
-		let backup_path = directory.path().join("backup.db");
-		let restored_path = directory.path().join("restored.db");
-		let mut source = SqliteStorage::new(&source_path).unwrap();
-		let entry = create_test_entry(vec![0.1], "Backed up");
-		source.insert(&entry).unwrap();
-		source.backup_to(&backup_path).unwrap();
+		assert_eq!(stats.relation_count, 0);
+		assert_eq!(stats.dangling_relations, vec![(source.id, target.id)]);
+	}
 
-		SqliteStorage::restore_from(&backup_path, &restored_path).unwrap();
-		let restored = SqliteStorage::new(&restored_path).unwrap();
+	// ==================== As-Of Query Tests ====================
 
-		assert_eq!(restored.get(entry.id).unwrap().expression, "Backed up");
-		assert!(restored.integrity_check().unwrap().is_healthy());
+	fn pause() -> DateTime<Utc> {
+		std::thread::sleep(std::time::Duration::from_millis(5));
+		let now = Utc::now();
+		std::thread::sleep(std::time::Duration::from_millis(5));
+		now
 	}
 
 	#[test]
-	fn test_revision_history_survives_deletion() {
+	fn test_as_of_query_reconstructs_past_state() {
 		let mut storage = create_test_storage();
-		let mut entry = create_test_entry(vec![0.1], "Original");
-		storage.insert(&entry).unwrap();
-		entry.expression = "Updated".to_string();
-		entry.updated_at = Utc::now();
-		storage.update(&entry).unwrap();
-		storage.delete(entry.id).unwrap();
+		let before_insert = pause();
+		let target = create_test_entry(vec![0.0, 1.0], "Deleted target");
+		let mut source = create_test_entry(vec![1.0, 0.0], "Original wording");
+		source.relations = vec![target.id];
+		storage
+			.insert_batch(&[target.clone(), source.clone()])
+			.unwrap();
+		let checkpoint = pause();
 
-		let revisions = storage.revisions(entry.id).unwrap();
-		assert_eq!(revisions.len(), 3);
-		assert_eq!(revisions[0].operation, RevisionOperation::Insert);
-		assert_eq!(revisions[0].snapshot.expression, "Original");
-		assert_eq!(revisions[1].operation, RevisionOperation::Update);
-		assert_eq!(revisions[1].snapshot.expression, "Updated");
-		assert_eq!(revisions[2].operation, RevisionOperation::Delete);
-		assert_eq!(revisions[2].snapshot.expression, "Updated");
+		let mut revised = storage.get(source.id).unwrap();
+		revised.expression = "Revised wording".to_string();
+		revised.relations.clear();
+		storage.update(&revised).unwrap();
+		storage.delete(target.id).unwrap();
+		storage
+			.insert(&create_test_entry(vec![0.5, 0.5], "Added later"))
+			.unwrap();
+
+		let past = storage.query(&Query::new().as_of(checkpoint)).unwrap();
+		let mut past_ids: Vec<Uuid> = past.iter().map(|result| result.entry.id).collect();
+		past_ids.sort();
+		let mut expected = vec![source.id, target.id];
+		expected.sort();
+		assert_eq!(past_ids, expected);
+		let past_source = past
+			.iter()
+			.find(|result| result.entry.id == source.id)
+			.unwrap();
+		assert_eq!(past_source.entry.expression, "Original wording");
+		assert_eq!(past_source.entry.relations, vec![target.id]);
+
+		assert!(storage
+			.query(&Query::new().as_of(before_insert))
+			.unwrap()
+			.is_empty());
+		assert_eq!(storage.query(&Query::new()).unwrap().len(), 2);
 	}
 
 	#[test]
-	fn test_delete_entry_with_relations() {
+	fn test_as_of_rejects_times_before_compacted_history() {
 		let mut storage = create_test_storage();
+		let mut edited = create_test_entry(vec![1.0], "First wording");
+		let untouched = create_test_entry(vec![1.0], "Other tenant").for_tenant("acme");
+		storage
+			.insert_batch(&[edited.clone(), untouched.clone()])
+			.unwrap();
+		let checkpoint = pause();
+		update_repeatedly(&mut storage, &mut edited, 1);
+		let after_edit = pause();
 
-		let target = create_test_entry(vec![0.1], "Target");
-		storage.insert(&target).unwrap();
-
-		let entry = create_test_entry(vec![0.2], "Entry").add_relation(target.id);
-		storage.insert(&entry).unwrap();
-
-		// Delete entry with relations
-		storage.delete(entry.id).unwrap();
-		assert_eq!(storage.count().unwrap(), 1);
+		storage
+			.compact_revisions_with(&RetentionPolicy {
+				keep_last: Some(1),
+				..RetentionPolicy::default()
+			})
+			.unwrap();
 
-		// Target should still exist
-		assert!(storage.get(target.id).is_ok());
+		assert!(matches!(
+			storage.query(&Query::new().as_of(checkpoint)),
+			Err(StorageError::InvalidArgument(_))
+		));
+		assert_eq!(
+			storage
+				.query(&Query::new().as_of(after_edit))
+				.unwrap()
+				.len(),
+			2
+		);
+		storage.scope_to_tenant("acme").unwrap();
+		let past = storage.query(&Query::new().as_of(checkpoint)).unwrap();
+		assert_eq!(past[0].entry.id, untouched.id);
 	}
 
 	#[test]
-	fn test_delete_target_of_relation() {
+	fn test_as_of_query_applies_filters_to_past_state() {
 		let mut storage = create_test_storage();
+		let target = create_test_entry(vec![0.0, 1.0], "Target entry");
+		let mut source = create_test_entry(vec![1.0, 0.0], "Original wording");
+		source.relations = vec![target.id];
+		storage
+			.insert_batch(&[target.clone(), source.clone()])
+			.unwrap();
+		let checkpoint = pause();
+		let mut revised = storage.get(source.id).unwrap();
+		revised.expression = "Revised wording".to_string();
+		storage.update(&revised).unwrap();
+		storage.delete(target.id).unwrap();
 
-		let target = create_test_entry(vec![0.1], "Target");
-		storage.insert(&target).unwrap();
-
-		let entry = create_test_entry(vec![0.2], "Entry").add_relation(target.id);
-		storage.insert(&entry).unwrap();
+		let full_text = Query::new()
+			.with_expression(ExpressionFilter::FullText("original".to_string()))
+			.with_explanation();
+		assert!(storage.query(&full_text).unwrap().is_empty());
+		let execution = storage.execute(&full_text.as_of(checkpoint)).unwrap();
+		assert_eq!(execution.results.len(), 1);
+		assert_eq!(execution.results[0].entry.id, source.id);
+		assert_eq!(execution.results[0].lexical_score, Some(1.0));
+		assert_eq!(
+			execution.plan.steps[0].strategy,
+			QueryPlanStrategy::RevisionReplay
+		);
+		assert!(execution.results[0]
+			.explanation
+			.as_ref()
+			.unwrap()
+			.contains("Evaluated as of"));
 
-		// Delete target (should clean up relation)
-		storage.delete(target.id).unwrap();
+		let related = Query::new()
+			.with_relations(RelationFilter::DirectlyRelatedTo(source.id))
+			.as_of(checkpoint);
+		let results = storage.query(&related).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].entry.id, target.id);
 
-		// Source entry should still exist but relation should be gone
-		// Note: This tests the DELETE cascade on relations
-		assert_eq!(storage.count().unwrap(), 1);
+		let semantic = Query::new()
+			.with_meaning(vec![0.0, 1.0], None)
+			.with_top_k(1)
+			.as_of(checkpoint);
+		assert_eq!(storage.query(&semantic).unwrap()[0].entry.id, target.id);
 	}
 
 	// ==================== Expression Filter Tests ====================
@@ -3558,6 +8826,13 @@ mod tests {
 			order: QueryOrder::default(),
 			hybrid_weights: None,
 			explain: false,
+			as_of: None,
+			collections: Vec::new(),
+			include_expired: false,
+			collapse_chunks: false,
+			decay: None,
+			skip_access_tracking: false,
+			feedback_boost: None,
 		};
 
 		let results = storage.query(&query).unwrap();
@@ -3665,6 +8940,13 @@ mod tests {
 			order: QueryOrder::default(),
 			hybrid_weights: None,
 			explain: false,
+			as_of: None,
+			collections: Vec::new(),
+			include_expired: false,
+			collapse_chunks: false,
+			decay: None,
+			skip_access_tracking: false,
+			feedback_boost: None,
 		};
 
 		let results = storage.query(&query).unwrap();
@@ -3706,6 +8988,13 @@ mod tests {
 			order: QueryOrder::default(),
 			hybrid_weights: None,
 			explain: false,
+			as_of: None,
+			collections: Vec::new(),
+			include_expired: false,
+			collapse_chunks: false,
+			decay: None,
+			skip_access_tracking: false,
+			feedback_boost: None,
 		};
 
 		let mut results = storage.query(&query).unwrap();
@@ -3746,6 +9035,13 @@ mod tests {
 			order: QueryOrder::default(),
 			hybrid_weights: None,
 			explain: false,
+			as_of: None,
+			collections: Vec::new(),
+			include_expired: false,
+			collapse_chunks: false,
+			decay: None,
+			skip_access_tracking: false,
+			feedback_boost: None,
 		};
 		let results_has = storage.query(&query_has).unwrap();
 		let has_ids: HashSet<Uuid> = results_has.into_iter().map(|r| r.entry.id).collect();
@@ -3763,6 +9059,13 @@ mod tests {
 			order: QueryOrder::default(),
 			hybrid_weights: None,
 			explain: false,
+			as_of: None,
+			collections: Vec::new(),
+			include_expired: false,
+			collapse_chunks: false,
+			decay: None,
+			skip_access_tracking: false,
+			feedback_boost: None,
 		};
 		let results_none = storage.query(&query_none).unwrap();
 		let no_relation_ids: HashSet<Uuid> = results_none
@@ -4123,6 +9426,169 @@ mod tests {
 		assert!(semantic_first[0].combined_score.is_some());
 	}
 
+	#[test]
+	fn test_decayed_ranking_weighs_recency_and_importance() {
+		let mut storage = create_test_storage();
+		let now = Utc::now();
+		let mut stale = create_test_entry(vec![1.0, 0.0], "Stale but relevant");
+		stale.created_at = now - chrono::Duration::days(30);
+		stale.updated_at = stale.created_at;
+		let fresh = create_test_entry(vec![0.8, 0.6], "Fresh and close");
+		let important =
+			create_test_entry(vec![0.0, 1.0], "Unrelated but vital").with_importance(1.0);
+		for entry in [&stale, &fresh, &important] {
+			storage.insert(entry).unwrap();
+		}
+		assert!(matches!(
+			storage.insert(&create_test_entry(vec![1.0, 0.0], "Bad").with_importance(1.5)),
+			Err(StorageError::InvalidArgument(_))
+		));
+		let query = Query::new().with_meaning(vec![1.0, 0.0], None);
+
+		let by_relevance = storage.query(&query).unwrap();
+		let decayed = storage
+			.execute(
+				&query
+					.clone()
+					.with_decay(DecayRanking::new(1.0, 1.0, 0.0, 24.0))
+					.with_explanation(),
+			)
+			.unwrap();
+		let by_importance = storage
+			.query(
+				&query
+					.clone()
+					.with_decay(DecayRanking::new(0.0, 0.0, 1.0, 24.0)),
+			)
+			.unwrap();
+
+		assert_eq!(by_relevance[0].entry.id, stale.id);
+		assert_eq!(decayed.results[0].entry.id, fresh.id);
+		assert_eq!(decayed.results[2].entry.id, stale.id);
+		assert_eq!(
+			decayed.plan.ordering.primary,
+			QueryPrimaryOrder::DecayedScoreDescending
+		);
+		assert!(matches!(
+			decayed.plan.ranking_mode,
+			QueryRankingMode::Decayed {
+				anchor: DecayAnchor::UpdatedAt,
+				..
+			}
+		));
+		let explanation = decayed.results[0].explanation.as_ref().unwrap();
+		assert!(explanation.contains("Recency since updated_at"));
+		assert!(explanation.contains("Decayed score"));
+		assert!(decayed.results[0].combined_score.unwrap() > 0.9);
+		assert_eq!(by_importance[0].entry.id, important.id);
+		assert!(matches!(
+			storage.query(&query.with_decay(DecayRanking::new(1.0, 1.0, 1.0, 0.0))),
+			Err(StorageError::InvalidArgument(_))
+		));
+	}
+
+	#[test]
+	fn test_feedback_boosts_and_demotes_results() {
+		let mut storage = create_test_storage();
+		let best = create_test_entry(vec![1.0, 0.0], "Closest");
+		let second = create_test_entry(vec![0.9, 0.1], "Second");
+		let third = create_test_entry(vec![0.8, 0.2], "Third");
+		for entry in [&best, &second, &third] {
+			storage.insert(entry).unwrap();
+		}
+		let query = Query::new().with_meaning(vec![1.0, 0.0], None);
+		let fingerprint = query.fingerprint();
+		storage
+			.record_feedback(&fingerprint, best.id, FeedbackSignal::Negative)
+			.unwrap();
+		storage
+			.record_feedback(&fingerprint, third.id, FeedbackSignal::Positive)
+			.unwrap();
+		storage
+			.record_feedback("other search", best.id, FeedbackSignal::Negative)
+			.unwrap();
+		assert!(matches!(
+			storage.record_feedback(" ", best.id, FeedbackSignal::Positive),
+			Err(StorageError::InvalidArgument(_))
+		));
+		assert!(matches!(
+			storage.record_feedback(&fingerprint, Uuid::new_v4(), FeedbackSignal::Positive),
+			Err(StorageError::NotFound(_))
+		));
+
+		let plain = storage.query(&query).unwrap();
+		let boosted = storage
+			.execute(
+				&query
+					.clone()
+					.with_feedback_boost(0.2, Some(fingerprint.clone()))
+					.with_explanation(),
+			)
+			.unwrap();
+		let everywhere = storage
+			.query(&query.clone().with_feedback_boost(0.2, None))
+			.unwrap();
+
+		assert_eq!(plain[0].entry.id, best.id);
+		assert!(plain[0].feedback_boost.is_none());
+		let ids: Vec<_> = boosted
+			.results
+			.iter()
+			.map(|result| result.entry.id)
+			.collect();
+		assert_eq!(ids, vec![third.id, second.id, best.id]);
+		assert!((boosted.results[0].feedback_boost.unwrap() - 0.1).abs() < 1e-6);
+		assert_eq!(boosted.results[1].feedback_boost, Some(0.0));
+		assert!(boosted.results[2]
+			.explanation
+			.as_ref()
+			.unwrap()
+			.contains("Feedback: 0 positive, 1 negative, boost -0.100"));
+		assert!(boosted
+			.plan
+			.steps
+			.iter()
+			.any(|step| step.strategy == QueryPlanStrategy::FeedbackRerank));
+		assert_eq!(
+			boosted.plan.ranking_mode,
+			QueryRankingMode::FeedbackBoosted {
+				feedback_weight: 0.2,
+				decayed: false,
+			}
+		);
+		assert_eq!(
+			boosted.plan.ordering.primary,
+			QueryPrimaryOrder::FeedbackBoostedScoreDescending
+		);
+		assert_eq!(
+			boosted.plan.ranking,
+			"relevance plus relevance feedback boost up to 0.2"
+		);
+		let best_boost = everywhere
+			.iter()
+			.find(|result| result.entry.id == best.id)
+			.and_then(|result| result.feedback_boost)
+			.unwrap();
+		assert!((best_boost + 0.2 * 2.0 / 3.0).abs() < 1e-6);
+		assert_eq!(storage.feedback(best.id).unwrap().len(), 2);
+		assert_eq!(
+			query.clone().with_limit(5).with_offset(5).fingerprint(),
+			fingerprint
+		);
+		assert_ne!(
+			Query::new()
+				.with_meaning(vec![0.0, 1.0], None)
+				.fingerprint(),
+			fingerprint
+		);
+
+		storage.delete(best.id).unwrap();
+		assert!(!storage
+			.feedback_counts(None)
+			.unwrap()
+			.contains_key(&best.id));
+	}
+
 	#[test]
 	fn test_query_within_distance_zero_hops() {
 		let mut storage = create_test_storage();
@@ -4150,6 +9616,13 @@ mod tests {
 			order: QueryOrder::default(),
 			hybrid_weights: None,
 			explain: false,
+			as_of: None,
+			collections: Vec::new(),
+			include_expired: false,
+			collapse_chunks: false,
+			decay: None,
+			skip_access_tracking: false,
+			feedback_boost: None,
 		};
 
 		let results = storage.query(&query).unwrap();
@@ -4176,9 +9649,17 @@ mod tests {
 			order: QueryOrder::default(),
 			hybrid_weights: None,
 			explain: true,
+			as_of: None,
+			collections: Vec::new(),
+			include_expired: false,
+			collapse_chunks: false,
+			decay: None,
+			skip_access_tracking: false,
+			feedback_boost: None,
 		};
 
-		let explanation = storage.generate_explanation(&entry, &query, Some(0.85), None, None);
+		let explanation =
+			storage.generate_explanation(&entry, &query, Some(0.85), None, None, None);
 
 		assert!(explanation.contains("Semantic similarity"));
 		assert!(explanation.contains("expression filter"));
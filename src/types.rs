@@ -2,6 +2,20 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// Collection that entries belong to unless they name another one
+pub const DEFAULT_COLLECTION: &str = "default";
+
+/// Tenant that owns entries unless they name another one
+pub const DEFAULT_TENANT: &str = "default";
+
+fn default_collection() -> String {
+	DEFAULT_COLLECTION.to_string()
+}
+
+fn default_tenant() -> String {
+	DEFAULT_TENANT.to_string()
+}
+
 /// The fundamental unit of ContextDB: an entry with both semantic meaning and human expression
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entry {
@@ -25,6 +39,30 @@ pub struct Entry {
 
 	/// IDs of related entries (for graph relationships)
 	pub relations: Vec<Uuid>,
+
+	/// Stored version: 1 on insert, incremented by every change, 0 before the entry is stored
+	#[serde(default)]
+	pub version: u64,
+
+	/// Named collection holding the entry; each collection has its own embedding profile
+	#[serde(default = "default_collection")]
+	pub collection: String,
+
+	/// Tenant that owns the entry; relations never cross tenants
+	#[serde(default = "default_tenant")]
+	pub tenant: String,
+
+	/// When the entry expires; expired entries are hidden from reads until swept
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub expires_at: Option<DateTime<Utc>>,
+
+	/// Key of the record this entry mirrors in another system, unique within its tenant
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub external_id: Option<String>,
+
+	/// How much the entry matters, from 0 to 1, for decayed ranking
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub importance: Option<f32>,
 }
 
 impl Entry {
@@ -39,9 +77,50 @@ impl Entry {
 			created_at: now,
 			updated_at: now,
 			relations: Vec::new(),
+			version: 0,
+			collection: default_collection(),
+			tenant: default_tenant(),
+			expires_at: None,
+			external_id: None,
+			importance: None,
 		}
 	}
 
+	/// Place the entry in a named collection
+	pub fn in_collection(mut self, collection: impl Into<String>) -> Self {
+		self.collection = collection.into();
+		self
+	}
+
+	/// Assign the entry to a tenant
+	pub fn for_tenant(mut self, tenant: impl Into<String>) -> Self {
+		self.tenant = tenant.into();
+		self
+	}
+
+	/// Expire the entry at the given time
+	pub fn with_expiry(mut self, expires_at: DateTime<Utc>) -> Self {
+		self.expires_at = Some(expires_at);
+		self
+	}
+
+	/// Key the entry by the ID of its record in another system
+	pub fn with_external_id(mut self, external_id: impl Into<String>) -> Self {
+		self.external_id = Some(external_id.into());
+		self
+	}
+
+	/// Rate how much the entry matters, from 0 to 1
+	pub fn with_importance(mut self, importance: f32) -> Self {
+		self.importance = Some(importance);
+		self
+	}
+
+	/// Whether the entry has expired at `now`
+	pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+		self.expires_at.is_some_and(|expires_at| expires_at <= now)
+	}
+
 	/// Create an entry with additional context metadata
 	pub fn with_context(mut self, context: serde_json::Value) -> Self {
 		self.context = context;
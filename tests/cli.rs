@@ -287,3 +287,531 @@ fn cli_profile_and_revisions_are_inspectable() {
 		.success()
 		.stdout(predicate::str::contains("Insert"));
 }
+
+#[test]
+fn cli_stats_reports_relation_graph_as_json() {
+	let (_temp_dir, db_path) = temp_db_path();
+	let mut db = ContextDB::new(&db_path).expect("db created");
+	let target = Entry::new(vec![0.1], "Target".to_string());
+	let source = Entry::new(vec![0.2], "Source".to_string()).add_relation(target.id);
+	db.insert_batch(&[target, source])
+		.expect("entries inserted");
+
+	let output = cmd_bin()
+		.arg("stats")
+		.arg(&db_path)
+		.args(["--format", "json"])
+		.assert()
+		.success()
+		.get_output()
+		.stdout
+		.clone();
+	let stats: serde_json::Value = serde_json::from_slice(&output).expect("stats are JSON");
+
+	assert_eq!(stats["entries"], 2);
+	assert_eq!(stats["graph"]["relation_count"], 1);
+	assert_eq!(stats["graph"]["component_count"], 1);
+}
+
+#[test]
+fn cli_stats_cold_lists_entries_never_accessed() {
+	let (_temp_dir, db_path) = temp_db_path();
+	let mut db = ContextDB::new(&db_path).expect("db created");
+	db.set_access_policy(&contextdb::AccessPolicy { track: true })
+		.expect("tracking enabled");
+	let read = Entry::new(vec![0.1], "Read often".to_string());
+	let cold = Entry::new(vec![0.2], "Never read".to_string());
+	db.insert_batch(&[read.clone(), cold.clone()])
+		.expect("entries inserted");
+	db.get(read.id).expect("entry read");
+	drop(db);
+
+	let output = cmd_bin()
+		.arg("stats")
+		.arg(&db_path)
+		.args(["--cold", "--format", "json"])
+		.assert()
+		.success()
+		.get_output()
+		.stdout
+		.clone();
+	let report: serde_json::Value = serde_json::from_slice(&output).expect("report is JSON");
+
+	assert_eq!(report["tracking"], true);
+	let ids: Vec<&str> = report["never_accessed"]
+		.as_array()
+		.expect("entries listed")
+		.iter()
+		.map(|entry| entry["id"].as_str().expect("entry has an ID"))
+		.collect();
+	assert_eq!(ids, vec![cold.id.to_string()]);
+	cmd_bin()
+		.arg("stats")
+		.arg(&db_path)
+		.arg("--cold")
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("1 never-accessed entries"));
+}
+
+#[test]
+fn cli_eval_scores_and_compares_configs() {
+	let (temp_dir, db_path) = temp_db_path();
+	let mut db = ContextDB::new(&db_path).expect("db created");
+	let relevant = Entry::new(vec![0.0, 1.0], "Relevant".to_string());
+	let distractor = Entry::new(vec![1.0, 0.0], "Distractor".to_string());
+	db.insert_batch(&[relevant.clone(), distractor.clone()])
+		.expect("entries inserted");
+	drop(db);
+	let qrels = temp_dir.path().join("qrels.json");
+	let cases = serde_json::json!([{
+		"name": "relevant first",
+		"query": {"meaning": {"vector": [0.1, 1.0], "threshold": null, "top_k": null}},
+		"judgments": {relevant.id.to_string(): 1, distractor.id.to_string(): 0},
+	}]);
+	std::fs::write(&qrels, cases.to_string()).expect("qrels written");
+	let narrow = temp_dir.path().join("narrow.json");
+	std::fs::write(&narrow, r#"{"threshold": 0.999}"#).expect("config written");
+
+	let output = cmd_bin()
+		.arg("eval")
+		.arg(&db_path)
+		.arg(&qrels)
+		.args(["-k", "2", "--format", "json"])
+		.assert()
+		.success()
+		.get_output()
+		.stdout
+		.clone();
+	let report: serde_json::Value = serde_json::from_slice(&output).expect("report is JSON");
+
+	assert_eq!(report["k"], 2);
+	assert_eq!(report["mean"]["reciprocal_rank"], 1.0);
+	assert_eq!(report["mean"]["precision"], 0.5);
+	assert_eq!(
+		report["cases"][0]["retrieved"][1],
+		distractor.id.to_string()
+	);
+	cmd_bin()
+		.arg("eval")
+		.arg(&db_path)
+		.arg(&qrels)
+		.args(["-k", "2", "--compare"])
+		.arg(&narrow)
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("-1.000"));
+}
+
+#[test]
+fn cli_relate_and_unrelate_edit_single_edges() {
+	let (_temp_dir, db_path) = temp_db_path();
+	let mut db = ContextDB::new(&db_path).expect("db created");
+	let source = Entry::new(vec![0.1], "Source".to_string());
+	let target = Entry::new(vec![0.2], "Target".to_string());
+	db.insert_batch(&[source.clone(), target.clone()])
+		.expect("entries inserted");
+
+	cmd_bin()
+		.arg("relate")
+		.arg(&db_path)
+		.arg(&source.id.to_string()[..8])
+		.arg(target.id.to_string())
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("Related"));
+	let db = ContextDB::new(&db_path).expect("db reopened");
+	assert_eq!(db.get(source.id).unwrap().relations, vec![target.id]);
+
+	cmd_bin()
+		.arg("unrelate")
+		.arg(&db_path)
+		.arg(source.id.to_string())
+		.arg(target.id.to_string())
+		.assert()
+		.success();
+	let db = ContextDB::new(&db_path).expect("db reopened");
+	assert!(db.get(source.id).unwrap().relations.is_empty());
+}
+
+#[test]
+fn cli_revert_restores_revisions_and_undeletes() {
+	let (_temp_dir, db_path) = temp_db_path();
+	let mut db = ContextDB::new(&db_path).expect("db created");
+	let mut entry = Entry::new(vec![0.1], "Original".to_string());
+	db.insert(&entry).expect("entry inserted");
+	entry.expression = "Updated".to_string();
+	entry.updated_at = Utc::now();
+	db.update(&entry).expect("entry updated");
+	let original = db.revisions(entry.id).expect("revisions")[0].revision_id;
+	db.delete(entry.id).expect("entry deleted");
+	drop(db);
+
+	cmd_bin()
+		.arg("revert")
+		.arg(&db_path)
+		.arg(entry.id.to_string())
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("Restored"));
+	cmd_bin()
+		.arg("revert")
+		.arg(&db_path)
+		.arg(&entry.id.to_string()[..8])
+		.arg(&original.to_string()[..8])
+		.assert()
+		.success();
+
+	let db = ContextDB::new(&db_path).expect("db reopened");
+	assert_eq!(db.get(entry.id).unwrap().expression, "Original");
+}
+
+#[test]
+fn cli_revisions_diff_shows_field_changes() {
+	let (_temp_dir, db_path) = temp_db_path();
+	let mut db = ContextDB::new(&db_path).expect("db created");
+	let mut entry = Entry::new(vec![0.1], "Original wording".to_string())
+		.with_context(serde_json::json!({"source": "chat"}));
+	db.insert(&entry).expect("entry inserted");
+	entry.expression = "Revised wording".to_string();
+	entry.context = serde_json::json!({"source": "email"});
+	entry.updated_at = Utc::now();
+	db.update(&entry).expect("entry updated");
+	drop(db);
+
+	cmd_bin()
+		.env("NO_COLOR", "1")
+		.arg("revisions")
+		.arg(&db_path)
+		.arg(entry.id.to_string())
+		.arg("--diff")
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("Expression:"))
+		.stdout(predicate::str::contains("~ /source \"email\""));
+}
+
+#[test]
+fn cli_compact_applies_and_saves_retention_policy() {
+	let (_temp_dir, db_path) = temp_db_path();
+	let mut db = ContextDB::new(&db_path).expect("db created");
+	let mut entry = Entry::new(vec![0.1], "Revision 0".to_string());
+	db.insert(&entry).expect("entry inserted");
+	for revision in 1..4 {
+		entry.expression = format!("Revision {revision}");
+		entry.updated_at = Utc::now();
+		db.update(&entry).expect("entry updated");
+	}
+	drop(db);
+
+	cmd_bin()
+		.arg("compact")
+		.arg(&db_path)
+		.arg("--keep-last")
+		.arg("2")
+		.arg("--save")
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("2 removed"));
+
+	let db = ContextDB::new(&db_path).expect("db reopened");
+	assert_eq!(db.revisions(entry.id).unwrap().len(), 2);
+	assert_eq!(db.retention_policy().unwrap().keep_last, Some(2));
+}
+
+#[test]
+fn cli_purge_erases_history_and_vacuums() {
+	let (_temp_dir, db_path) = temp_db_path();
+	let mut db = ContextDB::new(&db_path).expect("db created");
+	let entry = Entry::new(vec![0.1], "Erase me".to_string());
+	db.insert(&entry).expect("entry inserted");
+	db.delete(entry.id).expect("entry deleted");
+	drop(db);
+
+	cmd_bin()
+		.arg("purge")
+		.arg(&db_path)
+		.arg(entry.id.to_string())
+		.arg("--force")
+		.arg("--vacuum")
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("Purged entry"))
+		.stdout(predicate::str::contains("Vacuumed"));
+
+	let db = ContextDB::new(&db_path).expect("db reopened");
+	assert!(db.revisions(entry.id).unwrap().is_empty());
+	assert_eq!(db.tombstones().unwrap()[0].entry_id, entry.id);
+}
+
+#[test]
+fn cli_changes_prints_json_lines_after_sequence() {
+	let (_temp_dir, db_path) = temp_db_path();
+	let mut db = ContextDB::new(&db_path).expect("db created");
+	let first = Entry::new(vec![0.1], "First".to_string());
+	let second = Entry::new(vec![0.2], "Second".to_string());
+	db.insert(&first).expect("first inserted");
+	db.insert(&second).expect("second inserted");
+	db.delete(first.id).expect("first deleted");
+	let first_seq = db.changes_since(0, 1).expect("changes read")[0].seq;
+	drop(db);
+
+	let output = cmd_bin()
+		.arg("changes")
+		.arg(&db_path)
+		.arg("--since")
+		.arg(first_seq.to_string())
+		.output()
+		.expect("command ran");
+	assert!(output.status.success());
+	let lines: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+		.expect("utf8 output")
+		.lines()
+		.map(|line| serde_json::from_str(line).expect("json line"))
+		.collect();
+	assert_eq!(lines.len(), 2);
+	assert_eq!(lines[0]["entry_id"], second.id.to_string());
+	assert_eq!(lines[0]["operation"], "Insert");
+	assert_eq!(lines[1]["entry_id"], first.id.to_string());
+	assert_eq!(lines[1]["operation"], "Delete");
+}
+
+#[test]
+fn cli_patch_merges_context_from_file() {
+	let (temp_dir, db_path) = temp_db_path();
+	let mut db = ContextDB::new(&db_path).expect("db created");
+	let entry = Entry::new(vec![0.1, 0.2], "Release checklist".to_string())
+		.with_context(serde_json::json!({"status": "draft", "owner": "ana"}));
+	db.insert(&entry).expect("entry inserted");
+	drop(db);
+	let patch_path = temp_dir.path().join("patch.json");
+	std::fs::write(
+		&patch_path,
+		r#"{"context": {"status": "final", "owner": null}}"#,
+	)
+	.expect("patch written");
+
+	cmd_bin()
+		.arg("patch")
+		.arg(&db_path)
+		.arg(&entry.id.to_string()[..8])
+		.arg(&patch_path)
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("Patched entry"));
+
+	let db = ContextDB::new(&db_path).expect("db reopened");
+	let stored = db.get(entry.id).expect("entry exists");
+	assert_eq!(stored.context, serde_json::json!({"status": "final"}));
+	assert_eq!(stored.expression, "Release checklist");
+	assert_eq!(stored.meaning, entry.meaning);
+}
+
+#[test]
+fn cli_collection_flags_scope_add_list_and_profile() {
+	let (_temp_dir, db_path) = temp_db_path();
+	ContextDB::new(&db_path).expect("db created");
+
+	cmd_bin()
+		.args(["profile"])
+		.arg(&db_path)
+		.args([
+			"--collection",
+			"agent",
+			"--model",
+			"mini",
+			"--dimensions",
+			"3",
+		])
+		.assert()
+		.success();
+	cmd_bin()
+		.arg("add")
+		.arg(&db_path)
+		.args([
+			"-e",
+			"Agent note",
+			"-m",
+			"0.1,0.2,0.3",
+			"--collection",
+			"agent",
+		])
+		.assert()
+		.success();
+	cmd_bin()
+		.arg("add")
+		.arg(&db_path)
+		.args(["-e", "User note", "-m", "0.1,0.2"])
+		.assert()
+		.success();
+
+	cmd_bin()
+		.arg("list")
+		.arg(&db_path)
+		.args(["--collection", "agent", "--format", "plain"])
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("Agent note"))
+		.stdout(predicate::str::contains("User note").not());
+	cmd_bin()
+		.arg("collections")
+		.arg(&db_path)
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("agent"))
+		.stdout(predicate::str::contains("mini"))
+		.stdout(predicate::str::contains("default"));
+}
+
+#[test]
+fn cli_sweep_deletes_expired_entries() {
+	let (_temp_dir, db_path) = temp_db_path();
+	ContextDB::new(&db_path).expect("db created");
+
+	cmd_bin()
+		.arg("add")
+		.arg(&db_path)
+		.args([
+			"-e",
+			"Old chat turn",
+			"-m",
+			"0.1,0.2",
+			"--expires-at",
+			"2020-01-01T00:00:00Z",
+		])
+		.assert()
+		.success();
+	cmd_bin()
+		.arg("add")
+		.arg(&db_path)
+		.args(["-e", "Kept note", "-m", "0.1,0.2"])
+		.assert()
+		.success();
+
+	cmd_bin()
+		.arg("sweep")
+		.arg(&db_path)
+		.args(["--batch-size", "1"])
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("Swept 1 expired entries"));
+	let db = ContextDB::new(&db_path).expect("db opened");
+	assert_eq!(db.count().unwrap(), 1);
+}
+
+#[test]
+fn cli_show_finds_entries_by_external_id() {
+	let (_temp_dir, db_path) = temp_db_path();
+	let mut db = ContextDB::new(&db_path).expect("db created");
+	let entry =
+		Entry::new(vec![0.1, 0.2], "Checkout times out".to_string()).with_external_id("JIRA-42");
+	db.insert(&entry).expect("entry inserted");
+	drop(db);
+
+	cmd_bin()
+		.arg("show")
+		.arg(&db_path)
+		.args(["--key", "JIRA-42"])
+		.assert()
+		.success()
+		.stdout(predicate::str::contains(entry.id.to_string()))
+		.stdout(predicate::str::contains("External ID: JIRA-42"));
+	cmd_bin()
+		.arg("show")
+		.arg(&db_path)
+		.args(["--key", "JIRA-43"])
+		.assert()
+		.failure()
+		.stderr(predicate::str::contains("No entry found with external ID"));
+}
+
+#[test]
+fn cli_dedup_reports_clusters_and_merges_them() {
+	let (_temp_dir, db_path) = temp_db_path();
+	let mut db = ContextDB::new(&db_path).expect("db created");
+	let original = Entry::new(vec![1.0, 0.0], "Prefers dark mode".to_string());
+	let mut restated = Entry::new(vec![0.99, 0.01], "Likes dark mode".to_string())
+		.with_context(serde_json::json!({"source": "chat"}));
+	restated.created_at = original.created_at + Duration::seconds(1);
+	restated.updated_at = restated.created_at;
+	let unrelated = Entry::new(vec![0.0, 1.0], "Lives in Oslo".to_string());
+	db.insert_batch(&[original.clone(), restated.clone(), unrelated])
+		.expect("entries inserted");
+	drop(db);
+
+	cmd_bin()
+		.arg("dedup")
+		.arg(&db_path)
+		.arg("--dry-run")
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("Cluster 1"))
+		.stdout(predicate::str::contains("Likes dark mode"))
+		.stdout(predicate::str::contains("1 near-duplicate clusters"));
+	assert_eq!(ContextDB::new(&db_path).unwrap().count().unwrap(), 3);
+
+	cmd_bin()
+		.arg("dedup")
+		.arg(&db_path)
+		.args(["--threshold", "0.99"])
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("Merged 1 near-duplicate clusters"));
+	let db = ContextDB::new(&db_path).expect("db opened");
+	assert_eq!(db.count().unwrap(), 2);
+	let survivor = db.get(original.id).expect("oldest entry survives");
+	assert_eq!(survivor.context["source"], "chat");
+}
+
+#[test]
+fn cli_trash_lists_restores_and_empties_soft_deleted_entries() {
+	let (_temp_dir, db_path) = temp_db_path();
+	let mut db = ContextDB::new(&db_path).expect("db created");
+	let restored = Entry::new(vec![0.1], "Restored memory".to_string());
+	let emptied = Entry::new(vec![0.1], "Emptied memory".to_string());
+	db.insert_batch(&[restored.clone(), emptied.clone()])
+		.expect("entries inserted");
+	drop(db);
+
+	cmd_bin()
+		.args(["trash", "policy"])
+		.arg(&db_path)
+		.args(["--soft-delete", "true"])
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("Soft delete: on"));
+	for id in [restored.id, emptied.id] {
+		cmd_bin()
+			.arg("delete")
+			.arg(&db_path)
+			.arg(id.to_string())
+			.arg("--force")
+			.assert()
+			.success()
+			.stdout(predicate::str::contains("to the trash"));
+	}
+	cmd_bin()
+		.args(["trash", "list"])
+		.arg(&db_path)
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("Restored memory"))
+		.stdout(predicate::str::contains("Emptied memory"));
+	cmd_bin()
+		.args(["trash", "restore"])
+		.arg(&db_path)
+		.arg(&restored.id.to_string()[..8])
+		.assert()
+		.success();
+	cmd_bin()
+		.args(["trash", "empty"])
+		.arg(&db_path)
+		.arg("--all")
+		.assert()
+		.success()
+		.stdout(predicate::str::contains("Removed 1 entries"));
+
+	let db = ContextDB::new(&db_path).expect("db opened");
+	assert_eq!(db.get(restored.id).unwrap().expression, "Restored memory");
+	assert!(db.list_deleted().unwrap().is_empty());
+}